@@ -58,7 +58,7 @@ fn latency_by(c: &mut Criterion, transport: Transport) {
 }
 
 fn latency_by_native_udp(c: &mut Criterion) {
-    let msg = format!("latency by native Udp");
+    let msg = "latency by native Udp".to_string();
     c.bench_function(&msg, |b| {
         let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
         let addr = receiver.local_addr().unwrap();
@@ -76,7 +76,7 @@ fn latency_by_native_udp(c: &mut Criterion) {
 }
 
 fn latency_by_native_tcp(c: &mut Criterion) {
-    let msg = format!("latency by native Tcp");
+    let msg = "latency by native Tcp".to_string();
     c.bench_function(&msg, |b| {
         let listener = TcpListener::bind("127.0.0.1:0").unwrap();
         let addr = listener.local_addr().unwrap();
@@ -87,14 +87,14 @@ fn latency_by_native_tcp(c: &mut Criterion) {
         let mut buffer: [u8; 1] = [0; 1];
 
         b.iter(|| {
-            sender.write(&[0xFF]).unwrap();
-            receiver.read(&mut buffer).unwrap();
+            sender.write_all(&[0xFF]).unwrap();
+            receiver.read_exact(&mut buffer).unwrap();
         });
     });
 }
 
 fn latency_by_native_framed_tcp(c: &mut Criterion) {
-    let msg = format!("latency by native FramedTcp");
+    let msg = "latency by native FramedTcp".to_string();
     c.bench_function(&msg, |b| {
         let listener = TcpListener::bind("127.0.0.1:0").unwrap();
         let addr = listener.local_addr().unwrap();
@@ -108,8 +108,8 @@ fn latency_by_native_framed_tcp(c: &mut Criterion) {
 
         b.iter(|| {
             let encoded_size = encoding::encode_size(&[0xFF], &mut framming);
-            sender.write(&encoded_size).unwrap();
-            sender.write(&[0xFF]).unwrap();
+            sender.write_all(encoded_size).unwrap();
+            sender.write_all(&[0xFF]).unwrap();
 
             let mut message_received = false;
             while !message_received {
@@ -122,7 +122,7 @@ fn latency_by_native_framed_tcp(c: &mut Criterion) {
 
 #[cfg(feature = "websocket")]
 fn latency_by_native_web_socket(c: &mut Criterion) {
-    let msg = format!("latency by native Ws");
+    let msg = "latency by native Ws".to_string();
     c.bench_function(&msg, |b| {
         let listener = TcpListener::bind("127.0.0.1:0").unwrap();
         let addr = listener.local_addr().unwrap();
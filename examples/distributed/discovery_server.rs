@@ -42,7 +42,7 @@ impl DiscoveryServer {
         let listener = self.listener.take().unwrap();
         listener.for_each(move |event| match event.network() {
             NetEvent::Message(endpoint, input_data) => {
-                let message: Message = bincode::deserialize(&input_data).unwrap();
+                let message: Message = bincode::deserialize(input_data).unwrap();
                 match message {
                     Message::RegisterParticipant(name, addr) => {
                         self.register(&name, addr, endpoint);
@@ -54,7 +54,12 @@ impl DiscoveryServer {
                 }
             }
             NetEvent::Connected(_, _) => (),
-            NetEvent::Disconnected(endpoint) => {
+            NetEvent::ConnectionFailed(..) => unreachable!(),
+            NetEvent::Drained(_) => unreachable!(),
+            NetEvent::Reconnecting(_) | NetEvent::Reconnected(_) => unreachable!(),
+            NetEvent::Closed(..) => unreachable!(),
+            NetEvent::Error(_, err) => println!("Error: {}", err),
+            NetEvent::Disconnected(endpoint, _) => {
                 // Participant disconection without explict unregistration.
                 // We must remove from the registry too.
                 let participant_name = self.participants.iter().find_map(|(name, info)| {
@@ -42,7 +42,7 @@ impl Participant {
             }),
             Err(_) => {
                 println!("Can not connect to the discovery server at {}", discovery_addr);
-                return None
+                None
             }
         }
     }
@@ -56,7 +56,7 @@ impl Participant {
         let listener = self.listener.take().unwrap();
         listener.for_each(move |event| match event.network() {
             NetEvent::Message(_, input_data) => {
-                let message: Message = bincode::deserialize(&input_data).unwrap();
+                let message: Message = bincode::deserialize(input_data).unwrap();
                 match message {
                     Message::ParticipantList(participants) => {
                         println!("Participant list received ({} participants)", participants.len());
@@ -91,7 +91,12 @@ impl Participant {
                 }
             }
             NetEvent::Connected(_, _) => (),
-            NetEvent::Disconnected(endpoint) => {
+            NetEvent::ConnectionFailed(..) => unreachable!(),
+            NetEvent::Drained(_) => unreachable!(),
+            NetEvent::Reconnecting(_) | NetEvent::Reconnected(_) => unreachable!(),
+            NetEvent::Closed(..) => unreachable!(),
+            NetEvent::Error(_, err) => println!("Error: {}", err),
+            NetEvent::Disconnected(endpoint, _) => {
                 if endpoint == self.discovery_endpoint {
                     println!("Discovery server disconnected, closing");
                     self.handler.stop();
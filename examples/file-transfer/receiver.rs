@@ -27,7 +27,7 @@ pub fn run() {
 
     listener.for_each(move |event| match event.network() {
         NetEvent::Message(endpoint, input_data) => {
-            let message: SenderMsg = bincode::deserialize(&input_data).unwrap();
+            let message: SenderMsg = bincode::deserialize(input_data).unwrap();
             match message {
                 SenderMsg::FileRequest(name, size) => {
                     let able = match File::create(format!("{}.recv", name)) {
@@ -49,7 +49,7 @@ pub fn run() {
                 }
                 SenderMsg::Chunk(data) => {
                     let transfer = transfers.get_mut(&endpoint).unwrap();
-                    transfer.file.write(&data).unwrap();
+                    transfer.file.write_all(&data).unwrap();
                     transfer.current_size += data.len();
 
                     let current = transfer.current_size as f32;
@@ -65,7 +65,12 @@ pub fn run() {
             }
         }
         NetEvent::Connected(_, _) => {}
-        NetEvent::Disconnected(endpoint) => {
+        NetEvent::ConnectionFailed(..) => unreachable!(),
+        NetEvent::Drained(_) => unreachable!(),
+        NetEvent::Reconnecting(_) | NetEvent::Reconnected(_) => unreachable!(),
+        NetEvent::Closed(..) => unreachable!(),
+        NetEvent::Error(_, err) => println!("Error: {}", err),
+        NetEvent::Disconnected(endpoint, _) => {
             // Unexpected sender disconnection. Cleaninig.
             if transfers.contains_key(&endpoint) {
                 println!("\nUnexpected Sender disconnected");
@@ -1,11 +1,10 @@
 use super::common::{SenderMsg, ReceiverMsg};
 
-use message_io::network::{NetEvent, Transport};
+use message_io::network::{NetEvent, Transport, SendStatus, SendLimit};
 use message_io::node::{self, NodeEvent};
 
 use std::fs::{self, File};
 use std::io::{Read};
-use std::time::{Duration};
 
 enum Signal {
     SendChunk,
@@ -26,19 +25,24 @@ pub fn run(file_path: String) {
         Err(_) => return println!("Can not connect to the receiver by TCP to {}", server_addr),
     };
 
+    // Avoid to enqueue the whole file in memory if the receiver is slower than the sender.
+    let limit = SendLimit { high_water: 16 * CHUNK_SIZE, low_water: 4 * CHUNK_SIZE };
+    handler.network().set_send_limit(server_id.resource_id(), Some(limit));
+
     let file_size = fs::metadata(&file_path).unwrap().len() as usize;
     let mut file = File::open(&file_path).unwrap();
-    let file_name: String = file_path.rsplit('/').into_iter().next().unwrap_or(&file_path).into();
+    let file_name: String = file_path.rsplit('/').next().unwrap_or(&file_path).into();
 
     let request = SenderMsg::FileRequest(file_name.clone(), file_size);
     let output_data = bincode::serialize(&request).unwrap();
     handler.network().send(server_id, &output_data);
 
     let mut file_bytes_sent = 0;
+    let mut blocked_chunk: Option<Vec<u8>> = None;
     listener.for_each(move |event| match event {
         NodeEvent::Network(net_event) => match net_event {
             NetEvent::Message(_, input_data) => {
-                let message: ReceiverMsg = bincode::deserialize(&input_data).unwrap();
+                let message: ReceiverMsg = bincode::deserialize(input_data).unwrap();
                 match message {
                     ReceiverMsg::CanReceive(can) => match can {
                         true => handler.signals().send(Signal::SendChunk), // Start sending
@@ -50,7 +54,17 @@ pub fn run(file_path: String) {
                 }
             }
             NetEvent::Connected(_, _) => unreachable!(),
-            NetEvent::Disconnected(_) => {
+            NetEvent::ConnectionFailed(..) => unreachable!(),
+            NetEvent::Reconnecting(_) | NetEvent::Reconnected(_) => unreachable!(),
+            NetEvent::Closed(..) => unreachable!(),
+            NetEvent::Error(_, err) => println!("Error: {}", err),
+            NetEvent::Drained(_) => {
+                // The receiver has read enough data, the blocked chunk can be sent now.
+                let output_data = blocked_chunk.take().unwrap();
+                handler.network().send(server_id, &output_data);
+                handler.signals().send(Signal::SendChunk);
+            }
+            NetEvent::Disconnected(..) => {
                 handler.stop();
                 println!("\nReceiver disconnected");
             }
@@ -62,13 +76,16 @@ pub fn run(file_path: String) {
                 if bytes_read > 0 {
                     let chunk = SenderMsg::Chunk(Vec::from(&data[0..bytes_read]));
                     let output_data = bincode::serialize(&chunk).unwrap();
-                    handler.network().send(server_id, &output_data);
                     file_bytes_sent += bytes_read;
 
                     let percentage = ((file_bytes_sent as f32 / file_size as f32) * 100.0) as usize;
                     print!("\rSending '{}': {}%", file_name, percentage);
 
-                    handler.signals().send_with_timer(Signal::SendChunk, Duration::from_micros(10));
+                    match handler.network().send(server_id, &output_data) {
+                        // Wait for the Drained event to continue.
+                        SendStatus::WouldExceedLimit => blocked_chunk = Some(output_data),
+                        _ => handler.signals().send(Signal::SendChunk),
+                    }
                 }
                 else {
                     println!("\nFile sent!");
@@ -25,9 +25,14 @@ fn main() {
 
     listener.for_each(move |event| match event.network() {
         NetEvent::Message(_, data) => {
-            println!("{} greets to the network!", String::from_utf8_lossy(&data));
+            println!("{} greets to the network!", String::from_utf8_lossy(data));
         }
         NetEvent::Connected(_, _) => (),
-        NetEvent::Disconnected(_) => (),
+        NetEvent::ConnectionFailed(..) => unreachable!(),
+        NetEvent::Drained(_) => unreachable!(),
+        NetEvent::Reconnecting(_) | NetEvent::Reconnected(_) => unreachable!(),
+        NetEvent::Closed(..) => unreachable!(),
+        NetEvent::Error(_, err) => println!("Error: {}", err),
+        NetEvent::Disconnected(..) => (),
     });
 }
@@ -38,7 +38,7 @@ pub fn run(transport: Transport, remote_addr: RemoteAddr) {
         },
         NodeEvent::Network(net_event) => match net_event {
             NetEvent::Message(_, input_data) => {
-                let message: FromServerMessage = bincode::deserialize(&input_data).unwrap();
+                let message: FromServerMessage = bincode::deserialize(input_data).unwrap();
                 match message {
                     FromServerMessage::Pong(count) => {
                         println!("Pong from server: {} times", count)
@@ -47,7 +47,12 @@ pub fn run(transport: Transport, remote_addr: RemoteAddr) {
                 }
             }
             NetEvent::Connected(_, _) => unreachable!(), // Only generated when a listener accepts
-            NetEvent::Disconnected(_) => {
+            NetEvent::ConnectionFailed(..) => unreachable!(),
+            NetEvent::Drained(_) => unreachable!(),
+            NetEvent::Reconnecting(_) | NetEvent::Reconnected(_) => unreachable!(),
+            NetEvent::Closed(..) => unreachable!(),
+            NetEvent::Error(_, err) => println!("Error: {}", err),
+            NetEvent::Disconnected(..) => {
                 println!("Server is disconnected");
                 handler.stop();
             }
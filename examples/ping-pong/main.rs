@@ -27,17 +27,15 @@ pub fn main() {
                 let remote_addr = remote_addr.to_remote_addr().unwrap();
                 client::run(transport, remote_addr);
             }
-            None => return println!("{}", HELP_MSG),
+            None => println!("{}", HELP_MSG),
         },
-        "server" => {
-            match args.get(3).unwrap_or(&"".into()).parse() {
-                Ok(port) => {
-                    let addr = ("0.0.0.0", port).to_socket_addrs().unwrap().next().unwrap();
-                    server::run(transport, addr);
-                }
-                Err(_) => return println!("{}", HELP_MSG),
-            };
-        }
-        _ => return println!("{}", HELP_MSG),
+        "server" => match args.get(3).unwrap_or(&"".into()).parse() {
+            Ok(port) => {
+                let addr = ("0.0.0.0", port).to_socket_addrs().unwrap().next().unwrap();
+                server::run(transport, addr);
+            }
+            Err(_) => println!("{}", HELP_MSG),
+        },
+        _ => println!("{}", HELP_MSG),
     }
 }
@@ -24,7 +24,7 @@ pub fn run(transport: Transport, addr: SocketAddr) {
 
     listener.for_each(move |event| match event.network() {
         NetEvent::Message(endpoint, input_data) => {
-            let message: FromClientMessage = bincode::deserialize(&input_data).unwrap();
+            let message: FromClientMessage = bincode::deserialize(input_data).unwrap();
             match message {
                 FromClientMessage::Ping => {
                     let message = match clients.get_mut(&endpoint) {
@@ -50,7 +50,12 @@ pub fn run(transport: Transport, addr: SocketAddr) {
             clients.insert(endpoint, ClientInfo { count: 0 });
             println!("Client ({}) connected (total clients: {})", endpoint.addr(), clients.len());
         }
-        NetEvent::Disconnected(endpoint) => {
+        NetEvent::ConnectionFailed(..) => unreachable!(),
+        NetEvent::Drained(_) => unreachable!(),
+        NetEvent::Reconnecting(_) | NetEvent::Reconnected(_) => unreachable!(),
+        NetEvent::Closed(..) => unreachable!(),
+        NetEvent::Error(_, err) => println!("Error: {}", err),
+        NetEvent::Disconnected(endpoint, _) => {
             // Only connection oriented protocols will generate this event
             clients.remove(&endpoint).unwrap();
             println!(
@@ -23,7 +23,7 @@ const EXPECTED_BYTES: usize = 1024 * 1024 * 1024; // 1GB
 // This does not means that MacOS running slower,
 // since real environments uses an MTU of arround 1500 bytes.
 const CHUNK: usize =
-    if cfg!(macos) { udp::MAX_COMPATIBLE_PAYLOAD_LEN } else { udp::MAX_PAYLOAD_LEN };
+    if cfg!(target_os = "macos") { udp::MAX_COMPATIBLE_PAYLOAD_LEN } else { udp::MAX_PAYLOAD_LEN };
 
 fn main() {
     println!("Sending 1GB in chunks of {} bytes:\n", CHUNK);
@@ -31,7 +31,7 @@ fn main() {
     throughput_message_io(Transport::Tcp, CHUNK);
     throughput_message_io(Transport::FramedTcp, CHUNK);
     throughput_message_io(Transport::Ws, CHUNK);
-    println!("");
+    println!();
     throughput_native_udp(CHUNK);
     throughput_native_tcp(CHUNK);
     throughput_native_framed_tcp(CHUNK);
@@ -66,7 +66,12 @@ fn throughput_message_io(transport: Transport, packet_size: usize) {
                     t_time.send(Instant::now()).unwrap();
                 }
             }
-            NetEvent::Disconnected(_) => (),
+            NetEvent::ConnectionFailed(..) => unreachable!(),
+            NetEvent::Drained(_) => unreachable!(),
+            NetEvent::Reconnecting(_) | NetEvent::Reconnected(_) => unreachable!(),
+            NetEvent::Closed(..) => unreachable!(),
+            NetEvent::Error(_, err) => println!("Error: {}", err),
+            NetEvent::Disconnected(..) => (),
         })
     };
 
@@ -151,7 +156,7 @@ fn throughput_native_tcp(packet_size: usize) {
 
                 let start_time = Instant::now();
                 while total_sent < EXPECTED_BYTES {
-                    sender.write(&message).unwrap();
+                    sender.write_all(&message).unwrap();
                     total_sent += message.len();
                 }
                 start_time
@@ -193,8 +198,8 @@ fn throughput_native_framed_tcp(packet_size: usize) {
                 let start_time = Instant::now();
                 while total_sent < EXPECTED_BYTES {
                     let encoded_size = encoding::encode_size(&message, &mut framming);
-                    sender.write(&encoded_size).unwrap();
-                    sender.write(&message).unwrap();
+                    sender.write_all(encoded_size).unwrap();
+                    sender.write_all(&message).unwrap();
                     total_sent += message.len();
                 }
                 start_time
@@ -268,14 +273,14 @@ impl std::fmt::Display for ThroughputMeasure {
         if bytes_per_sec < 1000.0 {
             write!(f, "{:.2} B/s", bytes_per_sec)
         }
-        else if bytes_per_sec < 1000_000.0 {
+        else if bytes_per_sec < 1_000_000.0 {
             write!(f, "{:.2} KB/s", bytes_per_sec / 1000.0)
         }
-        else if bytes_per_sec < 1000_000_000.0 {
-            write!(f, "{:.2} MB/s", bytes_per_sec / 1000_000.0)
+        else if bytes_per_sec < 1_000_000_000.0 {
+            write!(f, "{:.2} MB/s", bytes_per_sec / 1_000_000.0)
         }
         else {
-            write!(f, "{:.2} GB/s", bytes_per_sec / 1000_000_000.0)
+            write!(f, "{:.2} GB/s", bytes_per_sec / 1_000_000_000.0)
         }
     }
 }
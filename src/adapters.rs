@@ -1,4 +1,14 @@
 mod template;
+#[cfg(any(feature = "tcp", feature = "websocket", feature = "tls"))]
+mod connecting;
+#[cfg(any(feature = "tcp", feature = "websocket", feature = "tls"))]
+mod proxy;
+#[cfg(any(feature = "tcp", feature = "websocket"))]
+mod proxy_protocol;
+#[cfg(any(feature = "memory", feature = "websocket", feature = "tls"))]
+mod no_source;
+#[cfg(any(feature = "tcp", feature = "tls", all(unix, feature = "unix")))]
+mod buffered_stream;
 
 #[cfg(feature = "tcp")]
 pub mod tcp;
@@ -8,5 +18,17 @@ pub mod framed_tcp;
 pub mod udp;
 #[cfg(feature = "websocket")]
 pub mod ws;
+#[cfg(feature = "tls")]
+pub mod framed_tls;
+#[cfg(all(unix, feature = "unix"))]
+pub mod unix_stream;
+#[cfg(all(unix, feature = "unix"))]
+pub mod unix_datagram;
+#[cfg(feature = "quic")]
+pub mod quic;
+#[cfg(feature = "udp")]
+pub mod reliable_udp;
+#[cfg(feature = "memory")]
+pub mod memory;
 // Add new adapters here
 // ...
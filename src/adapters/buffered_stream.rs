@@ -0,0 +1,180 @@
+use crate::network::adapter::{SendStatus, ResourceWaker};
+
+use mio::event::{Source};
+#[cfg(any(feature = "tcp", feature = "tls"))]
+use mio::net::{TcpStream};
+#[cfg(all(unix, feature = "unix"))]
+use mio::net::{UnixStream};
+
+#[cfg(feature = "tls")]
+use native_tls::{TlsStream};
+
+use std::collections::{VecDeque};
+use std::io::{self, ErrorKind, Write};
+
+/// Stream that can be buffered by a [`BufferedStream`].
+pub trait StreamSource: Write + Send + 'static {
+    /// The source registered in the poll for the stream.
+    fn source(&mut self) -> &mut dyn Source;
+}
+
+#[cfg(feature = "tcp")]
+impl StreamSource for TcpStream {
+    fn source(&mut self) -> &mut dyn Source {
+        self
+    }
+}
+
+#[cfg(all(unix, feature = "unix"))]
+impl StreamSource for UnixStream {
+    fn source(&mut self) -> &mut dyn Source {
+        self
+    }
+}
+
+#[cfg(feature = "tls")]
+impl StreamSource for TlsStream<TcpStream> {
+    fn source(&mut self) -> &mut dyn Source {
+        self.get_mut()
+    }
+}
+
+/// Non-blocking stream that keeps the data that could not be written without blocking.
+/// While there is pending data, the stream is registered for writable events
+/// in order to write it from the poll thread by [`BufferedStream::flush()`].
+pub struct BufferedStream<S: StreamSource> {
+    stream: S,
+    pending: VecDeque<u8>,
+    waker: Option<ResourceWaker>,
+}
+
+impl<S: StreamSource> BufferedStream<S> {
+    pub fn new(stream: S) -> Self {
+        Self { stream, pending: VecDeque::new(), waker: None }
+    }
+
+    #[cfg(any(feature = "tcp", all(unix, feature = "unix")))]
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.stream
+    }
+
+    /// Must be called from [`crate::network::adapter::Resource::set_waker()`].
+    pub fn set_waker(&mut self, waker: ResourceWaker) {
+        self.waker = Some(waker);
+    }
+
+    /// Number of bytes that are waiting to be written.
+    pub fn enqueued_size(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Sends the chunks in order as a whole.
+    /// The part that can not be written without blocking is enqueued.
+    pub fn send(&mut self, chunks: &[&[u8]]) -> SendStatus {
+        if !self.pending.is_empty() {
+            // To keep the order, it can not be written before the data already enqueued.
+            chunks.iter().for_each(|chunk| self.pending.extend(*chunk));
+            return SendStatus::Enqueued
+        }
+
+        for (index, chunk) in chunks.iter().enumerate() {
+            match write_available(self.get_mut(), chunk) {
+                Ok(size) if size == chunk.len() => (),
+                Ok(size) => {
+                    self.pending.extend(&chunk[size..]);
+                    chunks[index + 1..].iter().for_each(|chunk| self.pending.extend(*chunk));
+                    return match self.set_writable(true) {
+                        Ok(()) => SendStatus::Enqueued,
+                        Err(err) => {
+                            log::error!("Stream writable registration error: {}", err);
+                            self.pending.clear();
+                            SendStatus::ResourceNotFound
+                        }
+                    }
+                }
+                // Others errors are considered fatal for the connection.
+                // a Event::Disconnection will be generated later.
+                Err(err) => {
+                    log::error!("Stream send error: {}", err);
+                    return SendStatus::ResourceNotFound
+                }
+            }
+        }
+        SendStatus::Sent
+    }
+
+    /// Writes the pending data without blocking.
+    /// Once all of it has been written, the stream is no longer registered for writable events.
+    /// If there is an error, the pending data is discarded.
+    pub fn flush(&mut self) -> io::Result<()> {
+        if self.pending.is_empty() {
+            return Ok(())
+        }
+
+        match self.write_pending() {
+            Ok(true) => self.set_writable(false),
+            Ok(false) => Ok(()),
+            Err(err) => {
+                self.pending.clear();
+                Err(err)
+            }
+        }
+    }
+
+    /// Returns `true` if all the pending data has been written.
+    fn write_pending(&mut self) -> io::Result<bool> {
+        write_pending(&mut self.stream, &mut self.pending)
+    }
+
+    fn set_writable(&mut self, writable: bool) -> io::Result<()> {
+        match &self.waker {
+            Some(waker) => waker.set_writable(self.stream.source(), writable),
+            None => Ok(()), // Not registered yet, it can not be sending.
+        }
+    }
+}
+
+impl<S: StreamSource> Drop for BufferedStream<S> {
+    fn drop(&mut self) {
+        // At this point, the stream is already deregistered from the poll,
+        // so nobody would write the pending data. It is written by a graceful close.
+        if !self.pending.is_empty() {
+            log::error!("Stream closed with {} bytes not sent", self.pending.len());
+        }
+    }
+}
+
+/// Writes the pending data without blocking.
+/// Returns `true` if all the pending data has been written.
+fn write_pending(stream: &mut impl Write, pending: &mut VecDeque<u8>) -> io::Result<bool> {
+    while !pending.is_empty() {
+        let (data, _) = pending.as_slices();
+        let size = write_available(stream, data)?;
+        let would_block = size < data.len();
+        pending.drain(..size);
+        if would_block {
+            return Ok(false)
+        }
+    }
+    Ok(true)
+}
+
+/// Writes as much data as possible without blocking.
+/// Returns the number of bytes written.
+fn write_available(stream: &mut impl Write, data: &[u8]) -> io::Result<usize> {
+    let mut total_bytes_sent = 0;
+    while total_bytes_sent < data.len() {
+        match stream.write(&data[total_bytes_sent..]) {
+            Ok(0) => return Err(ErrorKind::WriteZero.into()),
+            Ok(bytes_sent) => total_bytes_sent += bytes_sent,
+            Err(ref err) if err.kind() == ErrorKind::Interrupted => continue,
+            Err(ref err) if err.kind() == ErrorKind::WouldBlock => break,
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(total_bytes_sent)
+}
@@ -0,0 +1,236 @@
+use crate::network::adapter::{PendingStatus};
+use crate::network::{ConnectOptions};
+#[cfg(any(feature = "tcp", feature = "tls"))]
+use crate::network::{RemoteAddr};
+use crate::error::{Error};
+use super::proxy::{self};
+
+use mio::net::{TcpStream};
+
+use socket2::{Socket, Domain, Type, Protocol, SockAddr};
+#[cfg(any(feature = "websocket", feature = "tls"))]
+use socket2::{SockRef};
+
+use std::net::{SocketAddr, ToSocketAddrs, TcpStream as StdTcpStream};
+use std::time::{Duration, Instant};
+use std::io::{self, ErrorKind};
+
+/// Peer of a TCP connection, as given by the user.
+/// A host name is resolved locally only if the connection is not performed through a proxy,
+/// otherwise it is given to the proxy to be resolved by it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TcpPeer {
+    Addr(SocketAddr),
+    Host(String, u16),
+}
+
+impl TcpPeer {
+    /// Creates the peer of an IP address, or of a `'host:port'` string.
+    /// The brackets of an IPv6 host are removed.
+    #[cfg(any(feature = "tcp", feature = "tls"))]
+    pub fn from_remote_addr(remote_addr: &RemoteAddr) -> io::Result<Self> {
+        match remote_addr {
+            RemoteAddr::Socket(addr) => Ok(TcpPeer::Addr(*addr)),
+            RemoteAddr::Str(host_port) => match host_port.rsplit_once(':') {
+                Some((host, port)) => match port.parse() {
+                    Ok(port) => Ok(TcpPeer::new(host, port)),
+                    Err(_) => Err(Error::UnresolvableHost(host_port.clone()).into()),
+                },
+                None => Err(Error::UnresolvableHost(host_port.clone()).into()),
+            },
+            RemoteAddr::Path(path) => {
+                let err = format!("the path '{}' is not a socket address", path.display());
+                Err(Error::InvalidAddress(err).into())
+            }
+        }
+    }
+
+    /// Creates the peer of a host, that can be an IP address.
+    pub fn new(host: &str, port: u16) -> Self {
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        match host.parse() {
+            Ok(ip) => TcpPeer::Addr(SocketAddr::new(ip, port)),
+            Err(_) => TcpPeer::Host(host.to_string(), port),
+        }
+    }
+
+    /// Resolves the address of the peer locally.
+    fn resolve(&self) -> io::Result<SocketAddr> {
+        match self {
+            TcpPeer::Addr(addr) => Ok(*addr),
+            TcpPeer::Host(host, port) => (host.as_str(), *port)
+                .to_socket_addrs()
+                .ok()
+                .and_then(|mut addrs| addrs.next())
+                .ok_or_else(|| Error::UnresolvableHost(self.to_string()).into()),
+        }
+    }
+
+    /// Returns the address to connect, along with the address of the peer.
+    /// With a proxy, the address of the peer is only known if it is not a host name:
+    /// otherwise the address of the proxy is used as the address of the peer.
+    fn resolve_with(&self, options: &ConnectOptions) -> io::Result<(SocketAddr, SocketAddr)> {
+        match &options.proxy {
+            Some(proxy) => {
+                let proxy_addr = proxy::resolve(proxy)?;
+                match self {
+                    TcpPeer::Addr(addr) => Ok((proxy_addr, *addr)),
+                    TcpPeer::Host(..) => Ok((proxy_addr, proxy_addr)),
+                }
+            }
+            None => self.resolve().map(|addr| (addr, addr)),
+        }
+    }
+}
+
+impl std::fmt::Display for TcpPeer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TcpPeer::Addr(addr) => write!(f, "{}", addr),
+            TcpPeer::Host(host, port) if host.contains(':') => write!(f, "[{}]:{}", host, port),
+            TcpPeer::Host(host, port) => write!(f, "{}:{}", host, port),
+        }
+    }
+}
+
+/// Creates a TCP socket of the same family as `addr`,
+/// ready to be configured before connecting or listening.
+pub fn tcp_socket(addr: SocketAddr) -> io::Result<Socket> {
+    Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))
+}
+
+/// Binds the socket to the local address of the options, if any.
+fn tcp_bind(socket: &Socket, options: &ConnectOptions) -> io::Result<()> {
+    match options.local_addr {
+        Some(local_addr) => socket.bind(&SockAddr::from(local_addr)),
+        None => Ok(()),
+    }
+}
+
+/// Performs a blocking TCP connection that fails if it takes more than the options timeout.
+/// The socket is created by `socket` for the address that is connected:
+/// the address of the proxy of the options if any, or the address of `peer` otherwise.
+/// Returns the stream, already non-blocking, along with its local and peer addresses.
+pub fn tcp_connect(
+    peer: &TcpPeer,
+    options: &ConnectOptions,
+    socket: impl FnOnce(SocketAddr) -> io::Result<Socket>,
+) -> io::Result<(TcpStream, SocketAddr, SocketAddr)> {
+    let deadline = Deadline::new(options.timeout);
+    let (connect_addr, peer_addr) = peer.resolve_with(options)?;
+
+    let socket = socket(connect_addr)?;
+    tcp_bind(&socket, options)?;
+    match deadline.remaining()? {
+        Some(timeout) => socket.connect_timeout(&SockAddr::from(connect_addr), timeout)?,
+        None => socket.connect(&SockAddr::from(connect_addr))?,
+    };
+
+    if let Some(proxy) = &options.proxy {
+        let timeout = deadline.remaining()?;
+        socket.set_read_timeout(timeout)?;
+        socket.set_write_timeout(timeout)?;
+        proxy::handshake(&socket, proxy, peer)?;
+        socket.set_read_timeout(None)?;
+        socket.set_write_timeout(None)?;
+    }
+
+    let stream = StdTcpStream::from(socket);
+    let local_addr = stream.local_addr()?;
+    stream.set_nonblocking(true)?;
+    Ok((TcpStream::from_std(stream), local_addr, peer_addr))
+}
+
+/// Sets a stream created by [`tcp_connect()`] as blocking to perform a handshake over it,
+/// with read and write timeouts given by the remaining time until the `deadline`.
+/// It must be called again each time the handshake is interrupted by a timeout,
+/// returning a `TimedOut` error once the deadline is reached.
+#[cfg(any(feature = "websocket", feature = "tls"))]
+pub fn set_blocking(stream: &TcpStream, deadline: &Deadline) -> io::Result<()> {
+    let timeout = deadline.remaining()?;
+    let socket = SockRef::from(stream);
+    socket.set_nonblocking(false)?;
+    socket.set_read_timeout(timeout)?;
+    socket.set_write_timeout(timeout)
+}
+
+/// Sets a stream as non-blocking again once its handshake has finished.
+#[cfg(any(feature = "websocket", feature = "tls"))]
+pub fn set_nonblocking(stream: &TcpStream) -> io::Result<()> {
+    let socket = SockRef::from(stream);
+    socket.set_read_timeout(None)?;
+    socket.set_write_timeout(None)?;
+    socket.set_nonblocking(true)
+}
+
+/// Starts a non-blocking TCP connection.
+/// The socket is created by `socket` as in [`tcp_connect()`].
+/// If the options have a proxy, the connection and the proxy handshake are performed
+/// synchronously by [`tcp_connect()`].
+/// Returns the stream along with its local and peer addresses.
+pub fn tcp_connect_async(
+    peer: &TcpPeer,
+    options: &ConnectOptions,
+    socket: impl FnOnce(SocketAddr) -> io::Result<Socket>,
+) -> io::Result<(TcpStream, SocketAddr, SocketAddr)> {
+    if options.proxy.is_some() {
+        return tcp_connect(peer, options, socket)
+    }
+
+    let peer_addr = peer.resolve()?;
+    let socket = socket(peer_addr)?;
+    tcp_bind(&socket, options)?;
+    socket.set_nonblocking(true)?;
+    match socket.connect(&SockAddr::from(peer_addr)) {
+        Ok(()) => (),
+        Err(ref err) if is_in_progress(err) => (),
+        Err(err) => return Err(err),
+    }
+    let stream = TcpStream::from_std(StdTcpStream::from(socket));
+    let local_addr = stream.local_addr()?;
+    Ok((stream, local_addr, peer_addr))
+}
+
+fn is_in_progress(err: &io::Error) -> bool {
+    #[cfg(unix)]
+    if err.raw_os_error() == Some(libc::EINPROGRESS) {
+        return true
+    }
+    err.kind() == ErrorKind::WouldBlock
+}
+
+/// Checks the status of a TCP stream created by [`tcp_connect_async()`].
+pub fn tcp_pending(stream: &TcpStream) -> PendingStatus {
+    match stream.take_error() {
+        Ok(Some(err)) | Err(err) => return PendingStatus::Failed(err),
+        Ok(None) => (),
+    }
+
+    match stream.peer_addr() {
+        Ok(_) => PendingStatus::Ready,
+        Err(ref err) if err.kind() == ErrorKind::NotConnected => PendingStatus::Incomplete,
+        Err(err) => PendingStatus::Failed(err),
+    }
+}
+
+/// Time limit of a connection that must be performed in at most `timeout`.
+#[derive(Clone, Copy)]
+pub struct Deadline(Option<Instant>);
+
+impl Deadline {
+    /// A `timeout` too long to be represented as an instant means no deadline.
+    pub fn new(timeout: Option<Duration>) -> Self {
+        Self(timeout.and_then(|timeout| Instant::now().checked_add(timeout)))
+    }
+
+    /// Remaining time until the deadline, or a `TimedOut` error if it was already reached.
+    pub fn remaining(&self) -> io::Result<Option<Duration>> {
+        match self.0 {
+            Some(instant) => match instant.checked_duration_since(Instant::now()) {
+                Some(remaining) if remaining > Duration::ZERO => Ok(Some(remaining)),
+                _ => Err(io::Error::new(ErrorKind::TimedOut, "The connection timed out")),
+            },
+            None => Ok(None),
+        }
+    }
+}
@@ -1,21 +1,137 @@
 use crate::network::adapter::{
-    Resource, Remote, Local, Adapter, SendStatus, AcceptedType, ReadStatus, ConnectionInfo,
-    ListeningInfo,
+    Resource, Remote, Local, Adapter, SendStatus, AcceptedType, AcceptStatus, ReadStatus,
+    DisconnectReason, ConnectionInfo, ListeningInfo, PendingStatus, ResourceWaker,
+    wrong_config_error,
 };
-use crate::network::{RemoteAddr};
-use crate::util::encoding::{self, Decoder, MAX_ENCODED_SIZE};
+use crate::network::{
+    RemoteAddr, TransportConnect, TransportListen, ConnectOptions, ListenOptions, CloseReason,
+};
+use crate::util::encoding::{self, Decoder, Frame, MAX_ENCODED_SIZE};
+use crate::util::thread::{OTHER_THREAD_ERR};
+use super::connecting::{self, TcpPeer};
+use super::buffered_stream::{BufferedStream};
+use super::proxy_protocol::{ProxyProtocolAcceptor};
+use super::tcp::{TcpSocketOptions};
 
 use mio::net::{TcpListener, TcpStream};
 use mio::event::{Source};
 
-use std::net::{SocketAddr, TcpStream as StdTcpStream};
-use std::io::{self, ErrorKind, Read, Write};
-use std::ops::{Deref};
+use socket2::{SockRef};
+
+use std::net::{SocketAddr, Shutdown};
+use std::time::{Duration, Instant};
+use std::io::{self, ErrorKind, Read};
+use std::sync::{Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::cell::{RefCell};
 use std::mem::{MaybeUninit};
 
 const INPUT_BUFFER_SIZE: usize = u16::MAX as usize; // 2^16 - 1
 
+/// Control frame codes.
+const HEARTBEAT_REQUEST: u8 = 0;
+const HEARTBEAT_RESPONSE: u8 = 1;
+
+/// Configuration used by the `FramedTcp` transport to connect.
+#[derive(Clone, Debug, Default)]
+pub struct FramedTcpConnectConfig {
+    /// Options of the socket, applied before connecting.
+    pub socket_options: TcpSocketOptions,
+
+    /// Heartbeats sent to detect if the peer is lost.
+    pub heartbeat: Option<Heartbeat>,
+}
+
+/// Configuration used by the `FramedTcp` transport to listen.
+#[derive(Clone, Debug, Default)]
+pub struct FramedTcpListenConfig {
+    /// Options of the listening socket, applied before binding the address.
+    /// The `nodelay` and `keepalive` options are applied to each accepted connection.
+    pub socket_options: TcpSocketOptions,
+
+    /// Max number of pending connections waiting to be accepted (the `listen()` backlog).
+    /// If it is `None`, [`DEFAULT_BACKLOG`](super::tcp::DEFAULT_BACKLOG) is used.
+    pub backlog: Option<i32>,
+
+    /// Heartbeats sent by each accepted connection to detect if its peer is lost.
+    pub heartbeat: Option<Heartbeat>,
+}
+
+/// Heartbeats sent by a `FramedTcp` connection to detect a peer that was lost
+/// without closing the connection (e.g. a half-open connection).
+/// They are set in the [`FramedTcpConnectConfig`] and
+/// the [`FramedTcpListenConfig`] of the connections.
+///
+/// Each `interval`, the connection sends a heartbeat that the peer answers,
+/// without generating messages to the users of both sides.
+/// The peer answers it even if it has not set the heartbeats.
+/// If `max_missed` intervals pass without receiving any data, the connection is closed
+/// and a [`NetEvent::Disconnected`](crate::network::NetEvent::Disconnected) is generated.
+///
+/// The heartbeats are control frames (`[0x80 | code, 0x00]`) that the versions of `FramedTcp`
+/// previous to them do not know: such a peer decodes them as the size of a message
+/// and takes the next bytes of the stream as its data, breaking the connection.
+/// Only set them if the peer also supports them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Heartbeat {
+    /// Time between two heartbeats.
+    pub interval: Duration,
+
+    /// Number of consecutive intervals without receiving data to consider the peer lost.
+    /// It should be at least `2` to give time to the peer to answer the last heartbeat.
+    pub max_missed: u32,
+}
+
+/// State of the heartbeats of a connection.
+struct HeartbeatState {
+    heartbeat: Heartbeat,
+    waker: Option<ResourceWaker>,
+    next_beat: Option<Instant>, // None if the interval can not be represented: no more beats.
+    missed: u32,
+    received: bool,
+}
+
+impl HeartbeatState {
+    fn new(heartbeat: Heartbeat) -> Self {
+        let next_beat = Instant::now().checked_add(heartbeat.interval);
+        // A new connection is considered alive during its first interval.
+        Self { heartbeat, waker: None, next_beat, missed: 0, received: true }
+    }
+
+    fn set_waker(&mut self, waker: ResourceWaker) {
+        if let Some(next_beat) = self.next_beat {
+            waker.wake_at(next_beat);
+        }
+        self.waker = Some(waker);
+    }
+
+    /// Once the interval is completed, counts it as missed if no data was received
+    /// and calls `send_beat` to send the next heartbeat.
+    /// Returns `false` if the peer is considered lost.
+    fn beat(&mut self, send_beat: impl FnOnce()) -> bool {
+        let now = Instant::now();
+        match self.next_beat {
+            Some(next_beat) if now >= next_beat => (),
+            _ => return true,
+        }
+
+        match std::mem::take(&mut self.received) {
+            true => self.missed = 0,
+            false => self.missed += 1,
+        }
+        if self.missed >= self.heartbeat.max_missed {
+            return false
+        }
+
+        send_beat();
+        self.next_beat = now.checked_add(self.heartbeat.interval);
+        if let (Some(waker), Some(next_beat)) = (&self.waker, self.next_beat) {
+            waker.wake_at(next_beat);
+        }
+        true
+    }
+}
+
 pub(crate) struct FramedTcpAdapter;
 impl Adapter for FramedTcpAdapter {
     type Remote = RemoteResource;
@@ -23,50 +139,114 @@ impl Adapter for FramedTcpAdapter {
 }
 
 pub(crate) struct RemoteResource {
-    stream: TcpStream,
+    stream: Mutex<BufferedStream<TcpStream>>,
     decoder: RefCell<Decoder>,
+    heartbeat: Option<RefCell<HeartbeatState>>,
+    closed: AtomicBool, // The writing side was closed by close()
 }
 
 // SAFETY:
 // That RefCell<Decoder> can be used with Sync because the decoder is only used in the read_event.
+// The same happens with the RefCell<HeartbeatState>.
 // This way, we save the cost of a Mutex.
 unsafe impl Sync for RemoteResource {}
 
-impl From<TcpStream> for RemoteResource {
-    fn from(stream: TcpStream) -> Self {
-        Self { stream, decoder: RefCell::new(Decoder::default()) }
+impl RemoteResource {
+    fn new(stream: TcpStream, heartbeat: Option<Heartbeat>) -> Self {
+        Self {
+            stream: Mutex::new(BufferedStream::new(stream)),
+            decoder: RefCell::new(Decoder::default()),
+            heartbeat: heartbeat.map(|heartbeat| RefCell::new(HeartbeatState::new(heartbeat))),
+            closed: AtomicBool::new(false),
+        }
+    }
+
+    fn send_control(&self, code: u8) {
+        if self.closed.load(Ordering::Acquire) {
+            return
+        }
+        let frame = encoding::encode_control(code);
+        let status = self.stream.lock().expect(OTHER_THREAD_ERR).send(&[&frame]);
+        log::trace!("Framed TCP control frame {} send status: {:?}", code, status);
+    }
+
+    fn config(config: TransportConnect) -> io::Result<FramedTcpConnectConfig> {
+        #[allow(unreachable_patterns)]
+        match config {
+            TransportConnect::FramedTcp(config) => Ok(config),
+            _ => Err(wrong_config_error()),
+        }
     }
 }
 
 impl Resource for RemoteResource {
     fn source(&mut self) -> &mut dyn Source {
-        &mut self.stream
+        self.stream.get_mut().expect(OTHER_THREAD_ERR).get_mut()
+    }
+
+    fn set_waker(&mut self, waker: ResourceWaker) {
+        if let Some(heartbeat) = &mut self.heartbeat {
+            heartbeat.get_mut().set_waker(waker.clone());
+        }
+        self.stream.get_mut().expect(OTHER_THREAD_ERR).set_waker(waker);
     }
 }
 
 impl Remote for RemoteResource {
-    fn connect(remote_addr: RemoteAddr) -> io::Result<ConnectionInfo<Self>> {
-        let peer_addr = *remote_addr.socket_addr();
-        let stream = StdTcpStream::connect(peer_addr)?;
-        let local_addr = stream.local_addr()?;
-        stream.set_nonblocking(true)?;
-        Ok(ConnectionInfo { remote: TcpStream::from_std(stream).into(), local_addr, peer_addr })
+    fn connect(
+        config: TransportConnect,
+        remote_addr: RemoteAddr,
+        options: ConnectOptions,
+    ) -> io::Result<ConnectionInfo<Self>> {
+        let peer = TcpPeer::from_remote_addr(&remote_addr)?;
+        let config = Self::config(config)?;
+        let (stream, local_addr, peer_addr) = connecting::tcp_connect(&peer, &options, |addr| {
+            config.socket_options.connect_socket(addr)
+        })?;
+        let remote = RemoteResource::new(stream, config.heartbeat);
+        Ok(ConnectionInfo { remote, local_addr, peer_addr })
+    }
+
+    fn connect_async(
+        config: TransportConnect,
+        remote_addr: RemoteAddr,
+        options: ConnectOptions,
+    ) -> io::Result<ConnectionInfo<Self>> {
+        let peer = TcpPeer::from_remote_addr(&remote_addr)?;
+        let config = Self::config(config)?;
+        let (stream, local_addr, peer_addr) =
+            connecting::tcp_connect_async(&peer, &options, |addr| {
+                config.socket_options.connect_socket(addr)
+            })?;
+        let remote = RemoteResource::new(stream, config.heartbeat);
+        Ok(ConnectionInfo { remote, local_addr, peer_addr })
+    }
+
+    fn pending(&self) -> PendingStatus {
+        connecting::tcp_pending(self.stream.lock().expect(OTHER_THREAD_ERR).get_ref())
     }
 
     fn receive(&self, mut process_data: impl FnMut(&[u8])) -> ReadStatus {
         let buffer: MaybeUninit<[u8; INPUT_BUFFER_SIZE]> = MaybeUninit::uninit();
         let mut input_buffer = unsafe { buffer.assume_init() }; // Avoid to initialize the array
 
-        loop {
-            let stream = &self.stream;
-            match stream.deref().read(&mut input_buffer) {
-                Ok(0) => break ReadStatus::Disconnected,
+        let status = loop {
+            // The lock is not kept while processing the data, the user could send from there.
+            let result =
+                self.stream.lock().expect(OTHER_THREAD_ERR).get_mut().read(&mut input_buffer);
+            match result {
+                Ok(0) => break ReadStatus::Disconnected(DisconnectReason::Closed),
                 Ok(size) => {
                     let data = &input_buffer[..size];
-                    let addr = self.stream.peer_addr().unwrap();
-                    log::trace!("Decoding data from {}, {} bytes", addr, data.len());
-                    self.decoder.borrow_mut().decode(data, |decoded_data| {
-                        process_data(decoded_data);
+                    log::trace!("Decoding data, {} bytes", data.len());
+                    if let Some(heartbeat) = &self.heartbeat {
+                        heartbeat.borrow_mut().received = true;
+                    }
+                    self.decoder.borrow_mut().decode_frames(data, |frame| match frame {
+                        Frame::Message(decoded_data) => process_data(decoded_data),
+                        Frame::Control(HEARTBEAT_REQUEST) => self.send_control(HEARTBEAT_RESPONSE),
+                        Frame::Control(HEARTBEAT_RESPONSE) => (),
+                        Frame::Control(code) => log::trace!("Unknown control frame: {}", code),
                     });
                 }
                 Err(ref err) if err.kind() == ErrorKind::Interrupted => continue,
@@ -74,73 +254,144 @@ impl Remote for RemoteResource {
                     break ReadStatus::WaitNextEvent
                 }
                 Err(ref err) if err.kind() == ErrorKind::ConnectionReset => {
-                    break ReadStatus::Disconnected
+                    break ReadStatus::Disconnected(DisconnectReason::Reset)
                 }
                 Err(err) => {
                     log::error!("TCP receive error: {}", err);
-                    break ReadStatus::Disconnected // should not happen
+                    break ReadStatus::Disconnected(DisconnectReason::from(&err))
+                }
+            }
+        };
+
+        // Once closed, the connection only waits for the peer to close its side.
+        let closed = self.closed.load(Ordering::Acquire);
+        match (status, &self.heartbeat) {
+            (ReadStatus::WaitNextEvent, Some(heartbeat)) if !closed => {
+                match heartbeat.borrow_mut().beat(|| self.send_control(HEARTBEAT_REQUEST)) {
+                    true => ReadStatus::WaitNextEvent,
+                    false => {
+                        log::trace!("Framed TCP peer lost: no heartbeat answered");
+                        ReadStatus::Disconnected(DisconnectReason::Timeout)
+                    }
                 }
             }
+            (status, _) => status,
         }
     }
 
     fn send(&self, data: &[u8]) -> SendStatus {
         let mut buf = [0; MAX_ENCODED_SIZE]; // used to avoid a heap allocation
         let encoded_size = encoding::encode_size(data, &mut buf);
+        self.stream.lock().expect(OTHER_THREAD_ERR).send(&[encoded_size, data])
+    }
 
-        let mut total_bytes_sent = 0;
-        let total_bytes = encoded_size.len() + data.len();
-        loop {
-            let data_to_send = match total_bytes_sent < encoded_size.len() {
-                true => &encoded_size[total_bytes_sent..],
-                false => &data[total_bytes_sent - encoded_size.len()..],
-            };
-
-            let stream = &self.stream;
-            match stream.deref().write(data_to_send) {
-                Ok(bytes_sent) => {
-                    total_bytes_sent += bytes_sent;
-                    if total_bytes_sent == total_bytes {
-                        break SendStatus::Sent
-                    }
-                }
-                Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => continue,
-                Err(err) => {
-                    log::error!("TCP receive error: {}", err);
-                    break SendStatus::ResourceNotFound // should not happen
-                }
-            }
+    fn flush(&self) {
+        if let Err(err) = self.stream.lock().expect(OTHER_THREAD_ERR).flush() {
+            log::error!("TCP send error: {}", err); // The disconnection will be read later.
         }
     }
+
+    fn enqueued_size(&self) -> usize {
+        self.stream.lock().expect(OTHER_THREAD_ERR).enqueued_size()
+    }
+
+    fn close(&self, _reason: &CloseReason) -> bool {
+        let stream = self.stream.lock().expect(OTHER_THREAD_ERR);
+        self.closed.store(true, Ordering::Release);
+        if let Err(err) = stream.get_ref().shutdown(Shutdown::Write) {
+            log::error!("TCP close error: {}", err); // The disconnection will be read later.
+        }
+        true
+    }
 }
 
 pub(crate) struct LocalResource {
     listener: TcpListener,
+    socket_options: TcpSocketOptions,
+    heartbeat: Option<Heartbeat>,
+    proxy_protocol: Option<ProxyProtocolAcceptor>,
 }
 
 impl Resource for LocalResource {
     fn source(&mut self) -> &mut dyn Source {
         &mut self.listener
     }
+
+    fn set_waker(&mut self, waker: ResourceWaker) {
+        if let Some(proxy_protocol) = &mut self.proxy_protocol {
+            proxy_protocol.set_waker(waker);
+        }
+    }
+}
+
+impl LocalResource {
+    fn accept_stream(
+        &self,
+        stream: TcpStream,
+        addr: SocketAddr,
+        accept_remote: &mut impl FnMut(AcceptedType<'_, RemoteResource>) -> AcceptStatus,
+    ) -> AcceptStatus {
+        if let Err(err) = self.socket_options.apply_to_stream(&SockRef::from(&stream)) {
+            log::error!("TCP socket options error: {}", err);
+        }
+        accept_remote(AcceptedType::Remote(addr, RemoteResource::new(stream, self.heartbeat)))
+    }
 }
 
 impl Local for LocalResource {
     type Remote = RemoteResource;
 
-    fn listen(addr: SocketAddr) -> io::Result<ListeningInfo<Self>> {
-        let listener = TcpListener::bind(addr)?;
+    fn listen(
+        config: TransportListen,
+        addr: RemoteAddr,
+        options: ListenOptions,
+    ) -> io::Result<ListeningInfo<Self>> {
+        let addr = addr.resolve()?;
+        #[allow(unreachable_patterns)]
+        let config = match config {
+            TransportListen::FramedTcp(config) => config,
+            _ => return Err(wrong_config_error()),
+        };
+
+        let listener = config.socket_options.listen(addr, config.backlog)?;
         let local_addr = listener.local_addr().unwrap();
-        Ok(ListeningInfo { local: { LocalResource { listener } }, local_addr })
+        let socket_options = config.socket_options;
+        let heartbeat = config.heartbeat;
+        let proxy_protocol = options.proxy_protocol.then(ProxyProtocolAcceptor::default);
+        let local = LocalResource { listener, socket_options, heartbeat, proxy_protocol };
+        Ok(ListeningInfo { local, local_addr })
     }
 
-    fn accept(&self, mut accept_remote: impl FnMut(AcceptedType<'_, Self::Remote>)) {
+    fn accept(
+        &self,
+        mut accept_remote: impl FnMut(AcceptedType<'_, Self::Remote>) -> AcceptStatus,
+    ) {
+        if let Some(proxy_protocol) = &self.proxy_protocol {
+            return proxy_protocol.accept(
+                &self.listener,
+                &mut accept_remote,
+                |stream, addr, accept| self.accept_stream(stream, addr, accept),
+            )
+        }
+
         loop {
             match self.listener.accept() {
-                Ok((stream, addr)) => accept_remote(AcceptedType::Remote(addr, stream.into())),
+                Ok((stream, addr)) => {
+                    if self.accept_stream(stream, addr, &mut accept_remote) == AcceptStatus::Stop {
+                        break
+                    }
+                }
                 Err(ref err) if err.kind() == ErrorKind::WouldBlock => break,
                 Err(ref err) if err.kind() == ErrorKind::Interrupted => continue,
-                Err(err) => break log::error!("TCP accept error: {}", err), // Should not happen
+                Err(err) => {
+                    accept_remote(AcceptedType::Error(err));
+                    break
+                }
             }
         }
     }
+
+    fn pending_accepts(&self) -> usize {
+        self.proxy_protocol.as_ref().map_or(0, |proxy_protocol| proxy_protocol.pending_accepts())
+    }
 }
@@ -0,0 +1,400 @@
+use crate::network::adapter::{
+    Resource, Remote, Local, Adapter, SendStatus, AcceptedType, AcceptStatus, ReadStatus,
+    DisconnectReason, ConnectionInfo, ListeningInfo, PendingStatus, ResourceWaker,
+    wrong_config_error,
+};
+use crate::network::{
+    RemoteAddr, TransportConnect, TransportListen, ConnectOptions, ListenOptions, CloseReason,
+};
+use crate::util::encoding::{self, Decoder, MAX_ENCODED_SIZE};
+use crate::util::thread::{OTHER_THREAD_ERR};
+use crate::util::tls::{TlsConnectConfig, TlsHandshake};
+
+use super::buffered_stream::{BufferedStream};
+use super::connecting::{self, Deadline, TcpPeer};
+use super::no_source::{NoSource};
+
+use mio::net::{TcpListener, TcpStream};
+use mio::event::{Source};
+
+use native_tls::{TlsAcceptor, TlsStream, MidHandshakeTlsStream, HandshakeError};
+
+use std::net::{Shutdown};
+use std::io::{self, ErrorKind, Read};
+use std::ops::{Deref, DerefMut};
+use std::cell::{RefCell};
+use std::sync::{Mutex};
+use std::mem::{MaybeUninit};
+
+const INPUT_BUFFER_SIZE: usize = u16::MAX as usize; // 2^16 - 1
+
+pub(crate) struct FramedTlsAdapter;
+impl Adapter for FramedTlsAdapter {
+    type Remote = RemoteResource;
+    type Local = LocalResource;
+}
+
+struct PendingHandshake {
+    mid_handshake: MidHandshakeTlsStream<TcpStream>,
+    pending_messages: Vec<Vec<u8>>,
+}
+
+/// Client connection started by `connect_async()`:
+/// first the tcp connection, then the TLS handshake.
+enum PendingConnection {
+    Tcp(TcpStream, TlsConnectConfig, String),
+    Tls(MidHandshakeTlsStream<TcpStream>),
+}
+
+enum RemoteState {
+    Tls(BufferedStream<TlsStream<TcpStream>>),
+    Handshake(Option<PendingHandshake>),
+    Connecting(Option<PendingConnection>),
+    Closed(NoSource), // The stream was consumed by a failed handshake.
+}
+
+pub(crate) struct RemoteResource {
+    state: Mutex<RemoteState>,
+    decoder: RefCell<Decoder>,
+    waker: Option<ResourceWaker>, // Given to the stream once the handshake is done.
+}
+
+// SAFETY:
+// That RefCell<Decoder> can be used with Sync because the decoder is only used in the read_event.
+// This way, we save the cost of a Mutex.
+unsafe impl Sync for RemoteResource {}
+
+impl From<RemoteState> for RemoteResource {
+    fn from(state: RemoteState) -> Self {
+        Self { state: Mutex::new(state), decoder: RefCell::new(Decoder::default()), waker: None }
+    }
+}
+
+impl Resource for RemoteResource {
+    fn source(&mut self) -> &mut dyn Source {
+        match self.state.get_mut().unwrap() {
+            RemoteState::Tls(stream) => stream.get_mut().get_mut(),
+            RemoteState::Handshake(Some(handshake)) => handshake.mid_handshake.get_mut(),
+            RemoteState::Connecting(Some(PendingConnection::Tcp(stream, ..))) => stream,
+            RemoteState::Connecting(Some(PendingConnection::Tls(mid_handshake))) => {
+                mid_handshake.get_mut()
+            }
+            RemoteState::Closed(no_source) => no_source,
+            RemoteState::Handshake(None) | RemoteState::Connecting(None) => unreachable!(),
+        }
+    }
+
+    fn set_waker(&mut self, waker: ResourceWaker) {
+        if let RemoteState::Tls(stream) = self.state.get_mut().unwrap() {
+            stream.set_waker(waker.clone());
+        }
+        self.waker = Some(waker);
+    }
+}
+
+impl Remote for RemoteResource {
+    fn connect(
+        config: TransportConnect,
+        remote_addr: RemoteAddr,
+        options: ConnectOptions,
+    ) -> io::Result<ConnectionInfo<Self>> {
+        let (config, peer, host) = Self::resolve(config, remote_addr)?;
+        let deadline = Deadline::new(options.timeout);
+
+        // Synchronous tcp handshake
+        let (stream, local_addr, peer_addr) =
+            connecting::tcp_connect(&peer, &options, connecting::tcp_socket)?;
+
+        // Synchronous tls handshake, over the stream blocked until the deadline
+        connecting::set_blocking(&stream, &deadline)?;
+        let mut handshake = config.start_connect(&host, stream)?;
+        let tls_stream = loop {
+            match handshake {
+                TlsHandshake::Done(tls_stream) => break tls_stream,
+                TlsHandshake::InProgress(mid_handshake) => {
+                    // Interrupted by a timeout of the stream.
+                    connecting::set_blocking(mid_handshake.get_ref(), &deadline)?;
+                    handshake = TlsHandshake::resume(mid_handshake)?;
+                }
+            }
+        };
+        connecting::set_nonblocking(tls_stream.get_ref())?;
+
+        let remote = RemoteState::Tls(BufferedStream::new(tls_stream)).into();
+        Ok(ConnectionInfo { remote, local_addr, peer_addr })
+    }
+
+    fn connect_async(
+        config: TransportConnect,
+        remote_addr: RemoteAddr,
+        options: ConnectOptions,
+    ) -> io::Result<ConnectionInfo<Self>> {
+        let (config, peer, host) = Self::resolve(config, remote_addr)?;
+        let (stream, local_addr, peer_addr) =
+            connecting::tcp_connect_async(&peer, &options, connecting::tcp_socket)?;
+        let connection = PendingConnection::Tcp(stream, config, host);
+        Ok(ConnectionInfo {
+            remote: RemoteState::Connecting(Some(connection)).into(),
+            local_addr,
+            peer_addr,
+        })
+    }
+
+    fn pending(&self) -> PendingStatus {
+        let mut state = self.state.lock().expect(OTHER_THREAD_ERR);
+        if let RemoteState::Connecting(connection) = state.deref_mut() {
+            let handshake = match connection.take().unwrap() {
+                PendingConnection::Tcp(stream, config, host) => {
+                    match connecting::tcp_pending(&stream) {
+                        PendingStatus::Ready => config.start_connect(&host, stream),
+                        status => {
+                            *connection = Some(PendingConnection::Tcp(stream, config, host));
+                            return status
+                        }
+                    }
+                }
+                PendingConnection::Tls(mid_handshake) => TlsHandshake::resume(mid_handshake),
+            };
+
+            match handshake {
+                Ok(TlsHandshake::Done(tls_stream)) => {
+                    *state = RemoteState::Tls(self.buffered(tls_stream))
+                }
+                Ok(TlsHandshake::InProgress(mid_handshake)) => {
+                    *connection = Some(PendingConnection::Tls(mid_handshake));
+                    return PendingStatus::Incomplete
+                }
+                Err(err) => {
+                    *state = RemoteState::Closed(NoSource);
+                    return PendingStatus::Failed(err)
+                }
+            }
+        }
+        PendingStatus::Ready
+    }
+
+    fn receive(&self, mut process_data: impl FnMut(&[u8])) -> ReadStatus {
+        let buffer: MaybeUninit<[u8; INPUT_BUFFER_SIZE]> = MaybeUninit::uninit();
+        let mut input_buffer = unsafe { buffer.assume_init() }; // Avoid to initialize the array
+
+        loop {
+            let mut state = self.state.lock().expect(OTHER_THREAD_ERR);
+            match state.deref_mut() {
+                RemoteState::Tls(stream) => match stream.get_mut().read(&mut input_buffer) {
+                    Ok(0) => break ReadStatus::Disconnected(DisconnectReason::Closed),
+                    Ok(size) => {
+                        // We can not call process_data while the stream is locked.
+                        // The user could lock it again if sends from the callback.
+                        drop(state);
+                        let data = &input_buffer[..size];
+                        log::trace!("Decoding data, {} bytes", data.len());
+                        self.decoder.borrow_mut().decode(data, |decoded_data| {
+                            process_data(decoded_data);
+                        });
+                    }
+                    Err(ref err) if err.kind() == ErrorKind::Interrupted => continue,
+                    Err(ref err) if err.kind() == ErrorKind::WouldBlock => {
+                        break ReadStatus::WaitNextEvent
+                    }
+                    Err(ref err) if err.kind() == ErrorKind::ConnectionReset => {
+                        break ReadStatus::Disconnected(DisconnectReason::Reset)
+                    }
+                    Err(err) => {
+                        log::error!("TLS receive error: {}", err);
+                        break ReadStatus::Disconnected(DisconnectReason::from(&err))
+                    }
+                },
+                RemoteState::Handshake(handshake) => {
+                    let current_handshake = handshake.take().unwrap();
+                    match current_handshake.mid_handshake.handshake() {
+                        Ok(tls_stream) => {
+                            let mut stream = self.buffered(tls_stream);
+                            for pending_data in current_handshake.pending_messages {
+                                Self::send_by_stream(&mut stream, &pending_data);
+                            }
+                            *state = RemoteState::Tls(stream);
+                        }
+                        Err(HandshakeError::WouldBlock(mid_handshake)) => {
+                            *handshake = Some(PendingHandshake {
+                                mid_handshake,
+                                pending_messages: current_handshake.pending_messages,
+                            });
+                            break ReadStatus::WaitNextEvent
+                        }
+                        Err(HandshakeError::Failure(ref err)) => {
+                            log::error!("TLS accept handshake error: {}", err);
+                            *state = RemoteState::Closed(NoSource);
+                            let reason = DisconnectReason::Protocol(err.to_string());
+                            break ReadStatus::Disconnected(reason)
+                        }
+                    }
+                }
+                RemoteState::Connecting(_) => break ReadStatus::WaitNextEvent,
+                RemoteState::Closed(_) => {
+                    let reason = DisconnectReason::Protocol("TLS handshake failed".into());
+                    break ReadStatus::Disconnected(reason)
+                }
+            }
+        }
+    }
+
+    fn send(&self, data: &[u8]) -> SendStatus {
+        match self.state.lock().expect(OTHER_THREAD_ERR).deref_mut() {
+            RemoteState::Tls(stream) => Self::send_by_stream(stream, data),
+            RemoteState::Handshake(handshake) => {
+                handshake.as_mut().unwrap().pending_messages.push(data.to_vec());
+                SendStatus::Enqueued
+            }
+            RemoteState::Connecting(_) => SendStatus::ResourceNotAvailable,
+            RemoteState::Closed(_) => SendStatus::ResourceNotFound,
+        }
+    }
+
+    fn flush(&self) {
+        if let RemoteState::Tls(stream) = self.state.lock().expect(OTHER_THREAD_ERR).deref_mut() {
+            if let Err(err) = stream.flush() {
+                log::error!("TLS send error: {}", err); // The disconnection will be read later.
+            }
+        }
+    }
+
+    fn enqueued_size(&self) -> usize {
+        match self.state.lock().expect(OTHER_THREAD_ERR).deref() {
+            RemoteState::Tls(stream) => stream.enqueued_size(),
+            _ => 0,
+        }
+    }
+
+    fn close(&self, _reason: &CloseReason) -> bool {
+        match self.state.lock().expect(OTHER_THREAD_ERR).deref_mut() {
+            RemoteState::Tls(stream) => {
+                // The TLS close_notify alert is sent before closing the writing side of the socket.
+                let tls_stream = stream.get_mut();
+                if let Err(err) = tls_stream.shutdown() {
+                    log::error!("TLS close error: {}", err);
+                }
+                if let Err(err) = tls_stream.get_ref().shutdown(Shutdown::Write) {
+                    log::error!("TCP close error: {}", err); // The disconnection will be read later.
+                }
+                true
+            }
+            // Without an established TLS stream there is nothing to notify.
+            _ => false,
+        }
+    }
+}
+
+impl RemoteResource {
+    fn resolve(
+        config: TransportConnect,
+        remote_addr: RemoteAddr,
+    ) -> io::Result<(TlsConnectConfig, TcpPeer, String)> {
+        #[allow(unreachable_patterns)]
+        let config = match config {
+            TransportConnect::FramedTls(config) => config,
+            _ => return Err(wrong_config_error()),
+        };
+
+        let host = match remote_addr.host() {
+            Some(host) => host,
+            None => {
+                let err = "A TLS connection can not be connected to a path";
+                return Err(io::Error::new(ErrorKind::InvalidInput, err))
+            }
+        };
+        let peer = TcpPeer::from_remote_addr(&remote_addr)?;
+        Ok((config, peer, host))
+    }
+
+    /// Buffers the stream of an established connection.
+    fn buffered(&self, tls_stream: TlsStream<TcpStream>) -> BufferedStream<TlsStream<TcpStream>> {
+        let mut stream = BufferedStream::new(tls_stream);
+        if let Some(waker) = &self.waker {
+            stream.set_waker(waker.clone());
+        }
+        stream
+    }
+
+    fn send_by_stream(
+        stream: &mut BufferedStream<TlsStream<TcpStream>>,
+        data: &[u8],
+    ) -> SendStatus {
+        let mut buf = [0; MAX_ENCODED_SIZE]; // used to avoid a heap allocation
+        let encoded_size = encoding::encode_size(data, &mut buf);
+        stream.send(&[encoded_size, data])
+    }
+}
+
+pub(crate) struct LocalResource {
+    listener: TcpListener,
+    acceptor: TlsAcceptor,
+}
+
+impl Resource for LocalResource {
+    fn source(&mut self) -> &mut dyn Source {
+        &mut self.listener
+    }
+}
+
+impl Local for LocalResource {
+    type Remote = RemoteResource;
+
+    fn listen(
+        config: TransportListen,
+        addr: RemoteAddr,
+        options: ListenOptions,
+    ) -> io::Result<ListeningInfo<Self>> {
+        options.check_no_proxy_protocol()?;
+
+        let addr = addr.resolve()?;
+        #[allow(unreachable_patterns)]
+        let config = match config {
+            TransportListen::FramedTls(config) => config,
+            _ => return Err(wrong_config_error()),
+        };
+
+        let acceptor = config.acceptor()?;
+        let listener = TcpListener::bind(addr)?;
+        let local_addr = listener.local_addr().unwrap();
+        Ok(ListeningInfo { local: LocalResource { listener, acceptor }, local_addr })
+    }
+
+    fn accept(
+        &self,
+        mut accept_remote: impl FnMut(AcceptedType<'_, Self::Remote>) -> AcceptStatus,
+    ) {
+        loop {
+            match self.listener.accept() {
+                Ok((stream, addr)) => {
+                    let remote_state = match self.acceptor.accept(stream) {
+                        Ok(tls_stream) => Some(RemoteState::Tls(BufferedStream::new(tls_stream))),
+                        Err(HandshakeError::WouldBlock(mid_handshake)) => {
+                            Some(RemoteState::Handshake(Some(PendingHandshake {
+                                mid_handshake,
+                                pending_messages: Vec::new(),
+                            })))
+                        }
+                        Err(HandshakeError::Failure(err)) => {
+                            let err = io::Error::new(ErrorKind::InvalidData, err.to_string());
+                            accept_remote(AcceptedType::Error(err));
+                            None
+                        }
+                    };
+
+                    if let Some(remote_state) = remote_state {
+                        let remote = AcceptedType::Remote(addr, remote_state.into());
+                        if accept_remote(remote) == AcceptStatus::Stop {
+                            break
+                        }
+                    }
+                }
+                Err(ref err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(ref err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(err) => {
+                    accept_remote(AcceptedType::Error(err));
+                    break
+                }
+            }
+        }
+    }
+}
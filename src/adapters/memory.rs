@@ -0,0 +1,263 @@
+use crate::network::adapter::{
+    Resource, Remote, Local, Adapter, SendStatus, AcceptedType, AcceptStatus, ReadStatus,
+    DisconnectReason, ConnectionInfo, ListeningInfo, ResourceWaker,
+};
+use crate::network::{
+    RemoteAddr, TransportConnect, TransportListen, ConnectOptions, ListenOptions, CloseReason,
+};
+use crate::util::thread::{OTHER_THREAD_ERR};
+
+use super::no_source::{NoSource};
+
+use mio::event::{Source};
+
+use std::net::{SocketAddr, IpAddr, Ipv4Addr};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::io::{self, ErrorKind};
+
+/// Address given by the API for the memory endpoints and listeners,
+/// since they are addressed by names instead of ip addresses.
+pub const UNSPECIFIED_ADDR: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0);
+
+lazy_static::lazy_static! {
+    /// Listeners of the process by name.
+    static ref LISTENERS: Mutex<HashMap<String, Arc<Mutex<ListenerState>>>> =
+        Mutex::new(HashMap::new());
+}
+
+fn memory_name(addr: RemoteAddr) -> String {
+    match addr {
+        RemoteAddr::Str(name) => name,
+        addr => addr.to_string(), // Any address can be used as a name.
+    }
+}
+
+/// Messages sent to a remote, pending to be read by it.
+#[derive(Default)]
+struct ChannelState {
+    messages: VecDeque<Vec<u8>>,
+    closed: bool,
+    waker: Option<ResourceWaker>, // Waker of the remote that reads from this channel.
+}
+
+type Channel = Arc<Mutex<ChannelState>>;
+
+pub(crate) struct MemoryAdapter;
+impl Adapter for MemoryAdapter {
+    type Remote = RemoteResource;
+    type Local = LocalResource;
+}
+
+pub(crate) struct RemoteResource {
+    source: NoSource,
+    inbox: Channel,
+    outbox: Channel,
+}
+
+impl RemoteResource {
+    /// Creates the two remotes of a connection.
+    fn pair() -> (RemoteResource, RemoteResource) {
+        let (first, second) = (Channel::default(), Channel::default());
+        (
+            RemoteResource { source: NoSource, inbox: first.clone(), outbox: second.clone() },
+            RemoteResource { source: NoSource, inbox: second, outbox: first },
+        )
+    }
+
+    /// Closes the channel to the peer, that reads the messages already sent
+    /// before its disconnection.
+    fn close_outbox(&self) {
+        let mut outbox = self.outbox.lock().expect(OTHER_THREAD_ERR);
+        outbox.closed = true;
+        if let Some(waker) = &outbox.waker {
+            waker.wake();
+        }
+    }
+}
+
+impl Resource for RemoteResource {
+    fn source(&mut self) -> &mut dyn Source {
+        &mut self.source
+    }
+
+    fn set_waker(&mut self, waker: ResourceWaker) {
+        let mut inbox = self.inbox.lock().expect(OTHER_THREAD_ERR);
+        // The peer could have sent messages or closed before the registration.
+        if !inbox.messages.is_empty() || inbox.closed {
+            waker.wake();
+        }
+        inbox.waker = Some(waker);
+    }
+}
+
+impl Remote for RemoteResource {
+    fn connect(
+        _: TransportConnect,
+        remote_addr: RemoteAddr,
+        options: ConnectOptions,
+    ) -> io::Result<ConnectionInfo<Self>> {
+        options.check_no_proxy()?;
+
+        if options.local_addr.is_some() {
+            let err = "A memory connection can not be bound to a local IP address";
+            return Err(io::Error::new(ErrorKind::InvalidInput, err))
+        }
+
+        let name = memory_name(remote_addr);
+        let listener = match LISTENERS.lock().expect(OTHER_THREAD_ERR).get(&name) {
+            Some(listener) => listener.clone(),
+            None => {
+                let err = format!("There is no memory listener named '{}'", name);
+                return Err(io::Error::new(ErrorKind::ConnectionRefused, err))
+            }
+        };
+
+        let (remote, accepted) = RemoteResource::pair();
+        let mut listener = listener.lock().expect(OTHER_THREAD_ERR);
+        if listener.closed {
+            let err = format!("The memory listener '{}' was closed", name);
+            return Err(io::Error::new(ErrorKind::ConnectionRefused, err))
+        }
+        listener.pending.push_back(accepted);
+        if let Some(waker) = &listener.waker {
+            waker.wake();
+        }
+
+        Ok(ConnectionInfo { remote, local_addr: UNSPECIFIED_ADDR, peer_addr: UNSPECIFIED_ADDR })
+    }
+
+    fn receive(&self, mut process_data: impl FnMut(&[u8])) -> ReadStatus {
+        let (messages, closed) = {
+            let mut inbox = self.inbox.lock().expect(OTHER_THREAD_ERR);
+            (std::mem::take(&mut inbox.messages), inbox.closed)
+        };
+
+        for message in messages {
+            process_data(&message);
+        }
+
+        match closed {
+            true => ReadStatus::Disconnected(DisconnectReason::Closed),
+            false => ReadStatus::WaitNextEvent,
+        }
+    }
+
+    fn send(&self, data: &[u8]) -> SendStatus {
+        let mut outbox = self.outbox.lock().expect(OTHER_THREAD_ERR);
+        if outbox.closed {
+            return SendStatus::ResourceNotFound
+        }
+        outbox.messages.push_back(data.to_vec());
+        // If there were messages, the peer was already woken.
+        if outbox.messages.len() == 1 {
+            if let Some(waker) = &outbox.waker {
+                waker.wake();
+            }
+        }
+        SendStatus::Sent
+    }
+
+    fn close(&self, _reason: &CloseReason) -> bool {
+        self.close_outbox();
+        true
+    }
+}
+
+impl Drop for RemoteResource {
+    fn drop(&mut self) {
+        self.inbox.lock().expect(OTHER_THREAD_ERR).closed = true;
+        self.close_outbox();
+    }
+}
+
+/// Connections waiting to be accepted by a listener.
+#[derive(Default)]
+struct ListenerState {
+    pending: VecDeque<RemoteResource>,
+    closed: bool,
+    waker: Option<ResourceWaker>,
+}
+
+pub(crate) struct LocalResource {
+    source: NoSource,
+    name: String,
+    state: Arc<Mutex<ListenerState>>,
+}
+
+impl Resource for LocalResource {
+    fn source(&mut self) -> &mut dyn Source {
+        &mut self.source
+    }
+
+    fn set_waker(&mut self, waker: ResourceWaker) {
+        let mut state = self.state.lock().expect(OTHER_THREAD_ERR);
+        if !state.pending.is_empty() {
+            waker.wake();
+        }
+        state.waker = Some(waker);
+    }
+}
+
+impl Local for LocalResource {
+    type Remote = RemoteResource;
+
+    fn listen(
+        _: TransportListen,
+        addr: RemoteAddr,
+        options: ListenOptions,
+    ) -> io::Result<ListeningInfo<Self>> {
+        options.check_no_proxy_protocol()?;
+
+        let name = memory_name(addr);
+        let state = Arc::new(Mutex::new(ListenerState::default()));
+
+        let mut listeners = LISTENERS.lock().expect(OTHER_THREAD_ERR);
+        if listeners.contains_key(&name) {
+            let err = format!("There is already a memory listener named '{}'", name);
+            return Err(io::Error::new(ErrorKind::AddrInUse, err))
+        }
+        listeners.insert(name.clone(), state.clone());
+
+        Ok(ListeningInfo {
+            local: LocalResource { source: NoSource, name, state },
+            local_addr: UNSPECIFIED_ADDR,
+        })
+    }
+
+    fn accept(
+        &self,
+        mut accept_remote: impl FnMut(AcceptedType<'_, Self::Remote>) -> AcceptStatus,
+    ) {
+        // The callback is called without the lock, the user could connect from there.
+        let mut pending = std::mem::take(&mut self.state.lock().expect(OTHER_THREAD_ERR).pending);
+        while let Some(remote) = pending.pop_front() {
+            if accept_remote(AcceptedType::Remote(UNSPECIFIED_ADDR, remote)) == AcceptStatus::Stop {
+                // The connections made meanwhile are queued after the ones not accepted yet.
+                let mut state = self.state.lock().expect(OTHER_THREAD_ERR);
+                pending.append(&mut state.pending);
+                state.pending = pending;
+                break
+            }
+        }
+    }
+}
+
+impl Drop for LocalResource {
+    fn drop(&mut self) {
+        // The name could have been bound again by other listener.
+        let mut listeners = LISTENERS.lock().expect(OTHER_THREAD_ERR);
+        if listeners.get(&self.name).is_some_and(|state| Arc::ptr_eq(state, &self.state)) {
+            listeners.remove(&self.name);
+        }
+        drop(listeners);
+
+        // The connections not accepted yet are closed.
+        let pending = {
+            let mut state = self.state.lock().expect(OTHER_THREAD_ERR);
+            state.closed = true;
+            std::mem::take(&mut state.pending)
+        };
+        drop(pending);
+    }
+}
@@ -0,0 +1,24 @@
+use mio::event::{Source};
+use mio::{Registry, Token, Interest};
+
+use std::io::{self};
+
+/// Source of the resources that have no OS socket registered in the poll.
+/// It is used by the resources whose events are generated by a
+/// [`crate::network::adapter::ResourceWaker`], and by the resources whose socket
+/// was already closed (e.g. consumed by a failed handshake).
+pub struct NoSource;
+
+impl Source for NoSource {
+    fn register(&mut self, _: &Registry, _: Token, _: Interest) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn reregister(&mut self, _: &Registry, _: Token, _: Interest) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn deregister(&mut self, _: &Registry) -> io::Result<()> {
+        Ok(())
+    }
+}
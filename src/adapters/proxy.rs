@@ -0,0 +1,257 @@
+use crate::network::{Proxy, ProxyProtocol, ProxyCredentials};
+use super::connecting::{TcpPeer};
+
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::io::{self, ErrorKind, Read, Write};
+
+const SOCKS_VERSION: u8 = 5;
+const SOCKS_NO_AUTH: u8 = 0x00;
+const SOCKS_USER_PASS_AUTH: u8 = 0x02;
+const SOCKS_NO_ACCEPTABLE_AUTH: u8 = 0xFF;
+const SOCKS_USER_PASS_VERSION: u8 = 1;
+const SOCKS_CONNECT: u8 = 1;
+const SOCKS_ADDR_IPV4: u8 = 1;
+const SOCKS_ADDR_DOMAIN: u8 = 3;
+const SOCKS_ADDR_IPV6: u8 = 4;
+
+/// Max size of the response header of an HTTP proxy.
+const MAX_HTTP_RESPONSE_SIZE: usize = 8192;
+
+/// Resolves the address of the proxy.
+pub fn resolve(proxy: &Proxy) -> io::Result<SocketAddr> {
+    proxy.addr.to_socket_addrs()?.next().ok_or_else(|| {
+        let err = format!("The proxy address '{}' can not be resolved", proxy.addr);
+        io::Error::new(ErrorKind::InvalidInput, err)
+    })
+}
+
+/// Asks the proxy, already connected by `stream`, for a tunnel to `peer`.
+/// A host name is sent to the proxy to be resolved by it.
+/// Once it returns, the data written into the stream is received by `peer`.
+pub fn handshake(mut stream: impl Read + Write, proxy: &Proxy, peer: &TcpPeer) -> io::Result<()> {
+    match proxy.protocol {
+        ProxyProtocol::Socks5 => socks5_handshake(&mut stream, proxy, peer),
+        ProxyProtocol::Http => http_handshake(&mut stream, proxy, peer),
+    }
+}
+
+fn socks5_handshake(
+    stream: &mut (impl Read + Write),
+    proxy: &Proxy,
+    peer: &TcpPeer,
+) -> io::Result<()> {
+    let method = match proxy.credentials {
+        Some(_) => SOCKS_USER_PASS_AUTH,
+        None => SOCKS_NO_AUTH,
+    };
+    stream.write_all(&[SOCKS_VERSION, 1, method])?;
+
+    let mut reply = [0; 2];
+    stream.read_exact(&mut reply)?;
+    check_socks_version(reply[0], SOCKS_VERSION)?;
+    match reply[1] {
+        SOCKS_NO_AUTH if method == SOCKS_NO_AUTH => (),
+        SOCKS_USER_PASS_AUTH if method == SOCKS_USER_PASS_AUTH => {
+            socks5_authenticate(stream, proxy.credentials.as_ref().unwrap())?
+        }
+        SOCKS_NO_ACCEPTABLE_AUTH => {
+            let err = "The SOCKS5 proxy does not accept the authentication method";
+            return Err(io::Error::new(ErrorKind::PermissionDenied, err))
+        }
+        _ => return Err(invalid_data("The SOCKS5 proxy chose an unexpected method")),
+    }
+
+    stream.write_all(&socks5_connect_request(peer)?)?;
+
+    let mut reply = [0; 4];
+    stream.read_exact(&mut reply)?;
+    check_socks_version(reply[0], SOCKS_VERSION)?;
+    if reply[1] != 0 {
+        return Err(socks5_error(reply[1]))
+    }
+
+    // The address bound by the proxy is not used.
+    let addr_len = match reply[3] {
+        SOCKS_ADDR_IPV4 => 4,
+        SOCKS_ADDR_IPV6 => 16,
+        SOCKS_ADDR_DOMAIN => {
+            let mut len = [0; 1];
+            stream.read_exact(&mut len)?;
+            len[0] as usize
+        }
+        _ => return Err(invalid_data("The SOCKS5 proxy replied an unknown address type")),
+    };
+    let mut bound_addr = vec![0; addr_len + 2]; // Address and port
+    stream.read_exact(&mut bound_addr)
+}
+
+fn socks5_connect_request(peer: &TcpPeer) -> io::Result<Vec<u8>> {
+    let mut request = vec![SOCKS_VERSION, SOCKS_CONNECT, 0];
+    let port = match peer {
+        TcpPeer::Addr(SocketAddr::V4(addr)) => {
+            request.push(SOCKS_ADDR_IPV4);
+            request.extend_from_slice(&addr.ip().octets());
+            addr.port()
+        }
+        TcpPeer::Addr(SocketAddr::V6(addr)) => {
+            request.push(SOCKS_ADDR_IPV6);
+            request.extend_from_slice(&addr.ip().octets());
+            addr.port()
+        }
+        TcpPeer::Host(host, port) => {
+            if host.len() > u8::MAX as usize {
+                let err = "The SOCKS5 host name can not be longer than 255 bytes";
+                return Err(io::Error::new(ErrorKind::InvalidInput, err))
+            }
+            request.push(SOCKS_ADDR_DOMAIN);
+            request.push(host.len() as u8);
+            request.extend_from_slice(host.as_bytes());
+            *port
+        }
+    };
+    request.extend_from_slice(&port.to_be_bytes());
+    Ok(request)
+}
+
+fn socks5_authenticate(
+    stream: &mut (impl Read + Write),
+    credentials: &ProxyCredentials,
+) -> io::Result<()> {
+    let username = credentials.username.as_bytes();
+    let password = credentials.password.as_bytes();
+    if username.len() > u8::MAX as usize || password.len() > u8::MAX as usize {
+        let err = "The SOCKS5 username and password can not be longer than 255 bytes";
+        return Err(io::Error::new(ErrorKind::InvalidInput, err))
+    }
+
+    let mut request = vec![SOCKS_USER_PASS_VERSION, username.len() as u8];
+    request.extend_from_slice(username);
+    request.push(password.len() as u8);
+    request.extend_from_slice(password);
+    stream.write_all(&request)?;
+
+    let mut reply = [0; 2];
+    stream.read_exact(&mut reply)?;
+    check_socks_version(reply[0], SOCKS_USER_PASS_VERSION)?;
+    match reply[1] {
+        0 => Ok(()),
+        _ => {
+            let err = "The SOCKS5 proxy rejected the credentials";
+            Err(io::Error::new(ErrorKind::PermissionDenied, err))
+        }
+    }
+}
+
+fn check_socks_version(version: u8, expected: u8) -> io::Result<()> {
+    match version == expected {
+        true => Ok(()),
+        false => Err(invalid_data("The proxy does not talk SOCKS5")),
+    }
+}
+
+fn socks5_error(code: u8) -> io::Error {
+    let (kind, reason) = match code {
+        1 => (ErrorKind::Other, "general failure"),
+        2 => (ErrorKind::PermissionDenied, "connection not allowed by ruleset"),
+        3 => (ErrorKind::Other, "network unreachable"),
+        4 => (ErrorKind::Other, "host unreachable"),
+        5 => (ErrorKind::ConnectionRefused, "connection refused"),
+        6 => (ErrorKind::TimedOut, "TTL expired"),
+        7 => (ErrorKind::Other, "command not supported"),
+        8 => (ErrorKind::Other, "address type not supported"),
+        _ => (ErrorKind::Other, "unknown error"),
+    };
+    io::Error::new(kind, format!("The SOCKS5 proxy failed to connect: {}", reason))
+}
+
+fn http_handshake(
+    stream: &mut (impl Read + Write),
+    proxy: &Proxy,
+    peer: &TcpPeer,
+) -> io::Result<()> {
+    let mut request = format!("CONNECT {0} HTTP/1.1\r\nHost: {0}\r\n", peer);
+    if let Some(credentials) = &proxy.credentials {
+        let user_pass = format!("{}:{}", credentials.username, credentials.password);
+        request += &format!("Proxy-Authorization: Basic {}\r\n", base64(user_pass.as_bytes()));
+    }
+    request += "\r\n";
+    stream.write_all(request.as_bytes())?;
+
+    // Read byte by byte to not consume the data sent through the tunnel after the response.
+    let mut response = Vec::new();
+    while !response.ends_with(b"\r\n\r\n") {
+        if response.len() == MAX_HTTP_RESPONSE_SIZE {
+            return Err(invalid_data("The response of the HTTP proxy is too long"))
+        }
+        let mut byte = [0; 1];
+        stream.read_exact(&mut byte)?;
+        response.push(byte[0]);
+    }
+
+    let response = String::from_utf8_lossy(&response);
+    let status_line = response.lines().next().unwrap_or_default();
+    let status = match status_line.split_whitespace().collect::<Vec<_>>().as_slice() {
+        [version, status, ..] if version.starts_with("HTTP/1.") => status.parse::<u16>(),
+        _ => return Err(invalid_data("The HTTP proxy replied an invalid response")),
+    };
+    match status {
+        Ok(200..=299) => Ok(()),
+        Ok(407) => {
+            let err = format!("The HTTP proxy requires authentication: {}", status_line);
+            Err(io::Error::new(ErrorKind::PermissionDenied, err))
+        }
+        Ok(_) => {
+            let err = format!("The HTTP proxy failed to connect: {}", status_line);
+            Err(io::Error::new(ErrorKind::ConnectionRefused, err))
+        }
+        Err(_) => Err(invalid_data("The HTTP proxy replied an invalid status")),
+    }
+}
+
+fn invalid_data(err: &str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, err)
+}
+
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let group = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+        for i in 0..4 {
+            match i <= chunk.len() {
+                true => encoded.push(ALPHABET[(group >> (18 - 6 * i) & 0x3F) as usize] as char),
+                false => encoded.push('='),
+            }
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn socks5_connect_requests() {
+        let peer = TcpPeer::new("10.0.0.1", 80);
+        assert_eq!(vec![5, 1, 0, 1, 10, 0, 0, 1, 0, 80], socks5_connect_request(&peer).unwrap());
+
+        let peer = TcpPeer::new("example.com", 443);
+        let mut expected = vec![5, 1, 0, 3, 11];
+        expected.extend_from_slice(b"example.com");
+        expected.extend_from_slice(&[1, 187]);
+        assert_eq!(expected, socks5_connect_request(&peer).unwrap());
+
+        let peer = TcpPeer::new(&"a".repeat(256), 80);
+        assert!(socks5_connect_request(&peer).is_err());
+    }
+
+    #[test]
+    fn base64_encoding() {
+        assert_eq!("", base64(b""));
+        assert_eq!("dQ==", base64(b"u"));
+        assert_eq!("dXM=", base64(b"us"));
+        assert_eq!("dXNlcjpwYXNz", base64(b"user:pass"));
+    }
+}
@@ -0,0 +1,320 @@
+use crate::network::adapter::{ResourceWaker, AcceptedType, AcceptStatus};
+use crate::util::thread::{OTHER_THREAD_ERR};
+
+use mio::net::{TcpListener, TcpStream};
+
+use std::net::{SocketAddr, IpAddr, Ipv4Addr, Ipv6Addr};
+use std::sync::{Mutex};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use std::io::{self, ErrorKind, Read};
+
+const V1_PREFIX: &[u8] = b"PROXY ";
+const V1_MAX_LEN: usize = 107;
+const V2_SIGNATURE: &[u8] = b"\r\n\r\n\0\r\nQUIT\n";
+const V2_FIXED_LEN: usize = 16;
+
+/// Max time to receive the header since the stream is accepted.
+const HEADER_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Max number of streams held at the same time.
+/// The rest wait in the listen backlog until some of them is released.
+const MAX_PENDING: usize = 128;
+
+/// Holds the accepted streams of a listener until their *PROXY protocol* header is read.
+/// The streams are attached to the listener's waker, so the listener receives an event
+/// each time some of them can be read, or when the header of some of them has timed out.
+#[derive(Default)]
+pub struct ProxyProtocolAcceptor {
+    pending: Mutex<Vec<PendingStream>>,
+    pending_count: AtomicUsize, // Read from the accept callback, while the pending are processed.
+    stopped: AtomicBool,        // The last accepted stream filled the listener.
+    waker: Option<ResourceWaker>,
+}
+
+struct PendingStream {
+    stream: TcpStream,
+    addr: SocketAddr,
+    header: Vec<u8>,
+    deadline: Instant,
+}
+
+impl ProxyProtocolAcceptor {
+    pub fn set_waker(&mut self, waker: ResourceWaker) {
+        self.waker = Some(waker);
+    }
+
+    /// Number of streams held until their header is read.
+    pub fn pending_accepts(&self) -> usize {
+        self.pending_count.load(Ordering::Acquire)
+    }
+
+    /// Accepts the streams of `listener`.
+    /// First, it reads the available data of the held streams:
+    /// `accept_stream` is called with each stream whose header is completely read,
+    /// along with the client address given by the header.
+    /// The streams with a malformed or timed out header are dropped.
+    /// Then, the new streams are accepted and held, given as [`AcceptedType::Pending`].
+    /// Once `accept_remote` or `accept_stream` returns [`AcceptStatus::Stop`],
+    /// it stops accepting and the rest of streams are kept held.
+    pub fn accept<R, F>(
+        &self,
+        listener: &TcpListener,
+        accept_remote: &mut F,
+        mut accept_stream: impl FnMut(TcpStream, SocketAddr, &mut F) -> AcceptStatus,
+    ) where
+        F: FnMut(AcceptedType<'_, R>) -> AcceptStatus,
+    {
+        let waker = self.waker.as_ref().expect("The listener must be registered");
+        let now = Instant::now();
+        let mut status = AcceptStatus::Continue;
+
+        // The lock is not kept while accepting, the callbacks read the pending count.
+        let held = std::mem::take(&mut *self.pending.lock().expect(OTHER_THREAD_ERR));
+        let mut still_held = Vec::with_capacity(held.len());
+        for mut held in held {
+            if status == AcceptStatus::Stop {
+                still_held.push(held);
+                continue
+            }
+            let result = match read_header(&mut held.stream, &mut held.header, held.addr) {
+                Ok(Some(client_addr)) => Ok(client_addr),
+                Ok(None) if now >= held.deadline => {
+                    Err(io::Error::new(ErrorKind::TimedOut, "Header not received in time"))
+                }
+                Ok(None) => {
+                    still_held.push(held);
+                    continue
+                }
+                Err(err) => Err(err),
+            };
+
+            self.pending_count.fetch_sub(1, Ordering::AcqRel);
+            let _ = waker.detach(&mut held.stream);
+            match result {
+                Ok(client_addr) => {
+                    status = accept_stream(held.stream, client_addr, accept_remote);
+                    self.stopped.store(status == AcceptStatus::Stop, Ordering::Release);
+                }
+                Err(err) => {
+                    self.stopped.store(false, Ordering::Release);
+                    log::warn!("PROXY protocol header rejected from {}: {}", held.addr, err);
+                }
+            }
+        }
+        self.pending.lock().expect(OTHER_THREAD_ERR).append(&mut still_held);
+
+        // While the last accepted stream filled the listener, no more are accepted
+        // until some held stream is released.
+        if status == AcceptStatus::Stop
+            || (self.stopped.load(Ordering::Acquire) && self.pending_accepts() > 0)
+        {
+            return
+        }
+
+        while self.pending_accepts() < MAX_PENDING {
+            match listener.accept() {
+                Ok((stream, addr)) => {
+                    if !self.add(stream, addr) {
+                        continue
+                    }
+                    if accept_remote(AcceptedType::Pending(addr)) == AcceptStatus::Stop {
+                        self.stopped.store(true, Ordering::Release);
+                        break
+                    }
+                }
+                Err(ref err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(ref err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(err) => {
+                    accept_remote(AcceptedType::Error(err));
+                    break
+                }
+            }
+        }
+    }
+
+    /// Holds an accepted stream until its header is read or its deadline is reached.
+    /// Returns `false` if it could not be held.
+    fn add(&self, mut stream: TcpStream, addr: SocketAddr) -> bool {
+        let waker = self.waker.as_ref().expect("The listener must be registered");
+        if let Err(err) = waker.attach(&mut stream) {
+            log::error!("PROXY protocol register error for {}: {}", addr, err);
+            return false
+        }
+
+        let deadline = Instant::now() + HEADER_TIMEOUT;
+        waker.wake_at(deadline);
+        let pending = PendingStream { stream, addr, header: Vec::new(), deadline };
+        self.pending.lock().expect(OTHER_THREAD_ERR).push(pending);
+        self.pending_count.fetch_add(1, Ordering::AcqRel);
+        true
+    }
+}
+
+/// Reads the header without reading further data.
+/// Returns the client address once the header is completed, or `None` if more data is needed.
+fn read_header(
+    stream: &mut TcpStream,
+    header: &mut Vec<u8>,
+    addr: SocketAddr,
+) -> io::Result<Option<SocketAddr>> {
+    loop {
+        let missing = missing_len(header)?;
+        if missing == 0 {
+            return parse_header(header, addr).map(Some)
+        }
+
+        let mut buffer = [0; V1_MAX_LEN];
+        let buffer = &mut buffer[..missing.min(V1_MAX_LEN)];
+        match stream.read(buffer) {
+            Ok(0) => return Err(io::Error::new(ErrorKind::UnexpectedEof, "Connection closed")),
+            Ok(size) => header.extend_from_slice(&buffer[..size]),
+            Err(ref err) if err.kind() == ErrorKind::WouldBlock => return Ok(None),
+            Err(ref err) if err.kind() == ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+/// Number of bytes that are known to be part of the header and have not been read yet.
+/// The version 1 header is read byte by byte because its length is unknown until its end.
+fn missing_len(header: &[u8]) -> io::Result<usize> {
+    if header.is_empty() {
+        return Ok(1)
+    }
+
+    if header.starts_with(&V2_SIGNATURE[..header.len().min(V2_SIGNATURE.len())]) {
+        if header.len() < V2_FIXED_LEN {
+            return Ok(V2_FIXED_LEN - header.len())
+        }
+        let len = u16::from_be_bytes([header[14], header[15]]) as usize;
+        return Ok(V2_FIXED_LEN + len - header.len())
+    }
+
+    if header.starts_with(&V1_PREFIX[..header.len().min(V1_PREFIX.len())]) {
+        if header.ends_with(b"\r\n") {
+            return Ok(0)
+        }
+        if header.len() == V1_MAX_LEN {
+            return Err(malformed("the version 1 header is too long"))
+        }
+        return Ok(1)
+    }
+
+    Err(malformed("missing signature"))
+}
+
+/// Parses a completed header.
+/// The address of the connection (`addr`) is returned for the headers without a client address
+/// (`UNKNOWN` in version 1, `LOCAL` or an unsupported family in version 2).
+fn parse_header(header: &[u8], addr: SocketAddr) -> io::Result<SocketAddr> {
+    match header.starts_with(V1_PREFIX) {
+        true => parse_v1(header, addr),
+        false => parse_v2(header, addr),
+    }
+}
+
+fn parse_v1(header: &[u8], addr: SocketAddr) -> io::Result<SocketAddr> {
+    let header = std::str::from_utf8(&header[..header.len() - 2])
+        .map_err(|_| malformed("the version 1 header is not valid text"))?;
+
+    let fields: Vec<&str> = header.split(' ').collect();
+    match fields.as_slice() {
+        ["PROXY", "UNKNOWN", ..] => Ok(addr),
+        ["PROXY", protocol, source_ip, _, source_port, _] => {
+            let ip: IpAddr = source_ip.parse().map_err(|_| malformed("invalid source address"))?;
+            let port: u16 = source_port.parse().map_err(|_| malformed("invalid source port"))?;
+            match (*protocol, ip) {
+                ("TCP4", IpAddr::V4(_)) | ("TCP6", IpAddr::V6(_)) => Ok(SocketAddr::new(ip, port)),
+                _ => Err(malformed("invalid protocol")),
+            }
+        }
+        _ => Err(malformed("invalid version 1 header")),
+    }
+}
+
+fn parse_v2(header: &[u8], addr: SocketAddr) -> io::Result<SocketAddr> {
+    let version_command = header[12];
+    let family = header[13] >> 4;
+    let addresses = &header[V2_FIXED_LEN..];
+
+    if version_command >> 4 != 2 {
+        return Err(malformed("invalid version"))
+    }
+    match version_command & 0x0F {
+        0 => return Ok(addr), // LOCAL: health checks of the proxy itself
+        1 => (),              // PROXY
+        _ => return Err(malformed("invalid command")),
+    }
+
+    match family {
+        // AF_INET: source address, destination address, source port and destination port
+        1 if addresses.len() >= 12 => {
+            let ip = Ipv4Addr::new(addresses[0], addresses[1], addresses[2], addresses[3]);
+            let port = u16::from_be_bytes([addresses[8], addresses[9]]);
+            Ok(SocketAddr::new(IpAddr::V4(ip), port))
+        }
+        // AF_INET6: as AF_INET but with 16 byte addresses
+        2 if addresses.len() >= 36 => {
+            let mut octets = [0; 16];
+            octets.copy_from_slice(&addresses[..16]);
+            let port = u16::from_be_bytes([addresses[32], addresses[33]]);
+            Ok(SocketAddr::new(IpAddr::V6(Ipv6Addr::from(octets)), port))
+        }
+        1 | 2 => Err(malformed("the addresses are too short")),
+        _ => Ok(addr), // AF_UNSPEC or AF_UNIX have no client IP address
+    }
+}
+
+fn malformed(reason: &str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, format!("Malformed PROXY protocol header: {}", reason))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ADDR: &str = "10.0.0.1:4000";
+
+    fn parse(header: &[u8]) -> io::Result<SocketAddr> {
+        assert_eq!(0, missing_len(header)?);
+        parse_header(header, ADDR.parse().unwrap())
+    }
+
+    #[test]
+    fn parse_v1_header() {
+        let header = b"PROXY TCP4 192.168.0.1 192.168.0.11 56324 443\r\n";
+        assert_eq!("192.168.0.1:56324".parse::<SocketAddr>().unwrap(), parse(header).unwrap());
+
+        let header = b"PROXY TCP6 ::1 ::2 56324 443\r\n";
+        assert_eq!("[::1]:56324".parse::<SocketAddr>().unwrap(), parse(header).unwrap());
+
+        let header = b"PROXY UNKNOWN\r\n";
+        assert_eq!(ADDR.parse::<SocketAddr>().unwrap(), parse(header).unwrap());
+
+        assert!(parse(b"PROXY TCP6 192.168.0.1 192.168.0.11 56324 443\r\n").is_err());
+        assert!(parse(b"PROXY TCP4 192.168.0.1 56324\r\n").is_err());
+        assert!(missing_len(b"GET / HTTP/1.1\r\n").is_err());
+        assert!(missing_len(&[b'P'; V1_MAX_LEN]).is_err());
+    }
+
+    #[test]
+    fn parse_v2_header() {
+        let mut header = V2_SIGNATURE.to_vec();
+        header.extend_from_slice(&[0x21, 0x11, 0, 12]); // PROXY, AF_INET and STREAM
+        header.extend_from_slice(&[192, 168, 0, 1, 192, 168, 0, 11, 0xDC, 0x04, 0x01, 0xBB]);
+        assert_eq!("192.168.0.1:56324".parse::<SocketAddr>().unwrap(), parse(&header).unwrap());
+
+        let mut header = V2_SIGNATURE.to_vec();
+        header.extend_from_slice(&[0x20, 0x00, 0, 0]); // LOCAL
+        assert_eq!(ADDR.parse::<SocketAddr>().unwrap(), parse(&header).unwrap());
+
+        let mut header = V2_SIGNATURE.to_vec();
+        header.extend_from_slice(&[0x21, 0x11, 0, 4, 192, 168, 0, 1]);
+        assert!(parse(&header).is_err());
+
+        let mut header = V2_SIGNATURE.to_vec();
+        header.extend_from_slice(&[0x11, 0x11, 0, 0]); // Version 1 in a version 2 header
+        assert!(parse(&header).is_err());
+    }
+}
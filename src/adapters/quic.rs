@@ -0,0 +1,741 @@
+use crate::network::adapter::{
+    Resource, Remote, Local, Adapter, SendStatus, AcceptedType, AcceptStatus, ReadStatus,
+    DisconnectReason, ConnectionInfo, ListeningInfo, ResourceWaker, wrong_config_error,
+};
+use crate::network::{
+    RemoteAddr, TransportConnect, TransportListen, ConnectOptions, ListenOptions, CloseReason,
+};
+use crate::util::encoding::{self, Decoder, MAX_ENCODED_SIZE};
+use crate::util::thread::{OTHER_THREAD_ERR};
+
+use mio::net::{UdpSocket};
+use mio::event::{Source};
+use mio::{Token, Interest};
+
+use quinn_proto::{
+    Endpoint, EndpointConfig, ServerConfig, ClientConfig, TransportConfig, Connection,
+    ConnectionHandle, ConnectionError, DatagramEvent, Event, StreamEvent, StreamId, Dir, VarInt,
+    ReadError, WriteError,
+};
+use quinn_proto::crypto::rustls::{QuicClientConfig, QuicServerConfig};
+
+use bytes::{Bytes, BytesMut};
+
+use std::net::{SocketAddr, UdpSocket as StdUdpSocket};
+use std::collections::{HashMap, VecDeque};
+use std::cell::{RefCell};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use std::io::{self, ErrorKind};
+use std::mem::{MaybeUninit};
+use std::convert::{TryFrom};
+
+const INPUT_BUFFER_SIZE: usize = u16::MAX as usize; // 2^16 - 1
+
+/// Application protocol negotiated by the QUIC connections.
+const ALPN_PROTOCOL: &[u8] = b"message-io";
+
+/// Interval to send keep alive packets in order to avoid the idle timeout of the connection.
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Maximum time that a removed connection waits to deliver its pending data before closing.
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Minimum time without answers from the peer after which a closing connection stops waiting
+/// for it, since it is considered unavailable.
+const CLOSE_PEER_TIMEOUT: Duration = Duration::from_millis(200);
+
+pub(crate) struct QuicAdapter;
+impl Adapter for QuicAdapter {
+    type Remote = RemoteResource;
+    type Local = LocalResource;
+}
+
+/// Events of the connections processed by [`QuicState::drive()`].
+enum QuicEvent {
+    Data(ConnectionHandle, SocketAddr, Bytes),
+    Finished,
+    Lost(ConnectionHandle, ConnectionError),
+}
+
+/// Progress of the graceful close of a [`QuicConnection`].
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum CloseState {
+    Open,
+    /// The stream is finished once its pending data is accepted.
+    Closing,
+    /// The stream was finished, the connection is closed once all the data is acknowledged.
+    Finished,
+}
+
+/// A QUIC connection with a bidirectional stream used to send framed messages.
+struct QuicConnection {
+    connection: Connection,
+    stream: Option<StreamId>,
+    outbound: VecDeque<u8>, // Framed data that the stream has not accepted yet.
+    close_state: CloseState,
+}
+
+impl QuicConnection {
+    fn new(connection: Connection) -> Self {
+        Self { connection, stream: None, outbound: VecDeque::new(), close_state: CloseState::Open }
+    }
+
+    fn write(&mut self, data: &[u8]) -> SendStatus {
+        if self.stream.is_none() || self.connection.is_closed() {
+            return SendStatus::ResourceNotFound
+        }
+
+        let mut buf = [0; MAX_ENCODED_SIZE]; // used to avoid a heap allocation
+        self.outbound.extend(encoding::encode_size(data, &mut buf));
+        self.outbound.extend(data);
+        match self.flush() {
+            Ok(()) => SendStatus::Sent,
+            Err(err) => {
+                log::error!("QUIC send error: {}", err);
+                SendStatus::ResourceNotFound
+            }
+        }
+    }
+
+    fn flush(&mut self) -> Result<(), WriteError> {
+        if let Some(id) = self.stream {
+            while !self.outbound.is_empty() {
+                let (data, _) = self.outbound.as_slices();
+                match self.connection.send_stream(id).write(data) {
+                    Ok(size) => drop(self.outbound.drain(..size)),
+                    Err(WriteError::Blocked) => break, // Flushed again when writable.
+                    Err(err) => return Err(err),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Finishes the stream of a closing connection once all its data was accepted.
+    fn finish(&mut self) {
+        if let (CloseState::Closing, true, Some(id)) =
+            (self.close_state, self.outbound.is_empty(), self.stream)
+        {
+            if let Err(err) = self.connection.send_stream(id).finish() {
+                log::trace!("QUIC stream could not be finished: {}", err);
+            }
+            self.close_state = CloseState::Finished;
+        }
+    }
+
+    fn read(&mut self, id: StreamId, mut process_data: impl FnMut(Bytes)) {
+        let mut stream = self.connection.recv_stream(id);
+        let mut chunks = match stream.read(true) {
+            Ok(chunks) => chunks,
+            Err(_) => return, // The stream was already finished by the peer.
+        };
+        loop {
+            match chunks.next(usize::MAX) {
+                Ok(Some(chunk)) => process_data(chunk.bytes),
+                Ok(None) => break, // The stream was finished by the peer.
+                Err(ReadError::Blocked) => break,
+                Err(ReadError::Reset(_)) => break,
+            }
+        }
+        let _ = chunks.finalize(); // Any required transmission is done after driving.
+    }
+
+    fn transmit(&mut self, now: Instant, buffer: &mut Vec<u8>, send: &dyn Fn(&[u8], SocketAddr)) {
+        while let Some(transmit) = self.connection.poll_transmit(now, 1, buffer) {
+            send(&buffer[..transmit.size], transmit.destination);
+            buffer.clear();
+        }
+    }
+}
+
+/// State shared by the QUIC connections of a socket.
+struct QuicState {
+    endpoint: Endpoint,
+    connections: HashMap<ConnectionHandle, QuicConnection>,
+    handles: HashMap<SocketAddr, ConnectionHandle>,
+    buffer: Vec<u8>,
+    wake_deadline: Option<Instant>, // Earliest wake up scheduled
+}
+
+impl QuicState {
+    fn new(endpoint: Endpoint) -> Self {
+        Self {
+            endpoint,
+            connections: HashMap::new(),
+            handles: HashMap::new(),
+            buffer: Vec::new(),
+            wake_deadline: None,
+        }
+    }
+
+    fn insert(&mut self, handle: ConnectionHandle, connection: Connection) {
+        self.handles.insert(connection.remote_address(), handle);
+        self.connections.insert(handle, QuicConnection::new(connection));
+    }
+
+    fn handle_datagram(
+        &mut self,
+        now: Instant,
+        addr: SocketAddr,
+        data: &[u8],
+        send: &dyn Fn(&[u8], SocketAddr),
+    ) {
+        let data = BytesMut::from(data);
+        match self.endpoint.handle(now, addr, None, None, data, &mut self.buffer) {
+            Some(DatagramEvent::ConnectionEvent(handle, event)) => {
+                if let Some(quic) = self.connections.get_mut(&handle) {
+                    quic.connection.handle_event(event);
+                }
+            }
+            Some(DatagramEvent::NewConnection(incoming)) => {
+                match self.endpoint.accept(incoming, now, &mut self.buffer, None) {
+                    Ok((handle, connection)) => self.insert(handle, connection),
+                    Err(err) => {
+                        if let Some(transmit) = err.response {
+                            send(&self.buffer[..transmit.size], transmit.destination);
+                        }
+                        log::trace!("QUIC connection from {} not accepted: {}", addr, err.cause);
+                    }
+                }
+            }
+            Some(DatagramEvent::Response(transmit)) => {
+                send(&self.buffer[..transmit.size], transmit.destination);
+            }
+            None => (),
+        }
+        self.buffer.clear();
+    }
+
+    /// Processes the timeouts and the events of the connections,
+    /// transmitting the packets generated by them.
+    fn drive(
+        &mut self,
+        now: Instant,
+        send: &dyn Fn(&[u8], SocketAddr),
+        events: &mut Vec<QuicEvent>,
+    ) {
+        let QuicState { endpoint, connections, handles, buffer, .. } = self;
+        connections.retain(|&handle, quic| {
+            if quic.connection.poll_timeout().is_some_and(|timeout| timeout <= now) {
+                quic.connection.handle_timeout(now);
+            }
+
+            let addr = quic.connection.remote_address();
+            while let Some(event) = quic.connection.poll() {
+                match event {
+                    Event::Stream(StreamEvent::Opened { dir: Dir::Bi }) => {
+                        // The data that opened the stream does not generate a readable event.
+                        while let Some(id) = quic.connection.streams().accept(Dir::Bi) {
+                            quic.stream.get_or_insert(id);
+                            quic.read(id, |data| events.push(QuicEvent::Data(handle, addr, data)));
+                        }
+                    }
+                    Event::Stream(StreamEvent::Readable { id }) => {
+                        quic.read(id, |data| events.push(QuicEvent::Data(handle, addr, data)));
+                    }
+                    Event::Stream(StreamEvent::Finished { .. }) => {
+                        events.push(QuicEvent::Finished);
+                        // All the data of a closing connection was acknowledged by the peer.
+                        if quic.close_state == CloseState::Finished {
+                            quic.connection.close(now, VarInt::from_u32(0), Bytes::new());
+                            events.push(QuicEvent::Lost(handle, ConnectionError::LocallyClosed));
+                        }
+                    }
+                    Event::ConnectionLost { reason } => {
+                        events.push(QuicEvent::Lost(handle, reason));
+                    }
+                    _ => (),
+                }
+            }
+
+            if let Err(err) = quic.flush() {
+                log::trace!("QUIC pending data to {} could not be sent: {}", addr, err);
+            }
+            quic.finish();
+            quic.transmit(now, buffer, send);
+
+            while let Some(event) = quic.connection.poll_endpoint_events() {
+                if let Some(event) = endpoint.handle_event(handle, event) {
+                    quic.connection.handle_event(event);
+                }
+            }
+
+            let drained = quic.connection.is_drained();
+            if drained && handles.get(&addr) == Some(&handle) {
+                handles.remove(&addr);
+            }
+            !drained
+        });
+    }
+
+    fn send(
+        &mut self,
+        handle: ConnectionHandle,
+        data: &[u8],
+        send: &dyn Fn(&[u8], SocketAddr),
+    ) -> SendStatus {
+        match self.connections.get_mut(&handle) {
+            Some(quic) => {
+                let status = quic.write(data);
+                quic.transmit(Instant::now(), &mut self.buffer, send);
+                status
+            }
+            None => SendStatus::ResourceNotFound,
+        }
+    }
+
+    /// Starts the graceful close of a connection: its stream is finished once its data is
+    /// written, and the connection is closed once the peer has acknowledged all of it.
+    /// Returns `false` if the connection has no stream to finish.
+    fn start_closing(&mut self, handle: ConnectionHandle) -> bool {
+        match self.connections.get_mut(&handle) {
+            Some(quic) if quic.stream.is_some() && !quic.connection.is_closed() => {
+                quic.close_state = CloseState::Closing;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn close(&mut self, send: &dyn Fn(&[u8], SocketAddr)) {
+        let now = Instant::now();
+        for quic in self.connections.values_mut() {
+            quic.connection.close(now, VarInt::from_u32(0), Bytes::new());
+            quic.transmit(now, &mut self.buffer, send);
+        }
+    }
+
+    /// Returns the earliest time in which a connection needs to be driven.
+    fn poll_timeout(&mut self) -> Option<Instant> {
+        self.connections.values_mut().filter_map(|quic| quic.connection.poll_timeout()).min()
+    }
+}
+
+/// UDP socket used by the QUIC connections along with the [`ResourceWaker`] used by their timers.
+struct QuicSocket {
+    socket: UdpSocket,
+    connected: bool,
+    waker: Option<ResourceWaker>, // Set once the resource is registered
+    state: Mutex<QuicState>,
+}
+
+impl QuicSocket {
+    fn new(socket: UdpSocket, connected: bool, state: QuicState) -> Self {
+        Self { socket, connected, waker: None, state: Mutex::new(state) }
+    }
+
+    fn set_waker(&mut self, waker: ResourceWaker) {
+        self.waker = Some(waker);
+        self.schedule_wake(&mut self.state.lock().expect(OTHER_THREAD_ERR));
+    }
+
+    fn send_datagram(&self, data: &[u8], addr: SocketAddr) {
+        let result = match self.connected {
+            true => self.socket.send(data),
+            false => self.socket.send_to(data, addr),
+        };
+        if let Err(err) = result {
+            // If the packet is lost, QUIC will send it again.
+            log::trace!("QUIC datagram to {} not sent: {}", addr, err);
+        }
+    }
+
+    /// Schedules a wake up at the next timeout of the connections,
+    /// unless there is an earlier one still pending.
+    fn schedule_wake(&self, state: &mut QuicState) {
+        if let (Some(waker), Some(deadline)) = (&self.waker, state.poll_timeout()) {
+            match state.wake_deadline {
+                Some(current) if current > Instant::now() && current <= deadline => (),
+                _ => {
+                    state.wake_deadline = Some(deadline);
+                    waker.wake_at(deadline);
+                }
+            }
+        }
+    }
+
+    /// Reads all the received datagrams and drives the connections.
+    /// It only fails if the peer of a connected socket is not reachable.
+    fn process(&self, events: &mut Vec<QuicEvent>) -> io::Result<()> {
+        let buffer: MaybeUninit<[u8; INPUT_BUFFER_SIZE]> = MaybeUninit::uninit();
+        let mut input_buffer = unsafe { buffer.assume_init() }; // Avoid to initialize the array
+
+        let send = |data: &[u8], addr| self.send_datagram(data, addr);
+        let mut state = self.state.lock().expect(OTHER_THREAD_ERR);
+        let result = loop {
+            match self.socket.recv_from(&mut input_buffer) {
+                Ok((size, addr)) => {
+                    state.handle_datagram(Instant::now(), addr, &input_buffer[..size], &send)
+                }
+                Err(ref err) if err.kind() == ErrorKind::WouldBlock => break Ok(()),
+                Err(ref err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(err) if self.connected => break Err(err),
+                Err(_) => continue, // Generated by ICMP packets from other peers.
+            }
+        };
+
+        state.drive(Instant::now(), &send, events);
+        self.schedule_wake(&mut state);
+        result
+    }
+
+    fn send(&self, handle: ConnectionHandle, data: &[u8]) -> SendStatus {
+        let mut state = self.state.lock().expect(OTHER_THREAD_ERR);
+        let status = state.send(handle, data, &|data, addr| self.send_datagram(data, addr));
+        self.schedule_wake(&mut state);
+        status
+    }
+}
+
+impl Drop for QuicSocket {
+    fn drop(&mut self) {
+        let QuicSocket { socket, connected, state, .. } = self;
+        let send = |data: &[u8], addr| {
+            let _ = match connected {
+                true => socket.send(data),
+                false => socket.send_to(data, addr),
+            };
+        };
+        state.get_mut().expect(OTHER_THREAD_ERR).close(&send);
+    }
+}
+
+fn transport_config() -> Arc<TransportConfig> {
+    let mut transport_config = TransportConfig::default();
+    transport_config.keep_alive_interval(Some(KEEP_ALIVE_INTERVAL));
+    Arc::new(transport_config)
+}
+
+fn quic_err(err: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> io::Error {
+    io::Error::new(ErrorKind::InvalidInput, err)
+}
+
+pub(crate) struct RemoteResource {
+    socket: QuicSocket,
+    handle: ConnectionHandle,
+    decoder: RefCell<Decoder>,
+}
+
+// SAFETY:
+// That RefCell<Decoder> can be used with Sync because the decoder is only used in the read_event.
+// This way, we save the cost of a Mutex.
+unsafe impl Sync for RemoteResource {}
+
+impl Resource for RemoteResource {
+    fn source(&mut self) -> &mut dyn Source {
+        &mut self.socket.socket
+    }
+
+    fn set_waker(&mut self, waker: ResourceWaker) {
+        self.socket.set_waker(waker);
+    }
+}
+
+impl Remote for RemoteResource {
+    fn connect(
+        config: TransportConnect,
+        remote_addr: RemoteAddr,
+        options: ConnectOptions,
+    ) -> io::Result<ConnectionInfo<Self>> {
+        options.check_no_proxy()?;
+
+        #[allow(unreachable_patterns)]
+        let config = match config {
+            TransportConnect::Quic(config) => config,
+            _ => return Err(wrong_config_error()),
+        };
+
+        let host = match remote_addr.host() {
+            Some(host) => host,
+            None => {
+                let err = "A QUIC connection can not be connected to a path";
+                return Err(io::Error::new(ErrorKind::InvalidInput, err))
+            }
+        };
+        let peer_addr = remote_addr.resolve()?;
+
+        let mut crypto = config.rustls_config()?;
+        crypto.alpn_protocols = vec![ALPN_PROTOCOL.to_vec()];
+        let mut client_config =
+            ClientConfig::new(Arc::new(QuicClientConfig::try_from(crypto).map_err(quic_err)?));
+        client_config.transport_config(transport_config());
+
+        let socket = StdUdpSocket::bind(options.bind_addr(peer_addr))?;
+        socket.connect(peer_addr)?;
+        let local_addr = socket.local_addr()?;
+
+        let mut endpoint = Endpoint::new(Arc::new(EndpointConfig::default()), None, false, None);
+        let domain = config.domain.as_deref().unwrap_or(&host);
+        let (handle, connection) =
+            endpoint.connect(Instant::now(), client_config, peer_addr, domain).map_err(quic_err)?;
+        let mut state = QuicState::new(endpoint);
+        state.insert(handle, connection);
+
+        // Synchronous waiting for the QUIC handshake
+        handshake(&socket, handle, &mut state)?;
+
+        socket.set_nonblocking(true)?;
+        let socket = QuicSocket::new(UdpSocket::from_std(socket), true, state);
+        let remote = RemoteResource { socket, handle, decoder: RefCell::new(Decoder::default()) };
+        Ok(ConnectionInfo { remote, local_addr, peer_addr })
+    }
+
+    fn receive(&self, mut process_data: impl FnMut(&[u8])) -> ReadStatus {
+        let mut events = Vec::new();
+        let mut status = match self.socket.process(&mut events) {
+            Ok(()) => ReadStatus::WaitNextEvent,
+            Err(err) => {
+                log::trace!("QUIC peer not reachable: {}", err);
+                ReadStatus::Disconnected(DisconnectReason::from(&err))
+            }
+        };
+
+        // The data is processed without the lock, the user could send from the callback.
+        let mut decoder = self.decoder.borrow_mut();
+        for event in events {
+            match event {
+                QuicEvent::Data(_, _, data) => {
+                    log::trace!("Decoding data, {} bytes", data.len());
+                    decoder.decode(&data, |decoded_data| process_data(decoded_data));
+                }
+                QuicEvent::Finished => (),
+                QuicEvent::Lost(_, reason) => {
+                    log::trace!("QUIC connection lost: {}", reason);
+                    status = ReadStatus::Disconnected(lost_reason(&reason));
+                }
+            }
+        }
+        status
+    }
+
+    fn send(&self, data: &[u8]) -> SendStatus {
+        self.socket.send(self.handle, data)
+    }
+
+    fn close(&self, _reason: &CloseReason) -> bool {
+        // The closing connection is driven by the next receive.
+        self.socket.state.lock().expect(OTHER_THREAD_ERR).start_closing(self.handle)
+    }
+}
+
+fn lost_reason(error: &ConnectionError) -> DisconnectReason {
+    match error {
+        ConnectionError::ConnectionClosed(_)
+        | ConnectionError::ApplicationClosed(_)
+        | ConnectionError::LocallyClosed => DisconnectReason::Closed,
+        ConnectionError::Reset => DisconnectReason::Reset,
+        ConnectionError::TimedOut => DisconnectReason::Timeout,
+        ConnectionError::VersionMismatch | ConnectionError::TransportError(_) => {
+            DisconnectReason::Protocol(error.to_string())
+        }
+        _ => DisconnectReason::Error(error.to_string()),
+    }
+}
+
+impl Drop for RemoteResource {
+    fn drop(&mut self) {
+        // As the OS does with a TCP socket, the pending data is delivered before closing.
+        // At this point the socket is no longer registered in the node poll,
+        // so a poll of its own is used to wait for the answers of the peer.
+        let mut poll = match mio::Poll::new() {
+            Ok(poll) => poll,
+            Err(_) => return,
+        };
+        let socket = &mut self.socket.socket;
+        if poll.registry().register(socket, Token(0), Interest::READABLE).is_err() {
+            return
+        }
+
+        let deadline = Instant::now() + CLOSE_TIMEOUT;
+        let mut last_answer = Instant::now();
+        let mut received = 0;
+        let mut finishing = false;
+        let mut events = Vec::new();
+        let mut poll_events = mio::Events::with_capacity(8);
+        loop {
+            let state = self.socket.state.get_mut().expect(OTHER_THREAD_ERR);
+            let quic = match state.connections.get_mut(&self.handle) {
+                Some(quic) if !quic.connection.is_closed() => quic,
+                _ => break,
+            };
+            if !finishing && quic.outbound.is_empty() {
+                match quic.stream.map(|id| quic.connection.send_stream(id).finish()) {
+                    Some(Ok(())) => finishing = true,
+                    _ => break,
+                }
+            }
+
+            if self.socket.process(&mut events).is_err() {
+                break
+            }
+            if events.iter().any(|event| !matches!(event, QuicEvent::Data(..))) {
+                break // Finished (all the data was acknowledged) or lost.
+            }
+            events.clear();
+
+            let state = self.socket.state.get_mut().expect(OTHER_THREAD_ERR);
+            let quic = match state.connections.get_mut(&self.handle) {
+                Some(quic) => &mut quic.connection,
+                None => break,
+            };
+            let now = Instant::now();
+            if quic.stats().udp_rx.datagrams != received {
+                received = quic.stats().udp_rx.datagrams;
+                last_answer = now;
+            }
+            let peer_deadline = last_answer + CLOSE_PEER_TIMEOUT.max(quic.rtt() * 3);
+            if now >= deadline || now >= peer_deadline {
+                break
+            }
+
+            let wake =
+                quic.poll_timeout().map_or(peer_deadline, |timeout| timeout.min(peer_deadline));
+            let timeout = deadline.min(wake).saturating_duration_since(now);
+            if poll.poll(&mut poll_events, Some(timeout)).is_err() {
+                break
+            }
+        }
+    }
+}
+
+/// Drives the connection until its handshake finishes, opening the stream used for the messages.
+fn handshake(
+    socket: &StdUdpSocket,
+    handle: ConnectionHandle,
+    state: &mut QuicState,
+) -> io::Result<()> {
+    let mut input_buffer = vec![0; INPUT_BUFFER_SIZE];
+    let send = |data: &[u8], _| {
+        let _ = socket.send(data); // If the packet is lost, QUIC will send it again.
+    };
+
+    let mut events = Vec::new();
+    loop {
+        state.drive(Instant::now(), &send, &mut events);
+        if let Some(QuicEvent::Lost(_, reason)) =
+            events.drain(..).find(|event| matches!(event, QuicEvent::Lost(..)))
+        {
+            return Err(io::Error::new(ErrorKind::ConnectionAborted, reason))
+        }
+
+        let quic = state.connections.get_mut(&handle).ok_or_else(|| {
+            io::Error::new(ErrorKind::ConnectionAborted, "The QUIC handshake was aborted")
+        })?;
+        if !quic.connection.is_handshaking() {
+            quic.stream = quic.connection.streams().open(Dir::Bi);
+            return Ok(())
+        }
+
+        let timeout = quic.connection.poll_timeout().map(|timeout| {
+            timeout.saturating_duration_since(Instant::now()).max(Duration::from_millis(1))
+        });
+        socket.set_read_timeout(timeout)?;
+        match socket.recv_from(&mut input_buffer) {
+            Ok((size, addr)) => {
+                state.handle_datagram(Instant::now(), addr, &input_buffer[..size], &send)
+            }
+            Err(ref err) if err.kind() == ErrorKind::WouldBlock => (),
+            Err(ref err) if err.kind() == ErrorKind::TimedOut => (),
+            Err(ref err) if err.kind() == ErrorKind::Interrupted => (),
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+pub(crate) struct LocalResource {
+    socket: QuicSocket,
+    decoders: RefCell<HashMap<ConnectionHandle, Decoder>>,
+}
+
+// SAFETY:
+// That RefCell<HashMap<_, Decoder>> can be used with Sync because the decoders are only used
+// in the accept_event.
+// This way, we save the cost of a Mutex.
+unsafe impl Sync for LocalResource {}
+
+impl Resource for LocalResource {
+    fn source(&mut self) -> &mut dyn Source {
+        &mut self.socket.socket
+    }
+
+    fn set_waker(&mut self, waker: ResourceWaker) {
+        self.socket.set_waker(waker);
+    }
+}
+
+impl Local for LocalResource {
+    type Remote = RemoteResource;
+
+    fn listen(
+        config: TransportListen,
+        addr: RemoteAddr,
+        options: ListenOptions,
+    ) -> io::Result<ListeningInfo<Self>> {
+        options.check_no_proxy_protocol()?;
+
+        #[allow(unreachable_patterns)]
+        let config = match config {
+            TransportListen::Quic(config) => config,
+            _ => return Err(wrong_config_error()),
+        };
+        let addr = addr.resolve()?;
+
+        let mut crypto = config.rustls_config()?;
+        crypto.alpn_protocols = vec![ALPN_PROTOCOL.to_vec()];
+        let mut server_config = ServerConfig::with_crypto(Arc::new(
+            QuicServerConfig::try_from(crypto).map_err(quic_err)?,
+        ));
+        server_config.transport_config(transport_config());
+
+        let socket = UdpSocket::bind(addr)?;
+        let local_addr = socket.local_addr()?;
+
+        let endpoint = Endpoint::new(
+            Arc::new(EndpointConfig::default()),
+            Some(Arc::new(server_config)),
+            false,
+            None,
+        );
+        let socket = QuicSocket::new(socket, false, QuicState::new(endpoint));
+        let local = LocalResource { socket, decoders: RefCell::new(HashMap::new()) };
+        Ok(ListeningInfo { local, local_addr })
+    }
+
+    fn accept(
+        &self,
+        mut accept_remote: impl FnMut(AcceptedType<'_, Self::Remote>) -> AcceptStatus,
+    ) {
+        let mut events = Vec::new();
+        if let Err(err) = self.socket.process(&mut events) {
+            accept_remote(AcceptedType::Error(err));
+        }
+
+        // The data is processed without the lock, the user could send from the callback.
+        let mut decoders = self.decoders.borrow_mut();
+        for event in events {
+            match event {
+                QuicEvent::Data(handle, addr, data) => {
+                    log::trace!("Decoding data, {} bytes", data.len());
+                    let decoder = decoders.entry(handle).or_default();
+                    decoder.decode(&data, |decoded_data| {
+                        accept_remote(AcceptedType::Data(addr, decoded_data));
+                    });
+                }
+                QuicEvent::Finished => (),
+                QuicEvent::Lost(handle, reason) => {
+                    log::trace!("QUIC connection lost: {}", reason);
+                    decoders.remove(&handle);
+                }
+            }
+        }
+    }
+
+    fn send_to(&self, addr: SocketAddr, data: &[u8]) -> SendStatus {
+        let handle = self.socket.state.lock().expect(OTHER_THREAD_ERR).handles.get(&addr).copied();
+        match handle {
+            Some(handle) => self.socket.send(handle, data),
+            None => SendStatus::ResourceNotFound,
+        }
+    }
+}
@@ -0,0 +1,824 @@
+use crate::network::adapter::{
+    Resource, Remote, Local, Adapter, SendStatus, AcceptedType, AcceptStatus, ReadStatus,
+    DisconnectReason, ConnectionInfo, ListeningInfo, ResourceWaker,
+};
+use crate::network::{
+    RemoteAddr, TransportConnect, TransportListen, ConnectOptions, ListenOptions, CloseReason,
+};
+use crate::util::thread::{OTHER_THREAD_ERR};
+
+use super::udp::{self};
+
+use mio::net::{UdpSocket};
+use mio::event::{Source};
+use mio::{Registry, Token, Interest};
+
+use std::net::{SocketAddr, UdpSocket as StdUdpSocket};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use std::io::{self, ErrorKind};
+use std::mem::{MaybeUninit};
+use std::convert::{TryInto};
+
+const INPUT_BUFFER_SIZE: usize = udp::MAX_PAYLOAD_LEN;
+
+/// Size of the header added to each message: the packet kind and the sequence number.
+const HEADER_LEN: usize = 5;
+
+/// Maximum payload that a message can have.
+/// Messages are not fragmented, each one is sent in its own datagram.
+pub const MAX_PAYLOAD_LEN: usize = udp::MAX_COMPATIBLE_PAYLOAD_LEN - HEADER_LEN;
+
+/// Maximum number of messages sent and not acknowledged yet.
+const WINDOW_SIZE: usize = 256;
+
+/// Retransmission timeout used until the round trip time is measured.
+const INITIAL_RTO: Duration = Duration::from_millis(200);
+const MIN_RTO: Duration = Duration::from_millis(20);
+const MAX_RTO: Duration = Duration::from_secs(2);
+
+/// Number of retransmissions of a message without acknowledgement
+/// after which the connection is considered lost.
+const MAX_RETRANSMISSIONS: u32 = 10;
+
+/// Interval to send an acknowledgement if nothing was sent, in order to show that it is alive.
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Time without receiving anything from the peer after which the connection is considered lost.
+const CONNECTION_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Maximum time that a connect waits for the answer of the listener
+/// if no timeout is given in its options.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Packet kinds
+const CONNECT: u8 = 0;
+const ACCEPT: u8 = 1;
+const DATA: u8 = 2;
+const ACK: u8 = 3;
+const DISCONNECT: u8 = 4;
+
+enum Packet<'a> {
+    Connect,
+    Accept,
+    /// Message with its sequence number.
+    Data(u32, &'a [u8]),
+    /// Next expected sequence number and a bit by each of the 32 following ones,
+    /// set if the message was received.
+    Ack(u32, u32),
+    Disconnect,
+}
+
+impl<'a> Packet<'a> {
+    fn decode(data: &'a [u8]) -> Option<Packet<'a>> {
+        let number =
+            |from: usize| Some(u32::from_be_bytes(data.get(from..from + 4)?.try_into().ok()?));
+        match *data.first()? {
+            CONNECT => Some(Packet::Connect),
+            ACCEPT => Some(Packet::Accept),
+            DATA => Some(Packet::Data(number(1)?, &data[HEADER_LEN..])),
+            ACK => Some(Packet::Ack(number(1)?, number(5)?)),
+            DISCONNECT => Some(Packet::Disconnect),
+            _ => None,
+        }
+    }
+}
+
+/// Round trip time estimation to compute the retransmission timeout (RFC 6298).
+#[derive(Default)]
+struct RttEstimator {
+    srtt: Option<Duration>,
+    rttvar: Duration,
+}
+
+impl RttEstimator {
+    fn update(&mut self, rtt: Duration) {
+        match self.srtt {
+            None => {
+                self.srtt = Some(rtt);
+                self.rttvar = rtt / 2;
+            }
+            Some(srtt) => {
+                let diff = srtt.abs_diff(rtt);
+                self.rttvar = (self.rttvar * 3 + diff) / 4;
+                self.srtt = Some((srtt * 7 + rtt) / 8);
+            }
+        }
+    }
+
+    fn rto(&self) -> Duration {
+        match self.srtt {
+            Some(srtt) => (srtt + self.rttvar * 4).max(MIN_RTO).min(MAX_RTO),
+            None => INITIAL_RTO,
+        }
+    }
+}
+
+/// Message sent and not acknowledged yet.
+struct SentPacket {
+    sequence: u32,
+    packet: Vec<u8>,
+    sent_at: Instant,
+    retransmissions: u32,
+    acked: bool,
+}
+
+impl SentPacket {
+    fn retransmission_time(&self, rto: Duration) -> Instant {
+        self.sent_at + (rto * 2u32.pow(self.retransmissions)).min(MAX_RTO)
+    }
+}
+
+/// State of a connection, independent of the socket used to send and receive its packets.
+/// The functions that need to send packets receive the function to send them.
+struct Session {
+    next_sequence: u32,
+    in_flight: VecDeque<SentPacket>, // Ordered by sequence
+    queued: VecDeque<Vec<u8>>,       // Packets waiting for room in the window
+    queued_size: usize,              // Bytes of the messages in the queued packets
+    unblocked: bool,                 // Queued packets were sent since the remote was processed
+    rtt: RttEstimator,
+    expected_sequence: u32,
+    out_of_order: HashMap<u32, Vec<u8>>,
+    ack_pending: bool,
+    inbox: VecDeque<Vec<u8>>, // Messages received in order, pending to be processed
+    last_sent: Instant,
+    last_received: Instant,
+    closing: bool, // The session is closed once the peer has everything
+    closed: Option<DisconnectReason>, // Set once the session is closed
+    waker: Option<ResourceWaker>, // Set once the remote is registered
+    wake_deadline: Option<Instant>, // Earliest wake up scheduled
+}
+
+impl Session {
+    fn new(now: Instant) -> Session {
+        Session {
+            next_sequence: 0,
+            in_flight: VecDeque::new(),
+            queued: VecDeque::new(),
+            queued_size: 0,
+            unblocked: false,
+            rtt: RttEstimator::default(),
+            expected_sequence: 0,
+            out_of_order: HashMap::new(),
+            ack_pending: false,
+            inbox: VecDeque::new(),
+            last_sent: now,
+            last_received: now,
+            closing: false,
+            closed: None,
+            waker: None,
+            wake_deadline: None,
+        }
+    }
+
+    /// Wakes the remote of the session to process it.
+    fn wake(&self) {
+        if let Some(waker) = &self.waker {
+            waker.wake();
+        }
+    }
+
+    /// Schedules a wake up of the remote at the next timeout,
+    /// unless there is an earlier one still pending.
+    fn schedule_wake(&mut self, now: Instant) {
+        if let (Some(waker), Some(deadline)) = (&self.waker, self.next_timeout()) {
+            match self.wake_deadline {
+                Some(current) if current > now && current <= deadline => (),
+                _ => {
+                    self.wake_deadline = Some(deadline);
+                    waker.wake_at(deadline);
+                }
+            }
+        }
+    }
+
+    fn is_window_full(&self) -> bool {
+        self.in_flight.len() >= WINDOW_SIZE
+    }
+
+    /// There are messages that the peer has not acknowledged yet.
+    fn is_pending(&self) -> bool {
+        !self.in_flight.is_empty() || !self.queued.is_empty()
+    }
+
+    fn send(&mut self, data: &[u8], now: Instant, out: &dyn Fn(&[u8])) -> SendStatus {
+        if self.closed.is_some() {
+            return SendStatus::ResourceNotFound
+        }
+        if data.len() > MAX_PAYLOAD_LEN {
+            return SendStatus::MaxPacketSizeExceeded(data.len(), MAX_PAYLOAD_LEN)
+        }
+
+        let mut packet = Vec::with_capacity(HEADER_LEN + data.len());
+        packet.push(DATA);
+        packet.extend_from_slice(&self.next_sequence.to_be_bytes());
+        packet.extend_from_slice(data);
+        self.next_sequence = self.next_sequence.wrapping_add(1);
+        self.queued.push_back(packet);
+        self.queued_size += data.len();
+        self.transmit_queued(now, out);
+        match self.queued.is_empty() {
+            true => SendStatus::Sent,
+            false => SendStatus::Enqueued, // Sent once the peer acknowledges the previous ones.
+        }
+    }
+
+    fn transmit_queued(&mut self, now: Instant, out: &dyn Fn(&[u8])) {
+        while !self.is_window_full() {
+            let packet = match self.queued.pop_front() {
+                Some(packet) => packet,
+                None => break,
+            };
+            self.queued_size -= packet.len() - HEADER_LEN;
+            out(&packet);
+            let sequence = u32::from_be_bytes(packet[1..HEADER_LEN].try_into().unwrap());
+            self.in_flight.push_back(SentPacket {
+                sequence,
+                packet,
+                sent_at: now,
+                retransmissions: 0,
+                acked: false,
+            });
+            self.last_sent = now;
+        }
+    }
+
+    fn handle_packet(&mut self, packet: Packet<'_>, now: Instant, out: &dyn Fn(&[u8])) {
+        self.last_received = now;
+        match packet {
+            Packet::Data(sequence, data) => self.receive_data(sequence, data),
+            Packet::Ack(expected_sequence, received) => {
+                self.receive_ack(expected_sequence, received, now, out)
+            }
+            Packet::Disconnect => self.closed = Some(DisconnectReason::Closed),
+            Packet::Connect => out(&[ACCEPT]), // The previous answer was lost.
+            Packet::Accept => (),              // Repeated answer of the connection request.
+        }
+    }
+
+    fn receive_data(&mut self, sequence: u32, data: &[u8]) {
+        self.ack_pending = true;
+        let offset = sequence.wrapping_sub(self.expected_sequence) as usize;
+        if offset >= WINDOW_SIZE {
+            return // Already received, the acknowledgement was lost.
+        }
+
+        if offset == 0 {
+            self.inbox.push_back(data.to_vec());
+            self.expected_sequence = self.expected_sequence.wrapping_add(1);
+            while let Some(data) = self.out_of_order.remove(&self.expected_sequence) {
+                self.inbox.push_back(data);
+                self.expected_sequence = self.expected_sequence.wrapping_add(1);
+            }
+        }
+        else {
+            self.out_of_order.entry(sequence).or_insert_with(|| data.to_vec());
+        }
+    }
+
+    fn receive_ack(
+        &mut self,
+        expected_sequence: u32,
+        received: u32,
+        now: Instant,
+        out: &dyn Fn(&[u8]),
+    ) {
+        for sent in self.in_flight.iter_mut().filter(|sent| !sent.acked) {
+            let offset = sent.sequence.wrapping_sub(expected_sequence);
+            sent.acked = match offset {
+                0 => false,
+                1..=32 => received & (1 << (offset - 1)) != 0,
+                _ => (offset as i32) < 0,
+            };
+            if sent.acked && sent.retransmissions == 0 {
+                // Only the messages not retransmitted give an unambiguous sample.
+                self.rtt.update(now - sent.sent_at);
+            }
+        }
+        while self.in_flight.front().is_some_and(|sent| sent.acked) {
+            self.in_flight.pop_front();
+        }
+        let queued = self.queued.len();
+        self.transmit_queued(now, out);
+        self.unblocked |= self.queued.len() < queued;
+        self.close_if_delivered(out);
+    }
+
+    /// Closes a closing session once the peer has acknowledged all the messages.
+    fn close_if_delivered(&mut self, out: &dyn Fn(&[u8])) {
+        if self.closing && self.closed.is_none() && !self.is_pending() {
+            out(&[DISCONNECT]);
+            self.closed = Some(DisconnectReason::Closed);
+        }
+    }
+
+    fn flush_ack(&mut self, now: Instant, out: &dyn Fn(&[u8])) {
+        if self.ack_pending && self.closed.is_none() {
+            let mut received = 0u32;
+            for bit in 0..32 {
+                let sequence = self.expected_sequence.wrapping_add(bit + 1);
+                if self.out_of_order.contains_key(&sequence) {
+                    received |= 1 << bit;
+                }
+            }
+            let mut packet = [ACK; 9];
+            packet[1..5].copy_from_slice(&self.expected_sequence.to_be_bytes());
+            packet[5..9].copy_from_slice(&received.to_be_bytes());
+            out(&packet);
+            self.ack_pending = false;
+            self.last_sent = now;
+        }
+    }
+
+    /// Retransmits the messages not acknowledged in time, checks if the peer is alive
+    /// and sends the pending acknowledgement.
+    fn handle_timeout(&mut self, now: Instant, out: &dyn Fn(&[u8])) {
+        if self.closed.is_some() {
+            return
+        }
+        if now >= self.last_received + CONNECTION_TIMEOUT {
+            log::trace!("Reliable UDP connection timeout");
+            self.closed = Some(DisconnectReason::Timeout);
+            return
+        }
+
+        let rto = self.rtt.rto();
+        for sent in self.in_flight.iter_mut().filter(|sent| !sent.acked) {
+            if now >= sent.retransmission_time(rto) {
+                if sent.retransmissions == MAX_RETRANSMISSIONS {
+                    log::trace!("Reliable UDP message {} not acknowledged", sent.sequence);
+                    self.closed = Some(DisconnectReason::Timeout);
+                    return
+                }
+                out(&sent.packet);
+                sent.retransmissions += 1;
+                sent.sent_at = now;
+                self.last_sent = now;
+            }
+        }
+
+        if now >= self.last_sent + KEEP_ALIVE_INTERVAL {
+            self.ack_pending = true;
+        }
+        self.flush_ack(now, out);
+    }
+
+    /// Time at which [`Session::handle_timeout()`] must be called.
+    fn next_timeout(&self) -> Option<Instant> {
+        if self.closed.is_some() {
+            return None
+        }
+        let rto = self.rtt.rto();
+        let retransmission_time = self
+            .in_flight
+            .iter()
+            .filter(|sent| !sent.acked)
+            .map(|sent| sent.retransmission_time(rto))
+            .min();
+        let liveness_time =
+            (self.last_sent + KEEP_ALIVE_INTERVAL).min(self.last_received + CONNECTION_TIMEOUT);
+        Some(retransmission_time.map_or(liveness_time, |time| time.min(liveness_time)))
+    }
+}
+
+fn send_datagram(data: &[u8], send_method: impl Fn(&[u8]) -> io::Result<usize>) {
+    loop {
+        match send_method(data) {
+            Ok(_) => break,
+            Err(ref err) if err.kind() == ErrorKind::Interrupted => continue,
+            // If the packet is lost (e.g. the socket buffer is full), it will be sent again.
+            Err(err) => break log::trace!("Reliable UDP datagram not sent: {}", err),
+        }
+    }
+}
+
+/// Reads the packets received by the socket of a connected resource.
+fn read_socket(socket: &UdpSocket, session: &mut Session) {
+    let buffer: MaybeUninit<[u8; INPUT_BUFFER_SIZE]> = MaybeUninit::uninit();
+    let mut input_buffer = unsafe { buffer.assume_init() }; // Avoid to initialize the array
+
+    let out = |data: &[u8]| send_datagram(data, |data| socket.send(data));
+    loop {
+        match socket.recv(&mut input_buffer) {
+            Ok(size) => {
+                if let Some(packet) = Packet::decode(&input_buffer[..size]) {
+                    session.handle_packet(packet, Instant::now(), &out);
+                }
+            }
+            Err(ref err) if err.kind() == ErrorKind::WouldBlock => break,
+            Err(ref err) if err.kind() == ErrorKind::Interrupted => continue,
+            Err(ref err) if err.kind() == ErrorKind::ConnectionRefused => {
+                session.closed = Some(DisconnectReason::Reset); // The peer is not reachable.
+                break
+            }
+            Err(err) => {
+                log::error!("Reliable UDP receive error: {}", err);
+                session.closed = Some(DisconnectReason::from(&err)); // Should not happen
+                break
+            }
+        }
+    }
+}
+
+/// Performs the connection request, returning the session once the listener answers.
+/// It fails if the listener does not answer before the `connect_timeout`.
+fn handshake(socket: &StdUdpSocket, connect_timeout: Duration) -> io::Result<Session> {
+    let mut input_buffer = vec![0; INPUT_BUFFER_SIZE];
+    let out = |data: &[u8]| send_datagram(data, |data| socket.send(data));
+    let start = Instant::now();
+    let mut session = Session::new(start);
+    let mut timeout = INITIAL_RTO;
+    for attempt in 0.. {
+        socket.send(&[CONNECT])?;
+        let sent_at = Instant::now();
+        let deadline = (sent_at + timeout).min(start + connect_timeout);
+        while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+            socket.set_read_timeout(Some(remaining.max(Duration::from_millis(1))))?;
+            let packet = match socket.recv(&mut input_buffer) {
+                Ok(size) => Packet::decode(&input_buffer[..size]),
+                Err(ref err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(ref err) if err.kind() == ErrorKind::TimedOut => break,
+                Err(ref err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
+            };
+            let now = Instant::now();
+            match packet {
+                Some(Packet::Accept) if attempt == 0 => session.rtt.update(now - sent_at),
+                Some(Packet::Accept) => (),
+                Some(Packet::Disconnect) => {
+                    let err = "The connection was refused by the listener";
+                    return Err(io::Error::new(ErrorKind::ConnectionRefused, err))
+                }
+                // The answer was lost, but the listener is already using the connection.
+                Some(packet) => session.handle_packet(packet, now, &out),
+                None => continue,
+            }
+            return Ok(session)
+        }
+        if Instant::now() >= start + connect_timeout {
+            break
+        }
+        timeout = (timeout * 2).min(MAX_RTO);
+    }
+    Err(io::Error::new(ErrorKind::TimedOut, "The listener did not answer"))
+}
+
+pub(crate) struct ReliableUdpAdapter;
+impl Adapter for ReliableUdpAdapter {
+    type Remote = RemoteResource;
+    type Local = LocalResource;
+}
+
+/// Way in which a remote sends and receives its packets.
+enum Link {
+    /// The remote was created by a connection and has a socket of its own.
+    Connected(UdpSocket),
+
+    /// The remote was accepted by a listener, that receives its packets.
+    Accepted(Arc<ListenerShared>),
+}
+
+pub(crate) struct RemoteResource {
+    link: Link,
+    peer_addr: SocketAddr,
+    session: Arc<Mutex<Session>>,
+}
+
+impl RemoteResource {
+    fn send_datagram(&self, data: &[u8]) {
+        match &self.link {
+            Link::Connected(socket) => send_datagram(data, |data| socket.send(data)),
+            Link::Accepted(listener) => {
+                send_datagram(data, |data| listener.socket.send_to(data, self.peer_addr))
+            }
+        }
+    }
+}
+
+impl Resource for RemoteResource {
+    fn source(&mut self) -> &mut dyn Source {
+        self
+    }
+
+    fn set_waker(&mut self, waker: ResourceWaker) {
+        let mut session = self.session.lock().expect(OTHER_THREAD_ERR);
+        // To process the messages received during the handshake and to start the timers.
+        waker.wake();
+        session.waker = Some(waker);
+    }
+}
+
+impl Source for RemoteResource {
+    fn register(
+        &mut self,
+        registry: &Registry,
+        token: Token,
+        interests: Interest,
+    ) -> io::Result<()> {
+        match &mut self.link {
+            Link::Connected(socket) => socket.register(registry, token, interests),
+            Link::Accepted(_) => Ok(()), // Its packets are received by the listener.
+        }
+    }
+
+    fn reregister(
+        &mut self,
+        registry: &Registry,
+        token: Token,
+        interests: Interest,
+    ) -> io::Result<()> {
+        match &mut self.link {
+            Link::Connected(socket) => socket.reregister(registry, token, interests),
+            Link::Accepted(_) => Ok(()),
+        }
+    }
+
+    fn deregister(&mut self, registry: &Registry) -> io::Result<()> {
+        match &mut self.link {
+            Link::Connected(socket) => socket.deregister(registry),
+            Link::Accepted(_) => Ok(()),
+        }
+    }
+}
+
+impl Remote for RemoteResource {
+    fn connect(
+        _: TransportConnect,
+        remote_addr: RemoteAddr,
+        options: ConnectOptions,
+    ) -> io::Result<ConnectionInfo<Self>> {
+        options.check_no_proxy()?;
+
+        let peer_addr = remote_addr.resolve()?;
+        let socket = StdUdpSocket::bind(options.bind_addr(peer_addr))?;
+        socket.connect(peer_addr)?;
+        let session = handshake(&socket, options.timeout.unwrap_or(CONNECT_TIMEOUT))?;
+        socket.set_nonblocking(true)?;
+        let local_addr = socket.local_addr()?;
+
+        let remote = RemoteResource {
+            link: Link::Connected(UdpSocket::from_std(socket)),
+            peer_addr,
+            session: Arc::new(Mutex::new(session)),
+        };
+        Ok(ConnectionInfo { remote, local_addr, peer_addr })
+    }
+
+    fn receive(&self, mut process_data: impl FnMut(&[u8])) -> ReadStatus {
+        let mut session = self.session.lock().expect(OTHER_THREAD_ERR);
+        if let Link::Connected(socket) = &self.link {
+            read_socket(socket, &mut session);
+        }
+        let now = Instant::now();
+        session.handle_timeout(now, &|data| self.send_datagram(data));
+        session.schedule_wake(now);
+        session.unblocked = false;
+        let messages = std::mem::take(&mut session.inbox);
+        let closed = session.closed.clone();
+        drop(session); // The user could send from the callback.
+
+        for message in messages {
+            process_data(&message);
+        }
+        match closed {
+            Some(reason) => ReadStatus::Disconnected(reason),
+            None => ReadStatus::WaitNextEvent,
+        }
+    }
+
+    fn send(&self, data: &[u8]) -> SendStatus {
+        let mut session = self.session.lock().expect(OTHER_THREAD_ERR);
+        let now = Instant::now();
+        let status = session.send(data, now, &|data| self.send_datagram(data));
+        session.schedule_wake(now);
+        status
+    }
+
+    fn enqueued_size(&self) -> usize {
+        self.session.lock().expect(OTHER_THREAD_ERR).queued_size
+    }
+
+    fn close(&self, _reason: &CloseReason) -> bool {
+        let mut session = self.session.lock().expect(OTHER_THREAD_ERR);
+        if session.closed.is_some() {
+            return false
+        }
+        // The messages sent and not acknowledged yet are delivered before disconnecting.
+        session.closing = true;
+        session.close_if_delivered(&|data| self.send_datagram(data));
+        true
+    }
+}
+
+impl Drop for RemoteResource {
+    fn drop(&mut self) {
+        match &mut self.link {
+            Link::Connected(socket) => {
+                // Without the poll, nobody would read the acknowledgements of the pending
+                // messages, so they are discarded. They are delivered by a graceful close.
+                let session = self.session.lock().expect(OTHER_THREAD_ERR);
+                if session.closed.is_none() {
+                    send_datagram(&[DISCONNECT], |data| socket.send(data));
+                }
+            }
+            Link::Accepted(listener) => {
+                let addr = self.peer_addr;
+                let mut peers = listener.peers.lock().expect(OTHER_THREAD_ERR);
+                let closing = match peers.get_mut(&addr) {
+                    Some(peer) => {
+                        let session = peer.session.lock().expect(OTHER_THREAD_ERR);
+                        if session.closed.is_none() && !session.is_pending() {
+                            send_datagram(&[DISCONNECT], |data| {
+                                listener.socket.send_to(data, addr)
+                            });
+                        }
+                        // If there are pending messages, the listener delivers them before closing.
+                        peer.removed = session.closed.is_none() && session.is_pending();
+                        peer.removed
+                    }
+                    None => return, // The listener was removed.
+                };
+                match closing {
+                    true => listener.wake(),
+                    false => drop(peers.remove(&addr)),
+                }
+            }
+        }
+    }
+}
+
+/// Connection accepted by a listener.
+struct Peer {
+    session: Arc<Mutex<Session>>,
+    removed: bool, // The remote was removed, but the session is delivering its pending messages.
+}
+
+/// Listener state shared with the remotes that it accepts.
+struct ListenerShared {
+    socket: StdUdpSocket, // The same socket as the listener one.
+    waker: Mutex<Option<ResourceWaker>>,
+    peers: Mutex<HashMap<SocketAddr, Peer>>,
+}
+
+impl ListenerShared {
+    fn wake(&self) {
+        if let Some(waker) = &*self.waker.lock().expect(OTHER_THREAD_ERR) {
+            waker.wake();
+        }
+    }
+
+    fn wake_at(&self, deadline: Instant) {
+        if let Some(waker) = &*self.waker.lock().expect(OTHER_THREAD_ERR) {
+            waker.wake_at(deadline);
+        }
+    }
+}
+
+pub(crate) struct LocalResource {
+    socket: UdpSocket,
+    shared: Arc<ListenerShared>,
+}
+
+impl Resource for LocalResource {
+    fn source(&mut self) -> &mut dyn Source {
+        &mut self.socket
+    }
+
+    fn set_waker(&mut self, waker: ResourceWaker) {
+        *self.shared.waker.lock().expect(OTHER_THREAD_ERR) = Some(waker);
+    }
+}
+
+impl Local for LocalResource {
+    type Remote = RemoteResource;
+
+    fn listen(
+        _: TransportListen,
+        addr: RemoteAddr,
+        options: ListenOptions,
+    ) -> io::Result<ListeningInfo<Self>> {
+        options.check_no_proxy_protocol()?;
+
+        let socket = StdUdpSocket::bind(addr.resolve()?)?;
+        socket.set_nonblocking(true)?;
+        let local_addr = socket.local_addr()?;
+        let shared = Arc::new(ListenerShared {
+            socket: socket.try_clone()?,
+            waker: Mutex::new(None),
+            peers: Mutex::new(HashMap::new()),
+        });
+        let local = LocalResource { socket: UdpSocket::from_std(socket), shared };
+        Ok(ListeningInfo { local, local_addr })
+    }
+
+    fn accept(
+        &self,
+        mut accept_remote: impl FnMut(AcceptedType<'_, Self::Remote>) -> AcceptStatus,
+    ) {
+        let buffer: MaybeUninit<[u8; INPUT_BUFFER_SIZE]> = MaybeUninit::uninit();
+        let mut input_buffer = unsafe { buffer.assume_init() }; // Avoid to initialize the array
+
+        let mut peers = self.shared.peers.lock().expect(OTHER_THREAD_ERR);
+        let mut updated_addrs = Vec::new();
+        let mut accepted = Vec::new();
+        loop {
+            let (size, addr) = match self.socket.recv_from(&mut input_buffer) {
+                Ok(received) => received,
+                Err(ref err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(ref err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(_) => continue, // Generated by ICMP packets from other peers.
+            };
+            let packet = match Packet::decode(&input_buffer[..size]) {
+                Some(packet) => packet,
+                None => continue,
+            };
+
+            let out = |data: &[u8]| send_datagram(data, |data| self.socket.send_to(data, addr));
+            let now = Instant::now();
+            match (peers.get(&addr), packet) {
+                (Some(peer), packet) => {
+                    peer.session.lock().expect(OTHER_THREAD_ERR).handle_packet(packet, now, &out);
+                    updated_addrs.push(addr);
+                }
+                (None, Packet::Connect) => {
+                    let session = Arc::new(Mutex::new(Session::new(now)));
+                    peers.insert(addr, Peer { session: session.clone(), removed: false });
+                    out(&[ACCEPT]);
+
+                    let link = Link::Accepted(self.shared.clone());
+                    let remote = RemoteResource { link, peer_addr: addr, session };
+                    accepted.push(AcceptedType::Remote(addr, remote));
+                }
+                (None, Packet::Disconnect) => (),
+                (None, _) => out(&[DISCONNECT]), // The connection no longer exists.
+            }
+        }
+
+        let now = Instant::now();
+        updated_addrs.sort_unstable();
+        updated_addrs.dedup();
+        for addr in updated_addrs {
+            if let Some(peer) = peers.get(&addr) {
+                let mut session = peer.session.lock().expect(OTHER_THREAD_ERR);
+                session.flush_ack(now, &|data| {
+                    send_datagram(data, |data| self.socket.send_to(data, addr))
+                });
+                let processable = !session.inbox.is_empty() || session.closed.is_some();
+                if !peer.removed && (processable || session.unblocked) {
+                    session.wake();
+                }
+            }
+        }
+
+        // Sessions of the removed remotes, delivering their pending messages.
+        let mut next_timeout: Option<Instant> = None;
+        peers.retain(|&addr, peer| {
+            if !peer.removed {
+                return true
+            }
+            let out = |data: &[u8]| send_datagram(data, |data| self.socket.send_to(data, addr));
+            let mut session = peer.session.lock().expect(OTHER_THREAD_ERR);
+            session.handle_timeout(now, &out);
+            if session.closed.is_some() {
+                return false
+            }
+            if !session.is_pending() {
+                out(&[DISCONNECT]);
+                return false
+            }
+            if let Some(timeout) = session.next_timeout() {
+                next_timeout = Some(next_timeout.map_or(timeout, |next| next.min(timeout)));
+            }
+            true
+        });
+        drop(peers);
+        if let Some(deadline) = next_timeout {
+            self.shared.wake_at(deadline);
+        }
+
+        // The callback is called without the lock, the user could send from there.
+        for accepted in accepted {
+            accept_remote(accepted);
+        }
+    }
+}
+
+impl Drop for LocalResource {
+    fn drop(&mut self) {
+        // The accepted remotes can not receive without the listener,
+        // so they are disconnected.
+        let mut peers = self.shared.peers.lock().expect(OTHER_THREAD_ERR);
+        for (addr, peer) in peers.drain() {
+            let mut session = peer.session.lock().expect(OTHER_THREAD_ERR);
+            if session.closed.is_none() {
+                send_datagram(&[DISCONNECT], |data| self.socket.send_to(data, addr));
+                session.closed = Some(DisconnectReason::Error("The listener was removed".into()));
+            }
+            if !peer.removed {
+                session.wake();
+            }
+        }
+    }
+}
@@ -1,15 +1,25 @@
 use crate::network::adapter::{
-    Resource, Remote, Local, Adapter, SendStatus, AcceptedType, ReadStatus, ConnectionInfo,
-    ListeningInfo,
+    Resource, Remote, Local, Adapter, SendStatus, AcceptedType, AcceptStatus, ReadStatus,
+    DisconnectReason, ConnectionInfo, ListeningInfo, PendingStatus, ResourceWaker,
+    wrong_config_error,
 };
-use crate::network::{RemoteAddr};
+use crate::network::{
+    RemoteAddr, TransportConnect, TransportListen, ConnectOptions, ListenOptions, CloseReason,
+};
+use crate::util::thread::{OTHER_THREAD_ERR};
+use super::connecting::{self, TcpPeer};
+use super::buffered_stream::{BufferedStream};
+use super::proxy_protocol::{ProxyProtocolAcceptor};
 
 use mio::net::{TcpListener, TcpStream};
 use mio::event::{Source};
 
-use std::net::{SocketAddr, TcpStream as StdTcpStream};
-use std::io::{self, ErrorKind, Read, Write};
-use std::ops::{Deref};
+use socket2::{Socket, SockAddr, SockRef};
+
+use std::net::{SocketAddr, Shutdown};
+use std::time::{Duration};
+use std::io::{self, ErrorKind, Read};
+use std::sync::{Mutex};
 use std::mem::{MaybeUninit};
 
 /// Size of the internal reading buffer.
@@ -17,6 +27,253 @@ use std::mem::{MaybeUninit};
 /// will contains a chunk of data of this value.
 pub const INPUT_BUFFER_SIZE: usize = u16::MAX as usize; // 2^16 - 1
 
+/// Max number of pending connections of a listener if no other value is specified.
+pub const DEFAULT_BACKLOG: i32 = 1024;
+
+/// Configuration used by the `Tcp` transport to connect.
+/// See [`FramedTcpConnectConfig`](super::framed_tcp::FramedTcpConnectConfig) for `FramedTcp`.
+#[derive(Clone, Debug, Default)]
+pub struct TcpConnectConfig {
+    /// Options of the socket, applied before connecting.
+    pub socket_options: TcpSocketOptions,
+}
+
+/// Configuration used by the `Tcp` transport to listen.
+/// See [`FramedTcpListenConfig`](super::framed_tcp::FramedTcpListenConfig) for `FramedTcp`.
+#[derive(Clone, Debug, Default)]
+pub struct TcpListenConfig {
+    /// Options of the listening socket, applied before binding the address.
+    /// The `nodelay` and `keepalive` options are applied to each accepted connection.
+    pub socket_options: TcpSocketOptions,
+
+    /// Max number of pending connections waiting to be accepted (the `listen()` backlog).
+    /// If it is `None`, [`DEFAULT_BACKLOG`] is used.
+    pub backlog: Option<i32>,
+}
+
+/// Options of a TCP socket.
+/// Each option with `None` keeps the value given by the OS.
+/// Connecting or listening with an option not supported by the platform
+/// fails with an [`ErrorKind::InvalidInput`] error.
+#[derive(Clone, Debug, Default)]
+pub struct TcpSocketOptions {
+    /// Disables the Nagle algorithm (`TCP_NODELAY`) to send the data as soon as possible.
+    pub nodelay: Option<bool>,
+
+    /// Enables the keepalive probes (`SO_KEEPALIVE`) with the given parameters.
+    pub keepalive: Option<TcpKeepalive>,
+
+    /// Size of the receive buffer of the OS (`SO_RCVBUF`).
+    pub recv_buffer_size: Option<usize>,
+
+    /// Size of the send buffer of the OS (`SO_SNDBUF`).
+    pub send_buffer_size: Option<usize>,
+
+    /// Allows to bind an address that is in use (`SO_REUSEADDR`).
+    /// In unix platforms, the listeners enable it by default.
+    pub reuse_address: Option<bool>,
+
+    /// Allows several sockets to bind the same address (`SO_REUSEPORT`).
+    /// Only supported in unix platforms.
+    pub reuse_port: Option<bool>,
+
+    /// Restricts an IPv6 socket to IPv6 communications (`IPV6_V6ONLY`).
+    /// It is ignored if the address is an IPv4 address.
+    pub only_v6: Option<bool>,
+
+    /// Time to live of the outgoing IP packets (`IP_TTL`).
+    pub ttl: Option<u32>,
+
+    /// Name of the network interface the socket is bound to (`SO_BINDTODEVICE`).
+    /// Only supported in Linux, Android and Fuchsia.
+    pub bind_device: Option<String>,
+}
+
+/// Parameters of the TCP keepalive probes.
+/// Each parameter with `None` keeps the value given by the OS.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TcpKeepalive {
+    /// Time that the connection must be idle before sending the first probe (`TCP_KEEPIDLE`).
+    pub idle: Option<Duration>,
+
+    /// Time between two probes (`TCP_KEEPINTVL`).
+    /// Not supported in some platforms, as Solaris.
+    pub interval: Option<Duration>,
+
+    /// Number of probes without answer before dropping the connection (`TCP_KEEPCNT`).
+    /// Not supported in some platforms, as Windows or Solaris.
+    pub retries: Option<u32>,
+}
+
+impl TcpSocketOptions {
+    /// Creates a socket to connect to `peer_addr` with the options applied.
+    pub(crate) fn connect_socket(&self, peer_addr: SocketAddr) -> io::Result<Socket> {
+        let socket = connecting::tcp_socket(peer_addr)?;
+        self.apply(&socket, peer_addr)?;
+        self.apply_to_stream(&socket)?;
+        Ok(socket)
+    }
+
+    /// Creates a non-blocking listener with the options applied.
+    /// If `backlog` is `None`, [`DEFAULT_BACKLOG`] is used.
+    pub(crate) fn listen(&self, addr: SocketAddr, backlog: Option<i32>) -> io::Result<TcpListener> {
+        let socket = connecting::tcp_socket(addr)?;
+        if cfg!(unix) && self.reuse_address.is_none() {
+            socket.set_reuse_address(true)?; // As the std listener does.
+        }
+        self.apply(&socket, addr)?;
+        socket.bind(&SockAddr::from(addr))?;
+        socket.listen(backlog.unwrap_or(DEFAULT_BACKLOG))?;
+        socket.set_nonblocking(true)?;
+        Ok(TcpListener::from_std(socket.into()))
+    }
+
+    /// Applies the options that must be set before binding or connecting the socket.
+    fn apply(&self, socket: &Socket, addr: SocketAddr) -> io::Result<()> {
+        if let Some(reuse_address) = self.reuse_address {
+            socket.set_reuse_address(reuse_address)?;
+        }
+        if let Some(reuse_port) = self.reuse_port {
+            set_reuse_port(socket, reuse_port)?;
+        }
+        if let (Some(only_v6), true) = (self.only_v6, addr.is_ipv6()) {
+            socket.set_only_v6(only_v6)?;
+        }
+        if let Some(ttl) = self.ttl {
+            socket.set_ttl(ttl)?;
+        }
+        if let Some(size) = self.recv_buffer_size {
+            socket.set_recv_buffer_size(size)?;
+        }
+        if let Some(size) = self.send_buffer_size {
+            socket.set_send_buffer_size(size)?;
+        }
+        if let Some(device) = &self.bind_device {
+            bind_device(socket, device)?;
+        }
+        Ok(())
+    }
+
+    /// Applies the options of the connection.
+    /// They are applied also to the accepted streams,
+    /// since not all platforms inherit them from the listener.
+    pub(crate) fn apply_to_stream(&self, socket: &Socket) -> io::Result<()> {
+        if let Some(nodelay) = self.nodelay {
+            socket.set_nodelay(nodelay)?;
+        }
+        if let Some(keepalive) = self.keepalive {
+            socket.set_tcp_keepalive(&keepalive.params()?)?;
+        }
+        Ok(())
+    }
+}
+
+impl TcpKeepalive {
+    fn params(&self) -> io::Result<socket2::TcpKeepalive> {
+        let mut params = socket2::TcpKeepalive::new();
+        if let Some(idle) = self.idle {
+            params = params.with_time(idle);
+        }
+        if let Some(interval) = self.interval {
+            params = keepalive_interval(params, interval)?;
+        }
+        if let Some(retries) = self.retries {
+            params = keepalive_retries(params, retries)?;
+        }
+        Ok(params)
+    }
+}
+
+#[allow(dead_code)] // Only used in the platforms that do not support some options.
+fn unsupported(option: &str) -> io::Error {
+    let message = format!("The socket option {} is not supported in this platform", option);
+    io::Error::new(ErrorKind::InvalidInput, message)
+}
+
+#[cfg(not(any(windows, target_os = "solaris", target_os = "illumos")))]
+fn set_reuse_port(socket: &Socket, reuse_port: bool) -> io::Result<()> {
+    socket.set_reuse_port(reuse_port)
+}
+
+#[cfg(any(windows, target_os = "solaris", target_os = "illumos"))]
+fn set_reuse_port(_: &Socket, _: bool) -> io::Result<()> {
+    Err(unsupported("SO_REUSEPORT"))
+}
+
+#[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+fn bind_device(socket: &Socket, device: &str) -> io::Result<()> {
+    socket.bind_device(Some(device.as_bytes()))
+}
+
+#[cfg(not(any(target_os = "android", target_os = "fuchsia", target_os = "linux")))]
+fn bind_device(_: &Socket, _: &str) -> io::Result<()> {
+    Err(unsupported("SO_BINDTODEVICE"))
+}
+
+#[cfg(any(
+    target_os = "android",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "fuchsia",
+    target_os = "illumos",
+    target_os = "linux",
+    target_os = "netbsd",
+    target_vendor = "apple",
+    windows,
+))]
+fn keepalive_interval(
+    params: socket2::TcpKeepalive,
+    interval: Duration,
+) -> io::Result<socket2::TcpKeepalive> {
+    Ok(params.with_interval(interval))
+}
+
+#[cfg(not(any(
+    target_os = "android",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "fuchsia",
+    target_os = "illumos",
+    target_os = "linux",
+    target_os = "netbsd",
+    target_vendor = "apple",
+    windows,
+)))]
+fn keepalive_interval(_: socket2::TcpKeepalive, _: Duration) -> io::Result<socket2::TcpKeepalive> {
+    Err(unsupported("TCP_KEEPINTVL"))
+}
+
+#[cfg(any(
+    target_os = "android",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "fuchsia",
+    target_os = "illumos",
+    target_os = "linux",
+    target_os = "netbsd",
+    target_vendor = "apple",
+))]
+fn keepalive_retries(
+    params: socket2::TcpKeepalive,
+    retries: u32,
+) -> io::Result<socket2::TcpKeepalive> {
+    Ok(params.with_retries(retries))
+}
+
+#[cfg(not(any(
+    target_os = "android",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "fuchsia",
+    target_os = "illumos",
+    target_os = "linux",
+    target_os = "netbsd",
+    target_vendor = "apple",
+)))]
+fn keepalive_retries(_: socket2::TcpKeepalive, _: u32) -> io::Result<socket2::TcpKeepalive> {
+    Err(unsupported("TCP_KEEPCNT"))
+}
+
 pub(crate) struct TcpAdapter;
 impl Adapter for TcpAdapter {
     type Remote = RemoteResource;
@@ -24,28 +281,65 @@ impl Adapter for TcpAdapter {
 }
 
 pub(crate) struct RemoteResource {
-    stream: TcpStream,
+    stream: Mutex<BufferedStream<TcpStream>>,
 }
 
 impl From<TcpStream> for RemoteResource {
     fn from(stream: TcpStream) -> Self {
-        Self { stream }
+        Self { stream: Mutex::new(BufferedStream::new(stream)) }
+    }
+}
+
+impl RemoteResource {
+    fn config(config: TransportConnect) -> io::Result<TcpConnectConfig> {
+        #[allow(unreachable_patterns)]
+        match config {
+            TransportConnect::Tcp(config) => Ok(config),
+            _ => Err(wrong_config_error()),
+        }
     }
 }
 
 impl Resource for RemoteResource {
     fn source(&mut self) -> &mut dyn Source {
-        &mut self.stream
+        self.stream.get_mut().expect(OTHER_THREAD_ERR).get_mut()
+    }
+
+    fn set_waker(&mut self, waker: ResourceWaker) {
+        self.stream.get_mut().expect(OTHER_THREAD_ERR).set_waker(waker);
     }
 }
 
 impl Remote for RemoteResource {
-    fn connect(remote_addr: RemoteAddr) -> io::Result<ConnectionInfo<Self>> {
-        let peer_addr = *remote_addr.socket_addr();
-        let stream = StdTcpStream::connect(peer_addr)?;
-        let local_addr = stream.local_addr()?;
-        stream.set_nonblocking(true)?;
-        Ok(ConnectionInfo { remote: TcpStream::from_std(stream).into(), local_addr, peer_addr })
+    fn connect(
+        config: TransportConnect,
+        remote_addr: RemoteAddr,
+        options: ConnectOptions,
+    ) -> io::Result<ConnectionInfo<Self>> {
+        let peer = TcpPeer::from_remote_addr(&remote_addr)?;
+        let config = Self::config(config)?;
+        let (stream, local_addr, peer_addr) = connecting::tcp_connect(&peer, &options, |addr| {
+            config.socket_options.connect_socket(addr)
+        })?;
+        Ok(ConnectionInfo { remote: stream.into(), local_addr, peer_addr })
+    }
+
+    fn connect_async(
+        config: TransportConnect,
+        remote_addr: RemoteAddr,
+        options: ConnectOptions,
+    ) -> io::Result<ConnectionInfo<Self>> {
+        let peer = TcpPeer::from_remote_addr(&remote_addr)?;
+        let config = Self::config(config)?;
+        let (stream, local_addr, peer_addr) =
+            connecting::tcp_connect_async(&peer, &options, |addr| {
+                config.socket_options.connect_socket(addr)
+            })?;
+        Ok(ConnectionInfo { remote: stream.into(), local_addr, peer_addr })
+    }
+
+    fn pending(&self) -> PendingStatus {
+        connecting::tcp_pending(self.stream.lock().expect(OTHER_THREAD_ERR).get_ref())
     }
 
     fn receive(&self, mut process_data: impl FnMut(&[u8])) -> ReadStatus {
@@ -53,80 +347,135 @@ impl Remote for RemoteResource {
         let mut input_buffer = unsafe { buffer.assume_init() }; // Avoid to initialize the array
 
         loop {
-            let stream = &self.stream;
-            match stream.deref().read(&mut input_buffer) {
-                Ok(0) => break ReadStatus::Disconnected,
+            // The lock is not kept while processing the data, the user could send from there.
+            let result =
+                self.stream.lock().expect(OTHER_THREAD_ERR).get_mut().read(&mut input_buffer);
+            match result {
+                Ok(0) => break ReadStatus::Disconnected(DisconnectReason::Closed),
                 Ok(size) => process_data(&input_buffer[..size]),
                 Err(ref err) if err.kind() == ErrorKind::Interrupted => continue,
                 Err(ref err) if err.kind() == ErrorKind::WouldBlock => {
                     break ReadStatus::WaitNextEvent
                 }
                 Err(ref err) if err.kind() == ErrorKind::ConnectionReset => {
-                    break ReadStatus::Disconnected
+                    break ReadStatus::Disconnected(DisconnectReason::Reset)
                 }
                 Err(err) => {
                     log::error!("TCP receive error: {}", err);
-                    break ReadStatus::Disconnected // should not happen
+                    break ReadStatus::Disconnected(DisconnectReason::from(&err))
                 }
             }
         }
     }
 
     fn send(&self, data: &[u8]) -> SendStatus {
-        // TODO: The current implementation implies an active waiting,
-        // improve it using POLLIN instead to avoid active waiting.
-        // Note: Despite the fear that an active waiting could generate,
-        // this only occurs in the case when the receiver is full because reads slower that it sends.
-        let mut total_bytes_sent = 0;
-        loop {
-            let stream = &self.stream;
-            match stream.deref().write(&data[total_bytes_sent..]) {
-                Ok(bytes_sent) => {
-                    total_bytes_sent += bytes_sent;
-                    if total_bytes_sent == data.len() {
-                        break SendStatus::Sent
-                    }
-                }
-                Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => continue,
+        self.stream.lock().expect(OTHER_THREAD_ERR).send(&[data])
+    }
 
-                // Others errors are considered fatal for the connection.
-                // a Event::Disconnection will be generated later.
-                Err(err) => {
-                    log::error!("TCP receive error: {}", err);
-                    break SendStatus::ResourceNotFound // should not happen
-                }
-            }
+    fn flush(&self) {
+        if let Err(err) = self.stream.lock().expect(OTHER_THREAD_ERR).flush() {
+            log::error!("TCP send error: {}", err); // The disconnection will be read later.
         }
     }
+
+    fn enqueued_size(&self) -> usize {
+        self.stream.lock().expect(OTHER_THREAD_ERR).enqueued_size()
+    }
+
+    fn close(&self, _reason: &CloseReason) -> bool {
+        let stream = self.stream.lock().expect(OTHER_THREAD_ERR);
+        if let Err(err) = stream.get_ref().shutdown(Shutdown::Write) {
+            log::error!("TCP close error: {}", err); // The disconnection will be read later.
+        }
+        true
+    }
 }
 
 pub(crate) struct LocalResource {
     listener: TcpListener,
+    socket_options: TcpSocketOptions,
+    proxy_protocol: Option<ProxyProtocolAcceptor>,
 }
 
 impl Resource for LocalResource {
     fn source(&mut self) -> &mut dyn Source {
         &mut self.listener
     }
+
+    fn set_waker(&mut self, waker: ResourceWaker) {
+        if let Some(proxy_protocol) = &mut self.proxy_protocol {
+            proxy_protocol.set_waker(waker);
+        }
+    }
+}
+
+impl LocalResource {
+    fn accept_stream(
+        &self,
+        stream: TcpStream,
+        addr: SocketAddr,
+        accept_remote: &mut impl FnMut(AcceptedType<'_, RemoteResource>) -> AcceptStatus,
+    ) -> AcceptStatus {
+        if let Err(err) = self.socket_options.apply_to_stream(&SockRef::from(&stream)) {
+            log::error!("TCP socket options error: {}", err);
+        }
+        accept_remote(AcceptedType::Remote(addr, stream.into()))
+    }
 }
 
 impl Local for LocalResource {
     type Remote = RemoteResource;
 
-    fn listen(addr: SocketAddr) -> io::Result<ListeningInfo<Self>> {
-        let listener = TcpListener::bind(addr)?;
+    fn listen(
+        config: TransportListen,
+        addr: RemoteAddr,
+        options: ListenOptions,
+    ) -> io::Result<ListeningInfo<Self>> {
+        let addr = addr.resolve()?;
+        #[allow(unreachable_patterns)]
+        let config = match config {
+            TransportListen::Tcp(config) => config,
+            _ => return Err(wrong_config_error()),
+        };
+
+        let listener = config.socket_options.listen(addr, config.backlog)?;
         let local_addr = listener.local_addr().unwrap();
-        Ok(ListeningInfo { local: { LocalResource { listener } }, local_addr })
+        let socket_options = config.socket_options;
+        let proxy_protocol = options.proxy_protocol.then(ProxyProtocolAcceptor::default);
+        let local = LocalResource { listener, socket_options, proxy_protocol };
+        Ok(ListeningInfo { local, local_addr })
     }
 
-    fn accept(&self, mut accept_remote: impl FnMut(AcceptedType<'_, Self::Remote>)) {
+    fn accept(
+        &self,
+        mut accept_remote: impl FnMut(AcceptedType<'_, Self::Remote>) -> AcceptStatus,
+    ) {
+        if let Some(proxy_protocol) = &self.proxy_protocol {
+            return proxy_protocol.accept(
+                &self.listener,
+                &mut accept_remote,
+                |stream, addr, accept| self.accept_stream(stream, addr, accept),
+            )
+        }
+
         loop {
             match self.listener.accept() {
-                Ok((stream, addr)) => accept_remote(AcceptedType::Remote(addr, stream.into())),
+                Ok((stream, addr)) => {
+                    if self.accept_stream(stream, addr, &mut accept_remote) == AcceptStatus::Stop {
+                        break
+                    }
+                }
                 Err(ref err) if err.kind() == ErrorKind::WouldBlock => break,
                 Err(ref err) if err.kind() == ErrorKind::Interrupted => continue,
-                Err(err) => break log::error!("TCP accept error: {}", err), // Should not happen
+                Err(err) => {
+                    accept_remote(AcceptedType::Error(err));
+                    break
+                }
             }
         }
     }
+
+    fn pending_accepts(&self) -> usize {
+        self.proxy_protocol.as_ref().map_or(0, |proxy_protocol| proxy_protocol.pending_accepts())
+    }
 }
@@ -1,14 +1,14 @@
 #![allow(unused_variables)]
+#![allow(dead_code)]
 
 use crate::network::adapter::{
-    Resource, Remote, Local, Adapter, SendStatus, AcceptedType, ReadStatus, ConnectionInfo,
-    ListeningInfo,
+    Resource, Remote, Local, Adapter, SendStatus, AcceptedType, AcceptStatus, ReadStatus,
+    ConnectionInfo, ListeningInfo,
 };
-use crate::network::{RemoteAddr};
+use crate::network::{RemoteAddr, TransportConnect, TransportListen, ConnectOptions, ListenOptions};
 
 use mio::event::{Source};
 
-use std::net::{SocketAddr};
 use std::io::{self};
 
 pub(crate) struct MyAdapter;
@@ -25,7 +25,11 @@ impl Resource for RemoteResource {
 }
 
 impl Remote for RemoteResource {
-    fn connect(remote_addr: RemoteAddr) -> io::Result<ConnectionInfo<Self>> {
+    fn connect(
+        config: TransportConnect,
+        remote_addr: RemoteAddr,
+        options: ConnectOptions,
+    ) -> io::Result<ConnectionInfo<Self>> {
         todo!();
     }
 
@@ -48,11 +52,15 @@ impl Resource for LocalResource {
 impl Local for LocalResource {
     type Remote = RemoteResource;
 
-    fn listen(addr: SocketAddr) -> io::Result<ListeningInfo<Self>> {
+    fn listen(
+        config: TransportListen,
+        addr: RemoteAddr,
+        options: ListenOptions,
+    ) -> io::Result<ListeningInfo<Self>> {
         todo!();
     }
 
-    fn accept(&self, accept_remote: impl FnMut(AcceptedType<'_, Self::Remote>)) {
+    fn accept(&self, accept_remote: impl FnMut(AcceptedType<'_, Self::Remote>) -> AcceptStatus) {
         todo!();
     }
 }
@@ -1,8 +1,8 @@
 use crate::network::adapter::{
-    Resource, Remote, Local, Adapter, SendStatus, AcceptedType, ReadStatus, ConnectionInfo,
-    ListeningInfo,
+    Resource, Remote, Local, Adapter, SendStatus, AcceptedType, AcceptStatus, ReadStatus,
+    ConnectionInfo, ListeningInfo,
 };
-use crate::network::{RemoteAddr};
+use crate::network::{RemoteAddr, TransportConnect, TransportListen, ConnectOptions, ListenOptions};
 
 use mio::net::{UdpSocket};
 use mio::event::{Source};
@@ -41,9 +41,15 @@ impl Resource for RemoteResource {
 }
 
 impl Remote for RemoteResource {
-    fn connect(remote_addr: RemoteAddr) -> io::Result<ConnectionInfo<Self>> {
-        let socket = UdpSocket::bind("0.0.0.0:0".parse().unwrap())?;
-        let peer_addr = *remote_addr.socket_addr();
+    fn connect(
+        _: TransportConnect,
+        remote_addr: RemoteAddr,
+        options: ConnectOptions,
+    ) -> io::Result<ConnectionInfo<Self>> {
+        options.check_no_proxy()?;
+
+        let peer_addr = remote_addr.resolve()?;
+        let socket = UdpSocket::bind(options.bind_addr(peer_addr))?;
         socket.connect(peer_addr)?;
         let local_addr = socket.local_addr()?;
         Ok(ConnectionInfo { remote: RemoteResource { socket }, local_addr, peer_addr })
@@ -63,10 +69,7 @@ impl Remote for RemoteResource {
                     // Avoid ICMP generated error to be logged
                     break ReadStatus::WaitNextEvent
                 }
-                Err(err) => {
-                    log::error!("UDP receive error: {}", err);
-                    break ReadStatus::WaitNextEvent // Should not happen
-                }
+                Err(err) => break ReadStatus::Error(err),
             }
         }
     }
@@ -89,13 +92,20 @@ impl Resource for LocalResource {
 impl Local for LocalResource {
     type Remote = RemoteResource;
 
-    fn listen(addr: SocketAddr) -> io::Result<ListeningInfo<Self>> {
+    fn listen(
+        _: TransportListen,
+        addr: RemoteAddr,
+        options: ListenOptions,
+    ) -> io::Result<ListeningInfo<Self>> {
+        options.check_no_proxy_protocol()?;
+
+        let addr = addr.resolve()?;
         let socket = match addr {
             SocketAddr::V4(addr) if addr.ip().is_multicast() => {
                 let listening_addr = SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, addr.port());
                 let socket = UdpBuilder::new_v4()?.reuse_address(true)?.bind(listening_addr)?;
                 socket.set_nonblocking(true)?;
-                socket.join_multicast_v4(&addr.ip(), &Ipv4Addr::UNSPECIFIED)?;
+                socket.join_multicast_v4(addr.ip(), &Ipv4Addr::UNSPECIFIED)?;
                 UdpSocket::from_std(socket)
             }
             _ => UdpSocket::bind(addr)?,
@@ -105,7 +115,10 @@ impl Local for LocalResource {
         Ok(ListeningInfo { local: { LocalResource { socket } }, local_addr })
     }
 
-    fn accept(&self, mut accept_remote: impl FnMut(AcceptedType<'_, Self::Remote>)) {
+    fn accept(
+        &self,
+        mut accept_remote: impl FnMut(AcceptedType<'_, Self::Remote>) -> AcceptStatus,
+    ) {
         let buffer: MaybeUninit<[u8; MAX_PAYLOAD_LEN]> = MaybeUninit::uninit();
         let mut input_buffer = unsafe { buffer.assume_init() }; // Avoid to initialize the array
 
@@ -113,10 +126,13 @@ impl Local for LocalResource {
             match self.socket.recv_from(&mut input_buffer) {
                 Ok((size, addr)) => {
                     let data = &mut input_buffer[..size];
-                    accept_remote(AcceptedType::Data(addr, data))
+                    accept_remote(AcceptedType::Data(addr, data));
                 }
                 Err(ref err) if err.kind() == ErrorKind::WouldBlock => break,
-                Err(err) => break log::error!("UDP accept error: {}", err), // Should never happen
+                Err(err) => {
+                    accept_remote(AcceptedType::Error(err));
+                    break
+                }
             };
         }
     }
@@ -130,7 +146,7 @@ impl Drop for LocalResource {
     fn drop(&mut self) {
         if let SocketAddr::V4(addr) = self.socket.local_addr().unwrap() {
             if addr.ip().is_multicast() {
-                self.socket.leave_multicast_v4(&addr.ip(), &Ipv4Addr::UNSPECIFIED).unwrap();
+                self.socket.leave_multicast_v4(addr.ip(), &Ipv4Addr::UNSPECIFIED).unwrap();
             }
         }
     }
@@ -0,0 +1,347 @@
+use crate::network::adapter::{
+    Resource, Remote, Local, Adapter, SendStatus, AcceptedType, AcceptStatus, ReadStatus,
+    ConnectionInfo, ListeningInfo, wrong_config_error,
+};
+use crate::network::{RemoteAddr, TransportConnect, TransportListen, ConnectOptions, ListenOptions};
+use crate::adapters::unix_stream::{unix_socket_addr, UNSPECIFIED_ADDR};
+use crate::util::thread::{OTHER_THREAD_ERR};
+
+use mio::event::{Source};
+use mio::unix::{SourceFd};
+use mio::{Registry, Token, Interest};
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use std::os::linux::net::{SocketAddrExt};
+use std::os::unix::net::{SocketAddr as UnixSocketAddr, UnixDatagram};
+use std::os::unix::io::{AsRawFd};
+use std::net::{SocketAddr, IpAddr, Ipv6Addr};
+use std::path::{PathBuf};
+use std::collections::{HashMap};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::io::{self, ErrorKind};
+use std::mem::{MaybeUninit};
+
+/// Maximun payload that a Unix datagram can send.
+/// Take into account that the OS could limit it more
+/// (e.g. MacOS limits it by default to 2048 bytes, see `net.local.dgram.maxdgram`).
+pub const MAX_PAYLOAD_LEN: usize = u16::MAX as usize;
+
+// Prefix of the virtual addresses given to the Unix datagram peers:
+// the IPv6 discard-only address block (RFC 6666).
+const VIRTUAL_ADDR_PREFIX: u128 = 0x0100 << 112;
+
+/// Configuration used by the `UnixDatagram` transport to connect.
+/// By default, a new socket is created and connected to the address given at connect.
+#[derive(Clone, Debug, Default)]
+pub struct UnixDatagramConnectConfig {
+    socket: Option<Arc<UnixDatagram>>,
+}
+
+impl UnixDatagramConnectConfig {
+    /// Uses an already connected socket instead of creating a new one,
+    /// e.g. one end of a pair created by [`UnixDatagram::pair()`].
+    /// In this case, the address given at connect is ignored (it can be any string, e.g. `""`).
+    pub fn with_socket(socket: UnixDatagram) -> Self {
+        Self { socket: Some(Arc::new(socket)) }
+    }
+}
+
+pub(crate) struct UnixDatagramAdapter;
+impl Adapter for UnixDatagramAdapter {
+    type Remote = RemoteResource;
+    type Local = LocalResource;
+}
+
+/// Datagram socket that can be registered in the poll.
+/// The std socket is used instead of the mio one to support the abstract namespace.
+struct Socket {
+    socket: UnixDatagram,
+    path: Option<PathBuf>, // Socket file to remove when dropping.
+}
+
+impl Socket {
+    fn bind(addr: &UnixSocketAddr) -> io::Result<Socket> {
+        let socket = UnixDatagram::bind_addr(addr)?;
+        socket.set_nonblocking(true)?;
+        Ok(Socket { socket, path: addr.as_pathname().map(|path| path.to_path_buf()) })
+    }
+
+    // Binds the socket to a new unique name in order to allow the peer to reply.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    fn bind_unique() -> io::Result<Socket> {
+        static SOCKET_COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let id = SOCKET_COUNTER.fetch_add(1, Ordering::Relaxed);
+        let name = format!("message-io-{}-{}", std::process::id(), id);
+        Self::bind(&UnixSocketAddr::from_abstract_name(name)?)
+    }
+
+    // Binds the socket to a new unique name in order to allow the peer to reply.
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    fn bind_unique() -> io::Result<Socket> {
+        static SOCKET_COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let id = SOCKET_COUNTER.fetch_add(1, Ordering::Relaxed);
+        let name = format!("message-io-{}-{}.sock", std::process::id(), id);
+        Self::bind(&UnixSocketAddr::from_pathname(std::env::temp_dir().join(name))?)
+    }
+}
+
+impl From<UnixDatagram> for Socket {
+    fn from(socket: UnixDatagram) -> Self {
+        Socket { socket, path: None }
+    }
+}
+
+impl Source for Socket {
+    fn register(
+        &mut self,
+        registry: &Registry,
+        token: Token,
+        interests: Interest,
+    ) -> io::Result<()> {
+        SourceFd(&self.socket.as_raw_fd()).register(registry, token, interests)
+    }
+
+    fn reregister(
+        &mut self,
+        registry: &Registry,
+        token: Token,
+        interests: Interest,
+    ) -> io::Result<()> {
+        SourceFd(&self.socket.as_raw_fd()).reregister(registry, token, interests)
+    }
+
+    fn deregister(&mut self, registry: &Registry) -> io::Result<()> {
+        SourceFd(&self.socket.as_raw_fd()).deregister(registry)
+    }
+}
+
+impl Drop for Socket {
+    fn drop(&mut self) {
+        // The socket file is created by the socket, so it is removed along with it.
+        if let Some(path) = &self.path {
+            if let Err(err) = std::fs::remove_file(path) {
+                log::error!("Unix socket file {} could not be removed: {}", path.display(), err);
+            }
+        }
+    }
+}
+
+pub(crate) struct RemoteResource {
+    socket: Socket,
+}
+
+impl Resource for RemoteResource {
+    fn source(&mut self) -> &mut dyn Source {
+        &mut self.socket
+    }
+}
+
+impl Remote for RemoteResource {
+    fn connect(
+        config: TransportConnect,
+        remote_addr: RemoteAddr,
+        options: ConnectOptions,
+    ) -> io::Result<ConnectionInfo<Self>> {
+        options.check_no_proxy()?;
+
+        if options.local_addr.is_some() {
+            let err = "A UnixDatagram connection can not be bound to a local IP address";
+            return Err(io::Error::new(ErrorKind::InvalidInput, err))
+        }
+
+        #[allow(unreachable_patterns)]
+        let config = match config {
+            TransportConnect::UnixDatagram(config) => config,
+            _ => return Err(wrong_config_error()),
+        };
+
+        let socket = match config.socket {
+            Some(socket) => {
+                let socket = socket.try_clone()?;
+                socket.set_nonblocking(true)?;
+                Socket::from(socket)
+            }
+            None => {
+                let socket = Socket::bind_unique()?;
+                socket.socket.connect_addr(&unix_socket_addr(&remote_addr)?)?;
+                socket
+            }
+        };
+
+        Ok(ConnectionInfo {
+            remote: RemoteResource { socket },
+            local_addr: UNSPECIFIED_ADDR,
+            peer_addr: UNSPECIFIED_ADDR,
+        })
+    }
+
+    fn receive(&self, mut process_data: impl FnMut(&[u8])) -> ReadStatus {
+        let buffer: MaybeUninit<[u8; MAX_PAYLOAD_LEN]> = MaybeUninit::uninit();
+        let mut input_buffer = unsafe { buffer.assume_init() }; // Avoid to initialize the array
+
+        loop {
+            match self.socket.socket.recv(&mut input_buffer) {
+                Ok(size) => process_data(&input_buffer[..size]),
+                Err(ref err) if err.kind() == ErrorKind::WouldBlock => {
+                    break ReadStatus::WaitNextEvent
+                }
+                Err(ref err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(ref err) if err.kind() == ErrorKind::ConnectionRefused => {
+                    // The peer socket is no longer available
+                    break ReadStatus::WaitNextEvent
+                }
+                Err(err) => break ReadStatus::Error(err),
+            }
+        }
+    }
+
+    fn send(&self, data: &[u8]) -> SendStatus {
+        send_packet(data, |data| self.socket.socket.send(data))
+    }
+}
+
+/// Name of a Unix datagram peer, used to reply it.
+#[derive(Clone, PartialEq, Eq, Hash)]
+enum PeerName {
+    Path(PathBuf),
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    Abstract(Vec<u8>),
+}
+
+impl PeerName {
+    fn from_socket_addr(addr: &UnixSocketAddr) -> Option<PeerName> {
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        if let Some(name) = addr.as_abstract_name() {
+            return Some(PeerName::Abstract(name.to_vec()))
+        }
+        addr.as_pathname().map(|path| PeerName::Path(path.to_path_buf()))
+    }
+
+    fn to_socket_addr(&self) -> io::Result<UnixSocketAddr> {
+        match self {
+            PeerName::Path(path) => UnixSocketAddr::from_pathname(path),
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            PeerName::Abstract(name) => UnixSocketAddr::from_abstract_name(name),
+        }
+    }
+}
+
+/// The API identifies the endpoints by a [`SocketAddr`], so each named peer is represented
+/// by a virtual one, given in the order the peers are known by the listener.
+#[derive(Default)]
+struct PeerTable {
+    addrs: HashMap<PeerName, SocketAddr>,
+    names: HashMap<SocketAddr, PeerName>,
+}
+
+impl PeerTable {
+    fn addr(&mut self, name: PeerName) -> SocketAddr {
+        let next_addr = SocketAddr::new(
+            IpAddr::V6(Ipv6Addr::from(VIRTUAL_ADDR_PREFIX | self.addrs.len() as u128)),
+            0,
+        );
+        let names = &mut self.names;
+        *self.addrs.entry(name).or_insert_with_key(|name| {
+            names.insert(next_addr, name.clone());
+            next_addr
+        })
+    }
+
+    fn name(&self, addr: &SocketAddr) -> Option<&PeerName> {
+        self.names.get(addr)
+    }
+}
+
+pub(crate) struct LocalResource {
+    socket: Socket,
+    peers: Mutex<PeerTable>,
+}
+
+impl Resource for LocalResource {
+    fn source(&mut self) -> &mut dyn Source {
+        &mut self.socket
+    }
+}
+
+impl Local for LocalResource {
+    type Remote = RemoteResource;
+
+    fn listen(
+        _: TransportListen,
+        addr: RemoteAddr,
+        options: ListenOptions,
+    ) -> io::Result<ListeningInfo<Self>> {
+        options.check_no_proxy_protocol()?;
+
+        let socket = Socket::bind(&unix_socket_addr(&addr)?)?;
+        let local = LocalResource { socket, peers: Mutex::new(PeerTable::default()) };
+        Ok(ListeningInfo { local, local_addr: UNSPECIFIED_ADDR })
+    }
+
+    fn accept(
+        &self,
+        mut accept_remote: impl FnMut(AcceptedType<'_, Self::Remote>) -> AcceptStatus,
+    ) {
+        let buffer: MaybeUninit<[u8; MAX_PAYLOAD_LEN]> = MaybeUninit::uninit();
+        let mut input_buffer = unsafe { buffer.assume_init() }; // Avoid to initialize the array
+
+        loop {
+            match self.socket.socket.recv_from(&mut input_buffer) {
+                Ok((size, addr)) => {
+                    // A not named peer can not be replied.
+                    let addr = match PeerName::from_socket_addr(&addr) {
+                        Some(name) => self.peers.lock().expect(OTHER_THREAD_ERR).addr(name),
+                        None => UNSPECIFIED_ADDR,
+                    };
+                    accept_remote(AcceptedType::Data(addr, &input_buffer[..size]));
+                }
+                Err(ref err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(ref err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(err) => {
+                    accept_remote(AcceptedType::Error(err));
+                    break
+                }
+            };
+        }
+    }
+
+    fn send_to(&self, addr: SocketAddr, data: &[u8]) -> SendStatus {
+        let peer_addr = match self.peers.lock().expect(OTHER_THREAD_ERR).name(&addr) {
+            Some(name) => name.to_socket_addr(),
+            None => return SendStatus::ResourceNotFound,
+        };
+        match peer_addr {
+            Ok(peer_addr) => {
+                send_packet(data, |data| self.socket.socket.send_to_addr(data, &peer_addr))
+            }
+            Err(err) => {
+                log::error!("Unix datagram send error: {}", err);
+                SendStatus::ResourceNotFound // Should not happen
+            }
+        }
+    }
+}
+
+fn send_packet(data: &[u8], send_method: impl Fn(&[u8]) -> io::Result<usize>) -> SendStatus {
+    if data.len() > MAX_PAYLOAD_LEN {
+        return SendStatus::MaxPacketSizeExceeded(data.len(), MAX_PAYLOAD_LEN)
+    }
+
+    loop {
+        match send_method(data) {
+            Ok(_) => break SendStatus::Sent,
+            // The peer socket is no longer available
+            Err(ref err) if err.kind() == ErrorKind::ConnectionRefused => {
+                break SendStatus::ResourceNotFound
+            }
+            Err(ref err) if err.kind() == ErrorKind::NotFound => break SendStatus::ResourceNotFound,
+            Err(ref err) if err.kind() == ErrorKind::WouldBlock => continue,
+            Err(ref err) if err.kind() == ErrorKind::Interrupted => continue,
+            Err(err) => {
+                log::error!("Unix datagram send error: {}", err);
+                break SendStatus::ResourceNotFound // should not happen
+            }
+        }
+    }
+}
@@ -0,0 +1,233 @@
+use crate::network::adapter::{
+    Resource, Remote, Local, Adapter, SendStatus, AcceptedType, AcceptStatus, ReadStatus,
+    DisconnectReason, ConnectionInfo, ListeningInfo, ResourceWaker,
+};
+use crate::network::{
+    RemoteAddr, TransportConnect, TransportListen, ConnectOptions, ListenOptions, CloseReason,
+};
+use crate::util::encoding::{self, Decoder, MAX_ENCODED_SIZE};
+use crate::util::thread::{OTHER_THREAD_ERR};
+use super::buffered_stream::{BufferedStream};
+
+use mio::net::{UnixListener, UnixStream};
+use mio::event::{Source};
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use std::os::linux::net::{SocketAddrExt};
+use std::os::unix::net::{
+    SocketAddr as UnixSocketAddr, UnixListener as StdUnixListener, UnixStream as StdUnixStream,
+};
+use std::net::{SocketAddr, IpAddr, Ipv4Addr, Shutdown};
+use std::path::{Path, PathBuf};
+use std::io::{self, ErrorKind, Read};
+use std::sync::{Mutex};
+use std::cell::{RefCell};
+use std::mem::{MaybeUninit};
+
+const INPUT_BUFFER_SIZE: usize = u16::MAX as usize; // 2^16 - 1
+
+/// Address given by the API for the Unix socket endpoints and listeners,
+/// since they are addressed by paths instead of ip addresses.
+pub const UNSPECIFIED_ADDR: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0);
+
+/// Builds the Unix socket address from a `RemoteAddr`.
+/// A path (or a string) is interpreted as a file system path.
+/// In Linux, a string starting with `@` is interpreted as a name of the abstract namespace.
+pub(crate) fn unix_socket_addr(addr: &RemoteAddr) -> io::Result<UnixSocketAddr> {
+    let path = match addr {
+        RemoteAddr::Path(path) => path.as_path(),
+        RemoteAddr::Str(string) => {
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            if let Some(name) = string.strip_prefix('@') {
+                return UnixSocketAddr::from_abstract_name(name)
+            }
+            Path::new(string)
+        }
+        RemoteAddr::Socket(_) => {
+            let err = "A Unix socket must be addressed by a path";
+            return Err(io::Error::new(ErrorKind::InvalidInput, err))
+        }
+    };
+    UnixSocketAddr::from_pathname(path)
+}
+
+pub(crate) struct UnixStreamAdapter;
+impl Adapter for UnixStreamAdapter {
+    type Remote = RemoteResource;
+    type Local = LocalResource;
+}
+
+pub(crate) struct RemoteResource {
+    stream: Mutex<BufferedStream<UnixStream>>,
+    decoder: RefCell<Decoder>,
+}
+
+// SAFETY:
+// That RefCell<Decoder> can be used with Sync because the decoder is only used in the read_event.
+// This way, we save the cost of a Mutex.
+unsafe impl Sync for RemoteResource {}
+
+impl From<UnixStream> for RemoteResource {
+    fn from(stream: UnixStream) -> Self {
+        Self {
+            stream: Mutex::new(BufferedStream::new(stream)),
+            decoder: RefCell::new(Decoder::default()),
+        }
+    }
+}
+
+impl Resource for RemoteResource {
+    fn source(&mut self) -> &mut dyn Source {
+        self.stream.get_mut().expect(OTHER_THREAD_ERR).get_mut()
+    }
+
+    fn set_waker(&mut self, waker: ResourceWaker) {
+        self.stream.get_mut().expect(OTHER_THREAD_ERR).set_waker(waker);
+    }
+}
+
+impl Remote for RemoteResource {
+    fn connect(
+        _: TransportConnect,
+        remote_addr: RemoteAddr,
+        options: ConnectOptions,
+    ) -> io::Result<ConnectionInfo<Self>> {
+        options.check_no_proxy()?;
+
+        if options.local_addr.is_some() {
+            let err = "A UnixStream connection can not be bound to a local IP address";
+            return Err(io::Error::new(ErrorKind::InvalidInput, err))
+        }
+
+        let stream = StdUnixStream::connect_addr(&unix_socket_addr(&remote_addr)?)?;
+        stream.set_nonblocking(true)?;
+        Ok(ConnectionInfo {
+            remote: UnixStream::from_std(stream).into(),
+            local_addr: UNSPECIFIED_ADDR,
+            peer_addr: UNSPECIFIED_ADDR,
+        })
+    }
+
+    fn receive(&self, mut process_data: impl FnMut(&[u8])) -> ReadStatus {
+        let buffer: MaybeUninit<[u8; INPUT_BUFFER_SIZE]> = MaybeUninit::uninit();
+        let mut input_buffer = unsafe { buffer.assume_init() }; // Avoid to initialize the array
+
+        loop {
+            // The lock is not kept while processing the data, the user could send from there.
+            let result =
+                self.stream.lock().expect(OTHER_THREAD_ERR).get_mut().read(&mut input_buffer);
+            match result {
+                Ok(0) => break ReadStatus::Disconnected(DisconnectReason::Closed),
+                Ok(size) => {
+                    let data = &input_buffer[..size];
+                    log::trace!("Decoding data, {} bytes", data.len());
+                    self.decoder.borrow_mut().decode(data, |decoded_data| {
+                        process_data(decoded_data);
+                    });
+                }
+                Err(ref err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(ref err) if err.kind() == ErrorKind::WouldBlock => {
+                    break ReadStatus::WaitNextEvent
+                }
+                Err(ref err) if err.kind() == ErrorKind::ConnectionReset => {
+                    break ReadStatus::Disconnected(DisconnectReason::Reset)
+                }
+                Err(err) => {
+                    log::error!("Unix stream receive error: {}", err);
+                    break ReadStatus::Disconnected(DisconnectReason::from(&err))
+                }
+            }
+        }
+    }
+
+    fn send(&self, data: &[u8]) -> SendStatus {
+        let mut buf = [0; MAX_ENCODED_SIZE]; // used to avoid a heap allocation
+        let encoded_size = encoding::encode_size(data, &mut buf);
+        self.stream.lock().expect(OTHER_THREAD_ERR).send(&[encoded_size, data])
+    }
+
+    fn flush(&self) {
+        if let Err(err) = self.stream.lock().expect(OTHER_THREAD_ERR).flush() {
+            log::error!("Unix stream send error: {}", err); // The disconnection will be read later.
+        }
+    }
+
+    fn enqueued_size(&self) -> usize {
+        self.stream.lock().expect(OTHER_THREAD_ERR).enqueued_size()
+    }
+
+    fn close(&self, _reason: &CloseReason) -> bool {
+        let stream = self.stream.lock().expect(OTHER_THREAD_ERR);
+        if let Err(err) = stream.get_ref().shutdown(Shutdown::Write) {
+            // The disconnection will be read later.
+            log::error!("Unix stream close error: {}", err);
+        }
+        true
+    }
+}
+
+pub(crate) struct LocalResource {
+    listener: UnixListener,
+    path: Option<PathBuf>,
+}
+
+impl Resource for LocalResource {
+    fn source(&mut self) -> &mut dyn Source {
+        &mut self.listener
+    }
+}
+
+impl Drop for LocalResource {
+    fn drop(&mut self) {
+        // The socket file is created by the listener, so it is removed along with it.
+        if let Some(path) = &self.path {
+            if let Err(err) = std::fs::remove_file(path) {
+                log::error!("Unix socket file {} could not be removed: {}", path.display(), err);
+            }
+        }
+    }
+}
+
+impl Local for LocalResource {
+    type Remote = RemoteResource;
+
+    fn listen(
+        _: TransportListen,
+        addr: RemoteAddr,
+        options: ListenOptions,
+    ) -> io::Result<ListeningInfo<Self>> {
+        options.check_no_proxy_protocol()?;
+
+        let addr = unix_socket_addr(&addr)?;
+        let listener = StdUnixListener::bind_addr(&addr)?;
+        listener.set_nonblocking(true)?;
+
+        let local = LocalResource {
+            listener: UnixListener::from_std(listener),
+            path: addr.as_pathname().map(|path| path.to_path_buf()),
+        };
+        Ok(ListeningInfo { local, local_addr: UNSPECIFIED_ADDR })
+    }
+
+    fn accept(
+        &self,
+        mut accept_remote: impl FnMut(AcceptedType<'_, Self::Remote>) -> AcceptStatus,
+    ) {
+        loop {
+            match self.listener.accept() {
+                Ok((stream, _)) => {
+                    let remote = AcceptedType::Remote(UNSPECIFIED_ADDR, stream.into());
+                    if accept_remote(remote) == AcceptStatus::Stop {
+                        break
+                    }
+                }
+                Err(ref err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(ref err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(err) => {
+                    accept_remote(AcceptedType::Error(err));
+                    break
+                }
+            }
+        }
+    }
+}
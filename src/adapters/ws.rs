@@ -1,27 +1,44 @@
 use crate::network::adapter::{
-    Resource, Remote, Local, Adapter, SendStatus, AcceptedType, ReadStatus, ConnectionInfo,
-    ListeningInfo,
+    Resource, Remote, Local, Adapter, SendStatus, AcceptedType, AcceptStatus, ReadStatus,
+    DisconnectReason, ConnectionInfo, ListeningInfo, PendingStatus, ResourceWaker,
+    wrong_config_error,
+};
+use crate::network::{
+    RemoteAddr, TransportConnect, TransportListen, ConnectOptions, ListenOptions, CloseReason,
 };
-use crate::network::{RemoteAddr};
 use crate::util::thread::{OTHER_THREAD_ERR};
+#[cfg(feature = "tls")]
+use crate::util::tls::{TlsHandshake};
+
+use super::connecting::{self, Deadline, TcpPeer};
+use super::no_source::{NoSource};
+use super::proxy_protocol::{ProxyProtocolAcceptor};
 
 use mio::event::{Source};
 use mio::net::{TcpStream, TcpListener};
 
-use tungstenite::protocol::{WebSocket, Message};
+use tungstenite::protocol::{WebSocket, Message, CloseFrame};
+use tungstenite::protocol::frame::coding::{CloseCode};
 use tungstenite::server::{accept as ws_accept};
 use tungstenite::client::{client as ws_connect};
 use tungstenite::handshake::{
     HandshakeError, MidHandshake,
     server::{ServerHandshake, NoCallback},
+    client::{ClientHandshake, Response},
 };
 use tungstenite::error::{Error};
 
-use url::Url;
+#[cfg(feature = "tls")]
+use native_tls::{TlsStream, TlsAcceptor, MidHandshakeTlsStream};
+
+use url::{Url};
+#[cfg(feature = "tls")]
+use url::{Host};
 
 use std::sync::{Mutex};
-use std::net::{SocketAddr, TcpStream as StdTcpStream};
-use std::io::{self, ErrorKind};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::net::{SocketAddr};
+use std::io::{self, ErrorKind, Read, Write};
 use std::ops::{DerefMut};
 
 /// Max message size for default config
@@ -34,77 +51,333 @@ impl Adapter for WsAdapter {
     type Local = LocalResource;
 }
 
+/// Stream over which the web socket works: plain tcp (`ws`) or tcp secured with TLS (`wss`).
+enum WsStream {
+    Plain(TcpStream),
+    #[cfg(feature = "tls")]
+    Tls(TlsStream<TcpStream>),
+}
+
+impl WsStream {
+    fn tcp_stream(&mut self) -> &mut TcpStream {
+        match self {
+            WsStream::Plain(stream) => stream,
+            #[cfg(feature = "tls")]
+            WsStream::Tls(tls_stream) => tls_stream.get_mut(),
+        }
+    }
+}
+
+impl Read for WsStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            WsStream::Plain(stream) => stream.read(buf),
+            #[cfg(feature = "tls")]
+            WsStream::Tls(tls_stream) => tls_stream.read(buf),
+        }
+    }
+}
+
+impl Write for WsStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            WsStream::Plain(stream) => stream.write(buf),
+            #[cfg(feature = "tls")]
+            WsStream::Tls(tls_stream) => tls_stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            WsStream::Plain(stream) => stream.flush(),
+            #[cfg(feature = "tls")]
+            WsStream::Tls(tls_stream) => tls_stream.flush(),
+        }
+    }
+}
+
+/// Server handshake in progress: first the TLS one (only for `wss`), then the web socket one.
+#[allow(clippy::large_enum_variant)] // It is a transient state, boxing it gives nothing.
+enum ServerHandshakeStage {
+    #[cfg(feature = "tls")]
+    Tls(MidHandshakeTlsStream<TcpStream>),
+    Ws(MidHandshake<ServerHandshake<WsStream, NoCallback>>),
+}
+
+enum ServerHandshakeStatus {
+    Done(WebSocket<WsStream>),
+    Pending(ServerHandshakeStage),
+    Failed,
+}
+
+impl ServerHandshakeStage {
+    fn start(stream: WsStream) -> ServerHandshakeStatus {
+        Self::check_ws(ws_accept(stream))
+    }
+
+    #[cfg(feature = "tls")]
+    fn start_tls(acceptor: &TlsAcceptor, stream: TcpStream) -> ServerHandshakeStatus {
+        Self::check_tls(acceptor.accept(stream))
+    }
+
+    fn resume(self) -> ServerHandshakeStatus {
+        match self {
+            #[cfg(feature = "tls")]
+            ServerHandshakeStage::Tls(mid_handshake) => Self::check_tls(mid_handshake.handshake()),
+            ServerHandshakeStage::Ws(mid_handshake) => Self::check_ws(mid_handshake.handshake()),
+        }
+    }
+
+    fn source(&mut self) -> &mut TcpStream {
+        match self {
+            #[cfg(feature = "tls")]
+            ServerHandshakeStage::Tls(mid_handshake) => mid_handshake.get_mut(),
+            ServerHandshakeStage::Ws(mid_handshake) => {
+                mid_handshake.get_mut().get_mut().tcp_stream()
+            }
+        }
+    }
+
+    fn check_ws(
+        result: Result<WebSocket<WsStream>, HandshakeError<ServerHandshake<WsStream, NoCallback>>>,
+    ) -> ServerHandshakeStatus {
+        match result {
+            Ok(web_socket) => ServerHandshakeStatus::Done(web_socket),
+            Err(HandshakeError::Interrupted(mid_handshake)) => {
+                ServerHandshakeStatus::Pending(ServerHandshakeStage::Ws(mid_handshake))
+            }
+            Err(HandshakeError::Failure(ref err)) => {
+                log::error!("WS accept handshake error: {}", err);
+                ServerHandshakeStatus::Failed
+            }
+        }
+    }
+
+    #[cfg(feature = "tls")]
+    fn check_tls(
+        result: Result<TlsStream<TcpStream>, native_tls::HandshakeError<TcpStream>>,
+    ) -> ServerHandshakeStatus {
+        match result {
+            Ok(tls_stream) => Self::start(WsStream::Tls(tls_stream)),
+            Err(native_tls::HandshakeError::WouldBlock(mid_handshake)) => {
+                ServerHandshakeStatus::Pending(ServerHandshakeStage::Tls(mid_handshake))
+            }
+            Err(native_tls::HandshakeError::Failure(ref err)) => {
+                log::error!("WS accept TLS handshake error: {}", err);
+                ServerHandshakeStatus::Failed
+            }
+        }
+    }
+}
+
 struct PendingHandshake {
-    mid_handshake: MidHandshake<ServerHandshake<TcpStream, NoCallback>>,
+    stage: ServerHandshakeStage,
     pending_messages: Vec<Vec<u8>>,
 }
 
+/// Client handshake in progress, started by `connect_async()`:
+/// first the tcp connection, then the TLS handshake (only for `wss`), then the web socket one.
+#[allow(clippy::large_enum_variant)] // It is a transient state, boxing it gives nothing.
+enum ClientHandshakeStage {
+    Tcp(TcpStream, Url, TransportConnect),
+    #[cfg(feature = "tls")]
+    Tls(MidHandshakeTlsStream<TcpStream>, Url),
+    Ws(MidHandshake<ClientHandshake<WsStream>>),
+}
+
+enum ClientHandshakeStatus {
+    Done(WebSocket<WsStream>),
+    Pending(ClientHandshakeStage),
+    Failed(io::Error),
+}
+
+impl ClientHandshakeStage {
+    fn resume(self) -> ClientHandshakeStatus {
+        match self {
+            ClientHandshakeStage::Tcp(stream, url, config) => {
+                match connecting::tcp_pending(&stream) {
+                    PendingStatus::Ready => Self::start(stream, url, config),
+                    PendingStatus::Incomplete => ClientHandshakeStatus::Pending(
+                        ClientHandshakeStage::Tcp(stream, url, config),
+                    ),
+                    PendingStatus::Failed(err) => ClientHandshakeStatus::Failed(err),
+                }
+            }
+            #[cfg(feature = "tls")]
+            ClientHandshakeStage::Tls(mid_handshake, url) => {
+                Self::check_tls(TlsHandshake::resume(mid_handshake), url)
+            }
+            ClientHandshakeStage::Ws(mid_handshake) => Self::check_ws(mid_handshake.handshake()),
+        }
+    }
+
+    fn source(&mut self) -> &mut TcpStream {
+        match self {
+            ClientHandshakeStage::Tcp(stream, ..) => stream,
+            #[cfg(feature = "tls")]
+            ClientHandshakeStage::Tls(mid_handshake, _) => mid_handshake.get_mut(),
+            ClientHandshakeStage::Ws(mid_handshake) => {
+                mid_handshake.get_mut().get_mut().tcp_stream()
+            }
+        }
+    }
+
+    #[cfg(feature = "tls")]
+    fn start(stream: TcpStream, url: Url, config: TransportConnect) -> ClientHandshakeStatus {
+        match RemoteResource::is_secure(&config, &url) {
+            true => {
+                let tls_config = RemoteResource::tls_config(config);
+                let handshake = tls_config.start_connect(&RemoteResource::host(&url), stream);
+                Self::check_tls(handshake, url)
+            }
+            false => Self::check_ws(ws_connect(url, WsStream::Plain(stream))),
+        }
+    }
+
+    #[cfg(not(feature = "tls"))]
+    fn start(stream: TcpStream, url: Url, config: TransportConnect) -> ClientHandshakeStatus {
+        match RemoteResource::is_secure(&config, &url) {
+            true => match RemoteResource::secure_stream(config, &url, stream) {
+                Ok(stream) => Self::check_ws(ws_connect(url, stream)),
+                Err(err) => ClientHandshakeStatus::Failed(err),
+            },
+            false => Self::check_ws(ws_connect(url, WsStream::Plain(stream))),
+        }
+    }
+
+    fn check_ws(
+        result: Result<(WebSocket<WsStream>, Response), HandshakeError<ClientHandshake<WsStream>>>,
+    ) -> ClientHandshakeStatus {
+        match result {
+            Ok((web_socket, _)) => ClientHandshakeStatus::Done(web_socket),
+            Err(HandshakeError::Interrupted(mid_handshake)) => {
+                ClientHandshakeStatus::Pending(ClientHandshakeStage::Ws(mid_handshake))
+            }
+            Err(HandshakeError::Failure(Error::Io(err))) => ClientHandshakeStatus::Failed(err),
+            Err(HandshakeError::Failure(err)) => {
+                let err = crate::Error::HandshakeFailed(format!("WS connect error: {}", err));
+                ClientHandshakeStatus::Failed(err.into())
+            }
+        }
+    }
+
+    #[cfg(feature = "tls")]
+    fn check_tls(result: io::Result<TlsHandshake<TcpStream>>, url: Url) -> ClientHandshakeStatus {
+        match result {
+            Ok(TlsHandshake::Done(tls_stream)) => {
+                Self::check_ws(ws_connect(url, WsStream::Tls(tls_stream)))
+            }
+            Ok(TlsHandshake::InProgress(mid_handshake)) => {
+                ClientHandshakeStatus::Pending(ClientHandshakeStage::Tls(mid_handshake, url))
+            }
+            Err(err) => ClientHandshakeStatus::Failed(err),
+        }
+    }
+}
+
 enum RemoteState {
-    WebSocket(WebSocket<TcpStream>),
+    WebSocket(WebSocket<WsStream>),
     Handshake(Option<PendingHandshake>),
+    Connecting(Option<ClientHandshakeStage>),
+    Closed(NoSource), // The stream was consumed by a failed handshake.
 }
 
 pub(crate) struct RemoteResource {
     state: Mutex<RemoteState>,
+    waker: Option<ResourceWaker>,
+    enqueued_size: AtomicUsize, // Data written since the web socket could not write all of it.
+}
+
+impl From<RemoteState> for RemoteResource {
+    fn from(state: RemoteState) -> Self {
+        Self { state: Mutex::new(state), waker: None, enqueued_size: AtomicUsize::new(0) }
+    }
 }
 
 impl Resource for RemoteResource {
     fn source(&mut self) -> &mut dyn Source {
         match self.state.get_mut().unwrap() {
-            RemoteState::WebSocket(web_socket) => web_socket.get_mut(),
-            RemoteState::Handshake(Some(handshake)) => handshake.mid_handshake.get_mut().get_mut(),
-            RemoteState::Handshake(None) => unreachable!(),
+            RemoteState::WebSocket(web_socket) => web_socket.get_mut().tcp_stream(),
+            RemoteState::Handshake(Some(handshake)) => handshake.stage.source(),
+            RemoteState::Connecting(Some(stage)) => stage.source(),
+            RemoteState::Closed(no_source) => no_source,
+            RemoteState::Handshake(None) | RemoteState::Connecting(None) => unreachable!(),
         }
     }
+
+    fn set_waker(&mut self, waker: ResourceWaker) {
+        self.waker = Some(waker);
+    }
 }
 
 impl Remote for RemoteResource {
-    fn connect(remote_addr: RemoteAddr) -> io::Result<ConnectionInfo<Self>> {
-        let (peer_addr, url) = match remote_addr {
-            RemoteAddr::Socket(addr) => {
-                (addr, Url::parse(&format!("ws://{}/message-io-default", addr)).unwrap())
-            }
-            RemoteAddr::Str(path) => {
-                let url = Url::parse(&path).expect("A valid URL");
-                let addr = url
-                    .socket_addrs(|| match url.scheme() {
-                        "ws" => Some(80),   // Plain
-                        "wss" => Some(443), //Tls
-                        _ => None,
-                    })
-                    .unwrap()[0];
-                (addr, url)
-            }
-        };
+    fn connect(
+        config: TransportConnect,
+        remote_addr: RemoteAddr,
+        options: ConnectOptions,
+    ) -> io::Result<ConnectionInfo<Self>> {
+        let (peer, url) = Self::resolve(&config, remote_addr)?;
+
+        let deadline = Deadline::new(options.timeout);
 
         // Synchronous tcp handshake
-        let stream = StdTcpStream::connect(peer_addr)?;
-        let local_addr = stream.local_addr()?;
-
-        // Make it an asynchronous mio TcpStream
-        stream.set_nonblocking(true)?;
-        let stream = TcpStream::from_std(stream);
-
-        // Synchronous waiting for web socket handshake
-        let mut handshake_result = ws_connect(url, stream);
-        let remote = loop {
-            match handshake_result {
-                Ok((web_socket, _)) => {
-                    break RemoteResource { state: Mutex::new(RemoteState::WebSocket(web_socket)) }
-                }
-                Err(HandshakeError::Interrupted(mid_handshake)) => {
-                    handshake_result = mid_handshake.handshake();
-                }
-                Err(HandshakeError::Failure(err)) => {
-                    //CHECK: give to the user an io::Error?
-                    panic!("WS connect handshake error: {}", err)
+        let (stream, local_addr, peer_addr) =
+            connecting::tcp_connect(&peer, &options, connecting::tcp_socket)?;
+
+        // Synchronous tls (if the connection is secure) and web socket handshakes,
+        // over the stream blocked until the deadline
+        connecting::set_blocking(&stream, &deadline)?;
+        let mut stage = ClientHandshakeStage::Tcp(stream, url, config);
+        let mut web_socket = loop {
+            match stage.resume() {
+                ClientHandshakeStatus::Done(web_socket) => break web_socket,
+                ClientHandshakeStatus::Pending(mut next_stage) => {
+                    // Interrupted by a timeout of the stream.
+                    connecting::set_blocking(next_stage.source(), &deadline)?;
+                    stage = next_stage;
                 }
+                ClientHandshakeStatus::Failed(err) => return Err(err),
             }
         };
+        connecting::set_nonblocking(web_socket.get_mut().tcp_stream())?;
 
+        let remote = RemoteState::WebSocket(web_socket).into();
         Ok(ConnectionInfo { remote, local_addr, peer_addr })
     }
 
+    fn connect_async(
+        config: TransportConnect,
+        remote_addr: RemoteAddr,
+        options: ConnectOptions,
+    ) -> io::Result<ConnectionInfo<Self>> {
+        let (peer, url) = Self::resolve(&config, remote_addr)?;
+        let (stream, local_addr, peer_addr) =
+            connecting::tcp_connect_async(&peer, &options, connecting::tcp_socket)?;
+        let stage = ClientHandshakeStage::Tcp(stream, url, config);
+        let remote = RemoteState::Connecting(Some(stage)).into();
+        Ok(ConnectionInfo { remote, local_addr, peer_addr })
+    }
+
+    fn pending(&self) -> PendingStatus {
+        let mut state = self.state.lock().expect(OTHER_THREAD_ERR);
+        if let RemoteState::Connecting(stage) = state.deref_mut() {
+            match stage.take().unwrap().resume() {
+                ClientHandshakeStatus::Done(web_socket) => {
+                    *state = RemoteState::WebSocket(web_socket)
+                }
+                ClientHandshakeStatus::Pending(next_stage) => {
+                    *stage = Some(next_stage);
+                    return PendingStatus::Incomplete
+                }
+                ClientHandshakeStatus::Failed(err) => {
+                    *state = RemoteState::Closed(NoSource);
+                    return PendingStatus::Failed(err)
+                }
+            }
+        }
+        PendingStatus::Ready
+    }
+
     fn receive(&self, mut process_data: impl FnMut(&[u8])) -> ReadStatus {
         loop {
             // "emulates" full duplex for the websocket case locking here and not outside the loop.
@@ -113,89 +386,235 @@ impl Remote for RemoteResource {
                 RemoteState::WebSocket(web_socket) => match web_socket.read_message() {
                     Ok(message) => match message {
                         Message::Binary(data) => {
-                            // As an optimization.
-                            // Fast check to know if there is more data to avoid call
-                            // WebSocket::read_message() again.
-                            // TODO: investigate why this code doesn't work in windows.
-                            // Seems like windows consume the `WouldBlock` notification
-                            // at peek() when it happens, and the poll never wakes it again.
-                            #[cfg(not(target_os = "windows"))]
-                            let _peek_result = web_socket.get_ref().peek(&mut [0; 0]);
+                            // It is read until WouldBlock instead of peeking the socket:
+                            // the next messages could be already buffered by the WebSocket,
+                            // and the poll would not wake it again to read them.
 
                             // We can not call process_data while the socket is blocked.
                             // The user could lock it again if sends from the callback.
                             drop(state);
                             process_data(&data);
-
-                            #[cfg(not(target_os = "windows"))]
-                            if let Err(err) = _peek_result {
-                                break Self::io_error_to_read_status(&err)
-                            }
                         }
-                        Message::Close(_) => break ReadStatus::Disconnected,
+                        Message::Close(frame) => {
+                            // Writes the answer to the Close frame before being removed.
+                            let _ = web_socket.write_pending();
+                            let reason = match frame {
+                                Some(frame) => DisconnectReason::WebSocketClose {
+                                    code: u16::from(frame.code),
+                                    reason: frame.reason.to_string(),
+                                },
+                                None => DisconnectReason::Closed,
+                            };
+                            break ReadStatus::Disconnected(reason)
+                        }
                         _ => continue,
                     },
                     Err(Error::Io(ref err)) => break Self::io_error_to_read_status(err),
                     Err(err) => {
                         log::error!("WS receive error: {}", err);
-                        break ReadStatus::Disconnected // should not happen
+                        let reason = DisconnectReason::Protocol(err.to_string());
+                        break ReadStatus::Disconnected(reason) // should not happen
                     }
                 },
                 RemoteState::Handshake(handshake) => {
                     let current_handshake = handshake.take().unwrap();
-                    match current_handshake.mid_handshake.handshake() {
-                        Ok(mut web_socket) => {
+                    match current_handshake.stage.resume() {
+                        ServerHandshakeStatus::Done(mut web_socket) => {
                             for pending_data in current_handshake.pending_messages {
-                                Self::send_by_socket(&mut web_socket, &pending_data);
+                                self.send_by_socket(&mut web_socket, &pending_data);
                             }
                             *state = RemoteState::WebSocket(web_socket);
                         }
-                        Err(HandshakeError::Interrupted(mid_handshake)) => {
+                        ServerHandshakeStatus::Pending(stage) => {
                             *handshake = Some(PendingHandshake {
-                                mid_handshake,
+                                stage,
                                 pending_messages: current_handshake.pending_messages,
                             });
                             break ReadStatus::WaitNextEvent
                         }
-                        Err(HandshakeError::Failure(ref err)) => {
-                            log::error!("WS accept handshake error: {}", err);
-                            break ReadStatus::Disconnected // should not happen
+                        ServerHandshakeStatus::Failed => {
+                            *state = RemoteState::Closed(NoSource);
+                            break ReadStatus::Disconnected(Self::handshake_failed())
                         }
                     }
                 }
+                RemoteState::Connecting(_) => break ReadStatus::WaitNextEvent,
+                RemoteState::Closed(_) => break ReadStatus::Disconnected(Self::handshake_failed()),
             }
         }
     }
 
     fn send(&self, data: &[u8]) -> SendStatus {
         match self.state.lock().expect(OTHER_THREAD_ERR).deref_mut() {
-            RemoteState::WebSocket(web_socket) => Self::send_by_socket(web_socket, data),
+            RemoteState::WebSocket(web_socket) => self.send_by_socket(web_socket, data),
             RemoteState::Handshake(handshake) => {
                 handshake.as_mut().unwrap().pending_messages.push(data.to_vec());
-                SendStatus::Sent //Future versions: SendStatus::Enqueued
+                SendStatus::Enqueued
+            }
+            RemoteState::Connecting(_) => SendStatus::ResourceNotAvailable,
+            RemoteState::Closed(_) => SendStatus::ResourceNotFound,
+        }
+    }
+
+    fn flush(&self) {
+        if self.enqueued_size.load(Ordering::Acquire) == 0 {
+            return
+        }
+        if let RemoteState::WebSocket(web_socket) =
+            self.state.lock().expect(OTHER_THREAD_ERR).deref_mut()
+        {
+            let result = web_socket.write_pending();
+            match self.track_write(web_socket, result, 0) {
+                Ok(_) => (),
+                // The close handshake has finished, there is nothing more to write.
+                Err(Error::ConnectionClosed) => self.enqueued_size.store(0, Ordering::Release),
+                Err(err) => {
+                    log::error!("WS send error: {}", err); // The disconnection will be read later.
+                    self.enqueued_size.store(0, Ordering::Release);
+                }
+            }
+        }
+    }
+
+    fn enqueued_size(&self) -> usize {
+        self.enqueued_size.load(Ordering::Acquire)
+    }
+
+    fn close(&self, reason: &CloseReason) -> bool {
+        match self.state.lock().expect(OTHER_THREAD_ERR).deref_mut() {
+            RemoteState::WebSocket(web_socket) => {
+                let frame = CloseFrame {
+                    code: CloseCode::from(reason.code),
+                    reason: reason.reason.clone().into(),
+                };
+                // If it can not be written, the Close frame is kept enqueued
+                // and flushed by the closing process on the writable events.
+                let size = 2 + frame.reason.len(); // Payload of the Close frame.
+                let result = web_socket.close(Some(frame));
+                match self.track_write(web_socket, result, size) {
+                    Ok(_) => true,
+                    Err(err) => {
+                        log::error!("WS close error: {}", err);
+                        false
+                    }
+                }
             }
+            // Without an established web socket there is no Close frame to send.
+            _ => false,
         }
     }
 }
 
 impl RemoteResource {
-    fn send_by_socket(web_socket: &mut WebSocket<TcpStream>, data: &[u8]) -> SendStatus {
-        let message = Message::Binary(data.to_vec());
-        let mut result = web_socket.write_message(message);
-        loop {
-            match result {
-                Ok(_) => break SendStatus::Sent,
-                Err(Error::Io(ref err)) if err.kind() == ErrorKind::WouldBlock => {
-                    result = web_socket.write_pending();
-                }
-                Err(Error::Capacity(_)) => {
-                    break SendStatus::MaxPacketSizeExceeded(data.len(), MAX_PAYLOAD_LEN)
+    fn resolve(config: &TransportConnect, remote_addr: RemoteAddr) -> io::Result<(TcpPeer, Url)> {
+        #[allow(unreachable_patterns)]
+        let secure = match config {
+            TransportConnect::Ws => false,
+            #[cfg(feature = "tls")]
+            TransportConnect::Wss(_) => true,
+            _ => return Err(wrong_config_error()),
+        };
+
+        let (peer, url) = match remote_addr {
+            RemoteAddr::Socket(addr) => {
+                let scheme = if secure { "wss" } else { "ws" };
+                let url = Url::parse(&format!("{}://{}/message-io-default", scheme, addr)).unwrap();
+                (TcpPeer::Addr(addr), url)
+            }
+            RemoteAddr::Str(path) => {
+                let url = Url::parse(&path).map_err(|err| {
+                    crate::Error::InvalidAddress(format!("'{}' is not a valid URL: {}", path, err))
+                })?;
+                // The host is resolved later, by the proxy if the connection uses one.
+                let port = url.port_or_known_default(); // 80 for 'ws' and 443 for 'wss'
+                let peer = match (url.host_str(), port) {
+                    (Some(host), Some(port)) => TcpPeer::new(host, port),
+                    _ => return Err(crate::Error::UnresolvableHost(path).into()),
+                };
+                (peer, url)
+            }
+            RemoteAddr::Path(_) => {
+                let err = "A web socket can not be connected to a path";
+                return Err(io::Error::new(ErrorKind::InvalidInput, err))
+            }
+        };
+
+        Ok((peer, url))
+    }
+
+    fn is_secure(config: &TransportConnect, url: &Url) -> bool {
+        url.scheme() == "wss" || !matches!(config, TransportConnect::Ws)
+    }
+
+    #[cfg(feature = "tls")]
+    fn tls_config(config: TransportConnect) -> crate::util::tls::TlsConnectConfig {
+        match config {
+            TransportConnect::Wss(tls_config) => tls_config,
+            _ => Default::default(), // A 'wss' url without explicit configuration.
+        }
+    }
+
+    #[cfg(feature = "tls")]
+    fn host(url: &Url) -> String {
+        match url.host() {
+            Some(Host::Domain(domain)) => domain.to_string(),
+            Some(Host::Ipv4(ip)) => ip.to_string(),
+            Some(Host::Ipv6(ip)) => ip.to_string(),
+            None => String::new(),
+        }
+    }
+
+    #[cfg(not(feature = "tls"))]
+    fn secure_stream(_: TransportConnect, _: &Url, _: TcpStream) -> io::Result<WsStream> {
+        let err = "A 'wss' connection needs the 'tls' feature of message-io";
+        Err(io::Error::new(ErrorKind::InvalidInput, err))
+    }
+
+    fn send_by_socket(&self, web_socket: &mut WebSocket<WsStream>, data: &[u8]) -> SendStatus {
+        let result = web_socket.write_message(Message::Binary(data.to_vec()));
+        match self.track_write(web_socket, result, data.len()) {
+            Ok(status) => status,
+            Err(Error::Capacity(_)) => {
+                SendStatus::MaxPacketSizeExceeded(data.len(), MAX_PAYLOAD_LEN)
+            }
+            Err(err) => {
+                log::error!("WS send error: {}", err);
+                SendStatus::ResourceNotFound // should not happen
+            }
+        }
+    }
+
+    /// Checks the result of a write of `size` bytes to the web socket.
+    /// If the web socket could not write all its data without blocking, it keeps the rest,
+    /// and the stream is registered for writable events until it is written by a flush.
+    #[allow(clippy::result_large_err)] // The error of the web socket is given back as is.
+    fn track_write(
+        &self,
+        web_socket: &mut WebSocket<WsStream>,
+        result: Result<(), Error>,
+        size: usize,
+    ) -> Result<SendStatus, Error> {
+        match result {
+            Ok(()) => {
+                if self.enqueued_size.swap(0, Ordering::AcqRel) > 0 {
+                    self.set_writable(web_socket, false)?;
                 }
-                Err(err) => {
-                    log::error!("WS send error: {}", err);
-                    break SendStatus::ResourceNotFound // should not happen
+                Ok(SendStatus::Sent)
+            }
+            Err(Error::Io(ref err)) if err.kind() == ErrorKind::WouldBlock => {
+                if self.enqueued_size.fetch_add(size, Ordering::AcqRel) == 0 {
+                    self.set_writable(web_socket, true)?;
                 }
+                Ok(SendStatus::Enqueued)
             }
+            Err(err) => Err(err),
+        }
+    }
+
+    fn set_writable(&self, web_socket: &mut WebSocket<WsStream>, writable: bool) -> io::Result<()> {
+        match &self.waker {
+            Some(waker) => waker.set_writable(web_socket.get_mut().tcp_stream(), writable),
+            None => Ok(()), // Not registered yet, it can not be sending.
         }
     }
 
@@ -204,61 +623,139 @@ impl RemoteResource {
             ReadStatus::WaitNextEvent
         }
         else if err.kind() == io::ErrorKind::ConnectionReset {
-            ReadStatus::Disconnected
+            ReadStatus::Disconnected(DisconnectReason::Reset)
         }
         else {
             log::error!("WS receive error: {}", err);
-            ReadStatus::Disconnected // should not happen
+            ReadStatus::Disconnected(DisconnectReason::from(err)) // should not happen
         }
     }
+
+    fn handshake_failed() -> DisconnectReason {
+        DisconnectReason::Protocol("The WebSocket handshake failed".into())
+    }
 }
 
 pub(crate) struct LocalResource {
     listener: TcpListener,
+    #[cfg(feature = "tls")]
+    tls_acceptor: Option<TlsAcceptor>,
+    proxy_protocol: Option<ProxyProtocolAcceptor>,
 }
 
 impl Resource for LocalResource {
     fn source(&mut self) -> &mut dyn Source {
         &mut self.listener
     }
+
+    fn set_waker(&mut self, waker: ResourceWaker) {
+        if let Some(proxy_protocol) = &mut self.proxy_protocol {
+            proxy_protocol.set_waker(waker);
+        }
+    }
+}
+
+impl LocalResource {
+    #[cfg(feature = "tls")]
+    fn start_handshake(&self, stream: TcpStream) -> ServerHandshakeStatus {
+        match &self.tls_acceptor {
+            Some(tls_acceptor) => ServerHandshakeStage::start_tls(tls_acceptor, stream),
+            None => ServerHandshakeStage::start(WsStream::Plain(stream)),
+        }
+    }
+
+    #[cfg(not(feature = "tls"))]
+    fn start_handshake(&self, stream: TcpStream) -> ServerHandshakeStatus {
+        ServerHandshakeStage::start(WsStream::Plain(stream))
+    }
+
+    fn accept_stream(
+        &self,
+        stream: TcpStream,
+        addr: SocketAddr,
+        accept_remote: &mut impl FnMut(AcceptedType<'_, RemoteResource>) -> AcceptStatus,
+    ) -> AcceptStatus {
+        let remote_state = match self.start_handshake(stream) {
+            ServerHandshakeStatus::Done(web_socket) => Some(RemoteState::WebSocket(web_socket)),
+            ServerHandshakeStatus::Pending(stage) => {
+                Some(RemoteState::Handshake(Some(PendingHandshake {
+                    stage,
+                    pending_messages: Vec::new(),
+                })))
+            }
+            ServerHandshakeStatus::Failed => None,
+        };
+
+        match remote_state {
+            Some(remote_state) => {
+                let remote = RemoteResource::from(remote_state);
+                accept_remote(AcceptedType::Remote(addr, remote))
+            }
+            None => AcceptStatus::Continue,
+        }
+    }
 }
 
 impl Local for LocalResource {
     type Remote = RemoteResource;
 
-    fn listen(addr: SocketAddr) -> io::Result<ListeningInfo<Self>> {
+    fn listen(
+        config: TransportListen,
+        addr: RemoteAddr,
+        options: ListenOptions,
+    ) -> io::Result<ListeningInfo<Self>> {
+        let addr = addr.resolve()?;
+        #[allow(unreachable_patterns)]
+        #[cfg(feature = "tls")]
+        let tls_acceptor = match config {
+            TransportListen::Ws => None,
+            TransportListen::Wss(tls_config) => Some(tls_config.acceptor()?),
+            _ => return Err(wrong_config_error()),
+        };
+        #[cfg(not(feature = "tls"))]
+        let _ = config;
+
         let listener = TcpListener::bind(addr)?;
         let local_addr = listener.local_addr().unwrap();
-        Ok(ListeningInfo { local: LocalResource { listener }, local_addr })
+        let local = LocalResource {
+            listener,
+            #[cfg(feature = "tls")]
+            tls_acceptor,
+            proxy_protocol: options.proxy_protocol.then(ProxyProtocolAcceptor::default),
+        };
+        Ok(ListeningInfo { local, local_addr })
     }
 
-    fn accept(&self, mut accept_remote: impl FnMut(AcceptedType<'_, Self::Remote>)) {
+    fn accept(
+        &self,
+        mut accept_remote: impl FnMut(AcceptedType<'_, Self::Remote>) -> AcceptStatus,
+    ) {
+        if let Some(proxy_protocol) = &self.proxy_protocol {
+            return proxy_protocol.accept(
+                &self.listener,
+                &mut accept_remote,
+                |stream, addr, accept| self.accept_stream(stream, addr, accept),
+            )
+        }
+
         loop {
             match self.listener.accept() {
                 Ok((stream, addr)) => {
-                    let remote_state = match ws_accept(stream) {
-                        Ok(web_socket) => Some(RemoteState::WebSocket(web_socket)),
-                        Err(HandshakeError::Interrupted(mid_handshake)) => {
-                            Some(RemoteState::Handshake(Some(PendingHandshake {
-                                mid_handshake,
-                                pending_messages: Vec::new(),
-                            })))
-                        }
-                        Err(HandshakeError::Failure(ref err)) => {
-                            log::error!("WS accept handshake error: {}", err);
-                            None
-                        }
-                    };
-
-                    if let Some(remote_state) = remote_state {
-                        let remote = RemoteResource { state: Mutex::new(remote_state) };
-                        accept_remote(AcceptedType::Remote(addr, remote));
+                    if self.accept_stream(stream, addr, &mut accept_remote) == AcceptStatus::Stop {
+                        break
                     }
                 }
                 Err(ref err) if err.kind() == ErrorKind::WouldBlock => break,
                 Err(ref err) if err.kind() == ErrorKind::Interrupted => continue,
-                Err(err) => break log::error!("WS accept error: {}", err), // Should not happen
+                Err(err) => {
+                    accept_remote(AcceptedType::Error(err));
+                    break
+                }
             }
         }
     }
+
+    fn pending_accepts(&self) -> usize {
+        self.proxy_protocol.as_ref().map_or(0, |proxy_protocol| proxy_protocol.pending_accepts())
+    }
 }
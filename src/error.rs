@@ -0,0 +1,111 @@
+use crate::network::{ResourceId};
+
+use std::io::{self};
+
+/// Error returned by the fallible operations of the API,
+/// as [`crate::network::NetworkController::connect()`] or
+/// [`crate::network::NetworkController::listen()`].
+///
+/// It can be converted from and into an [`io::Error`] without losing its information,
+/// so it can be propagated with `?` in functions that return an [`io::Result`].
+/// The adapters, that work with [`io::Error`]s, can return it with `.into()`.
+#[derive(Debug)]
+pub enum Error {
+    /// The address can not be used by the transport
+    /// (e.g. a malformed *WebSocket* URL or a path given to an IP based transport).
+    InvalidAddress(String),
+
+    /// The host name of the address could not be resolved to any socket address.
+    UnresolvableHost(String),
+
+    /// The handshake performed by the transport (e.g. *WebSocket* or *TLS*) failed.
+    HandshakeFailed(String),
+
+    /// The adapter id does not reference an available transport:
+    /// its feature is not enabled or no adapter was mounted with it.
+    UnsupportedTransport(u8),
+
+    /// The resource can not be used for the requested operation
+    /// (e.g. [`crate::network::Endpoint::from_listener()`] with a connection).
+    InvalidResource(ResourceId),
+
+    /// Any other I/O error.
+    Io(io::Error),
+}
+
+impl Error {
+    /// The [`io::ErrorKind`] of the error.
+    /// It is the kind of the [`io::Error`] obtained when converting it.
+    pub fn kind(&self) -> io::ErrorKind {
+        match self {
+            Error::InvalidAddress(_) => io::ErrorKind::InvalidInput,
+            Error::UnresolvableHost(_) => io::ErrorKind::NotFound,
+            Error::HandshakeFailed(_) => io::ErrorKind::ConnectionAborted,
+            Error::UnsupportedTransport(_) => io::ErrorKind::InvalidInput,
+            Error::InvalidResource(_) => io::ErrorKind::InvalidInput,
+            Error::Io(err) => err.kind(),
+        }
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::InvalidAddress(detail) => write!(f, "Invalid address: {}", detail),
+            Error::UnresolvableHost(host) => write!(f, "The host '{}' can not be resolved", host),
+            Error::HandshakeFailed(detail) => write!(f, "Handshake failed: {}", detail),
+            Error::UnsupportedTransport(adapter_id) => {
+                write!(f, "The adapter id {} does not reference an available transport", adapter_id)
+            }
+            Error::InvalidResource(id) => {
+                write!(f, "The resource {} can not be used for this operation", id)
+            }
+            Error::Io(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    /// Recovers the [`Error`] if the [`io::Error`] was created from one.
+    fn from(err: io::Error) -> Self {
+        if err.get_ref().is_some_and(|inner| inner.is::<Error>()) {
+            let inner = err.into_inner().expect("Checked inner error");
+            return *inner.downcast::<Error>().expect("Checked inner error type")
+        }
+        Error::Io(err)
+    }
+}
+
+impl From<Error> for io::Error {
+    fn from(err: Error) -> Self {
+        match err {
+            Error::Io(err) => err,
+            err => io::Error::new(err.kind(), err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn io_error_conversion() {
+        let err = io::Error::from(Error::UnresolvableHost("host:80".into()));
+        assert_eq!(io::ErrorKind::NotFound, err.kind());
+        assert!(matches!(Error::from(err), Error::UnresolvableHost(host) if host == "host:80"));
+
+        let err = Error::from(io::Error::new(io::ErrorKind::AddrInUse, "In use"));
+        assert_eq!(io::ErrorKind::AddrInUse, err.kind());
+        assert!(matches!(err, Error::Io(_)));
+    }
+}
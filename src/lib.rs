@@ -21,3 +21,6 @@ pub mod events;
 
 /// General purpose utilities.
 pub mod util;
+
+mod error;
+pub use error::{Error};
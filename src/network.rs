@@ -5,24 +5,35 @@ mod registry;
 mod driver;
 mod remote_addr;
 mod transport;
+mod options;
+mod proxy;
+mod filter;
 mod loader;
 
 /// Module that specify the pattern to follow to create adapters.
-/// This module is not part of the public API itself,
-/// it must be used from the internals to build new adapters.
+/// The adapters of the library are built with it,
+/// and the adapters of the user can be mounted with a [`Builder`].
 pub mod adapter;
 
 // Reexports
-pub use adapter::{SendStatus};
+pub use adapter::{SendStatus, DisconnectReason};
 pub use resource_id::{ResourceId, ResourceType};
 pub use endpoint::{Endpoint};
 pub use remote_addr::{RemoteAddr, ToRemoteAddr};
-pub use transport::{Transport};
+pub use transport::{Transport, TransportConnect, TransportListen, CustomConfig};
+pub use options::{ConnectOptions, ListenOptions, SendLimit, Reconnect, CloseReason};
+pub use proxy::{Proxy, ProxyProtocol, ProxyCredentials};
+pub use filter::{AcceptFilter, Cidr};
 pub use driver::{NetEvent};
 
 use loader::{DriverLoader, ActionControllerList, EventProcessorList};
+use driver::{ActionController};
 use poll::{Poll, PollEvent};
 
+use adapter::{Adapter};
+
+use crate::error::{Error};
+
 use strum::{IntoEnumIterator};
 
 use std::net::{SocketAddr, ToSocketAddrs};
@@ -31,15 +42,76 @@ use std::io::{self};
 
 /// Create a network instance giving its controller and processor.
 pub fn split() -> (NetworkController, NetworkProcessor) {
-    let mut drivers = DriverLoader::default();
-    Transport::iter().for_each(|transport| transport.mount_adapter(&mut drivers));
+    Builder::new().split()
+}
 
-    let (poll, controllers, processors) = drivers.take();
+/// Allows to create a network instance with adapters defined by the user,
+/// in addition to the adapters of the available [`Transport`]s.
+///
+/// An adapter is mounted with an id that identifies it.
+/// Then, it is used by [`NetworkController::connect_with()`] and
+/// [`NetworkController::listen_with()`] with a [`TransportConnect::Custom`] and
+/// [`TransportListen::Custom`] configuration with that id.
+/// The endpoints and resources created by the adapter are used as the others.
+/// See the [`adapter`] module to know how to build an adapter.
+///
+/// ```rust,no_run
+/// # use message_io::network::adapter::{Adapter};
+/// # fn example<A: Adapter + 'static>(my_adapter: A) {
+/// use message_io::network::{self, CustomConfig, TransportConnect, ResourceId};
+///
+/// const MY_ADAPTER_ID: u8 = ResourceId::MAX_ADAPTER_ID;
+///
+/// let (controller, processor) = network::Builder::new().mount(MY_ADAPTER_ID, my_adapter).split();
+/// let config = TransportConnect::Custom(CustomConfig::new(MY_ADAPTER_ID));
+/// let (endpoint, _) = controller.connect_with(config, "127.0.0.1:3000").unwrap();
+/// # }
+/// ```
+pub struct Builder {
+    drivers: DriverLoader,
+}
 
-    let network_controller = NetworkController::new(controllers);
-    let network_processor = NetworkProcessor::new(poll, processors);
+impl Builder {
+    /// Creates a builder with the adapters of the available [`Transport`]s already mounted.
+    pub fn new() -> Builder {
+        let mut drivers = DriverLoader::default();
+        Transport::iter().for_each(|transport| transport.mount_adapter(&mut drivers));
+        Builder { drivers }
+    }
+
+    /// Mounts an adapter defined by the user with an id.
+    /// The id must be free: it can not be the id of a [`Transport`]
+    /// or of an adapter mounted before.
+    /// To avoid collisions with the transports added in future versions,
+    /// it is recommended to use the highest ids, starting from [`ResourceId::MAX_ADAPTER_ID`].
+    /// This function panics if the id is not free or is greater than
+    /// [`ResourceId::MAX_ADAPTER_ID`].
+    pub fn mount(mut self, adapter_id: u8, adapter: impl Adapter + 'static) -> Builder {
+        assert!(
+            adapter_id <= ResourceId::MAX_ADAPTER_ID,
+            "The adapter id must be less than {}",
+            ResourceId::MAX_ADAPTERS,
+        );
+        assert!(!self.drivers.is_mounted(adapter_id), "The adapter id {} is in use", adapter_id);
+        self.drivers.mount(adapter_id, adapter);
+        self
+    }
 
-    (network_controller, network_processor)
+    /// Creates the network instance giving its controller and processor.
+    pub fn split(self) -> (NetworkController, NetworkProcessor) {
+        let (poll, controllers, processors) = self.drivers.take();
+
+        let network_controller = NetworkController::new(controllers);
+        let network_processor = NetworkProcessor::new(poll, processors);
+
+        (network_controller, network_processor)
+    }
+}
+
+impl Default for Builder {
+    fn default() -> Builder {
+        Builder::new()
+    }
 }
 
 /// Shareable instance in charge of control all the connections.
@@ -55,23 +127,119 @@ impl NetworkController {
     /// Creates a connection to the specific address.
     /// The endpoint, an identifier of the new connection, will be returned.
     /// If the connection can not be performed (e.g. the address is not reached)
-    /// the corresponding IO error is returned.
+    /// the corresponding [`Error`] is returned.
     /// This function blocks until the resource has been connected and is ready to use.
     pub fn connect(
         &self,
         transport: Transport,
         addr: impl ToRemoteAddr,
-    ) -> io::Result<(Endpoint, SocketAddr)> {
-        let addr = addr.to_remote_addr().unwrap();
-        log::trace!("Connect to {} by adapter: {}", addr, transport.id());
-        self.controllers[transport.id() as usize].connect(addr).map(|(endpoint, addr)| {
-            log::trace!("Connected to {}", endpoint);
-            (endpoint, addr)
-        })
+    ) -> Result<(Endpoint, SocketAddr), Error> {
+        self.connect_with(transport.into(), addr)
+    }
+
+    /// Creates a connection to the specific address using a specific configuration.
+    /// Similar to [`NetworkController::connect()`] but the [`TransportConnect`]
+    /// allows to configure the connection for the chosen transport
+    /// (e.g. the socket options of `Tcp` or the trust roots used by `FramedTls`).
+    pub fn connect_with(
+        &self,
+        transport_connect: TransportConnect,
+        addr: impl ToRemoteAddr,
+    ) -> Result<(Endpoint, SocketAddr), Error> {
+        self.connect_with_options(transport_connect, addr, ConnectOptions::default())
+    }
+
+    /// Creates a connection to the specific address from a specific local address.
+    /// Similar to [`NetworkController::connect()`] but the connection is bound to `local_addr`
+    /// before connecting, choosing the interface and port from which the traffic is originated.
+    /// Only the transports addressed by IP support it,
+    /// an [`io::ErrorKind::InvalidInput`] error is returned by the rest of them.
+    pub fn connect_from(
+        &self,
+        transport: Transport,
+        local_addr: SocketAddr,
+        remote_addr: impl ToRemoteAddr,
+    ) -> Result<(Endpoint, SocketAddr), Error> {
+        let options = ConnectOptions { local_addr: Some(local_addr), ..Default::default() };
+        self.connect_with_options(transport.into(), remote_addr, options)
+    }
+
+    /// Creates a connection to the specific address using a specific configuration
+    /// and some [`ConnectOptions`].
+    /// Similar to [`NetworkController::connect_with()`] but the options allow to limit
+    /// the time of each connection attempt and to retry the failed attempts.
+    /// The error of the last attempt is returned if all of them fail.
+    pub fn connect_with_options(
+        &self,
+        transport_connect: TransportConnect,
+        addr: impl ToRemoteAddr,
+        options: ConnectOptions,
+    ) -> Result<(Endpoint, SocketAddr), Error> {
+        let addr = addr.to_remote_addr()?;
+        let adapter_id = transport_connect.id();
+        let controller = self.controller(adapter_id)?;
+        let mut retry = 0;
+        loop {
+            log::trace!("Connect to {} by adapter: {}", addr, adapter_id);
+            match controller.connect(transport_connect.clone(), addr.clone(), options.clone()) {
+                Ok((endpoint, addr)) => {
+                    log::trace!("Connected to {}", endpoint);
+                    break Ok((endpoint, addr))
+                }
+                Err(err)
+                    if retry < options.retries && err.kind() != io::ErrorKind::InvalidInput =>
+                {
+                    let backoff = options.backoff(retry);
+                    log::trace!("Connection to {} failed: {}. Retry in {:?}", addr, err, backoff);
+                    std::thread::sleep(backoff);
+                    retry += 1;
+                }
+                Err(err) => break Err(err.into()),
+            }
+        }
+    }
+
+    /// Creates a connection to the specific address without waiting for it to be established.
+    /// The endpoint, an identifier of the new connection, will be returned immediately.
+    /// Once the connection is established a [`NetEvent::Connected`] will be generated
+    /// for this endpoint, or a [`NetEvent::ConnectionFailed`] if it could not be performed.
+    /// While the endpoint is connecting, [`NetworkController::send()`] will return
+    /// [`SendStatus::ResourceNotAvailable`].
+    ///
+    /// Transports without a non-blocking connection process perform a blocking connection
+    /// and generate the [`NetEvent::Connected`] event as soon as the endpoint is registered.
+    /// Note that the resolution of a hostname address is still blocking.
+    pub fn connect_async(
+        &self,
+        transport: Transport,
+        addr: impl ToRemoteAddr,
+    ) -> Result<(Endpoint, SocketAddr), Error> {
+        self.connect_async_with(transport.into(), addr)
+    }
+
+    /// Creates a non-blocking connection to the specific address using a specific configuration.
+    /// Similar to [`NetworkController::connect_async()`] but the [`TransportConnect`]
+    /// allows to configure the connection for the chosen transport.
+    pub fn connect_async_with(
+        &self,
+        transport_connect: TransportConnect,
+        addr: impl ToRemoteAddr,
+    ) -> Result<(Endpoint, SocketAddr), Error> {
+        let addr = addr.to_remote_addr()?;
+        let adapter_id = transport_connect.id();
+        log::trace!("Connect async to {} by adapter: {}", addr, adapter_id);
+        let (endpoint, addr) = self.controller(adapter_id)?.connect_async(
+            transport_connect,
+            addr,
+            ConnectOptions::default(),
+        )?;
+        log::trace!("Connecting to {}", endpoint);
+        Ok((endpoint, addr))
     }
 
     /// Listen messages from specified transport.
     /// The giver address will be used as interface and listening port.
+    /// Transports addressed by the file system, as `UnixStream`, accept also a path.
     /// If the port can be opened, a [ResourceId] identifying the listener is returned
     /// along with the local address, or an error if not.
     /// The address is returned despite you passed as parameter because
@@ -79,14 +247,57 @@ impl NetworkController {
     pub fn listen(
         &self,
         transport: Transport,
-        addr: impl ToSocketAddrs,
-    ) -> io::Result<(ResourceId, SocketAddr)> {
-        let addr = addr.to_socket_addrs().unwrap().next().unwrap();
-        log::trace!("Listen by {} by adapter: {}", addr, transport.id());
-        self.controllers[transport.id() as usize].listen(addr).map(|(resource_id, addr)| {
-            log::trace!("Listening by {}", resource_id);
-            (resource_id, addr)
-        })
+        addr: impl ToRemoteAddr,
+    ) -> Result<(ResourceId, SocketAddr), Error> {
+        self.listen_with(transport.into(), addr)
+    }
+
+    /// Listen messages from specified transport using a specific configuration.
+    /// Similar to [`NetworkController::listen()`] but the [`TransportListen`]
+    /// allows to configure the listener for the chosen transport
+    /// (e.g. the socket options of `Tcp` or the certificate used by `FramedTls`).
+    pub fn listen_with(
+        &self,
+        transport_listen: TransportListen,
+        addr: impl ToRemoteAddr,
+    ) -> Result<(ResourceId, SocketAddr), Error> {
+        self.listen_with_options(transport_listen, addr, ListenOptions::default())
+    }
+
+    /// Listen messages from specified transport using a specific configuration
+    /// and some [`ListenOptions`].
+    /// Similar to [`NetworkController::listen_with()`] but the options allow to control
+    /// how the connections are accepted (e.g. reading a *PROXY protocol* header).
+    pub fn listen_with_options(
+        &self,
+        transport_listen: TransportListen,
+        addr: impl ToRemoteAddr,
+        options: ListenOptions,
+    ) -> Result<(ResourceId, SocketAddr), Error> {
+        let addr = match addr.to_remote_addr()? {
+            // The string could be a resolvable 'host:port' (e.g. "localhost:3000").
+            RemoteAddr::Str(string) => match string.to_socket_addrs().map(|mut addrs| addrs.next())
+            {
+                Ok(Some(socket_addr)) => RemoteAddr::Socket(socket_addr),
+                _ => RemoteAddr::Str(string),
+            },
+            addr => addr,
+        };
+        let adapter_id = transport_listen.id();
+        log::trace!("Listen by {} by adapter: {}", addr, adapter_id);
+        let (resource_id, addr) =
+            self.controller(adapter_id)?.listen(transport_listen, addr, options)?;
+        log::trace!("Listening by {}", resource_id);
+        Ok((resource_id, addr))
+    }
+
+    /// Returns the controller of an adapter, or an error if the id is out of range.
+    /// The controller of an adapter not mounted returns an error when used to create resources.
+    fn controller(&self, adapter_id: u8) -> Result<&dyn ActionController, Error> {
+        match self.controllers.get(adapter_id as usize) {
+            Some(controller) => Ok(controller.as_ref()),
+            None => Err(Error::UnsupportedTransport(adapter_id)),
+        }
     }
 
     /// Remove a network resource.
@@ -108,6 +319,47 @@ impl NetworkController {
         value
     }
 
+    /// Closes gracefully the connection of an endpoint, as
+    /// [`NetworkController::close_gracefully_with_reason()`] with the default [`CloseReason`].
+    pub fn close_gracefully(&self, endpoint: Endpoint, timeout: Duration) -> bool {
+        self.close_gracefully_with_reason(endpoint, timeout, CloseReason::default())
+    }
+
+    /// Closes gracefully the connection of an endpoint.
+    /// Instead of dropping it as [`NetworkController::remove()`] does,
+    /// its enqueued data is written, the peer is notified by the transport
+    /// (e.g. a *TCP* FIN, or a Close frame with the `reason` in *Ws*),
+    /// and the connection is removed once the peer closes its side.
+    /// Meanwhile, the messages received from the peer are still generated, and
+    /// [`NetworkController::send()`] returns [`SendStatus::ResourceNotAvailable`].
+    /// A [`NetEvent::Closed`] is generated when it finishes, or when the `timeout` expires.
+    /// Returns `false` if the endpoint does not identify a connection
+    /// (e.g. it is a listener or an endpoint of a packet based listener as *Udp*).
+    pub fn close_gracefully_with_reason(
+        &self,
+        endpoint: Endpoint,
+        timeout: Duration,
+        reason: CloseReason,
+    ) -> bool {
+        let resource_id = endpoint.resource_id();
+        log::trace!("Close gracefully {}", resource_id);
+        self.controllers[resource_id.adapter_id() as usize].close(resource_id, timeout, reason)
+    }
+
+    /// Removes all the listeners and closes gracefully all the connections,
+    /// as [`NetworkController::close_gracefully()`] with the same `timeout`.
+    pub fn close_all_gracefully(&self, timeout: Duration) {
+        log::trace!("Close gracefully all resources");
+        for controller in &self.controllers {
+            controller.close_all(timeout);
+        }
+    }
+
+    /// Returns `true` if some connection is still registered.
+    pub(crate) fn has_connections(&self) -> bool {
+        self.controllers.iter().any(|controller| controller.has_remotes())
+    }
+
     /// Send the data message thought the connection represented by the given endpoint.
     /// This function returns a [`SendStatus`] indicating the status of this send.
     /// There is no guarantee that send over a correct connection generates a [`SendStatus::Sent`]
@@ -122,6 +374,38 @@ impl NetworkController {
         log::trace!("Send status: {:?}", status);
         status
     }
+
+    /// Sets the [`SendLimit`] of the data enqueued by the endpoints of a resource,
+    /// or removes it with `None`. By default, an endpoint has no limit.
+    /// Returns `false` if the resource id doesn't exists or it is not a remote resource.
+    ///
+    /// Once set, [`NetworkController::send()`] returns [`SendStatus::WouldExceedLimit`]
+    /// without sending the data if it would exceed the limit, and a [`NetEvent::Drained`]
+    /// is generated when the endpoint can accept data again.
+    /// It allows to pace the sending to the speed of the peer.
+    /// Only the transports that enqueue the data (as *Tcp* or *FramedTcp*) can exceed it.
+    pub fn set_send_limit(&self, resource_id: ResourceId, limit: Option<SendLimit>) -> bool {
+        log::trace!("Set send limit {:?} to {}", limit, resource_id);
+        self.controllers[resource_id.adapter_id() as usize].set_send_limit(resource_id, limit)
+    }
+
+    /// Stops accepting connections from a listener.
+    /// The connections requested meanwhile wait in the backlog of the listener
+    /// until [`NetworkController::resume_accept()`] is called.
+    /// Listeners of packet based transports (as *Udp*) stop receiving messages.
+    /// Returns `false` if the `resource_id` does not identify a listener.
+    pub fn pause_accept(&self, resource_id: ResourceId) -> bool {
+        log::trace!("Pause accept of {}", resource_id);
+        self.controllers[resource_id.adapter_id() as usize].set_accept_paused(resource_id, true)
+    }
+
+    /// Resumes accepting connections from a listener paused by
+    /// [`NetworkController::pause_accept()`].
+    /// Returns `false` if the `resource_id` does not identify a listener.
+    pub fn resume_accept(&self, resource_id: ResourceId) -> bool {
+        log::trace!("Resume accept of {}", resource_id);
+        self.controllers[resource_id.adapter_id() as usize].set_accept_paused(resource_id, false)
+    }
 }
 
 /// Instance in charge of process input network events.
@@ -148,17 +432,13 @@ impl NetworkProcessor {
         mut event_callback: impl FnMut(NetEvent<'_>),
     ) {
         let processors = &mut self.processors;
-        self.poll.process_event(timeout, |poll_event| {
-            match poll_event {
-                PollEvent::Network(resource_id) => {
-                    let adapter_id = resource_id.adapter_id() as usize;
-                    processors[adapter_id].process(resource_id, &mut |net_event| {
-                        log::trace!("Processed {:?}", net_event);
-                        event_callback(net_event);
-                    });
-                }
-                #[allow(dead_code)] //TODO: remove it with native event support
-                PollEvent::Waker => todo!(),
+        self.poll.process_event(timeout, |poll_event| match poll_event {
+            PollEvent::Network(resource_id) => {
+                let adapter_id = resource_id.adapter_id() as usize;
+                processors[adapter_id].process(resource_id, &mut |net_event| {
+                    log::trace!("Processed {:?}", net_event);
+                    event_callback(net_event);
+                });
             }
         });
     }
@@ -206,4 +486,72 @@ mod tests {
         processor.process_poll_event(Some(*TIMEOUT), |_| was_event = true);
         assert!(!was_event);
     }
+
+    #[test]
+    fn connect_async_not_available_until_connected() {
+        let (controller, mut processor) = self::split();
+        let (listener_id, addr) = controller.listen(Transport::Tcp, "127.0.0.1:0").unwrap();
+        let (endpoint, _) = controller.connect_async(Transport::Tcp, addr).unwrap();
+        assert_eq!(SendStatus::ResourceNotAvailable, controller.send(endpoint, &[42]));
+
+        let mut connected = false;
+        while !connected {
+            processor.process_poll_event(Some(*TIMEOUT), |net_event| match net_event {
+                NetEvent::Connected(connected_endpoint, id) if connected_endpoint == endpoint => {
+                    assert_eq!(endpoint.resource_id(), id);
+                    connected = true;
+                }
+                NetEvent::Connected(_, id) => assert_eq!(listener_id, id),
+                _ => unreachable!(),
+            });
+        }
+        assert_eq!(SendStatus::Sent, controller.send(endpoint, &[42]));
+    }
+
+    #[cfg(feature = "memory")]
+    #[test]
+    fn memory_connection_lifecycle() {
+        let (controller, mut processor) = self::split();
+        let (listener_id, _) = controller.listen(Transport::Memory, "network-test").unwrap();
+        assert!(controller.listen(Transport::Memory, "network-test").is_err());
+        let (endpoint, _) = controller.connect(Transport::Memory, "network-test").unwrap();
+        assert_eq!(SendStatus::Sent, controller.send(endpoint, &[42]));
+
+        let mut accepted = None;
+        processor.process_poll_event(Some(*TIMEOUT), |net_event| match net_event {
+            NetEvent::Connected(endpoint, id) => {
+                assert_eq!(listener_id, id);
+                accepted = Some(endpoint);
+            }
+            _ => unreachable!(),
+        });
+        let accepted = accepted.unwrap();
+
+        let mut was_event = false;
+        processor.process_poll_event(Some(*TIMEOUT), |net_event| match net_event {
+            NetEvent::Message(endpoint, data) => {
+                assert_eq!(accepted, endpoint);
+                assert_eq!(&[42], data);
+                was_event = true;
+            }
+            _ => unreachable!(),
+        });
+        assert!(was_event);
+
+        assert!(controller.remove(endpoint.resource_id()));
+        assert_eq!(SendStatus::ResourceNotFound, controller.send(accepted, &[42]));
+
+        let mut was_event = false;
+        processor.process_poll_event(Some(*TIMEOUT), |net_event| match net_event {
+            NetEvent::Disconnected(endpoint, _) => {
+                assert_eq!(accepted, endpoint);
+                was_event = true;
+            }
+            _ => unreachable!(),
+        });
+        assert!(was_event);
+
+        assert!(controller.remove(listener_id));
+        assert!(controller.connect(Transport::Memory, "network-test").is_err());
+    }
 }
@@ -1,4 +1,8 @@
-use crate::network::{RemoteAddr};
+use crate::network::{
+    RemoteAddr, TransportConnect, TransportListen, ConnectOptions, ListenOptions, CloseReason,
+};
+
+pub use crate::network::poll::{ResourceWaker};
 
 use mio::event::{Source};
 
@@ -28,6 +32,12 @@ pub trait Resource: Send + Sync {
     /// one of them as a base for your non-blocking transport.
     /// See [`Source`].
     fn source(&mut self) -> &mut dyn Source;
+
+    /// Called once the resource has been registered in the poll.
+    /// The [`ResourceWaker`] generates events for this resource without the need of
+    /// a source event, useful for resources that are not backed by an OS socket.
+    /// By default, the waker is not used.
+    fn set_waker(&mut self, _waker: ResourceWaker) {}
 }
 
 /// Plain struct used as a returned value of [`Remote::connect()`]
@@ -72,20 +82,105 @@ pub enum SendStatus {
     /// This implies that a [`crate::network::NetEvent::Disconnected`] has happened or that
     /// the resource never existed.
     ResourceNotFound,
+
+    /// The data could not be written entirely to the OS without blocking,
+    /// so the remaining part has been enqueued and will be written in order
+    /// as soon as the resource can accept it.
+    /// As `Sent`, it does not guarantees that the packet will be received by the endpoint.
+    /// If the resource is removed, the enqueued data is discarded.
+    /// It is written before closing by [`crate::network::NetworkController::close_gracefully()`].
+    Enqueued,
+
+    /// The resource is not ready to send yet: it is still connecting
+    /// (see [`crate::network::NetworkController::connect_async()`]).
+    /// The message can be sent once the `Connected` event is received.
+    /// It is also returned while the resource is being closed
+    /// (see [`crate::network::NetworkController::close_gracefully()`]).
+    ResourceNotAvailable,
+
+    /// The data has not been sent because the endpoint has too much data enqueued,
+    /// exceeding its [`crate::network::SendLimit`].
+    /// The message can be sent again once the `Drained` event is received.
+    WouldExceedLimit,
 }
 
 /// Returned as a result of [`Remote::receive()`]
 pub enum ReadStatus {
-    /// This status must be returned if the resource has been disconnected or there was an error.
+    /// This status must be returned if the resource has been disconnected or there was an error
+    /// that makes it unusable.
     /// The resource will be removed after this call and
     /// no more [`Remote::receive()`] calls will be produced by this resource.
-    Disconnected,
+    /// The [`DisconnectReason`] is given to the user in the
+    /// [`crate::network::NetEvent::Disconnected`] event.
+    Disconnected(DisconnectReason),
 
     /// This status must be returned when a the resource (treated as a non-bloking) would wait for
     /// process the next event.
     /// Usually, this status is returned if the resource receives
     /// a [`std::io::ErrorKind::WouldBlock`].
     WaitNextEvent,
+
+    /// This status must be returned if the resource could not read because of an error
+    /// but it is still usable (e.g. a packet based socket).
+    /// The resource is not removed and it waits for the next event as in
+    /// [`ReadStatus::WaitNextEvent`].
+    /// This will produce a [`crate::network::NetEvent::Error`] event with the error.
+    Error(io::Error),
+}
+
+/// Cause of the loss of a connection,
+/// given by [`crate::network::NetEvent::Disconnected`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DisconnectReason {
+    /// The peer closed the connection in an orderly way (e.g. a *TCP* FIN).
+    Closed,
+
+    /// The connection was reset by the peer or the network (e.g. a *TCP* RST).
+    Reset,
+
+    /// The peer was considered lost by a timeout, as the idle timeout of the connection
+    /// or the heartbeats of *FramedTcp*.
+    Timeout,
+
+    /// The peer did not follow the protocol of the transport (e.g. a malformed frame
+    /// or a failed handshake), described by the text.
+    Protocol(String),
+
+    /// The peer closed the *WebSocket* with a Close frame with this status code and reason.
+    WebSocketClose { code: u16, reason: String },
+
+    /// Unexpected error of the connection, described by the text.
+    Error(String),
+}
+
+impl From<&io::Error> for DisconnectReason {
+    /// Classifies the error returned by the connection.
+    fn from(err: &io::Error) -> Self {
+        match err.kind() {
+            io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::BrokenPipe => DisconnectReason::Reset,
+            io::ErrorKind::UnexpectedEof => DisconnectReason::Closed,
+            io::ErrorKind::TimedOut => DisconnectReason::Timeout,
+            io::ErrorKind::InvalidData => DisconnectReason::Protocol(err.to_string()),
+            _ => DisconnectReason::Error(err.to_string()),
+        }
+    }
+}
+
+impl std::fmt::Display for DisconnectReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Closed => write!(f, "closed by the peer"),
+            Self::Reset => write!(f, "reset"),
+            Self::Timeout => write!(f, "timeout"),
+            Self::Protocol(detail) => write!(f, "protocol error: {}", detail),
+            Self::WebSocketClose { code, reason } => {
+                write!(f, "web socket closed with code {}: '{}'", code, reason)
+            }
+            Self::Error(detail) => write!(f, "error: {}", detail),
+        }
+    }
 }
 
 /// The resource used to represent a remote.
@@ -96,7 +191,17 @@ pub trait Remote: Resource + Sized {
     /// The [`RemoteAddr`] contains either a [`SocketAddr`] or a [`url::Url`].
     /// It is in charge of deciding what to do in both cases.
     /// It also must return the extracted address as `SocketAddr`.
-    fn connect(remote_addr: RemoteAddr) -> io::Result<ConnectionInfo<Self>>;
+    /// The [`TransportConnect`] is always the variant of the adapter's transport
+    /// (or [`TransportConnect::Custom`] for an adapter mounted by the user)
+    /// and contains the configuration given by the user for this connection.
+    /// The [`ConnectOptions`] contains the options of this connection request
+    /// independent of the transport: the **implementator** must respect its `timeout`.
+    /// The retries are performed by the library calling this function again.
+    fn connect(
+        config: TransportConnect,
+        remote_addr: RemoteAddr,
+        options: ConnectOptions,
+    ) -> io::Result<ConnectionInfo<Self>>;
 
     /// Called when a remote endpoint received an event.
     /// It means that the resource has available data to read,
@@ -112,7 +217,80 @@ pub trait Remote: Resource + Sized {
     /// Sends a raw data from a resource.
     /// The **implementator** is in charge to send the entire `data`.
     /// The [`SendStatus`] will contain the status of this attempt.
+    /// If the resource can not write the entire `data` without blocking,
+    /// the **implementator** should keep the remaining data to write it later from
+    /// [`Remote::flush()`] and return [`SendStatus::Enqueued`].
+    /// See [`ResourceWaker::set_writable()`] to receive an event when it can be written again.
     fn send(&self, data: &[u8]) -> SendStatus;
+
+    /// Called when a remote endpoint received an event, before [`Remote::receive()`].
+    /// The **implementator** is in charge of writing the data enqueued by previous
+    /// [`Remote::send()`] calls, as much as possible without blocking.
+    /// By default, nothing is enqueued and nothing is done.
+    fn flush(&self) {}
+
+    /// Returns the number of bytes enqueued by previous [`Remote::send()`] calls
+    /// that have not been written yet.
+    /// It is used to apply the [`crate::network::SendLimit`] of the endpoint.
+    /// By default, nothing is enqueued.
+    fn enqueued_size(&self) -> usize {
+        0
+    }
+
+    /// Called when the user performs a non-blocking connection request
+    /// ([`crate::network::NetworkController::connect_async()`]).
+    /// Similar to [`Remote::connect()`] but the **implementator** must not wait for the
+    /// connection to be established: the remote is returned while it is still connecting.
+    /// The resource will be registered and [`Remote::pending()`] will be called
+    /// by each event until the connection is established or fails.
+    /// By default, the connection is performed by [`Remote::connect()`].
+    fn connect_async(
+        config: TransportConnect,
+        remote_addr: RemoteAddr,
+        options: ConnectOptions,
+    ) -> io::Result<ConnectionInfo<Self>> {
+        Self::connect(config, remote_addr, options)
+    }
+
+    /// Called when a remote created by [`Remote::connect_async()`] received an event
+    /// while it is connecting, and once after its registration.
+    /// The **implementator** is in charge of continuing the connection process
+    /// and returning a [`PendingStatus`].
+    /// Once [`PendingStatus::Ready`] is returned, the resource is used as any other remote
+    /// and this function is not called anymore.
+    /// By default, the remote is considered ready.
+    fn pending(&self) -> PendingStatus {
+        PendingStatus::Ready
+    }
+
+    /// Called when the remote is being closed gracefully
+    /// ([`crate::network::NetworkController::close_gracefully()`]),
+    /// once all the data enqueued by [`Remote::send()`] has been written.
+    /// The **implementator** is in charge of notifying the peer that no more data will be sent,
+    /// using the close mechanism of its protocol (e.g. a *TCP* FIN or a *WebSocket* Close frame
+    /// with the `reason`).
+    /// Returns `true` if the peer is expected to answer closing its side, that is detected
+    /// when [`Remote::receive()`] returns [`ReadStatus::Disconnected`].
+    /// Meanwhile, the data received from the peer is still processed.
+    /// By default, nothing is done and the close is considered completed.
+    fn close(&self, _reason: &CloseReason) -> bool {
+        false
+    }
+}
+
+/// Returned as a result of [`Remote::pending()`]
+pub enum PendingStatus {
+    /// The connection has been established and the resource is ready to be used.
+    /// This will produce a `Connected` API event.
+    Ready,
+
+    /// The connection is still in progress, it must wait for the next event.
+    Incomplete,
+
+    /// The connection could not be established.
+    /// The resource will be removed after this call.
+    /// This will produce a `ConnectionFailed` API event with the error.
+    Failed(io::Error),
 }
 
 /// Used as a parameter callback in [`Local::accept()`]
@@ -130,6 +308,31 @@ pub enum AcceptedType<'a, R> {
     /// This means that the user can treat the [`crate::network::Endpoint`] as if
     /// it was an internal resource.
     Data(SocketAddr, &'a [u8]),
+
+    /// The listener has accepted a connection from the specified addr that is held
+    /// until it can be given as a `Remote` (e.g. until its *PROXY protocol* header is read).
+    /// It does not produce any API event, but it counts against the max number of connections
+    /// of the listener while it is held (see [`Local::pending_accepts()`]).
+    Pending(SocketAddr),
+
+    /// The listener could not accept because of an error (e.g. the limit of open files was
+    /// reached), but it keeps listening.
+    /// This will produce a [`crate::network::NetEvent::Error`] event with the error.
+    Error(io::Error),
+}
+
+/// Returned by the callback of [`Local::accept()`] for each [`AcceptedType`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AcceptStatus {
+    /// The listener can keep accepting.
+    Continue,
+
+    /// The listener can not accept more connections for now
+    /// (it has reached its max number of connections or the accepting was paused).
+    /// The **implementator** must stop accepting connections and leave the pending ones
+    /// in the listen backlog.
+    /// The local resource will receive an event once it can accept again.
+    Stop,
 }
 
 /// The resource used to represent a local listener.
@@ -141,9 +344,21 @@ pub trait Local: Resource + Sized {
 
     /// Called when the user performs a listening request from an specific address.
     /// The **implementator** is in change of creating the corresponding local resource.
+    /// The [`RemoteAddr`] contains either a [`SocketAddr`], a string or a path,
+    /// the `SocketAddr` variant is used for any address that can be resolved as an 'ip:port'.
     /// It also must returned the listening address since it could not be the same as param `addr`
     /// (e.g. listening from port `0`).
-    fn listen(addr: SocketAddr) -> io::Result<ListeningInfo<Self>>;
+    /// The [`TransportListen`] is always the variant of the adapter's transport
+    /// (or [`TransportListen::Custom`] for an adapter mounted by the user)
+    /// and contains the configuration given by the user for this listener.
+    /// The [`ListenOptions`] contains the options of this listening request
+    /// independent of the transport: the **implementator** must return an
+    /// [`io::ErrorKind::InvalidInput`] error for the options it does not support.
+    fn listen(
+        config: TransportListen,
+        addr: RemoteAddr,
+        options: ListenOptions,
+    ) -> io::Result<ListeningInfo<Self>>;
 
     /// Called when a local resource received an event.
     /// It means that some resource have tried to connect.
@@ -153,8 +368,17 @@ pub trait Local: Resource + Sized {
     /// This function is called when the local resource has one or more pending connections.
     /// The **implementator** must process all these pending connections in this call.
     /// For most of the cases it means accept connections until the network
-    /// resource returns `WouldBlock`.
-    fn accept(&self, accept_remote: impl FnMut(AcceptedType<'_, Self::Remote>));
+    /// resource returns `WouldBlock`, or until `accept_remote` returns [`AcceptStatus::Stop`].
+    /// The transports that can not leave the connections waiting
+    /// (e.g. the ones accepted from datagrams) can ignore the returned status:
+    /// the remotes accepted over the limit are closed.
+    fn accept(&self, accept_remote: impl FnMut(AcceptedType<'_, Self::Remote>) -> AcceptStatus);
+
+    /// Number of connections accepted as [`AcceptedType::Pending`] that are still held.
+    /// The **implementator** must only implement this function if it accepts pending connections.
+    fn pending_accepts(&self) -> usize {
+        0
+    }
 
     /// Sends a raw data from a resource.
     /// Similar to [`Remote::send()`] but the resource that sends the data is a `Local`.
@@ -165,3 +389,10 @@ pub trait Local: Resource + Sized {
         panic!("Adapter not configured to send messages directly from the local resource")
     }
 }
+
+/// Error returned by an adapter that receives the configuration of another transport.
+#[cfg(any(feature = "tcp", feature = "websocket", feature = "tls", all(unix, feature = "unix")))]
+pub(crate) fn wrong_config_error() -> io::Error {
+    let err = "The configuration does not belong to the transport of the adapter";
+    io::Error::new(io::ErrorKind::InvalidInput, err)
+}
@@ -1,13 +1,24 @@
 use super::endpoint::{Endpoint};
 use super::resource_id::{ResourceId, ResourceType};
 use super::poll::{Poll};
-use super::registry::{ResourceRegistry};
+use super::registry::{ResourceRegistry, Register};
 use super::remote_addr::{RemoteAddr};
-use super::adapter::{Adapter, Remote, Local, SendStatus, AcceptedType, ReadStatus};
+use super::transport::{TransportConnect, TransportListen};
+use super::options::{ConnectOptions, ListenOptions, SendLimit, Reconnect, CloseReason};
+use super::adapter::{
+    Adapter, Remote, Local, SendStatus, AcceptedType, AcceptStatus, ReadStatus, PendingStatus,
+    DisconnectReason,
+};
+
+use crate::util::thread::{OTHER_THREAD_ERR};
 
 use std::net::{SocketAddr};
-use std::sync::{Arc};
-use std::io::{self};
+use std::sync::{Arc, Mutex};
+use std::collections::HashMap;
+use std::collections::hash_map::{RandomState};
+use std::hash::{BuildHasher};
+use std::time::{Duration, Instant};
+use std::io::{self, ErrorKind};
 
 #[cfg(doctest)]
 use super::transport::{Transport};
@@ -17,8 +28,17 @@ pub enum NetEvent<'a> {
     /// New endpoint has been connected to a listener.
     /// This event will be sent only in connection oriented protocols as *TCP*.
     /// It also contains the resource id of the listener that accepted this connection.
+    ///
+    /// It is also sent when a connection requested by
+    /// [`crate::network::NetworkController::connect_async()`] has been established.
+    /// In this case, the resource id is the one of the endpoint itself.
     Connected(Endpoint, ResourceId),
 
+    /// A connection requested by [`crate::network::NetworkController::connect_async()`]
+    /// could not be established.
+    /// When this event is received, the resource is considered already removed.
+    ConnectionFailed(Endpoint, &'a io::Error),
+
     /// Input message received by the network.
     Message(Endpoint, &'a [u8]),
 
@@ -29,34 +49,144 @@ pub enum NetEvent<'a> {
     /// A [`NetEvent::Message`] event will never be generated after this event from this endpoint.
     /// This event will be sent only in connection oriented protocols as *Tcp*.
     /// *UDP*, for example, is NOT connection oriented, and the event can no be detected.
-    Disconnected(Endpoint),
+    /// The [`DisconnectReason`] describes why the connection was lost.
+    Disconnected(Endpoint, DisconnectReason),
+
+    /// The endpoint has enqueued data below the low water mark of its
+    /// [`crate::network::SendLimit`] after a [`crate::network::NetworkController::send()`]
+    /// returned [`SendStatus::WouldExceedLimit`].
+    /// It is generated once per exceeded limit and the endpoint can accept data again.
+    Drained(Endpoint),
+
+    /// The connection of an endpoint connected with a [`crate::network::Reconnect`] policy
+    /// was lost and it is being reconnected.
+    /// It is generated instead of [`NetEvent::Disconnected`].
+    /// The endpoint is kept, but it can not send data until [`NetEvent::Reconnected`].
+    Reconnecting(Endpoint),
+
+    /// The connection of an endpoint was established again after a [`NetEvent::Reconnecting`].
+    /// The endpoint is the same that was used before losing the connection.
+    Reconnected(Endpoint),
+
+    /// The close of an endpoint requested by
+    /// [`crate::network::NetworkController::close_gracefully()`] has finished.
+    /// The value is `true` if the data was delivered and the close was acknowledged by the peer
+    /// (when the transport allows it) before the timeout,
+    /// or `false` if the timeout expired or the connection was lost before.
+    /// When this event is received, the resource is considered already removed.
+    Closed(Endpoint, bool),
+
+    /// An I/O error was produced by a resource that is still working:
+    /// a listener that could not accept a connection (e.g. the limit of open files was reached)
+    /// or a packet based resource that could not read.
+    /// The resource is not removed, the user can decide whether to remove it.
+    /// The errors that lose a connection generate a [`NetEvent::Disconnected`] instead.
+    Error(ResourceId, &'a io::Error),
 }
 
 impl std::fmt::Debug for NetEvent<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let string = match self {
             Self::Connected(endpoint, id) => format!("Connected({}, {})", endpoint, id),
+            Self::ConnectionFailed(endpoint, err) => {
+                format!("ConnectionFailed({}, {})", endpoint, err)
+            }
             Self::Message(endpoint, data) => format!("Message({}, {})", endpoint, data.len()),
-            Self::Disconnected(endpoint) => format!("Disconnected({})", endpoint),
+            Self::Disconnected(endpoint, reason) => {
+                format!("Disconnected({}, {})", endpoint, reason)
+            }
+            Self::Drained(endpoint) => format!("Drained({})", endpoint),
+            Self::Reconnecting(endpoint) => format!("Reconnecting({})", endpoint),
+            Self::Reconnected(endpoint) => format!("Reconnected({})", endpoint),
+            Self::Closed(endpoint, completed) => format!("Closed({}, {})", endpoint, completed),
+            Self::Error(id, err) => format!("Error({}, {})", id, err),
         };
         write!(f, "NetEvent::{}", string)
     }
 }
 
 pub trait ActionController: Send + Sync {
-    fn connect(&self, addr: RemoteAddr) -> io::Result<(Endpoint, SocketAddr)>;
-    fn listen(&self, addr: SocketAddr) -> io::Result<(ResourceId, SocketAddr)>;
+    fn connect(
+        &self,
+        config: TransportConnect,
+        addr: RemoteAddr,
+        options: ConnectOptions,
+    ) -> io::Result<(Endpoint, SocketAddr)>;
+    fn connect_async(
+        &self,
+        config: TransportConnect,
+        addr: RemoteAddr,
+        options: ConnectOptions,
+    ) -> io::Result<(Endpoint, SocketAddr)>;
+    fn listen(
+        &self,
+        config: TransportListen,
+        addr: RemoteAddr,
+        options: ListenOptions,
+    ) -> io::Result<(ResourceId, SocketAddr)>;
     fn send(&self, endpoint: Endpoint, data: &[u8]) -> SendStatus;
     fn remove(&self, id: ResourceId) -> bool;
+    fn set_send_limit(&self, id: ResourceId, limit: Option<SendLimit>) -> bool;
+    fn set_accept_paused(&self, id: ResourceId, paused: bool) -> bool;
+    fn close(&self, id: ResourceId, timeout: Duration, reason: CloseReason) -> bool;
+    fn close_all(&self, timeout: Duration);
+    fn has_remotes(&self) -> bool;
 }
 
 pub trait EventProcessor: Send + Sync {
     fn process(&self, resource_id: ResourceId, event_callback: &mut dyn FnMut(NetEvent<'_>));
 }
 
+/// Connection performed with a [`Reconnect`] policy,
+/// with the data needed to perform it again once it is lost.
+struct Reconnection {
+    config: TransportConnect,
+    addr: RemoteAddr,
+    options: ConnectOptions,
+    policy: Reconnect,
+    state: ReconnectionState,
+}
+
+enum ReconnectionState {
+    /// The first connection is being established.
+    Connecting,
+    Connected,
+    /// Waiting until `deadline` to perform the attempt number `attempt`.
+    /// A backoff too long to be represented as an instant means no deadline: it waits forever.
+    Waiting {
+        attempt: usize,
+        deadline: Option<Instant>,
+    },
+    /// The attempt number `attempt` is being established.
+    Reconnecting {
+        attempt: usize,
+    },
+}
+
+impl Reconnection {
+    /// Returns the data to reconnect a connection if it has a reconnect policy.
+    fn new(
+        config: &TransportConnect,
+        addr: &RemoteAddr,
+        options: &ConnectOptions,
+        state: ReconnectionState,
+    ) -> Option<Reconnection> {
+        options.reconnect.map(|policy| Reconnection {
+            config: config.clone(),
+            addr: addr.clone(),
+            options: options.clone(),
+            policy,
+            state,
+        })
+    }
+}
+
 pub struct Driver<R: Remote, L: Local> {
     remote_registry: Arc<ResourceRegistry<R>>,
     local_registry: Arc<ResourceRegistry<L>>,
+
+    /// Connections that are reconnected when they are lost.
+    reconnections: Arc<Mutex<HashMap<ResourceId, Reconnection>>>,
 }
 
 impl<R: Remote, L: Local> Driver<R, L> {
@@ -71,7 +201,110 @@ impl<R: Remote, L: Local> Driver<R, L> {
         Driver {
             remote_registry: Arc::new(ResourceRegistry::<R>::new(remote_poll_registry)),
             local_registry: Arc::new(ResourceRegistry::<L>::new(local_poll_registry)),
+            reconnections: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    fn send_limited(remote: &Register<R>, data: &[u8]) -> SendStatus {
+        if let Some(limit) = remote.send_limit() {
+            let enqueued_size = remote.resource.enqueued_size();
+            if enqueued_size > 0 && enqueued_size + data.len() > limit.high_water {
+                remote.mark_as_limited();
+                return SendStatus::WouldExceedLimit
+            }
         }
+        remote.resource.send(data)
+    }
+
+    /// Marks a remote as connected.
+    /// Returns `true` if the connection was a reconnection.
+    fn mark_as_connected(&self, id: ResourceId) -> bool {
+        match self.reconnections.lock().expect(OTHER_THREAD_ERR).get_mut(&id) {
+            Some(reconnection) => {
+                let connected = ReconnectionState::Connected;
+                let state = std::mem::replace(&mut reconnection.state, connected);
+                matches!(state, ReconnectionState::Reconnecting { .. })
+            }
+            None => false,
+        }
+    }
+
+    /// Returns `true` if the remote is establishing a reconnection attempt.
+    fn is_reconnection_attempt(&self, id: ResourceId) -> bool {
+        match self.reconnections.lock().expect(OTHER_THREAD_ERR).get(&id) {
+            Some(reconnection) => {
+                matches!(reconnection.state, ReconnectionState::Reconnecting { .. })
+            }
+            None => false,
+        }
+    }
+
+    /// Schedules the next reconnection attempt of a remote whose connection was lost
+    /// or whose last attempt failed.
+    /// Returns `false` if the remote has no reconnect policy or its attempts are exhausted.
+    fn schedule_reconnection(&self, id: ResourceId, remote: &Register<R>) -> bool {
+        let mut reconnections = self.reconnections.lock().expect(OTHER_THREAD_ERR);
+        let reconnection = match reconnections.get_mut(&id) {
+            Some(reconnection) => reconnection,
+            None => return false,
+        };
+        let attempt = match reconnection.state {
+            ReconnectionState::Reconnecting { attempt } => attempt + 1,
+            _ => 0,
+        };
+        if let Some(max_attempts) = reconnection.policy.max_attempts {
+            if attempt >= max_attempts {
+                return false
+            }
+        }
+        let deadline = Instant::now().checked_add(reconnection.policy.backoff(attempt, random()));
+        reconnection.state = ReconnectionState::Waiting { attempt, deadline };
+        remote.mark_as_reconnecting(deadline);
+        true
+    }
+
+    /// Performs the reconnection attempt of a remote once its waiting time has passed.
+    /// The resource of the remote is replaced by the new connection, keeping its id.
+    /// Returns the error of the attempt if it could not be performed and no more attempts remain.
+    fn reconnect(&self, id: ResourceId, remote: &Register<R>) -> io::Result<()> {
+        let (config, addr, options) = {
+            let mut reconnections = self.reconnections.lock().expect(OTHER_THREAD_ERR);
+            let reconnection = match reconnections.get_mut(&id) {
+                Some(reconnection) => reconnection,
+                None => return Ok(()),
+            };
+            match reconnection.state {
+                ReconnectionState::Waiting { attempt, deadline }
+                    if deadline.is_some_and(|deadline| deadline <= Instant::now()) =>
+                {
+                    reconnection.state = ReconnectionState::Reconnecting { attempt };
+                    let options = reconnection.options.clone();
+                    (reconnection.config.clone(), reconnection.addr.clone(), options)
+                }
+                _ => return Ok(()),
+            }
+        };
+
+        let (connect_timeout, idle_timeout) = (options.timeout, options.idle_timeout);
+        match R::connect_async(config, addr, options) {
+            Ok(info) => {
+                self.remote_registry.replace(id, info.remote, connect_timeout, idle_timeout);
+                Ok(())
+            }
+            Err(err) => {
+                log::trace!("Reconnection attempt failed for {}: {}", id, err);
+                match self.schedule_reconnection(id, remote) {
+                    true => Ok(()),
+                    false => Err(err),
+                }
+            }
+        }
+    }
+
+    /// Removes a remote along with its reconnection data.
+    fn remove_remote(&self, id: ResourceId) -> bool {
+        self.reconnections.lock().expect(OTHER_THREAD_ERR).remove(&id);
+        self.remote_registry.remove(id)
     }
 }
 
@@ -80,32 +313,76 @@ impl<R: Remote, L: Local> Clone for Driver<R, L> {
         Driver {
             remote_registry: self.remote_registry.clone(),
             local_registry: self.local_registry.clone(),
+            reconnections: self.reconnections.clone(),
         }
     }
 }
 
 impl<R: Remote, L: Local> ActionController for Driver<R, L> {
-    fn connect(&self, addr: RemoteAddr) -> io::Result<(Endpoint, SocketAddr)> {
-        R::connect(addr).map(|info| {
-            (
-                Endpoint::new(
-                    self.remote_registry.add(info.remote, info.peer_addr),
-                    info.peer_addr,
-                ),
-                info.local_addr,
-            )
+    fn connect(
+        &self,
+        config: TransportConnect,
+        addr: RemoteAddr,
+        options: ConnectOptions,
+    ) -> io::Result<(Endpoint, SocketAddr)> {
+        let idle_timeout = options.idle_timeout;
+        let reconnection =
+            Reconnection::new(&config, &addr, &options, ReconnectionState::Connected);
+        R::connect(config, addr, options).map(|info| {
+            // Locked to ensure that the remote is not processed before knowing its reconnection.
+            let mut reconnections = self.reconnections.lock().expect(OTHER_THREAD_ERR);
+            let id = self.remote_registry.add(info.remote, info.peer_addr, idle_timeout);
+            if let Some(reconnection) = reconnection {
+                reconnections.insert(id, reconnection);
+            }
+            (Endpoint::new(id, info.peer_addr), info.local_addr)
+        })
+    }
+
+    fn connect_async(
+        &self,
+        config: TransportConnect,
+        addr: RemoteAddr,
+        options: ConnectOptions,
+    ) -> io::Result<(Endpoint, SocketAddr)> {
+        let (connect_timeout, idle_timeout) = (options.timeout, options.idle_timeout);
+        let reconnection =
+            Reconnection::new(&config, &addr, &options, ReconnectionState::Connecting);
+        R::connect_async(config, addr, options).map(|info| {
+            // Locked to ensure that the remote is not processed before knowing its reconnection.
+            let mut reconnections = self.reconnections.lock().expect(OTHER_THREAD_ERR);
+            let id = self.remote_registry.add_pending(
+                info.remote,
+                info.peer_addr,
+                connect_timeout,
+                idle_timeout,
+            );
+            if let Some(reconnection) = reconnection {
+                reconnections.insert(id, reconnection);
+            }
+            (Endpoint::new(id, info.peer_addr), info.local_addr)
         })
     }
 
-    fn listen(&self, addr: SocketAddr) -> io::Result<(ResourceId, SocketAddr)> {
-        L::listen(addr)
-            .map(|info| (self.local_registry.add(info.local, info.local_addr), info.local_addr))
+    fn listen(
+        &self,
+        config: TransportListen,
+        addr: RemoteAddr,
+        options: ListenOptions,
+    ) -> io::Result<(ResourceId, SocketAddr)> {
+        L::listen(config, addr, options.clone()).map(|info| {
+            let id = self.local_registry.add_listener(info.local, info.local_addr, &options);
+            (id, info.local_addr)
+        })
     }
 
     fn send(&self, endpoint: Endpoint, data: &[u8]) -> SendStatus {
         match endpoint.resource_id().resource_type() {
             ResourceType::Remote => match self.remote_registry.get(endpoint.resource_id()) {
-                Some(remote) => remote.resource.send(data),
+                Some(remote) if remote.is_pending() || remote.is_closing() => {
+                    SendStatus::ResourceNotAvailable
+                }
+                Some(remote) => Self::send_limited(&remote, data),
                 None => SendStatus::ResourceNotFound,
             },
             ResourceType::Local => match self.local_registry.get(endpoint.resource_id()) {
@@ -117,10 +394,62 @@ impl<R: Remote, L: Local> ActionController for Driver<R, L> {
 
     fn remove(&self, id: ResourceId) -> bool {
         match id.resource_type() {
-            ResourceType::Remote => self.remote_registry.remove(id),
+            ResourceType::Remote => self.remove_remote(id),
             ResourceType::Local => self.local_registry.remove(id),
         }
     }
+
+    fn set_send_limit(&self, id: ResourceId, limit: Option<SendLimit>) -> bool {
+        match id.resource_type() {
+            ResourceType::Remote => match self.remote_registry.get(id) {
+                Some(remote) => {
+                    remote.set_send_limit(limit);
+                    true
+                }
+                None => false,
+            },
+            ResourceType::Local => false,
+        }
+    }
+
+    fn set_accept_paused(&self, id: ResourceId, paused: bool) -> bool {
+        match id.resource_type() {
+            ResourceType::Remote => false,
+            ResourceType::Local => match self.local_registry.get(id) {
+                Some(local) => {
+                    local.set_accept_paused(paused);
+                    true
+                }
+                None => false,
+            },
+        }
+    }
+
+    fn close(&self, id: ResourceId, timeout: Duration, reason: CloseReason) -> bool {
+        match id.resource_type() {
+            ResourceType::Remote => match self.remote_registry.get(id) {
+                Some(remote) => {
+                    remote.start_closing(reason, timeout);
+                    true
+                }
+                None => false,
+            },
+            ResourceType::Local => false,
+        }
+    }
+
+    fn close_all(&self, timeout: Duration) {
+        for id in self.local_registry.ids() {
+            self.remove(id);
+        }
+        for id in self.remote_registry.ids() {
+            self.close(id, timeout, CloseReason::default());
+        }
+    }
+
+    fn has_remotes(&self) -> bool {
+        !self.remote_registry.is_empty()
+    }
 }
 
 impl<R: Remote, L: Local<Remote = R>> EventProcessor for Driver<R, L> {
@@ -137,35 +466,215 @@ impl<R: Remote, L: Local<Remote = R>> Driver<R, L> {
         if let Some(remote) = self.remote_registry.get(id) {
             let endpoint = Endpoint::new(id, remote.addr);
             log::trace!("Processed remote for {}", endpoint);
+
+            if remote.is_closing() {
+                return self.process_closing(id, &remote, endpoint, &mut event_callback)
+            }
+
+            if remote.is_reconnecting() {
+                if let Err(err) = self.reconnect(id, &remote) {
+                    if self.remove_remote(id) {
+                        event_callback(NetEvent::Disconnected(endpoint, (&err).into()));
+                    }
+                }
+                return
+            }
+
+            if remote.is_pending() {
+                let status = match remote.resource.pending() {
+                    PendingStatus::Incomplete if remote.is_connect_expired() => {
+                        let err = io::Error::new(ErrorKind::TimedOut, "The connection timed out");
+                        PendingStatus::Failed(err)
+                    }
+                    status => status,
+                };
+                match status {
+                    PendingStatus::Ready => {
+                        log::trace!("Processed remote connection established for {}", endpoint);
+                        remote.mark_as_ready();
+                        remote.mark_as_active();
+                        match self.mark_as_connected(id) {
+                            true => event_callback(NetEvent::Reconnected(endpoint)),
+                            false => event_callback(NetEvent::Connected(endpoint, id)),
+                        }
+                        // The user in the callback could have removed the same resource.
+                        if self.remote_registry.get(id).is_none() {
+                            return
+                        }
+                    }
+                    PendingStatus::Incomplete => return,
+                    PendingStatus::Failed(err) => {
+                        log::trace!("Processed remote connection failed for {}", endpoint);
+                        if self.is_reconnection_attempt(id) {
+                            if !self.schedule_reconnection(id, &remote) && self.remove_remote(id) {
+                                event_callback(NetEvent::Disconnected(endpoint, (&err).into()));
+                            }
+                        }
+                        else if self.remove_remote(id) {
+                            event_callback(NetEvent::ConnectionFailed(endpoint, &err));
+                        }
+                        return
+                    }
+                }
+            }
+
+            remote.resource.flush();
+
+            let mut received = false;
             let status = remote.resource.receive(|data| {
+                received = true;
                 event_callback(NetEvent::Message(endpoint, data));
             });
             log::trace!("Processed remote receive status {}", status);
 
-            if let ReadStatus::Disconnected = status {
-                // Checked becasue, the user in the callback could have removed the same resource.
-                if self.remote_registry.remove(id) {
-                    event_callback(NetEvent::Disconnected(endpoint));
+            if received {
+                remote.mark_as_active();
+            }
+
+            if let ReadStatus::Disconnected(reason) = status {
+                self.process_lost(id, &remote, endpoint, reason, &mut event_callback);
+            }
+            else if let ReadStatus::Error(err) = status {
+                log::trace!("Processed remote error for {}: {}", endpoint, err);
+                event_callback(NetEvent::Error(id, &err));
+            }
+            else if remote.check_idle() {
+                log::trace!("Processed remote idle timeout for {}", endpoint);
+                let reason = DisconnectReason::Timeout;
+                self.process_lost(id, &remote, endpoint, reason, &mut event_callback);
+            }
+            else if remote.check_drained(remote.resource.enqueued_size()) {
+                log::trace!("Processed remote drained for {}", endpoint);
+                event_callback(NetEvent::Drained(endpoint));
+            }
+        }
+    }
+
+    /// Closes a remote gracefully: its enqueued data is written, the peer is notified,
+    /// and the remote is removed once the peer closes its side or the timeout expires.
+    /// Meanwhile, the messages received are still processed.
+    fn process_closing(
+        &self,
+        id: ResourceId,
+        remote: &Register<R>,
+        endpoint: Endpoint,
+        event_callback: &mut impl FnMut(NetEvent<'_>),
+    ) {
+        // A remote that is not connected has no data to deliver.
+        if remote.is_pending() {
+            return self.finish_closing(id, endpoint, true, event_callback)
+        }
+
+        remote.resource.flush();
+        if remote.resource.enqueued_size() == 0 {
+            if let Some(reason) = remote.take_close_reason() {
+                log::trace!("Processed remote close notified for {}", endpoint);
+                if !remote.resource.close(&reason) {
+                    return self.finish_closing(id, endpoint, true, event_callback)
                 }
             }
         }
+
+        let status = remote.resource.receive(|data| {
+            event_callback(NetEvent::Message(endpoint, data));
+        });
+        log::trace!("Processed remote receive status {}", status);
+
+        if let ReadStatus::Disconnected(_) = status {
+            let completed = remote.is_close_notified();
+            self.finish_closing(id, endpoint, completed, event_callback);
+        }
+        else if remote.is_close_expired() {
+            log::trace!("Processed remote close timeout for {}", endpoint);
+            self.finish_closing(id, endpoint, false, event_callback);
+        }
+    }
+
+    fn finish_closing(
+        &self,
+        id: ResourceId,
+        endpoint: Endpoint,
+        completed: bool,
+        event_callback: &mut impl FnMut(NetEvent<'_>),
+    ) {
+        // Checked becasue, the user in the callback could have removed the same resource.
+        if self.remove_remote(id) {
+            event_callback(NetEvent::Closed(endpoint, completed));
+        }
+    }
+
+    /// Reconnects a remote whose connection was lost if it has a reconnect policy,
+    /// otherwise it is removed.
+    fn process_lost(
+        &self,
+        id: ResourceId,
+        remote: &Register<R>,
+        endpoint: Endpoint,
+        reason: DisconnectReason,
+        event_callback: &mut impl FnMut(NetEvent<'_>),
+    ) {
+        if self.schedule_reconnection(id, remote) {
+            log::trace!("Processed remote reconnection scheduled for {}", endpoint);
+            event_callback(NetEvent::Reconnecting(endpoint));
+        }
+        // Checked becasue, the user in the callback could have removed the same resource.
+        else if self.remove_remote(id) {
+            event_callback(NetEvent::Disconnected(endpoint, reason));
+        }
     }
 
     fn process_local(&self, id: ResourceId, mut event_callback: impl FnMut(NetEvent<'_>)) {
         if let Some(local) = self.local_registry.get(id) {
+            if local.is_accept_paused() {
+                return log::trace!("Processed local with the accept paused for {}", id)
+            }
+
+            if local.is_at_max_connections(0) {
+                // The connections wait in the listen backlog until some slot is released.
+                // The pending ones are not processed either: they could not take a slot.
+                return log::trace!("Processed local at max connections for {}", id)
+            }
+
             log::trace!("Processed local for {}", id);
             local.resource.accept(|accepted| {
                 log::trace!("Processed local accepted type {}", accepted);
                 match accepted {
-                    AcceptedType::Remote(addr, remote) => {
-                        let remote_id = self.remote_registry.add(remote, addr);
-                        let endpoint = Endpoint::new(remote_id, addr);
-                        event_callback(NetEvent::Connected(endpoint, id));
+                    AcceptedType::Remote(addr, _) | AcceptedType::Data(addr, _)
+                        if !local.accepts(addr) =>
+                    {
+                        // The remote is closed when dropped.
+                        log::trace!("Processed local rejected peer {} for {}", addr, id)
                     }
+                    AcceptedType::Remote(addr, remote) => match local.take_connection_slot() {
+                        Some(slot) => {
+                            let idle_timeout = local.accepted_idle_timeout();
+                            let remote_id =
+                                self.remote_registry.add_accepted(remote, addr, slot, idle_timeout);
+                            let endpoint = Endpoint::new(remote_id, addr);
+                            event_callback(NetEvent::Connected(endpoint, id));
+                        }
+                        None => {
+                            // The adapter did not stop accepting at the limit.
+                            // The remote is closed when dropped.
+                            log::trace!(
+                                "Processed local closed peer {} over the max for {}",
+                                addr,
+                                id
+                            )
+                        }
+                    },
                     AcceptedType::Data(addr, data) => {
                         let endpoint = Endpoint::new(id, addr);
                         event_callback(NetEvent::Message(endpoint, data));
                     }
+                    AcceptedType::Pending(_) => (),
+                    AcceptedType::Error(err) => event_callback(NetEvent::Error(id, &err)),
+                }
+                // The user could have paused the accepting from the callback.
+                let pending = local.resource.pending_accepts();
+                match local.is_accept_paused() || local.is_at_max_connections(pending) {
+                    true => AcceptStatus::Stop,
+                    false => AcceptStatus::Continue,
                 }
             });
         }
@@ -175,8 +684,9 @@ impl<R: Remote, L: Local<Remote = R>> Driver<R, L> {
 impl std::fmt::Display for ReadStatus {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let string = match self {
-            ReadStatus::Disconnected => "Disconnected",
+            ReadStatus::Disconnected(_) => "Disconnected",
             ReadStatus::WaitNextEvent => "WaitNextEvent",
+            ReadStatus::Error(_) => "Error",
         };
         write!(f, "ReadStatus::{}", string)
     }
@@ -187,7 +697,15 @@ impl<R> std::fmt::Display for AcceptedType<'_, R> {
         let string = match self {
             AcceptedType::Remote(addr, _) => format!("Remote({})", addr),
             AcceptedType::Data(addr, _) => format!("Data({})", addr),
+            AcceptedType::Pending(addr) => format!("Pending({})", addr),
+            AcceptedType::Error(err) => format!("Error({})", err),
         };
         write!(f, "AcceptedType::{}", string)
     }
 }
+
+/// Random value from `0.0` to `1.0`, used as jitter of the reconnections.
+/// The std hasher is seeded with random keys for each instance.
+fn random() -> f64 {
+    RandomState::new().hash_one(Instant::now()) as f64 / u64::MAX as f64
+}
@@ -1,4 +1,9 @@
 use super::resource_id::{ResourceId};
+use super::transport::{Transport};
+
+use crate::error::{Error};
+
+use strum::{IntoEnumIterator};
 
 use std::net::{SocketAddr};
 
@@ -17,7 +22,9 @@ impl Endpoint {
     /// from a **listener resource** to send messages to different address without
     /// creating a connection.
     ///
-    /// For connection oriented protocol, creating manually an endpoint is not allowed.
+    /// For connection oriented protocol, creating manually an endpoint is not allowed:
+    /// [`Error::InvalidResource`] is returned if the resource is not a listener
+    /// of a non connection-oriented transport.
     ///
     /// # Example
     /// ```rust
@@ -33,8 +40,8 @@ impl Endpoint {
     /// let (sender_id, _) = handler.network().listen(Transport::Udp, listen_addr).unwrap();
     ///
     /// //addr_1 and addr_2 contain the addresses with the listening ports.
-    /// handler.network().send(Endpoint::from_listener(sender_id, addr_1), &[23]);
-    /// handler.network().send(Endpoint::from_listener(sender_id, addr_2), &[42]);
+    /// handler.network().send(Endpoint::from_listener(sender_id, addr_1).unwrap(), &[23]);
+    /// handler.network().send(Endpoint::from_listener(sender_id, addr_2).unwrap(), &[42]);
     ///
     /// let (mut msg_1, mut msg_2) = (0, 0);
     /// listener.for_each(|event| match event {
@@ -51,14 +58,21 @@ impl Endpoint {
     ///
     /// assert_eq!((msg_1, msg_2), (23, 42));
     /// ```
-    pub fn from_listener(id: ResourceId, addr: SocketAddr) -> Self {
+    pub fn from_listener(id: ResourceId, addr: SocketAddr) -> Result<Self, Error> {
         // Only local resources allowed
-        assert_eq!(id.resource_type(), super::resource_id::ResourceType::Local);
+        if id.resource_type() != super::resource_id::ResourceType::Local {
+            return Err(Error::InvalidResource(id))
+        }
 
-        // Only packet based transport protocols allowed
-        assert!(!super::transport::Transport::from(id.adapter_id()).is_connection_oriented());
+        // Only packet based transport protocols allowed.
+        // The adapters mounted by the user are not checked.
+        if let Some(transport) = Transport::iter().find(|t| t.id() == id.adapter_id()) {
+            if transport.is_connection_oriented() {
+                return Err(Error::InvalidResource(id))
+            }
+        }
 
-        Endpoint::new(id, addr)
+        Ok(Endpoint::new(id, addr))
     }
 
     pub(crate) fn new(resource_id: ResourceId, addr: SocketAddr) -> Self {
@@ -91,25 +105,25 @@ mod tests {
     use crate::network::transport::{Transport};
 
     #[test]
-    #[should_panic]
     fn from_remote_non_connection_oriented() {
         let addr = "0.0.0.0:0".parse().unwrap();
         let generator = ResourceIdGenerator::new(Transport::Udp.id(), ResourceType::Remote);
-        Endpoint::from_listener(generator.generate(), addr);
+        let result = Endpoint::from_listener(generator.generate(), addr);
+        assert!(matches!(result, Err(Error::InvalidResource(_))));
     }
 
     #[test]
-    #[should_panic]
     fn from_local_connection_oriented() {
         let addr = "0.0.0.0:0".parse().unwrap();
         let generator = ResourceIdGenerator::new(Transport::Tcp.id(), ResourceType::Local);
-        Endpoint::from_listener(generator.generate(), addr);
+        let result = Endpoint::from_listener(generator.generate(), addr);
+        assert!(matches!(result, Err(Error::InvalidResource(_))));
     }
 
     #[test]
     fn from_local_non_connection_oriented() {
         let addr = "0.0.0.0:0".parse().unwrap();
         let generator = ResourceIdGenerator::new(Transport::Udp.id(), ResourceType::Local);
-        Endpoint::from_listener(generator.generate(), addr);
+        assert!(Endpoint::from_listener(generator.generate(), addr).is_ok());
     }
 }
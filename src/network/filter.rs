@@ -0,0 +1,203 @@
+use std::net::{SocketAddr, IpAddr, Ipv4Addr};
+use std::str::{FromStr};
+use std::sync::{Arc};
+use std::io::{self, ErrorKind};
+
+/// Decides which peers a listener accepts.
+/// It is set in the [`ListenOptions`](crate::network::ListenOptions) of a listener.
+///
+/// The connections of the rejected peers are closed and their datagrams are discarded
+/// without generating any event.
+///
+/// ```
+/// use message_io::network::{AcceptFilter};
+///
+/// let filter = AcceptFilter::deny(vec!["10.0.0.0/8".parse().unwrap()]);
+/// assert!(!filter.accepts("10.1.2.3:4000".parse().unwrap()));
+/// assert!(filter.accepts("192.168.0.1:4000".parse().unwrap()));
+///
+/// let filter = AcceptFilter::new(|addr| addr.port() != 4000);
+/// assert!(!filter.accepts("192.168.0.1:4000".parse().unwrap()));
+/// ```
+#[derive(Clone)]
+pub struct AcceptFilter(Arc<dyn Fn(SocketAddr) -> bool + Send + Sync>);
+
+impl AcceptFilter {
+    /// Creates a filter that accepts the peers for which `filter` returns `true`.
+    /// It is called from the thread that processes the network events,
+    /// so it should not block.
+    pub fn new(filter: impl Fn(SocketAddr) -> bool + Send + Sync + 'static) -> Self {
+        Self(Arc::new(filter))
+    }
+
+    /// Creates a filter that only accepts the peers contained in some of the `ranges`.
+    pub fn allow(ranges: Vec<Cidr>) -> Self {
+        Self::new(move |addr| ranges.iter().any(|range| range.contains(addr.ip())))
+    }
+
+    /// Creates a filter that accepts all peers except the ones contained in some of the `ranges`.
+    pub fn deny(ranges: Vec<Cidr>) -> Self {
+        Self::new(move |addr| !ranges.iter().any(|range| range.contains(addr.ip())))
+    }
+
+    /// Returns `true` if the peer with address `addr` is accepted.
+    pub fn accepts(&self, addr: SocketAddr) -> bool {
+        (self.0)(addr)
+    }
+}
+
+impl std::fmt::Debug for AcceptFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "AcceptFilter")
+    }
+}
+
+/// Range of IP addresses in *CIDR* notation, as `192.168.0.0/16` or `fe80::/10`.
+/// An address without prefix length represents only that address.
+/// The IPv4-mapped IPv6 addresses (as `::ffff:10.0.0.1`) are contained
+/// in the IPv4 ranges, and an IPv4-mapped range (as `::ffff:10.0.0.0/104`)
+/// is the same as its IPv4 range (`10.0.0.0/8`).
+///
+/// ```
+/// use message_io::network::{Cidr};
+///
+/// let range: Cidr = "192.168.0.0/16".parse().unwrap();
+/// assert!(range.contains("192.168.1.1".parse().unwrap()));
+/// assert!(!range.contains("192.169.0.1".parse().unwrap()));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Cidr {
+    addr: IpAddr,
+    prefix_len: u8,
+}
+
+impl Cidr {
+    /// Creates a range with the addresses whose first `prefix_len` bits are the ones of `addr`.
+    /// Fails with an [`ErrorKind::InvalidInput`] error if `prefix_len` is greater than
+    /// the bits of the address.
+    pub fn new(addr: IpAddr, prefix_len: u8) -> io::Result<Self> {
+        let max_len = match addr {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+        match prefix_len <= max_len {
+            true => match to_ipv4(addr) {
+                // The prefix covers the whole IPv4-mapped part, so it is an IPv4 range.
+                IpAddr::V4(ipv4) if addr.is_ipv6() && prefix_len >= MAPPED_PREFIX_LEN => {
+                    Ok(Cidr { addr: IpAddr::V4(ipv4), prefix_len: prefix_len - MAPPED_PREFIX_LEN })
+                }
+                _ => Ok(Cidr { addr, prefix_len }),
+            },
+            false => {
+                let err = format!("The prefix length of {} can not exceed {}", addr, max_len);
+                Err(io::Error::new(ErrorKind::InvalidInput, err))
+            }
+        }
+    }
+
+    /// Returns `true` if `ip` is in the range.
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.addr, to_ipv4(ip)) {
+            (IpAddr::V4(range), IpAddr::V4(ip)) => {
+                prefix_eq(&range.octets(), &ip.octets(), self.prefix_len)
+            }
+            (IpAddr::V6(range), IpAddr::V6(ip)) => {
+                prefix_eq(&range.octets(), &ip.octets(), self.prefix_len)
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = io::Error;
+
+    fn from_str(range: &str) -> io::Result<Self> {
+        let invalid = || {
+            let err = format!("Invalid CIDR range '{}'", range);
+            io::Error::new(ErrorKind::InvalidInput, err)
+        };
+
+        let (addr, prefix_len) = match range.split_once('/') {
+            Some((addr, prefix_len)) => {
+                (addr, Some(prefix_len.parse::<u8>().map_err(|_| invalid())?))
+            }
+            None => (range, None),
+        };
+        let addr: IpAddr = addr.parse().map_err(|_| invalid())?;
+        let prefix_len = prefix_len.unwrap_or(match addr {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        });
+        Cidr::new(addr, prefix_len)
+    }
+}
+
+/// Bits of the `::ffff:0:0/96` prefix of the IPv4-mapped IPv6 addresses.
+const MAPPED_PREFIX_LEN: u8 = 96;
+
+/// Converts an IPv4-mapped IPv6 address into its IPv4 address.
+fn to_ipv4(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(ipv6) => match ipv6.octets() {
+            [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xFF, 0xFF, a, b, c, d] => {
+                IpAddr::V4(Ipv4Addr::new(a, b, c, d))
+            }
+            _ => ip,
+        },
+        ip => ip,
+    }
+}
+
+fn prefix_eq(range: &[u8], ip: &[u8], prefix_len: u8) -> bool {
+    let full_bytes = prefix_len as usize / 8;
+    let rest_bits = prefix_len % 8;
+    if range[..full_bytes] != ip[..full_bytes] {
+        return false
+    }
+    match rest_bits {
+        0 => true,
+        _ => {
+            let mask = 0xFF << (8 - rest_bits);
+            range[full_bytes] & mask == ip[full_bytes] & mask
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn contains(range: &str, ip: &str) -> bool {
+        range.parse::<Cidr>().unwrap().contains(ip.parse().unwrap())
+    }
+
+    #[test]
+    fn cidr_contains() {
+        assert!(contains("10.0.0.0/8", "10.255.0.1"));
+        assert!(!contains("10.0.0.0/8", "11.0.0.1"));
+        assert!(contains("192.168.0.0/22", "192.168.3.255"));
+        assert!(!contains("192.168.0.0/22", "192.168.4.0"));
+        assert!(contains("127.0.0.1", "127.0.0.1"));
+        assert!(!contains("127.0.0.1", "127.0.0.2"));
+        assert!(contains("0.0.0.0/0", "8.8.8.8"));
+        assert!(contains("10.0.0.0/8", "::ffff:10.0.0.1"));
+        assert!(!contains("10.0.0.0/8", "::1"));
+        assert!(contains("fe80::/10", "febf::1"));
+        assert!(!contains("fe80::/10", "fec0::1"));
+        assert!(!contains("::/0", "10.0.0.1"));
+        assert!(contains("::ffff:10.0.0.0/104", "10.1.2.3"));
+        assert!(contains("::ffff:10.0.0.0/104", "::ffff:10.1.2.3"));
+        assert!(!contains("::ffff:10.0.0.0/104", "11.0.0.1"));
+        assert!(contains("::ffff:127.0.0.1", "127.0.0.1"));
+        assert_eq!("10.0.0.0/8".parse::<Cidr>().unwrap(), "::ffff:10.0.0.0/104".parse().unwrap());
+    }
+
+    #[test]
+    fn parse_invalid_cidr() {
+        for range in ["10.0.0.0/33", "::/129", "10.0.0/8", "10.0.0.0/", "10.0.0.0/a"] {
+            let err = range.parse::<Cidr>().unwrap_err();
+            assert_eq!(ErrorKind::InvalidInput, err.kind());
+        }
+    }
+}
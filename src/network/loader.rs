@@ -2,10 +2,15 @@ use super::endpoint::{Endpoint};
 use super::resource_id::{ResourceId};
 use super::poll::{Poll};
 use super::remote_addr::{RemoteAddr};
+use super::transport::{TransportConnect, TransportListen};
+use super::options::{ConnectOptions, ListenOptions, SendLimit, CloseReason};
 use super::driver::{NetEvent, Driver, ActionController, EventProcessor};
 use super::adapter::{Adapter, SendStatus};
 
+use crate::error::{Error};
+
 use std::net::{SocketAddr};
+use std::time::{Duration};
 use std::io::{self};
 use std::panic::{UnwindSafe};
 
@@ -20,6 +25,7 @@ pub struct DriverLoader {
     poll: Poll,
     controllers: ActionControllerList,
     processors: EventProcessorList,
+    mounted: Vec<bool>,
 }
 
 impl Default for DriverLoader {
@@ -27,11 +33,12 @@ impl Default for DriverLoader {
         Self {
             poll: Poll::default(),
             controllers: (0..ResourceId::MAX_ADAPTERS)
-                .map(|_| Box::new(UnimplementedDriver) as Controller)
+                .map(|adapter_id| Box::new(UnimplementedDriver(adapter_id as u8)) as Controller)
                 .collect::<Vec<_>>(),
             processors: (0..ResourceId::MAX_ADAPTERS)
-                .map(|_| Box::new(UnimplementedDriver) as Processor)
+                .map(|adapter_id| Box::new(UnimplementedDriver(adapter_id as u8)) as Processor)
                 .collect(),
+            mounted: vec![false; ResourceId::MAX_ADAPTERS],
         }
     }
 }
@@ -45,6 +52,12 @@ impl DriverLoader {
 
         self.controllers[index] = Box::new(driver.clone()) as Controller;
         self.processors[index] = Box::new(driver) as Processor;
+        self.mounted[index] = true;
+    }
+
+    /// Tells if there is an adapter mounted with the given id.
+    pub fn is_mounted(&self, adapter_id: u8) -> bool {
+        self.mounted.get(adapter_id as usize).copied().unwrap_or(false)
     }
 
     /// Consume this instance to obtain the driver handles.
@@ -56,26 +69,67 @@ impl DriverLoader {
 // The following unimplemented driver is used to fill
 // the invalid adapter id gaps in the controllers/processors lists.
 // It is faster and cleanest than to use an option that always must to be unwrapped.
+// The resources can not be created from it, so the creation returns an error
+// and the actions over a resource id of the adapter do not find it,
+// as with a resource already removed (e.g. an id created by the user).
 
 const UNIMPLEMENTED_DRIVER_ERR: &str =
     "The chosen adapter id doesn't reference an existing adapter";
 
-struct UnimplementedDriver;
+struct UnimplementedDriver(u8);
 impl ActionController for UnimplementedDriver {
-    fn connect(&self, _: RemoteAddr) -> io::Result<(Endpoint, SocketAddr)> {
-        panic!("{}", UNIMPLEMENTED_DRIVER_ERR);
+    fn connect(
+        &self,
+        _: TransportConnect,
+        _: RemoteAddr,
+        _: ConnectOptions,
+    ) -> io::Result<(Endpoint, SocketAddr)> {
+        Err(Error::UnsupportedTransport(self.0).into())
     }
 
-    fn listen(&self, _: SocketAddr) -> io::Result<(ResourceId, SocketAddr)> {
-        panic!("{}", UNIMPLEMENTED_DRIVER_ERR);
+    fn connect_async(
+        &self,
+        _: TransportConnect,
+        _: RemoteAddr,
+        _: ConnectOptions,
+    ) -> io::Result<(Endpoint, SocketAddr)> {
+        Err(Error::UnsupportedTransport(self.0).into())
+    }
+
+    fn listen(
+        &self,
+        _: TransportListen,
+        _: RemoteAddr,
+        _: ListenOptions,
+    ) -> io::Result<(ResourceId, SocketAddr)> {
+        Err(Error::UnsupportedTransport(self.0).into())
     }
 
     fn send(&self, _: Endpoint, _: &[u8]) -> SendStatus {
-        panic!("{}", UNIMPLEMENTED_DRIVER_ERR);
+        SendStatus::ResourceNotFound
     }
 
     fn remove(&self, _: ResourceId) -> bool {
-        panic!("{}", UNIMPLEMENTED_DRIVER_ERR);
+        false
+    }
+
+    fn set_send_limit(&self, _: ResourceId, _: Option<SendLimit>) -> bool {
+        false
+    }
+
+    fn set_accept_paused(&self, _: ResourceId, _: bool) -> bool {
+        false
+    }
+
+    fn close(&self, _: ResourceId, _: Duration, _: CloseReason) -> bool {
+        false
+    }
+
+    // Called for all the adapters, it has nothing to close.
+    fn close_all(&self, _: Duration) {}
+
+    fn has_remotes(&self) -> bool {
+        false
     }
 }
 
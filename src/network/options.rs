@@ -0,0 +1,286 @@
+use super::proxy::{Proxy};
+use super::filter::{AcceptFilter};
+
+use std::net::{SocketAddr};
+#[cfg(any(feature = "udp", feature = "quic"))]
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::time::{Duration};
+#[cfg(any(feature = "udp", feature = "tls", feature = "memory", all(unix, feature = "unix")))]
+use std::io::{self, ErrorKind};
+
+/// Options of a connection request, independent of the transport used.
+/// It can be passed to
+/// [`NetworkController::connect_with_options()`](crate::network::NetworkController::connect_with_options())
+/// to control how the connection is performed.
+///
+/// ```
+/// use message_io::network::{ConnectOptions};
+/// use std::time::{Duration};
+///
+/// let options = ConnectOptions {
+///     timeout: Some(Duration::from_secs(5)),
+///     retries: 3,
+///     backoff: Duration::from_millis(500),
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ConnectOptions {
+    /// Max time that each connection attempt can take,
+    /// including the handshakes of the transport (e.g. *TLS* or *WebSocket*).
+    /// If it is exceeded, the attempt fails with an [`std::io::ErrorKind::TimedOut`] error.
+    /// It also limits the non-blocking connections until they are established.
+    /// If it is `None`, the timeouts of the OS are used.
+    /// Transports without a connection process (as *Udp*) can not exceed it.
+    pub timeout: Option<Duration>,
+
+    /// Number of new attempts performed after a failed connection.
+    /// The errors produced by an invalid address or configuration
+    /// ([`std::io::ErrorKind::InvalidInput`]) are never retried.
+    pub retries: usize,
+
+    /// Waiting time before the first retry.
+    /// It is doubled before each following retry.
+    pub backoff: Duration,
+
+    /// Local address to which the connection is bound before connecting,
+    /// used to choose the interface and port from which the traffic is originated.
+    /// If it is `None`, the OS chooses them.
+    /// Transports not addressed by IP (as *UnixStream* or *Memory*) fail with an
+    /// [`std::io::ErrorKind::InvalidInput`] error if it is set.
+    pub local_addr: Option<SocketAddr>,
+
+    /// Proxy through which the connection is performed.
+    /// Its handshake is performed before the handshakes of the transport.
+    /// A host name is given to the proxy to be resolved by it, so the address of the endpoint
+    /// of a connection to a host name is the address of the proxy.
+    /// Only the transports over TCP (*Tcp*, *FramedTcp*, *FramedTls* and *Ws*) support it,
+    /// the rest of them fail with an [`std::io::ErrorKind::InvalidInput`] error if it is set.
+    /// A non-blocking connection through a proxy is performed synchronously.
+    pub proxy: Option<Proxy>,
+
+    /// Max time that the connection can be without receiving messages once established.
+    /// If it is exceeded, the connection is closed and a
+    /// [`NetEvent::Disconnected`](crate::network::NetEvent::Disconnected) is generated.
+    pub idle_timeout: Option<Duration>,
+
+    /// Reconnects the connection automatically each time it is lost,
+    /// keeping the same [`Endpoint`](crate::network::Endpoint).
+    /// See [`Reconnect`].
+    pub reconnect: Option<Reconnect>,
+}
+
+impl ConnectOptions {
+    /// Returns the waiting time before the retry number `retry` (starting by `0`).
+    pub(crate) fn backoff(&self, retry: usize) -> Duration {
+        self.backoff.saturating_mul(1 << retry.min(31) as u32)
+    }
+
+    /// Returns the local address to bind before connecting to `peer_addr`.
+    /// If no local address was specified, it is the unspecified address of the same family
+    /// as `peer_addr` with a port chosen by the OS.
+    #[cfg(any(feature = "udp", feature = "quic"))]
+    pub(crate) fn bind_addr(&self, peer_addr: SocketAddr) -> SocketAddr {
+        self.local_addr.unwrap_or_else(|| {
+            let unspecified_ip = match peer_addr {
+                SocketAddr::V4(_) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+                SocketAddr::V6(_) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+            };
+            SocketAddr::new(unspecified_ip, 0)
+        })
+    }
+
+    /// Returns an [`ErrorKind::InvalidInput`] error if a proxy was set,
+    /// used by the transports that can not be connected through it.
+    #[cfg(any(feature = "udp", feature = "quic", feature = "memory", all(unix, feature = "unix")))]
+    pub(crate) fn check_no_proxy(&self) -> io::Result<()> {
+        match self.proxy {
+            Some(_) => {
+                let err = "The transport can not be connected through a proxy";
+                Err(io::Error::new(ErrorKind::InvalidInput, err))
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+/// Policy used to reconnect a connection that was lost.
+/// It is set in the [`ConnectOptions`] of the connection.
+///
+/// When the connection is lost, a
+/// [`NetEvent::Reconnecting`](crate::network::NetEvent::Reconnecting) is generated instead of
+/// [`NetEvent::Disconnected`](crate::network::NetEvent::Disconnected)
+/// and the connection is performed again with the same options after a waiting time.
+/// Once connected, a [`NetEvent::Reconnected`](crate::network::NetEvent::Reconnected)
+/// is generated.
+/// Meanwhile, the endpoint is kept and sending through it returns
+/// [`SendStatus::ResourceNotAvailable`](crate::network::SendStatus::ResourceNotAvailable).
+/// If all the attempts fail, the endpoint is removed and a
+/// [`NetEvent::Disconnected`](crate::network::NetEvent::Disconnected) is generated.
+/// An attempt that is not connected within [`ConnectOptions::timeout`] fails.
+///
+/// Each attempt is started from the network thread: the host name is resolved
+/// and the connection through a [`ConnectOptions::proxy`] is performed synchronously there,
+/// blocking the processing of the rest of the events meanwhile.
+///
+/// ```
+/// use message_io::network::{ConnectOptions, Reconnect};
+/// use std::time::{Duration};
+///
+/// let reconnect = Reconnect { max_backoff: Duration::from_secs(5), ..Default::default() };
+/// let options = ConnectOptions { reconnect: Some(reconnect), ..Default::default() };
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Reconnect {
+    /// Waiting time before the first attempt.
+    /// It is doubled before each following attempt.
+    /// By default, 100 milliseconds.
+    pub backoff: Duration,
+
+    /// Max waiting time between two attempts.
+    /// By default, 30 seconds.
+    pub max_backoff: Duration,
+
+    /// Fraction of the waiting time (from `0.0` to `1.0`) that is randomly subtracted from it,
+    /// so that many clients losing their connections at once do not reconnect at once.
+    /// By default, `0.5`.
+    pub jitter: f64,
+
+    /// Max number of consecutive failed attempts before giving up.
+    /// If it is `None`, the attempts never end.
+    pub max_attempts: Option<usize>,
+}
+
+impl Default for Reconnect {
+    fn default() -> Self {
+        Self {
+            backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(30),
+            jitter: 0.5,
+            max_attempts: None,
+        }
+    }
+}
+
+impl Reconnect {
+    /// Returns the waiting time before the attempt number `attempt` (starting by `0`).
+    /// `random` is a random value from `0.0` to `1.0` used to apply the jitter.
+    pub(crate) fn backoff(&self, attempt: usize, random: f64) -> Duration {
+        let backoff = self.backoff.saturating_mul(1 << attempt.min(31) as u32);
+        let backoff = backoff.min(self.max_backoff);
+        backoff.mul_f64(1.0 - self.jitter.clamp(0.0, 1.0) * random)
+    }
+}
+
+/// Options of a listening request, independent of the transport used.
+/// It can be passed to
+/// [`NetworkController::listen_with_options()`](crate::network::NetworkController::listen_with_options())
+/// to control how the connections are accepted.
+///
+/// ```
+/// use message_io::network::{ListenOptions};
+///
+/// let options = ListenOptions { proxy_protocol: true, ..Default::default() };
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ListenOptions {
+    /// Reads a *PROXY protocol* (v1 or v2) header at the beginning of each accepted connection,
+    /// as sent by load balancers as *HAProxy* or *AWS NLB*.
+    /// The client address given by the header is used as the address of the endpoint,
+    /// already in its [`NetEvent::Connected`](crate::network::NetEvent::Connected) event,
+    /// that is generated once the header is read.
+    /// The connections with a malformed header, or without a complete header after 5 seconds,
+    /// are rejected without generating any event.
+    /// Meanwhile, they count against the [`ListenOptions::max_connections`].
+    /// Only *Tcp*, *FramedTcp* and *Ws* support it,
+    /// the rest of transports fail with an [`std::io::ErrorKind::InvalidInput`] error if it is set.
+    pub proxy_protocol: bool,
+
+    /// Max number of connections accepted by the listener that can be alive at the same time.
+    /// Once it is reached, the listener stops accepting connections until one of them
+    /// is closed or removed, and the new connections wait in the backlog of the listener.
+    /// Listeners of packet based transports (as *Udp*) have no connections to limit.
+    pub max_connections: Option<usize>,
+
+    /// Filter that decides which peers are accepted by the listener.
+    /// The connections of the rejected peers are closed and their datagrams discarded,
+    /// without generating [`NetEvent::Connected`](crate::network::NetEvent::Connected) or
+    /// [`NetEvent::Message`](crate::network::NetEvent::Message) events.
+    /// When the *PROXY protocol* is enabled, the address given by its header is filtered.
+    pub accept_filter: Option<AcceptFilter>,
+
+    /// Max time that each connection accepted by the listener can be without receiving messages.
+    /// If it is exceeded, the connection is closed and a
+    /// [`NetEvent::Disconnected`](crate::network::NetEvent::Disconnected) is generated.
+    /// Listeners of packet based transports (as *Udp*) have no connections to close.
+    pub idle_timeout: Option<Duration>,
+}
+
+impl ListenOptions {
+    /// Returns an [`ErrorKind::InvalidInput`] error if the PROXY protocol was enabled,
+    /// used by the transports that do not support it.
+    #[cfg(any(feature = "udp", feature = "tls", feature = "memory", all(unix, feature = "unix")))]
+    pub(crate) fn check_no_proxy_protocol(&self) -> io::Result<()> {
+        match self.proxy_protocol {
+            true => {
+                let err = "The transport does not support the PROXY protocol";
+                Err(io::Error::new(ErrorKind::InvalidInput, err))
+            }
+            false => Ok(()),
+        }
+    }
+}
+
+/// Limits of the data that an endpoint can keep enqueued to be sent.
+/// It can be set for an endpoint by
+/// [`NetworkController::set_send_limit()`](crate::network::NetworkController::set_send_limit())
+/// to know when the peer is reading slower than the data is sent.
+///
+/// While the endpoint has more than `high_water` bytes enqueued, or sending the data would
+/// exceed them, `send()` does not accept more data and returns
+/// [`SendStatus::WouldExceedLimit`](crate::network::SendStatus::WouldExceedLimit).
+/// Once the enqueued data falls to `low_water` bytes or less,
+/// a [`NetEvent::Drained`](crate::network::NetEvent::Drained) is generated for the endpoint.
+///
+/// ```
+/// use message_io::network::{SendLimit};
+///
+/// let limit = SendLimit { high_water: 1024 * 1024, low_water: 64 * 1024 };
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SendLimit {
+    /// Max number of bytes that can be enqueued.
+    /// Data bigger than this value is still accepted if there is nothing enqueued.
+    pub high_water: usize,
+
+    /// Number of enqueued bytes at which the endpoint is considered drained.
+    /// It should not be greater than `high_water`.
+    pub low_water: usize,
+}
+
+/// Reason of a graceful close, given to
+/// [`NetworkController::close_gracefully_with_reason()`](crate::network::NetworkController::close_gracefully_with_reason())
+/// and sent to the peer by the transports that can carry it (*Ws*, in its Close frame).
+/// The rest of transports close the connection without it.
+///
+/// ```
+/// use message_io::network::{CloseReason};
+///
+/// let reason = CloseReason { code: 1001, reason: "Server restarting".into() };
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CloseReason {
+    /// Status code of the close, as defined by RFC 6455 for *WebSocket*.
+    /// By default, `1000` (normal closure).
+    pub code: u16,
+
+    /// Text explaining the close, that should not exceed 123 bytes.
+    /// By default, empty.
+    pub reason: String,
+}
+
+impl Default for CloseReason {
+    fn default() -> Self {
+        Self { code: 1000, reason: String::new() }
+    }
+}
@@ -1,15 +1,22 @@
 use super::resource_id::{ResourceId, ResourceType, ResourceIdGenerator};
 
+use crate::util::thread::{OTHER_THREAD_ERR};
+
 use mio::{Poll as MioPoll, Interest, Token, Events, Registry, Waker};
 use mio::event::{Source};
 
-use std::time::{Duration};
-use std::sync::{Arc};
-use std::io::{ErrorKind};
+use std::time::{Duration, Instant};
+use std::sync::{Arc, Mutex, Condvar};
+use std::collections::{BTreeSet, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::io::{self, ErrorKind};
+
+lazy_static::lazy_static! {
+    static ref POLL_TIMER: PollTimer = PollTimer::new();
+}
 
 pub enum PollEvent {
     Network(ResourceId),
-    Waker,
 }
 
 impl From<Token> for ResourceId {
@@ -27,15 +34,14 @@ impl From<ResourceId> for Token {
 pub struct Poll {
     mio_poll: MioPoll,
     events: Events,
-    #[allow(dead_code)] //TODO: remove it with poll native event support
-    waker: Arc<Waker>,
+    waker: PollWaker,
 }
 
 impl Default for Poll {
     fn default() -> Self {
         let mio_poll = MioPoll::new().unwrap();
         Self {
-            waker: Arc::new(Waker::new(&mio_poll.registry(), Self::WAKER_TOKEN).unwrap()),
+            waker: PollWaker::new(Waker::new(mio_poll.registry(), Self::WAKER_TOKEN).unwrap()),
             mio_poll,
             events: Events::with_capacity(Self::EVENTS_SIZE),
         }
@@ -53,86 +59,274 @@ impl Poll {
             match self.mio_poll.poll(&mut self.events, timeout) {
                 Ok(_) => {
                     for mio_event in &self.events {
-                        let poll_event = match mio_event.token() {
+                        match mio_event.token() {
                             Self::WAKER_TOKEN => {
-                                log::trace!("POLL EVENT: waker");
-                                PollEvent::Waker
+                                for resource_id in self.waker.take_woken() {
+                                    log::trace!("POLL EVENT (waker): {}", resource_id);
+                                    event_callback(PollEvent::Network(resource_id));
+                                }
                             }
                             token => {
                                 let resource_id = ResourceId::from(token);
                                 log::trace!("POLL EVENT: {}", resource_id);
-                                PollEvent::Network(resource_id)
+                                event_callback(PollEvent::Network(resource_id));
                             }
-                        };
-
-                        event_callback(poll_event);
+                        }
                     }
                     break
                 }
                 Err(ref err) if err.kind() == ErrorKind::Interrupted => continue,
-                Err(ref err) => Err(err).expect("No error here"),
+                Err(ref err) => panic!("No error here: {}", err),
             }
         }
     }
 
     pub fn create_registry(&mut self, adapter_id: u8, resource_type: ResourceType) -> PollRegistry {
-        PollRegistry::new(adapter_id, resource_type, self.mio_poll.registry().try_clone().unwrap())
-    }
-
-    #[allow(dead_code)] //TODO: remove it with poll native event support
-    pub fn create_waker(&mut self) -> PollWaker {
-        PollWaker::new(self.waker.clone())
+        PollRegistry::new(
+            adapter_id,
+            resource_type,
+            self.mio_poll.registry().try_clone().unwrap(),
+            self.waker.clone(),
+        )
     }
 }
 
 pub struct PollRegistry {
     id_generator: Arc<ResourceIdGenerator>,
-    registry: Registry,
+    registry: Arc<Registry>,
+    waker: PollWaker,
 }
 
 impl PollRegistry {
-    fn new(adapter_id: u8, resource_type: ResourceType, registry: Registry) -> Self {
+    fn new(
+        adapter_id: u8,
+        resource_type: ResourceType,
+        registry: Registry,
+        waker: PollWaker,
+    ) -> Self {
         Self {
             id_generator: Arc::new(ResourceIdGenerator::new(adapter_id, resource_type)),
-            registry,
+            registry: Arc::new(registry),
+            waker,
         }
     }
 
-    pub fn add(&self, source: &mut dyn Source) -> ResourceId {
+    pub fn add(&self, source: &mut dyn Source, interest: Interest) -> ResourceId {
         let id = self.id_generator.generate();
-        self.registry.register(source, id.into(), Interest::READABLE).unwrap();
-        log::trace!("Register to poll: {}", id);
+        self.add_with_id(source, id, interest);
         id
     }
 
+    /// Registers a source with the id of a resource that already exists,
+    /// used to replace the source of the resource.
+    pub fn add_with_id(&self, source: &mut dyn Source, id: ResourceId, interest: Interest) {
+        self.registry.register(source, id.into(), interest).unwrap();
+        log::trace!("Register to poll: {}", id);
+    }
+
     pub fn remove(&self, source: &mut dyn Source) {
         self.registry.deregister(source).unwrap()
     }
+
+    /// Creates a waker that generates poll events for the resource with the given id.
+    pub fn waker(&self, id: ResourceId) -> ResourceWaker {
+        ResourceWaker {
+            id,
+            timer_id: POLL_TIMER.generate_id(),
+            waker: self.waker.clone(),
+            registry: self.registry.clone(),
+        }
+    }
 }
 
 impl Clone for PollRegistry {
     fn clone(&self) -> Self {
         Self {
             id_generator: self.id_generator.clone(),
-            registry: self.registry.try_clone().unwrap(),
+            registry: self.registry.clone(),
+            waker: self.waker.clone(),
         }
     }
 }
 
-#[allow(dead_code)] //TODO: remove it with poll native event support
-pub struct PollWaker {
+/// Wakes the poll on behalf of the resources that are not woken by a source event.
+#[derive(Clone)]
+struct PollWaker {
     waker: Arc<Waker>,
+    woken: Arc<Mutex<Vec<ResourceId>>>,
 }
 
 impl PollWaker {
-    #[allow(dead_code)] //TODO: remove it with poll native event support
-    fn new(waker: Arc<Waker>) -> Self {
-        Self { waker }
+    fn new(waker: Waker) -> Self {
+        Self { waker: Arc::new(waker), woken: Arc::new(Mutex::new(Vec::new())) }
     }
 
-    #[allow(dead_code)] //TODO: remove it with poll native event support
-    pub fn wake(&self) {
+    fn wake(&self, id: ResourceId) {
+        self.woken.lock().expect(OTHER_THREAD_ERR).push(id);
         self.waker.wake().unwrap();
-        log::trace!("Wake poll...");
+        log::trace!("Wake poll by {}", id);
+    }
+
+    fn take_woken(&self) -> Vec<ResourceId> {
+        std::mem::take(&mut *self.woken.lock().expect(OTHER_THREAD_ERR))
+    }
+}
+
+/// Resource to wake by the [`PollTimer`] at its deadlines.
+struct TimedWake {
+    id: ResourceId,
+    waker: PollWaker,
+    deadlines: BTreeSet<Instant>,
+}
+
+/// Deadlines of all the resources, with one entry per resource identified by its timer id.
+#[derive(Default)]
+struct Deadlines {
+    queue: BTreeSet<(Instant, u64)>,
+    entries: HashMap<u64, TimedWake>,
+}
+
+impl Deadlines {
+    /// Removes and wakes the entries whose deadline has been reached.
+    /// Returns the next deadline, if any.
+    fn wake_expired(&mut self, now: Instant) -> Option<Instant> {
+        while let Some(&(deadline, timer_id)) = self.queue.first() {
+            if deadline > now {
+                return Some(deadline)
+            }
+            self.queue.pop_first();
+            if let Some(timed) = self.entries.get_mut(&timer_id) {
+                timed.deadlines.remove(&deadline);
+                timed.waker.wake(timed.id);
+                if timed.deadlines.is_empty() {
+                    self.entries.remove(&timer_id);
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Thread that wakes the resources that need to be processed at some time
+/// (e.g. to check their timeouts), since the poll only wakes up by events.
+struct PollTimer {
+    deadlines: Arc<(Mutex<Deadlines>, Condvar)>,
+    next_id: AtomicU64,
+}
+
+impl PollTimer {
+    fn new() -> PollTimer {
+        let deadlines: Arc<(Mutex<Deadlines>, Condvar)> = Arc::default();
+        let thread_deadlines = deadlines.clone();
+        std::thread::Builder::new()
+            .name("message-io/poll-timer".into())
+            .spawn(move || {
+                let (deadlines, condvar) = &*thread_deadlines;
+                let mut deadlines = deadlines.lock().expect(OTHER_THREAD_ERR);
+                loop {
+                    let now = Instant::now();
+                    deadlines = match deadlines.wake_expired(now) {
+                        Some(deadline) => {
+                            let timeout = deadline - now;
+                            condvar.wait_timeout(deadlines, timeout).expect(OTHER_THREAD_ERR).0
+                        }
+                        None => condvar.wait(deadlines).expect(OTHER_THREAD_ERR),
+                    }
+                }
+            })
+            .unwrap();
+
+        PollTimer { deadlines, next_id: AtomicU64::new(0) }
+    }
+
+    /// Generates the id that identifies the deadlines of a resource.
+    fn generate_id(&self) -> u64 {
+        self.next_id.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// Adds a deadline to the entry of the resource, ignoring it if it was already added.
+    fn wake_at(&self, timer_id: u64, id: ResourceId, waker: &PollWaker, deadline: Instant) {
+        let (deadlines, condvar) = &*self.deadlines;
+        let mut deadlines = deadlines.lock().expect(OTHER_THREAD_ERR);
+        let timed = deadlines.entries.entry(timer_id).or_insert_with(|| TimedWake {
+            id,
+            waker: waker.clone(),
+            deadlines: BTreeSet::new(),
+        });
+        if timed.deadlines.insert(deadline) {
+            deadlines.queue.insert((deadline, timer_id));
+            condvar.notify_one();
+        }
+    }
+
+    /// Removes the entry of the resource along with all its deadlines.
+    fn cancel(&self, timer_id: u64) {
+        let mut deadlines = self.deadlines.0.lock().expect(OTHER_THREAD_ERR);
+        if let Some(timed) = deadlines.entries.remove(&timer_id) {
+            for deadline in timed.deadlines {
+                deadlines.queue.remove(&(deadline, timer_id));
+            }
+        }
+    }
+}
+
+/// Generates poll events for a resource without the need of a source event.
+/// It allows to create resources that are not based in an OS socket.
+/// It also allows a resource to be notified when its source can be written again.
+/// See [`crate::network::adapter::Resource::set_waker()`].
+#[derive(Clone)]
+pub struct ResourceWaker {
+    id: ResourceId,
+    timer_id: u64,
+    waker: PollWaker,
+    registry: Arc<Registry>,
+}
+
+impl ResourceWaker {
+    /// Generates a poll event for the resource, as if its source had received an event.
+    pub fn wake(&self) {
+        self.waker.wake(self.id);
+    }
+
+    /// Generates a poll event for the resource at the `deadline`.
+    /// The resource must check when it is processed if the time has come,
+    /// since it can also be processed before by other events.
+    pub fn wake_at(&self, deadline: Instant) {
+        POLL_TIMER.wake_at(self.timer_id, self.id, &self.waker, deadline);
+    }
+
+    /// Cancels the poll events generated by [`ResourceWaker::wake_at()`]
+    /// that are still pending for the resource.
+    pub fn cancel_wakes(&self) {
+        POLL_TIMER.cancel(self.timer_id);
+    }
+
+    /// Registers (`writable = true`) or unregisters the interest of the resource's source
+    /// in writable events.
+    /// While it is registered, the resource receives a poll event each time its source
+    /// can be written again.
+    /// The `source` must be the one returned by
+    /// [`crate::network::adapter::Resource::source()`].
+    pub fn set_writable(&self, source: &mut dyn Source, writable: bool) -> io::Result<()> {
+        let interest = match writable {
+            true => Interest::READABLE | Interest::WRITABLE,
+            false => Interest::READABLE,
+        };
+        self.registry.reregister(source, self.id.into(), interest)
+    }
+
+    /// Registers another source whose readable events are received by the resource,
+    /// as if they were events of its own source.
+    /// It allows a resource to wait for sources that are not given to the user yet
+    /// (e.g. accepted connections that must be read before being accepted).
+    /// The source must be detached with [`ResourceWaker::detach()`] before registering it
+    /// as a resource.
+    pub fn attach(&self, source: &mut dyn Source) -> io::Result<()> {
+        self.registry.register(source, self.id.into(), Interest::READABLE)
+    }
+
+    /// Unregisters a source registered by [`ResourceWaker::attach()`].
+    pub fn detach(&self, source: &mut dyn Source) -> io::Result<()> {
+        self.registry.deregister(source)
     }
 }
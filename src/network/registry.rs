@@ -1,27 +1,334 @@
 use super::resource_id::{ResourceId};
-use super::poll::{PollRegistry};
+use super::poll::{PollRegistry, ResourceWaker};
 use super::adapter::{Resource};
+use super::options::{SendLimit, ListenOptions, CloseReason};
+use super::filter::{AcceptFilter};
 
 use crate::util::thread::{OTHER_THREAD_ERR};
 
+use mio::{Interest};
+
 use std::collections::{HashMap};
 use std::net::{SocketAddr};
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex, RwLock};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
 
 pub struct Register<S: Resource> {
     pub resource: S,
     pub addr: SocketAddr,
+    pending: AtomicBool,
+    send_limit: Mutex<Option<SendLimit>>,
+    limited: AtomicBool,
+    reconnecting: AtomicBool,
+    accept_paused: AtomicBool,
+    connections: Arc<Connections>,
+    _slot: Option<ConnectionSlot>,
+    accept_filter: Option<AcceptFilter>,
+    accepted_idle_timeout: Option<Duration>,
+    idle: Mutex<Option<IdleTimer>>,
+    connect_deadline: Mutex<Option<Instant>>,
+    closing: Mutex<Option<Closing>>,
+    waker: ResourceWaker,
     poll_registry: Arc<PollRegistry>,
 }
 
 impl<S: Resource> Register<S> {
-    fn new(resource: S, addr: SocketAddr, poll_registry: Arc<PollRegistry>) -> Self {
-        Self { resource, addr, poll_registry }
+    fn new(
+        resource: S,
+        addr: SocketAddr,
+        pending: bool,
+        options: &ListenOptions,
+        slot: Option<ConnectionSlot>,
+        waker: ResourceWaker,
+        poll_registry: Arc<PollRegistry>,
+    ) -> Self {
+        let connections = Connections {
+            count: AtomicUsize::new(0),
+            max: options.max_connections.unwrap_or(usize::MAX),
+            waker: waker.clone(),
+        };
+        Self {
+            resource,
+            addr,
+            pending: AtomicBool::new(pending),
+            send_limit: Mutex::new(None),
+            limited: AtomicBool::new(false),
+            reconnecting: AtomicBool::new(false),
+            accept_paused: AtomicBool::new(false),
+            connections: Arc::new(connections),
+            _slot: slot,
+            accept_filter: options.accept_filter.clone(),
+            accepted_idle_timeout: options.idle_timeout,
+            idle: Mutex::new(None),
+            connect_deadline: Mutex::new(None),
+            closing: Mutex::new(None),
+            waker,
+            poll_registry,
+        }
+    }
+
+    /// Returns `true` while the resource is still connecting.
+    pub fn is_pending(&self) -> bool {
+        self.pending.load(Ordering::Acquire)
+    }
+
+    /// Marks the resource as ready to be used.
+    pub fn mark_as_ready(&self) {
+        self.pending.store(false, Ordering::Release);
+        *self.connect_deadline.lock().expect(OTHER_THREAD_ERR) = None;
+    }
+
+    /// Sets the max time that the resource can be connecting.
+    /// The resource is woken at that time to check it.
+    /// A `timeout` too long to be represented as an instant never expires.
+    pub fn set_connect_timeout(&self, timeout: Option<Duration>) {
+        let deadline = timeout.and_then(|timeout| Instant::now().checked_add(timeout));
+        *self.connect_deadline.lock().expect(OTHER_THREAD_ERR) = deadline;
+        if let Some(deadline) = deadline {
+            self.waker.wake_at(deadline);
+        }
+    }
+
+    /// Returns `true` if the timeout to connect the resource has expired.
+    pub fn is_connect_expired(&self) -> bool {
+        self.connect_deadline
+            .lock()
+            .expect(OTHER_THREAD_ERR)
+            .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    /// Marks the resource as lost while it waits to be replaced by a reconnected resource.
+    /// Meanwhile, it is considered pending and it is woken at `deadline`, if any, to reconnect it.
+    pub fn mark_as_reconnecting(&self, deadline: Option<Instant>) {
+        self.pending.store(true, Ordering::Release);
+        self.reconnecting.store(true, Ordering::Release);
+        if let Some(deadline) = deadline {
+            self.waker.wake_at(deadline);
+        }
+    }
+
+    /// Returns `true` while the resource is waiting to be reconnected.
+    pub fn is_reconnecting(&self) -> bool {
+        self.reconnecting.load(Ordering::Acquire)
+    }
+
+    pub fn send_limit(&self) -> Option<SendLimit> {
+        *self.send_limit.lock().expect(OTHER_THREAD_ERR)
+    }
+
+    /// Sets the limit of the enqueued data.
+    /// If the resource was limited, it is woken to check if it is already drained.
+    pub fn set_send_limit(&self, limit: Option<SendLimit>) {
+        *self.send_limit.lock().expect(OTHER_THREAD_ERR) = limit;
+        if self.limited.load(Ordering::Acquire) {
+            self.waker.wake();
+        }
+    }
+
+    /// Marks that a send has exceeded the limit.
+    /// The resource is woken to check its enqueued data once marked,
+    /// because the data could have been written before.
+    pub fn mark_as_limited(&self) {
+        self.limited.store(true, Ordering::Release);
+        self.waker.wake();
+    }
+
+    /// Returns `true` only once after the resource was marked as limited
+    /// and its enqueued data falls to the low water mark.
+    pub fn check_drained(&self, enqueued_size: usize) -> bool {
+        if !self.limited.load(Ordering::Acquire) {
+            return false
+        }
+
+        let drained = match self.send_limit() {
+            Some(limit) => enqueued_size <= limit.low_water,
+            None => true,
+        };
+        drained && self.limited.swap(false, Ordering::AcqRel)
+    }
+
+    /// Sets the time without receiving data after which the resource is considered idle.
+    /// The resource is woken at that time to check it.
+    /// A `timeout` too long to be represented as an instant means no idle timeout.
+    pub fn set_idle_timeout(&self, timeout: Option<Duration>) {
+        let now = Instant::now();
+        *self.idle.lock().expect(OTHER_THREAD_ERR) = timeout.and_then(|timeout| {
+            let deadline = now.checked_add(timeout)?;
+            self.waker.wake_at(deadline);
+            Some(IdleTimer { timeout, last_active: now, wake_deadline: deadline })
+        });
+    }
+
+    /// Marks the resource as active, restarting the count of its idle timeout.
+    pub fn mark_as_active(&self) {
+        if let Some(idle) = &mut *self.idle.lock().expect(OTHER_THREAD_ERR) {
+            idle.last_active = Instant::now();
+        }
+    }
+
+    /// Returns `true` if the resource has been inactive during its idle timeout.
+    /// Otherwise, if the time to check it has come, it is woken again at its next deadline.
+    pub fn check_idle(&self) -> bool {
+        if let Some(idle) = &mut *self.idle.lock().expect(OTHER_THREAD_ERR) {
+            let now = Instant::now();
+            if now >= idle.wake_deadline {
+                let deadline = match idle.last_active.checked_add(idle.timeout) {
+                    Some(deadline) => deadline,
+                    None => return false,
+                };
+                if now >= deadline {
+                    return true
+                }
+                idle.wake_deadline = deadline;
+                self.waker.wake_at(deadline);
+            }
+        }
+        false
+    }
+
+    /// Starts closing the resource gracefully, until it is closed or the `timeout` expires.
+    /// The resource is woken to start it and at the timeout to check it.
+    /// A `timeout` too long to be represented as an instant never expires.
+    /// Returns `false` if it was already closing.
+    pub fn start_closing(&self, reason: CloseReason, timeout: Duration) -> bool {
+        let mut closing = self.closing.lock().expect(OTHER_THREAD_ERR);
+        if closing.is_some() {
+            return false
+        }
+        let deadline = Instant::now().checked_add(timeout);
+        *closing = Some(Closing { reason: Some(reason), deadline });
+        if let Some(deadline) = deadline {
+            self.waker.wake_at(deadline);
+        }
+        self.waker.wake();
+        true
+    }
+
+    /// Returns `true` while the resource is closing gracefully.
+    pub fn is_closing(&self) -> bool {
+        self.closing.lock().expect(OTHER_THREAD_ERR).is_some()
+    }
+
+    /// Takes the reason to notify the peer that the resource is closing.
+    /// It returns it only once, after that the peer is considered notified.
+    pub fn take_close_reason(&self) -> Option<CloseReason> {
+        let mut closing = self.closing.lock().expect(OTHER_THREAD_ERR);
+        closing.as_mut().and_then(|closing| closing.reason.take())
+    }
+
+    /// Returns `true` if the peer was notified that the resource is closing.
+    pub fn is_close_notified(&self) -> bool {
+        match &*self.closing.lock().expect(OTHER_THREAD_ERR) {
+            Some(closing) => closing.reason.is_none(),
+            None => false,
+        }
+    }
+
+    /// Returns `true` if the timeout to close the resource gracefully has expired.
+    pub fn is_close_expired(&self) -> bool {
+        match &*self.closing.lock().expect(OTHER_THREAD_ERR) {
+            Some(closing) => closing.deadline.is_some_and(|deadline| Instant::now() >= deadline),
+            None => false,
+        }
+    }
+
+    /// Idle timeout of the resources accepted by the listener.
+    pub fn accepted_idle_timeout(&self) -> Option<Duration> {
+        self.accepted_idle_timeout
+    }
+
+    /// Returns `true` if the accepting of connections was paused by the user.
+    pub fn is_accept_paused(&self) -> bool {
+        self.accept_paused.load(Ordering::Acquire)
+    }
+
+    /// Returns `true` if the listener accepts the peer with address `addr`.
+    pub fn accepts(&self, addr: SocketAddr) -> bool {
+        match &self.accept_filter {
+            Some(filter) => filter.accepts(addr),
+            None => true,
+        }
+    }
+
+    /// Returns `true` if the listener has reached its max number of connections,
+    /// counting also the `pending` ones held by its transport.
+    pub fn is_at_max_connections(&self, pending: usize) -> bool {
+        self.connections.is_full(pending)
+    }
+
+    /// Pauses or resumes the accepting of connections.
+    /// Once resumed, the listener is woken to accept the connections that are waiting.
+    pub fn set_accept_paused(&self, paused: bool) {
+        self.accept_paused.store(paused, Ordering::Release);
+        if !paused {
+            self.waker.wake();
+        }
+    }
+
+    /// Takes a slot for a connection accepted by the listener,
+    /// or `None` if it has reached its max number of connections.
+    /// The slot must be kept by the connection while it is registered.
+    pub fn take_connection_slot(&self) -> Option<ConnectionSlot> {
+        let connections = &self.connections;
+        let mut count = connections.count.load(Ordering::Acquire);
+        while count < connections.max {
+            match connections.count.compare_exchange_weak(
+                count,
+                count + 1,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => return Some(ConnectionSlot(connections.clone())),
+                Err(current) => count = current,
+            }
+        }
+        None
+    }
+}
+
+/// Connections accepted by a listener that are still registered.
+struct Connections {
+    count: AtomicUsize,
+    max: usize,
+    waker: ResourceWaker,
+}
+
+impl Connections {
+    fn is_full(&self, pending: usize) -> bool {
+        self.count.load(Ordering::Acquire).saturating_add(pending) >= self.max
+    }
+}
+
+/// Inactivity tracking of a resource with an idle timeout.
+struct IdleTimer {
+    timeout: Duration,
+    last_active: Instant,
+    wake_deadline: Instant,
+}
+
+/// Graceful close of a resource.
+struct Closing {
+    reason: Option<CloseReason>, // Taken once the peer is notified.
+    deadline: Option<Instant>,   // None if it can not be represented: it never expires.
+}
+
+/// Place of an accepted connection in the connections of its listener.
+/// It is released when the connection is dropped,
+/// waking the listener if it can accept connections again.
+pub struct ConnectionSlot(Arc<Connections>);
+
+impl Drop for ConnectionSlot {
+    fn drop(&mut self) {
+        if self.0.count.fetch_sub(1, Ordering::AcqRel) == self.0.max {
+            self.0.waker.wake();
+        }
     }
 }
 
 impl<S: Resource> Drop for Register<S> {
     fn drop(&mut self) {
+        self.waker.cancel_wakes();
         self.poll_registry.remove(self.resource.source());
     }
 }
@@ -44,19 +351,157 @@ impl<S: Resource> ResourceRegistry<S> {
     }
 
     /// Add a resource into the registry.
-    pub fn add(&self, mut resource: S, addr: SocketAddr) -> ResourceId {
-        let id = self.poll_registry.add(resource.source());
-        let register = Register::new(resource, addr, self.poll_registry.clone());
-        self.resources.write().expect(OTHER_THREAD_ERR).insert(id, Arc::new(register));
+    pub fn add(&self, resource: S, addr: SocketAddr, idle_timeout: Option<Duration>) -> ResourceId {
+        let init = |register: &Register<S>| register.set_idle_timeout(idle_timeout);
+        self.register(resource, addr, false, &ListenOptions::default(), None, init)
+    }
+
+    /// Add a listener that accepts the connections as specified by its `options`.
+    pub fn add_listener(
+        &self,
+        resource: S,
+        addr: SocketAddr,
+        options: &ListenOptions,
+    ) -> ResourceId {
+        self.register(resource, addr, false, options, None, |_| ())
+    }
+
+    /// Add a resource accepted by a listener, that keeps its slot while it is registered.
+    pub fn add_accepted(
+        &self,
+        resource: S,
+        addr: SocketAddr,
+        slot: ConnectionSlot,
+        idle_timeout: Option<Duration>,
+    ) -> ResourceId {
+        let init = |register: &Register<S>| register.set_idle_timeout(idle_timeout);
+        self.register(resource, addr, false, &ListenOptions::default(), Some(slot), init)
+    }
+
+    /// Add a resource that is still connecting into the registry.
+    /// The resource is registered also for writable events to know when it is connected,
+    /// and it is woken once to check its status after the registration.
+    /// It is also woken when its `connect_timeout` expires.
+    pub fn add_pending(
+        &self,
+        resource: S,
+        addr: SocketAddr,
+        connect_timeout: Option<Duration>,
+        idle_timeout: Option<Duration>,
+    ) -> ResourceId {
+        let init = |register: &Register<S>| {
+            register.set_connect_timeout(connect_timeout);
+            register.set_idle_timeout(idle_timeout);
+        };
+        self.register(resource, addr, true, &ListenOptions::default(), None, init)
+    }
+
+    /// Registers the resource, initializing its register by `init` before inserting it.
+    fn register(
+        &self,
+        mut resource: S,
+        addr: SocketAddr,
+        pending: bool,
+        options: &ListenOptions,
+        slot: Option<ConnectionSlot>,
+        init: impl FnOnce(&Register<S>),
+    ) -> ResourceId {
+        // Locked before the registration to ensure that the events generated by the resource
+        // will find it in the registry.
+        let mut resources = self.resources.write().expect(OTHER_THREAD_ERR);
+        let id = self.poll_registry.add(resource.source(), Self::interest(pending));
+        let waker = self.waker(&mut resource, id, pending);
+        let register = Register::new(
+            resource,
+            addr,
+            pending,
+            options,
+            slot,
+            waker,
+            self.poll_registry.clone(),
+        );
+        init(&register);
+        resources.insert(id, Arc::new(register));
         id
     }
 
+    /// Replaces the resource of a register by a new resource that is still connecting,
+    /// keeping the id, the address and the send limit of the register.
+    /// It is woken when its `connect_timeout` expires.
+    /// Returns `false` if the register does not exist.
+    pub fn replace(
+        &self,
+        id: ResourceId,
+        mut resource: S,
+        connect_timeout: Option<Duration>,
+        idle_timeout: Option<Duration>,
+    ) -> bool {
+        let mut resources = self.resources.write().expect(OTHER_THREAD_ERR);
+        let replaced = match resources.get(&id) {
+            Some(replaced) => replaced.clone(),
+            None => return false,
+        };
+
+        self.poll_registry.add_with_id(resource.source(), id, Self::interest(true));
+        let waker = self.waker(&mut resource, id, true);
+        let register = Register::new(
+            resource,
+            replaced.addr,
+            true,
+            &ListenOptions::default(),
+            None,
+            waker,
+            self.poll_registry.clone(),
+        );
+        register.set_send_limit(replaced.send_limit());
+        register.set_connect_timeout(connect_timeout);
+        register.set_idle_timeout(idle_timeout);
+        resources.insert(id, Arc::new(register));
+        true
+    }
+
+    /// A pending resource is registered also for writable events to know when it is connected.
+    fn interest(pending: bool) -> Interest {
+        match pending {
+            true => Interest::READABLE | Interest::WRITABLE,
+            false => Interest::READABLE,
+        }
+    }
+
+    /// Gives the waker to a resource already added to the poll.
+    /// A pending resource is woken once to check its status after the registration.
+    fn waker(&self, resource: &mut S, id: ResourceId, pending: bool) -> ResourceWaker {
+        let waker = self.poll_registry.waker(id);
+        resource.set_waker(waker.clone());
+        if pending {
+            waker.wake();
+        }
+        waker
+    }
+
     /// Remove a register from the registry.
     /// This function ensure that the register is removed from the registry,
     /// but not the destruction of the resource itself.
     /// Because the resource is shared, the destruction will be delayed until the last reference.
+    /// Its pending timed wakes are cancelled, since it will no longer be processed.
     pub fn remove(&self, id: ResourceId) -> bool {
-        self.resources.write().expect(OTHER_THREAD_ERR).remove(&id).is_some()
+        match self.resources.write().expect(OTHER_THREAD_ERR).remove(&id) {
+            Some(register) => {
+                register.waker.cancel_wakes();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns the ids of all the registers.
+    pub fn ids(&self) -> Vec<ResourceId> {
+        self.resources.read().expect(OTHER_THREAD_ERR).keys().copied().collect()
+    }
+
+    /// Returns `true` if there are no registers.
+    pub fn is_empty(&self) -> bool {
+        self.resources.read().expect(OTHER_THREAD_ERR).is_empty()
     }
 
     /// Returned a shared reference of the register.
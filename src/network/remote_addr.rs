@@ -1,11 +1,15 @@
+use crate::error::{Error};
+
 use serde::{Serialize, Deserialize};
 
 use std::net::{SocketAddr, ToSocketAddrs, IpAddr, Ipv4Addr, Ipv6Addr, SocketAddrV4, SocketAddrV6};
+use std::path::{Path, PathBuf};
 use std::io::{self};
 
 /// An struct that contains a remote address.
-/// It can be Either, a [`SocketAddr`] as usual or a `String` used for protocols
+/// It can be Either, a [`SocketAddr`] as usual, a `String` used for protocols
 /// that needs more than a `SocketAddr` to get connected (e.g. WebSocket)
+/// or a [`PathBuf`] used for protocols addressed by the file system (e.g. Unix sockets).
 /// It is usually used in
 /// [`NetworkController::connect()`](crate::network::NetworkController::connect())
 /// to specify the remote address.
@@ -13,6 +17,7 @@ use std::io::{self};
 pub enum RemoteAddr {
     Socket(SocketAddr),
     Str(String),
+    Path(PathBuf),
 }
 
 impl RemoteAddr {
@@ -26,6 +31,43 @@ impl RemoteAddr {
         matches!(self, RemoteAddr::Socket(_))
     }
 
+    /// Check if the `RemoteAddr` is a path.
+    pub fn is_path(&self) -> bool {
+        matches!(self, RemoteAddr::Path(_))
+    }
+
+    /// Obtains the [`SocketAddr`], resolving the string of a `Str` variant as a 'host:port'.
+    /// Unlike [`RemoteAddr::socket_addr()`], it returns an error instead of panicking:
+    /// [`Error::UnresolvableHost`] if the string can not be resolved to any address,
+    /// or [`Error::InvalidAddress`] for a path.
+    pub fn resolve(&self) -> Result<SocketAddr, Error> {
+        match self {
+            RemoteAddr::Socket(addr) => Ok(*addr),
+            RemoteAddr::Str(host_port) => resolve(host_port.as_str(), host_port),
+            RemoteAddr::Path(path) => {
+                let err = format!("the path '{}' is not a socket address", path.display());
+                Err(Error::InvalidAddress(err))
+            }
+        }
+    }
+
+    /// Host of the address without the port, as used for the TLS server name.
+    /// The brackets of an IPv6 host are removed. It is `None` for a path.
+    #[cfg(feature = "tls")]
+    pub(crate) fn host(&self) -> Option<String> {
+        match self {
+            RemoteAddr::Socket(addr) => Some(addr.ip().to_string()),
+            RemoteAddr::Str(host_port) => {
+                let host = match host_port.rsplit_once(':') {
+                    Some((host, _port)) => host,
+                    None => host_port,
+                };
+                Some(host.trim_start_matches('[').trim_end_matches(']').to_string())
+            }
+            RemoteAddr::Path(_) => None,
+        }
+    }
+
     /// Extract the [`SocketAddr`].
     /// This function panics if the `RemoteAddr` do not represent a `SocketAddr`.
     pub fn socket_addr(&self) -> &SocketAddr {
@@ -43,6 +85,15 @@ impl RemoteAddr {
             _ => panic!("The RemoteAddr must be a String"),
         }
     }
+
+    /// Extract the path.
+    /// This function panics if the `RemoteAddr` is not a `Path` variant.
+    pub fn path(&self) -> &Path {
+        match self {
+            RemoteAddr::Path(path) => path,
+            _ => panic!("The RemoteAddr must be a Path"),
+        }
+    }
 }
 
 impl ToSocketAddrs for RemoteAddr {
@@ -50,7 +101,7 @@ impl ToSocketAddrs for RemoteAddr {
     fn to_socket_addrs(&self) -> io::Result<Self::Iter> {
         match self {
             RemoteAddr::Socket(addr) => addr.to_socket_addrs(),
-            RemoteAddr::Str(_) => Err(io::Error::new(
+            RemoteAddr::Str(_) | RemoteAddr::Path(_) => Err(io::Error::new(
                 io::ErrorKind::InvalidInput,
                 "The RemoteAddr is not a SocketAddr",
             )),
@@ -63,6 +114,7 @@ impl std::fmt::Display for RemoteAddr {
         match self {
             RemoteAddr::Socket(addr) => write!(f, "{}", addr),
             RemoteAddr::Str(string) => write!(f, "{}", string),
+            RemoteAddr::Path(path) => write!(f, "{}", path.display()),
         }
     }
 }
@@ -87,7 +139,7 @@ impl ToRemoteAddr for &str {
 
 impl ToRemoteAddr for String {
     fn to_remote_addr(&self) -> io::Result<RemoteAddr> {
-        (&self as &str).to_remote_addr()
+        (self as &str).to_remote_addr()
     }
 }
 
@@ -115,6 +167,24 @@ impl ToRemoteAddr for SocketAddrV6 {
     }
 }
 
+impl ToRemoteAddr for &Path {
+    fn to_remote_addr(&self) -> io::Result<RemoteAddr> {
+        Ok(RemoteAddr::Path(self.to_path_buf()))
+    }
+}
+
+impl ToRemoteAddr for PathBuf {
+    fn to_remote_addr(&self) -> io::Result<RemoteAddr> {
+        Ok(RemoteAddr::Path(self.clone()))
+    }
+}
+
+impl ToRemoteAddr for &PathBuf {
+    fn to_remote_addr(&self) -> io::Result<RemoteAddr> {
+        Ok(RemoteAddr::Path(self.to_path_buf()))
+    }
+}
+
 impl ToRemoteAddr for RemoteAddr {
     fn to_remote_addr(&self) -> io::Result<RemoteAddr> {
         Ok(self.clone())
@@ -123,34 +193,42 @@ impl ToRemoteAddr for RemoteAddr {
 
 impl ToRemoteAddr for (&str, u16) {
     fn to_remote_addr(&self) -> io::Result<RemoteAddr> {
-        Ok(RemoteAddr::Socket(self.to_socket_addrs().unwrap().next().unwrap()))
+        Ok(RemoteAddr::Socket(resolve(self, &format!("{}:{}", self.0, self.1))?))
     }
 }
 
 impl ToRemoteAddr for (String, u16) {
     fn to_remote_addr(&self) -> io::Result<RemoteAddr> {
-        Ok(RemoteAddr::Socket(self.to_socket_addrs().unwrap().next().unwrap()))
+        Ok(RemoteAddr::Socket(resolve(self, &format!("{}:{}", self.0, self.1))?))
     }
 }
 
 impl ToRemoteAddr for (IpAddr, u16) {
     fn to_remote_addr(&self) -> io::Result<RemoteAddr> {
-        Ok(RemoteAddr::Socket(self.to_socket_addrs().unwrap().next().unwrap()))
+        Ok(RemoteAddr::Socket(resolve(self, &format!("{}:{}", self.0, self.1))?))
     }
 }
 
 impl ToRemoteAddr for (Ipv4Addr, u16) {
     fn to_remote_addr(&self) -> io::Result<RemoteAddr> {
-        Ok(RemoteAddr::Socket(self.to_socket_addrs().unwrap().next().unwrap()))
+        Ok(RemoteAddr::Socket(resolve(self, &format!("{}:{}", self.0, self.1))?))
     }
 }
 
 impl ToRemoteAddr for (Ipv6Addr, u16) {
     fn to_remote_addr(&self) -> io::Result<RemoteAddr> {
-        Ok(RemoteAddr::Socket(self.to_socket_addrs().unwrap().next().unwrap()))
+        Ok(RemoteAddr::Socket(resolve(self, &format!("{}:{}", self.0, self.1))?))
     }
 }
 
+/// Resolves an address to its first socket address.
+fn resolve(addr: impl ToSocketAddrs, host: &str) -> Result<SocketAddr, Error> {
+    addr.to_socket_addrs()
+        .ok()
+        .and_then(|mut addrs| addrs.next())
+        .ok_or_else(|| Error::UnresolvableHost(host.into()))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -178,6 +256,33 @@ mod tests {
         assert!(String::from("127.0.0.1:80").to_remote_addr().unwrap().is_socket_addr());
     }
 
+    #[test]
+    fn resolve_socket_addr() {
+        let addr = "127.0.0.1:80".to_remote_addr().unwrap();
+        assert_eq!("127.0.0.1:80".parse::<SocketAddr>().unwrap(), addr.resolve().unwrap());
+        let path = Path::new("/tmp/message-io.sock").to_remote_addr().unwrap();
+        assert!(matches!(path.resolve(), Err(Error::InvalidAddress(_))));
+        let string = "no-port".to_remote_addr().unwrap();
+        assert!(matches!(string.resolve(), Err(Error::UnresolvableHost(_))));
+    }
+
+    #[test]
+    #[cfg(feature = "tls")]
+    fn host() {
+        assert_eq!("domain", "domain:1234".to_remote_addr().unwrap().host().unwrap());
+        assert_eq!("::1", "[::1]:1234".to_remote_addr().unwrap().host().unwrap());
+        let string = RemoteAddr::Str("[fe80::1%eth0]:1234".into());
+        assert_eq!("fe80::1%eth0", string.host().unwrap());
+        assert_eq!(None, Path::new("/tmp/message-io.sock").to_remote_addr().unwrap().host());
+    }
+
+    #[test]
+    fn path_to_path() {
+        let path = Path::new("/tmp/message-io.sock");
+        assert_eq!(path, path.to_remote_addr().unwrap().path());
+        assert_eq!(path, path.to_path_buf().to_remote_addr().unwrap().path());
+    }
+
     #[test]
     fn socket_addr_to_socket_addr() {
         let socket_addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
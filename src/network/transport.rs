@@ -1,15 +1,35 @@
 use super::loader::{DriverLoader};
 
 #[cfg(feature = "tcp")]
-use crate::adapters::tcp::{TcpAdapter};
+use crate::adapters::tcp::{TcpAdapter, TcpConnectConfig, TcpListenConfig};
 #[cfg(feature = "tcp")]
-use crate::adapters::framed_tcp::{FramedTcpAdapter};
+use crate::adapters::framed_tcp::{FramedTcpAdapter, FramedTcpConnectConfig, FramedTcpListenConfig};
 #[cfg(feature = "udp")]
 use crate::adapters::udp::{self, UdpAdapter};
 #[cfg(feature = "websocket")]
 use crate::adapters::ws::{self, WsAdapter};
+#[cfg(feature = "tls")]
+use crate::adapters::framed_tls::{FramedTlsAdapter};
+#[cfg(all(unix, feature = "unix"))]
+use crate::adapters::unix_stream::{UnixStreamAdapter};
+#[cfg(all(unix, feature = "unix"))]
+use crate::adapters::unix_datagram::{self, UnixDatagramAdapter, UnixDatagramConnectConfig};
+#[cfg(feature = "quic")]
+use crate::adapters::quic::{QuicAdapter};
+#[cfg(feature = "udp")]
+use crate::adapters::reliable_udp::{self, ReliableUdpAdapter};
+#[cfg(feature = "memory")]
+use crate::adapters::memory::{MemoryAdapter};
+#[cfg(feature = "tls")]
+use crate::util::tls::{TlsConnectConfig, TlsListenConfig};
+use crate::error::{Error};
 
 use serde::{Serialize, Deserialize};
+use strum::{IntoEnumIterator};
+
+use std::any::{Any};
+use std::sync::{Arc};
+use std::convert::{TryFrom};
 
 /// Enum to identified the underlying transport used.
 /// It can be passed to
@@ -22,6 +42,8 @@ pub enum Transport {
     /// As stream protocol, receiving a message from TCP do not imply to read
     /// the entire message.
     /// If you want a packet based way to send over TCP, use `FramedTcp` instead.
+    /// The options of the socket (e.g. `TCP_NODELAY`) can be given using
+    /// [`TransportConnect::Tcp`] and [`TransportListen::Tcp`].
     #[cfg(feature = "tcp")]
     Tcp,
 
@@ -46,8 +68,93 @@ pub enum Transport {
     /// you can specify an URL with `wss` of `ws` schemas to connect with or without security.
     /// If you use a [`crate::network::RemoteAddr::Socket`] the socket will be a normal
     /// websocket with the following uri: `ws://{SocketAddr}/message-io-default`.
+    /// The `wss` schema needs the *tls* feature.
+    /// Use `TransportConnect::Wss` to configure the TLS of the connection
+    /// (with a `Socket` address, the uri will be `wss://{SocketAddr}/message-io-default`),
+    /// and `TransportListen::Wss` to listen for secure web sockets.
     #[cfg(feature = "websocket")]
     Ws,
+
+    /// Tcp framed protocol secured with TLS (available through the *tls* feature).
+    /// Like `FramedTcp`, but the stream is encrypted.
+    /// Since the server needs a certificate to listen, and the client could need
+    /// some trust roots to validate it, use
+    /// [`NetworkController::listen_with()`](crate::network::NetworkController::listen_with())
+    /// and [`NetworkController::connect_with()`](crate::network::NetworkController::connect_with())
+    /// to specify them (see [`TransportListen`] and [`TransportConnect`]).
+    #[cfg(feature = "tls")]
+    FramedTls,
+
+    /// Unix domain socket in stream mode (available through the *unix* feature in unix platforms).
+    /// Like `FramedTcp`, it is framed to manage the data as a packet.
+    /// It communicates processes of the same host without going through the network stack.
+    /// It is addressed by a file system path instead of a socket address:
+    /// use a path or a string in the `connect()` and `listen()` methods.
+    /// In Linux, a string starting with `@` refers to a name of the abstract namespace
+    /// (e.g. `"@my-socket"`).
+    /// Since there is no ip address, the [`std::net::SocketAddr`] given by the API
+    /// (the endpoint addresses and the listening address) is
+    /// [`UNSPECIFIED_ADDR`](crate::adapters::unix_stream::UNSPECIFIED_ADDR).
+    /// The socket file created by the listener is removed when the listener is removed.
+    #[cfg(all(unix, feature = "unix"))]
+    UnixStream,
+
+    /// Unix domain socket in datagram mode
+    /// (available through the *unix* feature in unix platforms).
+    /// Like `Udp`, it is not connection oriented,
+    /// but it communicates processes of the same host without going through the network stack.
+    /// It is addressed in the same way as `UnixStream`.
+    /// Since the API identifies the endpoints by [`std::net::SocketAddr`],
+    /// each named peer that sends data to a listener is represented by a virtual `SocketAddr`
+    /// that can be used to reply it from that listener, e.g. with
+    /// [`Endpoint::from_listener()`](crate::network::Endpoint::from_listener()).
+    /// To connect using an already existing socket (e.g. one end of a socket pair),
+    /// use [`TransportConnect::UnixDatagram`] with
+    /// [`UnixDatagramConnectConfig::with_socket()`].
+    #[cfg(all(unix, feature = "unix"))]
+    UnixDatagram,
+
+    /// QUIC protocol (available through the *quic* feature).
+    /// The messages are framed over a bidirectional QUIC stream,
+    /// so they are encrypted and delivered reliably and in order,
+    /// without the head-of-line blocking of a TCP connection
+    /// (each connection has its own stream).
+    /// As in `FramedTls`, the certificate of the server and the trust roots of the client are
+    /// given using [`TransportListen::Quic`] and [`TransportConnect::Quic`].
+    /// Since all the connections accepted by a listener share its UDP socket,
+    /// the listener does not create a resource by connection: as in `Udp`,
+    /// the messages are received from an endpoint of the listener,
+    /// and no `Connected` or `Disconnected` events are generated by the listener side.
+    /// Instead, a connected endpoint generates a `Disconnected` event if the connection is lost.
+    #[cfg(feature = "quic")]
+    Quic,
+
+    /// UDP with reliable and ordered delivery (available through the *udp* feature).
+    /// Each message is numbered and sent in its own datagram,
+    /// being retransmitted until the peer acknowledges it.
+    /// The retransmission timeout is computed from the measured round trip time.
+    /// The messages are delivered in the same order they were sent.
+    /// Unlike `Udp`, it is connection oriented: the listener generates a `Connected` event
+    /// by each new peer and a `Disconnected` event is generated when the peer is removed,
+    /// stops answering or does not acknowledge a message after several retransmissions.
+    /// The size of a message is limited by [`reliable_udp::MAX_PAYLOAD_LEN`].
+    /// There is no congestion control: a fixed number of messages can be sent
+    /// without acknowledgement, and if this limit is reached,
+    /// a connected endpoint waits for the acknowledgements before sending.
+    #[cfg(feature = "udp")]
+    ReliableUdp,
+
+    /// In-process transport (available through the *memory* feature).
+    /// It does not use the OS network: the messages are moved through memory queues
+    /// between the endpoints of the same process, even from different nodes.
+    /// A listener is addressed by a name (any string) given at listen,
+    /// and connecting to that name creates a connection with the listener.
+    /// As `FramedTcp`, it is connection oriented and keeps the boundaries of the messages,
+    /// without size limit.
+    /// Since there are no ip addresses, the endpoints and listeners are given with the
+    /// [`UNSPECIFIED_ADDR`](crate::adapters::memory::UNSPECIFIED_ADDR).
+    #[cfg(feature = "memory")]
+    Memory,
 }
 
 impl Transport {
@@ -63,6 +170,18 @@ impl Transport {
             Self::Udp => loader.mount(self.id(), UdpAdapter),
             #[cfg(feature = "websocket")]
             Self::Ws => loader.mount(self.id(), WsAdapter),
+            #[cfg(feature = "tls")]
+            Self::FramedTls => loader.mount(self.id(), FramedTlsAdapter),
+            #[cfg(all(unix, feature = "unix"))]
+            Self::UnixStream => loader.mount(self.id(), UnixStreamAdapter),
+            #[cfg(all(unix, feature = "unix"))]
+            Self::UnixDatagram => loader.mount(self.id(), UnixDatagramAdapter),
+            #[cfg(feature = "quic")]
+            Self::Quic => loader.mount(self.id(), QuicAdapter),
+            #[cfg(feature = "udp")]
+            Self::ReliableUdp => loader.mount(self.id(), ReliableUdpAdapter),
+            #[cfg(feature = "memory")]
+            Self::Memory => loader.mount(self.id(), MemoryAdapter),
         };
     }
 
@@ -83,6 +202,18 @@ impl Transport {
             Self::Udp => udp::MAX_PAYLOAD_LEN,
             #[cfg(feature = "websocket")]
             Self::Ws => ws::MAX_PAYLOAD_LEN,
+            #[cfg(feature = "tls")]
+            Self::FramedTls => usize::MAX,
+            #[cfg(all(unix, feature = "unix"))]
+            Self::UnixStream => usize::MAX,
+            #[cfg(all(unix, feature = "unix"))]
+            Self::UnixDatagram => unix_datagram::MAX_PAYLOAD_LEN,
+            #[cfg(feature = "quic")]
+            Self::Quic => usize::MAX,
+            #[cfg(feature = "udp")]
+            Self::ReliableUdp => reliable_udp::MAX_PAYLOAD_LEN,
+            #[cfg(feature = "memory")]
+            Self::Memory => usize::MAX,
         }
     }
 
@@ -98,6 +229,18 @@ impl Transport {
             Transport::Udp => false,
             #[cfg(feature = "websocket")]
             Transport::Ws => true,
+            #[cfg(feature = "tls")]
+            Transport::FramedTls => true,
+            #[cfg(all(unix, feature = "unix"))]
+            Transport::UnixStream => true,
+            #[cfg(all(unix, feature = "unix"))]
+            Transport::UnixDatagram => false,
+            #[cfg(feature = "quic")]
+            Transport::Quic => false,
+            #[cfg(feature = "udp")]
+            Transport::ReliableUdp => true,
+            #[cfg(feature = "memory")]
+            Transport::Memory => true,
         }
     }
 
@@ -117,6 +260,18 @@ impl Transport {
             Transport::Udp => true,
             #[cfg(feature = "websocket")]
             Transport::Ws => true,
+            #[cfg(feature = "tls")]
+            Transport::FramedTls => true,
+            #[cfg(all(unix, feature = "unix"))]
+            Transport::UnixStream => true,
+            #[cfg(all(unix, feature = "unix"))]
+            Transport::UnixDatagram => true,
+            #[cfg(feature = "quic")]
+            Transport::Quic => true,
+            #[cfg(feature = "udp")]
+            Transport::ReliableUdp => true,
+            #[cfg(feature = "memory")]
+            Transport::Memory => true,
         }
     }
 
@@ -132,28 +287,279 @@ impl Transport {
             Transport::Udp => 2,
             #[cfg(feature = "websocket")]
             Transport::Ws => 3,
+            #[cfg(feature = "tls")]
+            Transport::FramedTls => 4,
+            #[cfg(all(unix, feature = "unix"))]
+            Transport::UnixStream => 5,
+            #[cfg(all(unix, feature = "unix"))]
+            Transport::UnixDatagram => 6,
+            #[cfg(feature = "quic")]
+            Transport::Quic => 7,
+            #[cfg(feature = "udp")]
+            Transport::ReliableUdp => 8,
+            #[cfg(feature = "memory")]
+            Transport::Memory => 9,
         }
     }
 }
 
-impl From<u8> for Transport {
-    fn from(id: u8) -> Self {
-        match id {
+impl TryFrom<u8> for Transport {
+    type Error = Error;
+
+    /// Obtains the transport of an adapter id,
+    /// or [`Error::UnsupportedTransport`] if no transport uses it (e.g. its feature is disabled).
+    fn try_from(id: u8) -> Result<Self, Error> {
+        Transport::iter()
+            .find(|transport| transport.id() == id)
+            .ok_or(Error::UnsupportedTransport(id))
+    }
+}
+
+impl std::fmt::Display for Transport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// A [`Transport`] along with the configuration used to connect with it.
+/// It can be passed to
+/// [`NetworkController::connect_with()`](crate::network::NetworkController::connect_with())
+/// to specify the transport and how the connection is performed.
+/// Any `Transport` can be converted into a `TransportConnect` with its default configuration.
+#[derive(Clone, Debug)]
+pub enum TransportConnect {
+    #[cfg(feature = "tcp")]
+    Tcp(TcpConnectConfig),
+    #[cfg(feature = "tcp")]
+    FramedTcp(FramedTcpConnectConfig),
+    #[cfg(feature = "udp")]
+    Udp,
+    #[cfg(feature = "websocket")]
+    Ws,
+    /// Secure web socket (`wss`), it refers to [`Transport::Ws`].
+    #[cfg(all(feature = "websocket", feature = "tls"))]
+    Wss(TlsConnectConfig),
+    #[cfg(feature = "tls")]
+    FramedTls(TlsConnectConfig),
+    #[cfg(all(unix, feature = "unix"))]
+    UnixStream,
+    #[cfg(all(unix, feature = "unix"))]
+    UnixDatagram(UnixDatagramConnectConfig),
+    #[cfg(feature = "quic")]
+    Quic(TlsConnectConfig),
+    #[cfg(feature = "udp")]
+    ReliableUdp,
+    #[cfg(feature = "memory")]
+    Memory,
+    /// Configuration for an adapter mounted by the user with
+    /// [`Builder::mount()`](crate::network::Builder::mount()).
+    /// It has no [`Transport`] associated.
+    Custom(CustomConfig),
+}
+
+impl TransportConnect {
+    /// Returns the transport that this configuration refers to,
+    /// or `None` if the configuration is [`TransportConnect::Custom`],
+    /// that refers to an adapter mounted by the user.
+    pub fn transport(&self) -> Option<Transport> {
+        match self {
             #[cfg(feature = "tcp")]
-            0 => Transport::Tcp,
+            Self::Tcp(_) => Some(Transport::Tcp),
             #[cfg(feature = "tcp")]
-            1 => Transport::FramedTcp,
+            Self::FramedTcp(_) => Some(Transport::FramedTcp),
             #[cfg(feature = "udp")]
-            2 => Transport::Udp,
+            Self::Udp => Some(Transport::Udp),
             #[cfg(feature = "websocket")]
-            3 => Transport::Ws,
-            _ => panic!("Not available transport"),
+            Self::Ws => Some(Transport::Ws),
+            #[cfg(all(feature = "websocket", feature = "tls"))]
+            Self::Wss(_) => Some(Transport::Ws),
+            #[cfg(feature = "tls")]
+            Self::FramedTls(_) => Some(Transport::FramedTls),
+            #[cfg(all(unix, feature = "unix"))]
+            Self::UnixStream => Some(Transport::UnixStream),
+            #[cfg(all(unix, feature = "unix"))]
+            Self::UnixDatagram(_) => Some(Transport::UnixDatagram),
+            #[cfg(feature = "quic")]
+            Self::Quic(_) => Some(Transport::Quic),
+            #[cfg(feature = "udp")]
+            Self::ReliableUdp => Some(Transport::ReliableUdp),
+            #[cfg(feature = "memory")]
+            Self::Memory => Some(Transport::Memory),
+            Self::Custom(_) => None,
+        }
+    }
+
+    /// Returns the adapter id used for this transport.
+    /// See [`Transport::id()`].
+    pub fn id(&self) -> u8 {
+        match (self, self.transport()) {
+            (Self::Custom(config), _) => config.adapter_id,
+            (_, Some(transport)) => transport.id(),
+            (_, None) => unreachable!("Only a custom configuration has no transport"),
         }
     }
 }
 
-impl std::fmt::Display for Transport {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{:?}", self)
+impl From<Transport> for TransportConnect {
+    fn from(transport: Transport) -> Self {
+        match transport {
+            #[cfg(feature = "tcp")]
+            Transport::Tcp => Self::Tcp(TcpConnectConfig::default()),
+            #[cfg(feature = "tcp")]
+            Transport::FramedTcp => Self::FramedTcp(FramedTcpConnectConfig::default()),
+            #[cfg(feature = "udp")]
+            Transport::Udp => Self::Udp,
+            #[cfg(feature = "websocket")]
+            Transport::Ws => Self::Ws,
+            #[cfg(feature = "tls")]
+            Transport::FramedTls => Self::FramedTls(TlsConnectConfig::default()),
+            #[cfg(all(unix, feature = "unix"))]
+            Transport::UnixStream => Self::UnixStream,
+            #[cfg(all(unix, feature = "unix"))]
+            Transport::UnixDatagram => Self::UnixDatagram(UnixDatagramConnectConfig::default()),
+            #[cfg(feature = "quic")]
+            Transport::Quic => Self::Quic(TlsConnectConfig::default()),
+            #[cfg(feature = "udp")]
+            Transport::ReliableUdp => Self::ReliableUdp,
+            #[cfg(feature = "memory")]
+            Transport::Memory => Self::Memory,
+        }
+    }
+}
+
+/// A [`Transport`] along with the configuration used to listen with it.
+/// It can be passed to
+/// [`NetworkController::listen_with()`](crate::network::NetworkController::listen_with())
+/// to specify the transport and how the listening is performed.
+/// Any `Transport` can be converted into a `TransportListen` with its default configuration.
+/// Note that some transports, as `FramedTls` or `Quic`, can not listen without an explicit configuration.
+#[derive(Clone, Debug)]
+pub enum TransportListen {
+    #[cfg(feature = "tcp")]
+    Tcp(TcpListenConfig),
+    #[cfg(feature = "tcp")]
+    FramedTcp(FramedTcpListenConfig),
+    #[cfg(feature = "udp")]
+    Udp,
+    #[cfg(feature = "websocket")]
+    Ws,
+    /// Secure web socket (`wss`), it refers to [`Transport::Ws`].
+    #[cfg(all(feature = "websocket", feature = "tls"))]
+    Wss(TlsListenConfig),
+    #[cfg(feature = "tls")]
+    FramedTls(TlsListenConfig),
+    #[cfg(all(unix, feature = "unix"))]
+    UnixStream,
+    #[cfg(all(unix, feature = "unix"))]
+    UnixDatagram,
+    #[cfg(feature = "quic")]
+    Quic(TlsListenConfig),
+    #[cfg(feature = "udp")]
+    ReliableUdp,
+    #[cfg(feature = "memory")]
+    Memory,
+    /// Configuration for an adapter mounted by the user with
+    /// [`Builder::mount()`](crate::network::Builder::mount()).
+    /// It has no [`Transport`] associated.
+    Custom(CustomConfig),
+}
+
+impl TransportListen {
+    /// Returns the transport that this configuration refers to,
+    /// or `None` if the configuration is [`TransportListen::Custom`],
+    /// that refers to an adapter mounted by the user.
+    pub fn transport(&self) -> Option<Transport> {
+        match self {
+            #[cfg(feature = "tcp")]
+            Self::Tcp(_) => Some(Transport::Tcp),
+            #[cfg(feature = "tcp")]
+            Self::FramedTcp(_) => Some(Transport::FramedTcp),
+            #[cfg(feature = "udp")]
+            Self::Udp => Some(Transport::Udp),
+            #[cfg(feature = "websocket")]
+            Self::Ws => Some(Transport::Ws),
+            #[cfg(all(feature = "websocket", feature = "tls"))]
+            Self::Wss(_) => Some(Transport::Ws),
+            #[cfg(feature = "tls")]
+            Self::FramedTls(_) => Some(Transport::FramedTls),
+            #[cfg(all(unix, feature = "unix"))]
+            Self::UnixStream => Some(Transport::UnixStream),
+            #[cfg(all(unix, feature = "unix"))]
+            Self::UnixDatagram => Some(Transport::UnixDatagram),
+            #[cfg(feature = "quic")]
+            Self::Quic(_) => Some(Transport::Quic),
+            #[cfg(feature = "udp")]
+            Self::ReliableUdp => Some(Transport::ReliableUdp),
+            #[cfg(feature = "memory")]
+            Self::Memory => Some(Transport::Memory),
+            Self::Custom(_) => None,
+        }
+    }
+
+    /// Returns the adapter id used for this transport.
+    /// See [`Transport::id()`].
+    pub fn id(&self) -> u8 {
+        match (self, self.transport()) {
+            (Self::Custom(config), _) => config.adapter_id,
+            (_, Some(transport)) => transport.id(),
+            (_, None) => unreachable!("Only a custom configuration has no transport"),
+        }
+    }
+}
+
+impl From<Transport> for TransportListen {
+    fn from(transport: Transport) -> Self {
+        match transport {
+            #[cfg(feature = "tcp")]
+            Transport::Tcp => Self::Tcp(TcpListenConfig::default()),
+            #[cfg(feature = "tcp")]
+            Transport::FramedTcp => Self::FramedTcp(FramedTcpListenConfig::default()),
+            #[cfg(feature = "udp")]
+            Transport::Udp => Self::Udp,
+            #[cfg(feature = "websocket")]
+            Transport::Ws => Self::Ws,
+            #[cfg(feature = "tls")]
+            Transport::FramedTls => Self::FramedTls(TlsListenConfig::default()),
+            #[cfg(all(unix, feature = "unix"))]
+            Transport::UnixStream => Self::UnixStream,
+            #[cfg(all(unix, feature = "unix"))]
+            Transport::UnixDatagram => Self::UnixDatagram,
+            #[cfg(feature = "quic")]
+            Transport::Quic => Self::Quic(TlsListenConfig::default()),
+            #[cfg(feature = "udp")]
+            Transport::ReliableUdp => Self::ReliableUdp,
+            #[cfg(feature = "memory")]
+            Transport::Memory => Self::Memory,
+        }
+    }
+}
+
+/// Configuration to connect or listen using an adapter mounted by the user.
+/// See [`Builder::mount()`](crate::network::Builder::mount()).
+#[derive(Clone, Debug)]
+pub struct CustomConfig {
+    /// Id with which the adapter was mounted.
+    pub adapter_id: u8,
+
+    /// Configuration given to the adapter, if any.
+    /// The adapter can obtain it with [`CustomConfig::data()`].
+    pub data: Option<Arc<dyn Any + Send + Sync>>,
+}
+
+impl CustomConfig {
+    /// Creates a configuration for the adapter mounted with the given id, without data.
+    pub fn new(adapter_id: u8) -> Self {
+        Self { adapter_id, data: None }
+    }
+
+    /// Creates a configuration for the adapter mounted with the given id
+    /// that carries the data expected by the adapter.
+    pub fn with_data(adapter_id: u8, data: impl Any + Send + Sync) -> Self {
+        Self { adapter_id, data: Some(Arc::new(data)) }
+    }
+
+    /// Returns the data of the configuration if it is of type `T`.
+    pub fn data<T: Any>(&self) -> Option<&T> {
+        self.data.as_ref()?.downcast_ref()
     }
 }
@@ -1,4 +1,6 @@
-use crate::network::{self, NetworkController, NetworkProcessor, NetEvent, Endpoint, ResourceId};
+use crate::network::{
+    self, NetworkController, NetworkProcessor, NetEvent, Endpoint, ResourceId, DisconnectReason,
+};
 use crate::events::{self, EventSender, EventReceiver};
 use crate::util::thread::{NamespacedThread, OTHER_THREAD_ERR};
 
@@ -6,8 +8,9 @@ use std::sync::{
     Arc, Mutex,
     atomic::{AtomicBool, Ordering},
 };
-use std::time::{Duration};
+use std::time::{Duration, Instant};
 use std::collections::{VecDeque};
+use std::io::{self};
 
 lazy_static::lazy_static! {
     static ref SAMPLING_TIMEOUT: Duration = Duration::from_millis(50);
@@ -111,16 +114,33 @@ impl<S> From<NodeEvent<'_, S>> for StoredNodeEvent<S> {
 #[derive(Debug, Clone)]
 pub enum StoredNetEvent {
     Connected(Endpoint, ResourceId),
+    ConnectionFailed(Endpoint, Arc<io::Error>),
     Message(Endpoint, Vec<u8>),
-    Disconnected(Endpoint),
+    Disconnected(Endpoint, DisconnectReason),
+    Drained(Endpoint),
+    Reconnecting(Endpoint),
+    Reconnected(Endpoint),
+    Closed(Endpoint, bool),
+    Error(ResourceId, Arc<io::Error>),
 }
 
 impl From<NetEvent<'_>> for StoredNetEvent {
     fn from(net_event: NetEvent<'_>) -> Self {
         match net_event {
             NetEvent::Connected(endpoint, id) => Self::Connected(endpoint, id),
+            NetEvent::ConnectionFailed(endpoint, err) => Self::ConnectionFailed(
+                endpoint,
+                Arc::new(io::Error::new(err.kind(), err.to_string())),
+            ),
             NetEvent::Message(endpoint, data) => Self::Message(endpoint, Vec::from(data)),
-            NetEvent::Disconnected(endpoint) => Self::Disconnected(endpoint),
+            NetEvent::Disconnected(endpoint, reason) => Self::Disconnected(endpoint, reason),
+            NetEvent::Drained(endpoint) => Self::Drained(endpoint),
+            NetEvent::Reconnecting(endpoint) => Self::Reconnecting(endpoint),
+            NetEvent::Reconnected(endpoint) => Self::Reconnected(endpoint),
+            NetEvent::Closed(endpoint, completed) => Self::Closed(endpoint, completed),
+            NetEvent::Error(id, err) => {
+                Self::Error(id, Arc::new(io::Error::new(err.kind(), err.to_string())))
+            }
         }
     }
 }
@@ -130,8 +150,16 @@ impl StoredNetEvent {
     fn borrow(&self) -> NetEvent<'_> {
         match self {
             Self::Connected(endpoint, id) => NetEvent::Connected(*endpoint, *id),
-            Self::Message(endpoint, data) => NetEvent::Message(*endpoint, &data),
-            Self::Disconnected(endpoint) => NetEvent::Disconnected(*endpoint),
+            Self::ConnectionFailed(endpoint, err) => NetEvent::ConnectionFailed(*endpoint, err),
+            Self::Message(endpoint, data) => NetEvent::Message(*endpoint, data),
+            Self::Disconnected(endpoint, reason) => {
+                NetEvent::Disconnected(*endpoint, reason.clone())
+            }
+            Self::Drained(endpoint) => NetEvent::Drained(*endpoint),
+            Self::Reconnecting(endpoint) => NetEvent::Reconnecting(*endpoint),
+            Self::Reconnected(endpoint) => NetEvent::Reconnected(*endpoint),
+            Self::Closed(endpoint, completed) => NetEvent::Closed(*endpoint, *completed),
+            Self::Error(id, err) => NetEvent::Error(*id, err),
         }
     }
 }
@@ -174,7 +202,13 @@ impl StoredNetEvent {
 /// let (handler, listener) = node::split::<()>();
 /// ```
 pub fn split<S: Send>() -> (NodeHandler<S>, NodeListener<S>) {
-    let (network_controller, network_processor) = network::split();
+    split_with(network::Builder::new())
+}
+
+/// Creates a node already working using the network built by a [`network::Builder`].
+/// Similar to [`split()`] but the network can contain adapters defined by the user.
+pub fn split_with<S: Send>(network: network::Builder) -> (NodeHandler<S>, NodeListener<S>) {
+    let (network_controller, network_processor) = network.split();
     let (signal_sender, signal_receiver) = events::split();
     let running = AtomicBool::new(true);
 
@@ -182,6 +216,7 @@ pub fn split<S: Send>() -> (NodeHandler<S>, NodeListener<S>) {
         network: network_controller,
         signals: signal_sender,
         running,
+        shutdown_deadline: Mutex::new(None),
     }));
 
     let listener = NodeListener::new(network_processor, signal_receiver, handler.clone());
@@ -193,6 +228,7 @@ struct NodeHandlerImpl<S> {
     network: NetworkController,
     signals: EventSender<S>,
     running: AtomicBool,
+    shutdown_deadline: Mutex<Option<Option<Instant>>>, // Set once shutting down.
 }
 
 /// A shareable and clonable entity that allows to deal with
@@ -220,6 +256,28 @@ impl<S> NodeHandler<S> {
         self.0.running.store(false, Ordering::Relaxed);
     }
 
+    /// Closes gracefully all the connections of the node and stops it once they are closed,
+    /// or once the `timeout` expires.
+    /// The listeners are removed first, so no more connections are accepted.
+    /// Meanwhile, the events of the closing connections are still processed,
+    /// with a [`NetEvent::Closed`] for each of them.
+    /// See [`NetworkController::close_gracefully()`].
+    pub fn shutdown(&self, timeout: Duration) {
+        let deadline = Instant::now().checked_add(timeout);
+        *self.0.shutdown_deadline.lock().expect(OTHER_THREAD_ERR) = Some(deadline);
+        self.0.network.close_all_gracefully(timeout);
+    }
+
+    /// Stops the node if it is shutting down and its connections are already closed.
+    fn check_shutdown(&self) {
+        if let Some(deadline) = *self.0.shutdown_deadline.lock().expect(OTHER_THREAD_ERR) {
+            let expired = deadline.is_some_and(|deadline| Instant::now() >= deadline);
+            if !self.0.network.has_connections() || expired {
+                self.stop();
+            }
+        }
+    }
+
     /// Check if the node is running.
     /// Note that the node is running and listening events from its creation,
     /// not only once you call to [`NodeListener::for_each()`].
@@ -319,6 +377,7 @@ impl<S: Send + 'static> NodeListener<S> {
                 // so only one call is performed at the same time.
                 // It implies that any object moved into the callback do not have
                 // any concurrence issues.
+                #[allow(clippy::type_complexity)]
                 struct SendableEventCallback<'a, S>(Arc<Mutex<dyn FnMut(NodeEvent<S>) + 'a>>);
                 unsafe impl<'a, S> Send for SendableEventCallback<'a, S> {}
 
@@ -348,6 +407,7 @@ impl<S: Send + 'static> NodeListener<S> {
                         event_callback(NodeEvent::Network(net_event));
                     }
                 });
+                self.handler.check_shutdown();
             }
         })
         .unwrap();
@@ -422,6 +482,7 @@ impl<S: Send + 'static> NodeListener<S> {
                             event_callback(NodeEvent::Network(net_event));
                         }
                     });
+                    handler.check_shutdown();
                 }
             })
         };
@@ -4,3 +4,7 @@ pub mod thread;
 /// Frame encoding to convert a data stream into packets.
 /// It can be used as a utility to build adapters.
 pub mod encoding;
+
+/// TLS configuration used by the secure transports (available through the *tls* feature).
+#[cfg(feature = "tls")]
+pub mod tls;
@@ -17,6 +17,66 @@ pub fn decode_size(data: &[u8]) -> Option<(usize, usize)> {
     usize::decode_var(data)
 }
 
+/// Size of an encoded control frame.
+pub const CONTROL_FRAME_SIZE: usize = 2;
+
+/// Max value of a control frame code.
+pub const MAX_CONTROL_CODE: u8 = 0x7F;
+
+/// Encodes a control frame, used by the transports to talk between them
+/// without generating messages to the user.
+/// A control frame is a size encoded as a non-minimal varint of two bytes whose value is
+/// the `code` and it has no data.
+/// Since [`encode_size()`] always uses the minimal encoding, it never conflicts with a message.
+/// The `code` can not be greater than [`MAX_CONTROL_CODE`].
+pub fn encode_control(code: u8) -> [u8; CONTROL_FRAME_SIZE] {
+    debug_assert!(code <= MAX_CONTROL_CODE);
+    [0x80 | code, 0x00]
+}
+
+/// Frame decoded by a [`Decoder`].
+#[derive(Debug, PartialEq, Eq)]
+pub enum Frame<'a> {
+    /// A message sent with the size given by [`encode_size()`].
+    Message(&'a [u8]),
+
+    /// A control frame encoded by [`encode_control()`] with its code.
+    Control(u8),
+}
+
+/// Header of a frame.
+enum Header {
+    Message(usize),
+    Control(u8),
+}
+
+impl Header {
+    fn data_size(&self) -> usize {
+        match self {
+            Header::Message(size) => *size,
+            Header::Control(_) => 0,
+        }
+    }
+
+    fn frame<'a>(&self, data: &'a [u8]) -> Frame<'a> {
+        match self {
+            Header::Message(_) => Frame::Message(data),
+            Header::Control(code) => Frame::Control(*code),
+        }
+    }
+}
+
+/// Decodes the header of a frame, returning it with the consumed bytes
+/// or none if the buffer is too small.
+fn decode_header(data: &[u8]) -> Option<(Header, usize)> {
+    decode_size(data).map(|(size, used_bytes)| {
+        match used_bytes == CONTROL_FRAME_SIZE && size <= MAX_CONTROL_CODE as usize {
+            true => (Header::Control(size as u8), used_bytes),
+            false => (Header::Message(size), used_bytes),
+        }
+    })
+}
+
 /// Used to decoded messages from several/partial data chunks
 pub struct Decoder {
     stored: Vec<u8>,
@@ -31,14 +91,14 @@ impl Default for Decoder {
 }
 
 impl Decoder {
-    fn try_decode(&mut self, data: &[u8], mut decoded_callback: impl FnMut(&[u8])) {
+    fn try_decode(&mut self, data: &[u8], mut frame_callback: impl FnMut(Frame<'_>)) {
         let mut next_data = data;
         loop {
-            if let Some((expected_size, used_bytes)) = decode_size(&next_data) {
+            if let Some((header, used_bytes)) = decode_header(next_data) {
                 let remaining = &next_data[used_bytes..];
-                if remaining.len() >= expected_size {
-                    let (decoded, not_decoded) = remaining.split_at(expected_size);
-                    decoded_callback(decoded);
+                if remaining.len() >= header.data_size() {
+                    let (decoded, not_decoded) = remaining.split_at(header.data_size());
+                    frame_callback(header.frame(decoded));
                     if !not_decoded.is_empty() {
                         next_data = not_decoded;
                         continue
@@ -53,18 +113,21 @@ impl Decoder {
         }
     }
 
-    fn store_and_decoded_data<'a>(&mut self, data: &'a [u8]) -> Option<(&[u8], &'a [u8])> {
+    fn store_and_decoded_data<'a>(&mut self, data: &'a [u8]) -> Option<(Frame<'_>, &'a [u8])> {
         // Process frame header
-        let ((expected_size, used_bytes), data) = match decode_size(&self.stored) {
-            Some(size_info) => (size_info, data),
+        let ((header, used_bytes), data) = match decode_header(&self.stored) {
+            Some(header_info) => (header_info, data),
             None => {
                 // we append at most the potential data needed to decode the size
-                let max_remaining = (MAX_ENCODED_SIZE - self.stored.len()).min(data.len());
+                let stored_size = self.stored.len();
+                let max_remaining = (MAX_ENCODED_SIZE - stored_size).min(data.len());
                 self.stored.extend_from_slice(&data[..max_remaining]);
 
-                if let Some(x) = decode_size(&self.stored) {
-                    // Now we know the size
-                    (x, &data[max_remaining..])
+                if let Some((header, used_bytes)) = decode_header(&self.stored) {
+                    // Now we know the size.
+                    // The appended bytes after the header are processed as the rest of the data.
+                    self.stored.truncate(used_bytes);
+                    ((header, used_bytes), &data[used_bytes - stored_size..])
                 }
                 else {
                     // We still don't know the size (data was too small)
@@ -74,7 +137,7 @@ impl Decoder {
         };
 
         // At this point we know at least the expected size of the frame.
-        let remaining = expected_size - (self.stored.len() - used_bytes);
+        let remaining = header.data_size() - (self.stored.len() - used_bytes);
         if data.len() < remaining {
             // We need more data to decoder
             self.stored.extend_from_slice(data);
@@ -84,24 +147,35 @@ impl Decoder {
             // We can complete a message here
             let (to_store, remaining) = data.split_at(remaining);
             self.stored.extend_from_slice(to_store);
-            Some((&self.stored[used_bytes..], remaining))
+            Some((header.frame(&self.stored[used_bytes..]), remaining))
         }
     }
 
     /// Tries to decode data without reserve any memory, direcly from `data`.
     /// `decoded_callback` will be called for each decoded message.
+    /// The control frames are skipped.
     /// If `data` is not enough to decoding a message, the data will be stored
     /// until more data is decoded (more successives calls to this function).
     pub fn decode(&mut self, data: &[u8], mut decoded_callback: impl FnMut(&[u8])) {
+        self.decode_frames(data, |frame| {
+            if let Frame::Message(decoded_data) = frame {
+                decoded_callback(decoded_data)
+            }
+        })
+    }
+
+    /// Similar to [`Decoder::decode()`] but `frame_callback` is called
+    /// for each decoded frame, including the control frames.
+    pub fn decode_frames(&mut self, data: &[u8], mut frame_callback: impl FnMut(Frame<'_>)) {
         if self.stored.is_empty() {
-            self.try_decode(data, decoded_callback);
+            self.try_decode(data, frame_callback);
         }
         else {
             //There was already data in the Decoder
-            if let Some((decoded_data, remaining)) = self.store_and_decoded_data(data) {
-                decoded_callback(decoded_data);
+            if let Some((frame, remaining)) = self.store_and_decoded_data(data) {
+                frame_callback(frame);
                 self.stored.clear();
-                self.try_decode(remaining, decoded_callback);
+                self.try_decode(remaining, frame_callback);
             }
         }
     }
@@ -121,13 +195,13 @@ mod tests {
     const MESSAGE_SIZE: usize = 20; // only works if (X + PADDING ) % 6 == 0
     const ENCODED_MESSAGE_SIZE: usize = 1 + MESSAGE_SIZE; // 1 = log_2(20)/7
     const MESSAGE: [u8; MESSAGE_SIZE] = [42; MESSAGE_SIZE];
-    const MESSAGE_A: [u8; MESSAGE_SIZE] = ['A' as u8; MESSAGE_SIZE];
-    const MESSAGE_B: [u8; MESSAGE_SIZE] = ['B' as u8; MESSAGE_SIZE];
-    const MESSAGE_C: [u8; MESSAGE_SIZE] = ['C' as u8; MESSAGE_SIZE];
+    const MESSAGE_A: [u8; MESSAGE_SIZE] = [b'A'; MESSAGE_SIZE];
+    const MESSAGE_B: [u8; MESSAGE_SIZE] = [b'B'; MESSAGE_SIZE];
+    const MESSAGE_C: [u8; MESSAGE_SIZE] = [b'C'; MESSAGE_SIZE];
 
     fn encode_message(buffer: &mut Vec<u8>, message: &[u8]) {
         let mut buf = [0; MAX_ENCODED_SIZE];
-        buffer.extend_from_slice(&*encode_size(message, &mut buf));
+        buffer.extend_from_slice(encode_size(message, &mut buf));
         buffer.extend_from_slice(message);
     }
 
@@ -247,7 +321,7 @@ mod tests {
         let mut decoder = Decoder::default();
 
         let mut times_called = 0;
-        decoder.decode(&first, |_decoded| {
+        decoder.decode(first, |_decoded| {
             // Should not be called
             times_called += 1;
         });
@@ -255,7 +329,7 @@ mod tests {
         assert_eq!(0, times_called);
         assert_eq!(SPLIT, decoder.stored.len());
 
-        decoder.decode(&second, |decoded| {
+        decoder.decode(second, |decoded| {
             times_called += 1;
             assert_eq!(MESSAGE, decoded);
         });
@@ -278,7 +352,7 @@ mod tests {
         let mut decoder = Decoder::default();
 
         let mut times_called = 0;
-        decoder.decode(&first, |_decoded| {
+        decoder.decode(first, |_decoded| {
             // Should not be called
             times_called += 1;
         });
@@ -286,7 +360,7 @@ mod tests {
         assert_eq!(0, times_called);
         assert_eq!(SPLIT, decoder.stored.len());
 
-        decoder.decode(&second, |decoded| {
+        decoder.decode(second, |decoded| {
             times_called += 1;
             assert_eq!(MESSAGE, decoded);
         });
@@ -334,7 +408,7 @@ mod tests {
         let mut decoder = Decoder::default();
 
         let mut times_called = 0;
-        decoder.decode(&start_1b, |_decoded| {
+        decoder.decode(start_1b, |_decoded| {
             // Should not be called
             times_called += 1;
         });
@@ -342,7 +416,7 @@ mod tests {
         assert_eq!(0, times_called);
         assert_eq!(2, decoder.stored.len());
 
-        decoder.decode(&remaining, |decoded| {
+        decoder.decode(remaining, |decoded| {
             times_called += 1;
             assert_eq!(msg, decoded);
         });
@@ -364,7 +438,7 @@ mod tests {
         let mut decoder = Decoder::default();
 
         let mut times_called = 0;
-        decoder.decode(&start_1b, |_decoded| {
+        decoder.decode(start_1b, |_decoded| {
             // Should not be called
             times_called += 1;
         });
@@ -375,7 +449,7 @@ mod tests {
         let (next_1b, remaining) = remaining.split_at(1);
 
         let mut times_called = 0;
-        decoder.decode(&next_1b, |_decoded| {
+        decoder.decode(next_1b, |_decoded| {
             // Should not be called
             times_called += 1;
         });
@@ -383,7 +457,7 @@ mod tests {
         assert_eq!(0, times_called);
         assert_eq!(2, decoder.stored.len());
 
-        decoder.decode(&remaining, |decoded| {
+        decoder.decode(remaining, |decoded| {
             times_called += 1;
             assert_eq!(msg, decoded);
         });
@@ -391,4 +465,77 @@ mod tests {
         assert_eq!(1, times_called);
         assert_eq!(0, decoder.stored.len());
     }
+
+    #[test]
+    // [message][control][message]
+    fn decode_control_frames() {
+        let mut buffer = Vec::new();
+        encode_message(&mut buffer, &MESSAGE_A);
+        buffer.extend_from_slice(&encode_control(0));
+        encode_message(&mut buffer, &[]);
+        buffer.extend_from_slice(&encode_control(MAX_CONTROL_CODE));
+        encode_message(&mut buffer, &MESSAGE_B);
+
+        let expected = [
+            Frame::Message(&MESSAGE_A),
+            Frame::Control(0),
+            Frame::Message(&[]),
+            Frame::Control(MAX_CONTROL_CODE),
+            Frame::Message(&MESSAGE_B),
+        ];
+
+        let mut decoder = Decoder::default();
+        let mut times_called = 0;
+        decoder.decode_frames(&buffer, |frame| {
+            assert_eq!(expected[times_called], frame);
+            times_called += 1;
+        });
+        assert_eq!(expected.len(), times_called);
+
+        let mut messages = 0;
+        Decoder::default().decode(&buffer, |_| messages += 1);
+        assert_eq!(3, messages);
+    }
+
+    #[test]
+    // [ 1B ][ 1B ][ 1B ][...]
+    // [  control ][ message ]
+    fn decode_control_frame_byte_per_byte() {
+        let mut buffer = encode_control(1).to_vec();
+        encode_message(&mut buffer, &MESSAGE);
+
+        let mut decoder = Decoder::default();
+        let mut frames = Vec::new();
+        for i in 0..buffer.len() {
+            decoder.decode_frames(&buffer[i..i + 1], |frame| match frame {
+                Frame::Message(data) => frames.push((None, data.to_vec())),
+                Frame::Control(code) => frames.push((Some(code), Vec::new())),
+            });
+        }
+
+        assert_eq!(vec![(Some(1), Vec::new()), (None, MESSAGE.to_vec())], frames);
+        assert_eq!(0, decoder.stored.len());
+    }
+
+    #[test]
+    // [ 1B ][        data        ]
+    // [  control ][   message   ]
+    fn decode_control_frame_in_two_parts() {
+        let mut buffer = encode_control(1).to_vec();
+        encode_message(&mut buffer, &MESSAGE);
+
+        let mut decoder = Decoder::default();
+        let mut frames = 0;
+        decoder.decode_frames(&buffer[..1], |_| frames += 1);
+        decoder.decode_frames(&buffer[1..], |frame| {
+            match frames {
+                0 => assert_eq!(Frame::Control(1), frame),
+                _ => assert_eq!(Frame::Message(&MESSAGE), frame),
+            }
+            frames += 1;
+        });
+
+        assert_eq!(2, frames);
+        assert_eq!(0, decoder.stored.len());
+    }
 }
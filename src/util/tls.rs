@@ -0,0 +1,174 @@
+use crate::error::{Error};
+
+use native_tls::{
+    TlsConnector, TlsAcceptor, TlsStream, MidHandshakeTlsStream, Certificate, Identity,
+    HandshakeError,
+};
+
+#[cfg(feature = "quic")]
+use rustls::{RootCertStore, ClientConfig, ServerConfig};
+#[cfg(feature = "quic")]
+use rustls::crypto::{CryptoProvider};
+#[cfg(feature = "quic")]
+use rustls_pki_types::{CertificateDer, PrivateKeyDer};
+#[cfg(feature = "quic")]
+use rustls_pki_types::pem::{PemObject};
+
+#[cfg(feature = "quic")]
+use std::sync::{Arc};
+
+use std::io::{self, Read, Write};
+
+#[cfg(feature = "quic")]
+lazy_static::lazy_static! {
+    /// System trust roots, loaded once since reading them is expensive.
+    static ref NATIVE_ROOT_CERTIFICATES: Vec<CertificateDer<'static>> = {
+        let native_certs = rustls_native_certs::load_native_certs();
+        for err in native_certs.errors {
+            log::trace!("System root certificate not loaded: {}", err);
+        }
+        native_certs.certs
+    };
+}
+
+/// Configuration used by the TLS based transports to connect to a server.
+/// By default, the server certificate is validated against the system trust roots
+/// and the host of the address given at connect is used as server name.
+#[derive(Clone, Debug, Default)]
+pub struct TlsConnectConfig {
+    /// Additional root certificates, in PEM format, trusted to validate the server certificate.
+    /// Useful when the server uses a self-signed certificate or a private certificate authority.
+    pub root_certificates: Vec<Vec<u8>>,
+
+    /// If `true`, only the `root_certificates` will be trusted, ignoring the system trust roots.
+    pub disable_built_in_roots: bool,
+
+    /// Server name used for SNI and for the certificate validation.
+    /// If it is `None`, the host of the address passed to connect will be used.
+    pub domain: Option<String>,
+}
+
+impl TlsConnectConfig {
+    /// Starts the client TLS handshake over `stream` without waiting for it.
+    /// If the stream is non-blocking, the handshake could be still in progress,
+    /// and it should be resumed with [`TlsHandshake::resume()`] once the stream is ready.
+    /// The `host` is used as server name if no `domain` was specified.
+    pub(crate) fn start_connect<S: Read + Write>(
+        &self,
+        host: &str,
+        stream: S,
+    ) -> io::Result<TlsHandshake<S>> {
+        let domain = self.domain.as_deref().unwrap_or(host);
+        TlsHandshake::from_result(self.connector()?.connect(domain, stream))
+    }
+
+    fn connector(&self) -> io::Result<TlsConnector> {
+        let mut builder = TlsConnector::builder();
+        builder.disable_built_in_roots(self.disable_built_in_roots);
+        for root_certificate in &self.root_certificates {
+            builder.add_root_certificate(Certificate::from_pem(root_certificate).map_err(tls_err)?);
+        }
+        builder.build().map_err(tls_err)
+    }
+
+    /// Creates the equivalent *rustls* configuration, only allowing TLS 1.3.
+    #[cfg(feature = "quic")]
+    pub(crate) fn rustls_config(&self) -> io::Result<ClientConfig> {
+        let mut roots = RootCertStore::empty();
+        if !self.disable_built_in_roots {
+            roots.add_parsable_certificates(NATIVE_ROOT_CERTIFICATES.iter().cloned());
+        }
+        for root_certificate in &self.root_certificates {
+            for certificate in CertificateDer::pem_slice_iter(root_certificate) {
+                roots.add(certificate.map_err(tls_err)?).map_err(tls_err)?;
+            }
+        }
+
+        Ok(ClientConfig::builder_with_provider(crypto_provider())
+            .with_protocol_versions(&[&rustls::version::TLS13])
+            .map_err(tls_err)?
+            .with_root_certificates(roots)
+            .with_no_client_auth())
+    }
+}
+
+/// State of a client TLS handshake performed over a non-blocking stream.
+pub(crate) enum TlsHandshake<S> {
+    Done(TlsStream<S>),
+    InProgress(MidHandshakeTlsStream<S>),
+}
+
+impl<S: Read + Write> TlsHandshake<S> {
+    /// Continues a handshake that was in progress.
+    pub(crate) fn resume(mid_handshake: MidHandshakeTlsStream<S>) -> io::Result<Self> {
+        Self::from_result(mid_handshake.handshake())
+    }
+
+    fn from_result(result: Result<TlsStream<S>, HandshakeError<S>>) -> io::Result<Self> {
+        match result {
+            Ok(tls_stream) => Ok(TlsHandshake::Done(tls_stream)),
+            Err(HandshakeError::WouldBlock(mid_handshake)) => {
+                Ok(TlsHandshake::InProgress(mid_handshake))
+            }
+            Err(HandshakeError::Failure(err)) => {
+                Err(Error::HandshakeFailed(format!("TLS error: {}", err)).into())
+            }
+        }
+    }
+}
+
+/// Configuration used by the TLS based transports to listen.
+/// It contains the identity that the server presents to its clients.
+#[derive(Clone, Default)]
+pub struct TlsListenConfig {
+    /// Certificate chain in PEM format. The first certificate is the server one.
+    pub certificate_chain: Vec<u8>,
+
+    /// Private key of the server certificate in PEM format (PKCS #8).
+    pub private_key: Vec<u8>,
+}
+
+impl TlsListenConfig {
+    /// Creates a listen configuration from a PEM certificate chain and its PEM private key.
+    pub fn new(certificate_chain: impl Into<Vec<u8>>, private_key: impl Into<Vec<u8>>) -> Self {
+        Self { certificate_chain: certificate_chain.into(), private_key: private_key.into() }
+    }
+
+    pub(crate) fn acceptor(&self) -> io::Result<TlsAcceptor> {
+        let identity =
+            Identity::from_pkcs8(&self.certificate_chain, &self.private_key).map_err(tls_err)?;
+        TlsAcceptor::new(identity).map_err(tls_err)
+    }
+
+    /// Creates the equivalent *rustls* configuration, only allowing TLS 1.3.
+    #[cfg(feature = "quic")]
+    pub(crate) fn rustls_config(&self) -> io::Result<ServerConfig> {
+        let certificate_chain = CertificateDer::pem_slice_iter(&self.certificate_chain)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(tls_err)?;
+        let private_key = PrivateKeyDer::from_pem_slice(&self.private_key).map_err(tls_err)?;
+
+        ServerConfig::builder_with_provider(crypto_provider())
+            .with_protocol_versions(&[&rustls::version::TLS13])
+            .map_err(tls_err)?
+            .with_no_client_auth()
+            .with_single_cert(certificate_chain, private_key)
+            .map_err(tls_err)
+    }
+}
+
+impl std::fmt::Debug for TlsListenConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // The private key is never shown.
+        write!(f, "TlsListenConfig {{ certificate_chain: {} bytes }}", self.certificate_chain.len())
+    }
+}
+
+#[cfg(feature = "quic")]
+fn crypto_provider() -> Arc<CryptoProvider> {
+    Arc::new(rustls::crypto::ring::default_provider())
+}
+
+fn tls_err(err: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, err)
+}
@@ -1,19 +1,36 @@
-use message_io::network::{NetEvent, Transport, SendStatus};
+#![allow(clippy::unused_unit)] // Generated by test_case
+
+use message_io::network::{
+    self, NetEvent, Transport, TransportConnect, TransportListen, SendStatus, NetworkController,
+    ResourceId, ResourceType, Endpoint, RemoteAddr, CustomConfig, ConnectOptions, ListenOptions,
+    SendLimit, Proxy, ProxyProtocol, ProxyCredentials, AcceptFilter, Reconnect, CloseReason,
+    DisconnectReason,
+};
 use message_io::node::{self, NodeEvent};
 use message_io::util::thread::{NamespacedThread};
+#[cfg(feature = "tcp")]
+use message_io::adapters::tcp::{TcpListenConfig, TcpSocketOptions, TcpKeepalive};
+#[cfg(feature = "tcp")]
+use message_io::adapters::framed_tcp::{FramedTcpConnectConfig, FramedTcpListenConfig, Heartbeat};
 use message_io::adapters::udp::{self};
+use message_io::adapters::reliable_udp::{self};
+#[cfg(all(unix, feature = "unix"))]
+use message_io::adapters::unix_datagram::{self};
 
 use test_case::test_case;
 
 use rand::{SeedableRng, Rng};
 
 use std::collections::{HashSet};
-use std::net::{SocketAddr};
-use std::time::{Duration};
+use std::sync::{Mutex};
+use std::time::{Duration, Instant};
+use std::io::{ErrorKind, Read, Write};
+use std::convert::{TryFrom};
+use std::net::{SocketAddr, TcpListener as StdTcpListener, TcpStream as StdTcpStream};
 
-const LOCAL_ADDR: &'static str = "127.0.0.1:0";
-const MIN_MESSAGE: &'static [u8] = &[42];
-const SMALL_MESSAGE: &'static str = "Integration test message";
+const LOCAL_ADDR: &str = "127.0.0.1:0";
+const MIN_MESSAGE: &[u8] = &[42];
+const SMALL_MESSAGE: &str = "Integration test message";
 const BIG_MESSAGE_SIZE: usize = 1024 * 1024 * 8; // 8MB
 
 lazy_static::lazy_static! {
@@ -21,7 +38,7 @@ lazy_static::lazy_static! {
 }
 
 // Common error messages
-const TIMEOUT_EVENT_RECV_ERR: &'static str = "Timeout, but an event was expected.";
+const TIMEOUT_EVENT_RECV_ERR: &str = "Timeout, but an event was expected.";
 
 mod util {
     use std::sync::{Once};
@@ -63,10 +80,339 @@ mod util {
 #[allow(unused_imports)]
 use util::{LogThread};
 
+#[cfg(all(unix, feature = "unix"))]
+mod unix {
+    use std::path::{PathBuf};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static SOCKET_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    // Unique path by test to avoid collisions between tests running in parallel.
+    pub fn socket_path() -> PathBuf {
+        let id = SOCKET_COUNTER.fetch_add(1, Ordering::Relaxed);
+        std::env::temp_dir().join(format!("message-io-{}-{}.sock", std::process::id(), id))
+    }
+}
+
+#[cfg(feature = "memory")]
+mod memory {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static LISTENER_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    // Unique name by test to avoid collisions between tests running in parallel.
+    pub fn listener_name() -> String {
+        format!("message-io-test-{}", LISTENER_COUNTER.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+#[cfg(feature = "tls")]
+mod tls {
+    use message_io::util::tls::{TlsConnectConfig, TlsListenConfig};
+    #[cfg(feature = "websocket")]
+    use message_io::network::{TransportConnect, TransportListen};
+
+    // Certificate for 'localhost' and '127.0.0.1', issued by a test certificate authority.
+    const CA_CERTIFICATE: &[u8] = include_bytes!("certs/ca.crt");
+    const CERTIFICATE: &[u8] = include_bytes!("certs/localhost.crt");
+    const PRIVATE_KEY: &[u8] = include_bytes!("certs/localhost.key");
+
+    pub fn listen_config() -> TlsListenConfig {
+        TlsListenConfig::new(CERTIFICATE, PRIVATE_KEY)
+    }
+
+    pub fn connect_config() -> TlsConnectConfig {
+        TlsConnectConfig {
+            root_certificates: vec![CA_CERTIFICATE.to_vec()],
+            domain: Some("localhost".into()),
+            ..Default::default()
+        }
+    }
+
+    #[cfg(feature = "websocket")]
+    pub fn wss_listen() -> TransportListen {
+        TransportListen::Wss(listen_config())
+    }
+
+    #[cfg(feature = "websocket")]
+    pub fn wss_connect() -> TransportConnect {
+        TransportConnect::Wss(connect_config())
+    }
+}
+
+#[cfg(feature = "udp")]
+mod lossy {
+    use rand::{SeedableRng, Rng};
+    use rand::rngs::{StdRng};
+
+    use std::net::{UdpSocket, SocketAddr};
+    use std::sync::{Arc};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::thread::{self, JoinHandle};
+    use std::time::{Duration};
+
+    const DROP_PROBABILITY: f64 = 0.1;
+    const DUPLICATE_PROBABILITY: f64 = 0.05;
+    const REORDER_PROBABILITY: f64 = 0.05;
+
+    /// UDP proxy between a client and a server that drops, duplicates and reorders datagrams.
+    pub struct LossyProxy {
+        addr: SocketAddr,
+        running: Arc<AtomicBool>,
+        thread: Option<JoinHandle<()>>,
+    }
+
+    impl LossyProxy {
+        pub fn new(server_addr: SocketAddr) -> LossyProxy {
+            let client_side = UdpSocket::bind("127.0.0.1:0").unwrap();
+            let server_side = UdpSocket::bind("127.0.0.1:0").unwrap();
+            server_side.connect(server_addr).unwrap();
+            client_side.set_read_timeout(Some(Duration::from_millis(1))).unwrap();
+            server_side.set_read_timeout(Some(Duration::from_millis(1))).unwrap();
+
+            let addr = client_side.local_addr().unwrap();
+            let running = Arc::new(AtomicBool::new(true));
+            let thread_running = running.clone();
+            let thread = thread::spawn(move || {
+                let mut rng = StdRng::seed_from_u64(42);
+                let mut client_addr = None;
+                let mut held: [Option<Vec<u8>>; 2] = [None, None];
+                let mut buffer = [0; 65535];
+                while thread_running.load(Ordering::Relaxed) {
+                    if let Ok((size, addr)) = client_side.recv_from(&mut buffer) {
+                        client_addr = Some(addr);
+                        let send = |data: &[u8]| server_side.send(data).map(|_| ());
+                        forward(&mut rng, &buffer[..size], &mut held[0], send);
+                    }
+                    if let Ok(size) = server_side.recv(&mut buffer) {
+                        if let Some(addr) = client_addr {
+                            let send = |data: &[u8]| client_side.send_to(data, addr).map(|_| ());
+                            forward(&mut rng, &buffer[..size], &mut held[1], send);
+                        }
+                    }
+                }
+            });
+
+            LossyProxy { addr, running, thread: Some(thread) }
+        }
+
+        pub fn addr(&self) -> SocketAddr {
+            self.addr
+        }
+    }
+
+    fn forward(
+        rng: &mut StdRng,
+        data: &[u8],
+        held: &mut Option<Vec<u8>>,
+        send: impl Fn(&[u8]) -> std::io::Result<()>,
+    ) {
+        if rng.gen_bool(DROP_PROBABILITY) {
+            return
+        }
+        if held.is_none() && rng.gen_bool(REORDER_PROBABILITY) {
+            *held = Some(data.to_vec()); // Sent after the next one.
+            return
+        }
+        send(data).ok();
+        if rng.gen_bool(DUPLICATE_PROBABILITY) {
+            send(data).ok();
+        }
+        if let Some(held) = held.take() {
+            send(&held).ok();
+        }
+    }
+
+    impl Drop for LossyProxy {
+        fn drop(&mut self) {
+            self.running.store(false, Ordering::Relaxed);
+            self.thread.take().unwrap().join().unwrap();
+        }
+    }
+}
+
+// Adapter defined outside of the library: raw TCP where each read is a message.
+#[cfg(feature = "tcp")]
+mod custom {
+    use message_io::network::adapter::{
+        Resource, Remote, Local, Adapter, SendStatus, AcceptedType, AcceptStatus, ReadStatus,
+        DisconnectReason, ConnectionInfo, ListeningInfo,
+    };
+    use message_io::network::{
+        RemoteAddr, TransportConnect, TransportListen, ConnectOptions, ListenOptions, ResourceId,
+    };
+
+    use mio::net::{TcpListener, TcpStream};
+    use mio::event::{Source};
+
+    use std::net::{TcpStream as StdTcpStream};
+    use std::io::{self, ErrorKind, Read, Write};
+
+    pub const ADAPTER_ID: u8 = ResourceId::MAX_ADAPTER_ID;
+
+    /// Configuration of the connection: a message sent once connected.
+    pub struct Greeting(pub Vec<u8>);
+
+    /// Configuration of the listener: the accepted connections are rejected with an error.
+    pub struct RejectAll;
+
+    pub struct RawTcpAdapter;
+    impl Adapter for RawTcpAdapter {
+        type Remote = RemoteResource;
+        type Local = LocalResource;
+    }
+
+    pub struct RemoteResource(TcpStream);
+    impl Resource for RemoteResource {
+        fn source(&mut self) -> &mut dyn Source {
+            &mut self.0
+        }
+    }
+
+    impl Remote for RemoteResource {
+        fn connect(
+            config: TransportConnect,
+            addr: RemoteAddr,
+            _: ConnectOptions,
+        ) -> io::Result<ConnectionInfo<Self>> {
+            let mut stream = StdTcpStream::connect(addr.socket_addr())?;
+            if let TransportConnect::Custom(config) = config {
+                if let Some(Greeting(greeting)) = config.data() {
+                    stream.write_all(greeting)?;
+                }
+            }
+            stream.set_nonblocking(true)?;
+            Ok(ConnectionInfo {
+                local_addr: stream.local_addr()?,
+                peer_addr: stream.peer_addr()?,
+                remote: RemoteResource(TcpStream::from_std(stream)),
+            })
+        }
+
+        fn receive(&self, mut process_data: impl FnMut(&[u8])) -> ReadStatus {
+            let mut buffer = [0; 1024];
+            loop {
+                match (&self.0).read(&mut buffer) {
+                    Ok(0) => break ReadStatus::Disconnected(DisconnectReason::Closed),
+                    Ok(size) => process_data(&buffer[..size]),
+                    Err(ref err) if err.kind() == ErrorKind::WouldBlock => {
+                        break ReadStatus::WaitNextEvent
+                    }
+                    Err(err) => break ReadStatus::Disconnected(DisconnectReason::from(&err)),
+                }
+            }
+        }
+
+        fn send(&self, data: &[u8]) -> SendStatus {
+            match (&self.0).write(data) {
+                Ok(size) if size == data.len() => SendStatus::Sent,
+                _ => SendStatus::ResourceNotFound,
+            }
+        }
+    }
+
+    pub struct LocalResource(TcpListener, bool);
+    impl Resource for LocalResource {
+        fn source(&mut self) -> &mut dyn Source {
+            &mut self.0
+        }
+    }
+
+    impl Local for LocalResource {
+        type Remote = RemoteResource;
+
+        fn listen(
+            config: TransportListen,
+            addr: RemoteAddr,
+            _: ListenOptions,
+        ) -> io::Result<ListeningInfo<Self>> {
+            let reject_all = match config {
+                TransportListen::Custom(config) => config.data::<RejectAll>().is_some(),
+                _ => false,
+            };
+            let listener = TcpListener::bind(*addr.socket_addr())?;
+            let local_addr = listener.local_addr()?;
+            Ok(ListeningInfo { local_addr, local: LocalResource(listener, reject_all) })
+        }
+
+        fn accept(
+            &self,
+            mut accept_remote: impl FnMut(AcceptedType<'_, Self::Remote>) -> AcceptStatus,
+        ) {
+            loop {
+                match self.0.accept() {
+                    Ok(_) if self.1 => {
+                        let err = io::Error::new(ErrorKind::PermissionDenied, "Rejected");
+                        accept_remote(AcceptedType::Error(err));
+                    }
+                    Ok((stream, addr)) => {
+                        let remote = AcceptedType::Remote(addr, RemoteResource(stream));
+                        if accept_remote(remote) == AcceptStatus::Stop {
+                            break
+                        }
+                    }
+                    Err(ref err) if err.kind() == ErrorKind::WouldBlock => break,
+                    Err(err) => {
+                        accept_remote(AcceptedType::Error(err));
+                        break
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn listen_config(transport: Transport) -> TransportListen {
+    #[cfg(feature = "tls")]
+    if transport == Transport::FramedTls {
+        return TransportListen::FramedTls(tls::listen_config())
+    }
+    #[cfg(feature = "quic")]
+    if transport == Transport::Quic {
+        return TransportListen::Quic(tls::listen_config())
+    }
+    transport.into()
+}
+
+fn connect_config(transport: Transport) -> TransportConnect {
+    #[cfg(feature = "tls")]
+    if transport == Transport::FramedTls {
+        return TransportConnect::FramedTls(tls::connect_config())
+    }
+    #[cfg(feature = "quic")]
+    if transport == Transport::Quic {
+        return TransportConnect::Quic(tls::connect_config())
+    }
+    transport.into()
+}
+
+// Listen in a local address, returning the address to connect with it.
+fn listen_local(
+    network: &NetworkController,
+    transport_listen: TransportListen,
+) -> (ResourceId, RemoteAddr) {
+    #[cfg(all(unix, feature = "unix"))]
+    if matches!(transport_listen.transport(), Some(Transport::UnixStream | Transport::UnixDatagram))
+    {
+        let path = unix::socket_path();
+        let (id, _) = network.listen_with(transport_listen, &path).unwrap();
+        return (id, RemoteAddr::Path(path))
+    }
+    #[cfg(feature = "memory")]
+    if transport_listen.transport() == Some(Transport::Memory) {
+        let name = memory::listener_name();
+        let (id, _) = network.listen_with(transport_listen, &name).unwrap();
+        return (id, RemoteAddr::Str(name))
+    }
+    let (id, addr) = network.listen_with(transport_listen, LOCAL_ADDR).unwrap();
+    (id, RemoteAddr::Socket(addr))
+}
+
 fn start_echo_server(
-    transport: Transport,
+    transport_listen: TransportListen,
     expected_clients: usize,
-) -> (NamespacedThread<()>, SocketAddr) {
+) -> (NamespacedThread<()>, RemoteAddr) {
+    let transport = transport_listen.transport().unwrap();
     let (tx, rx) = crossbeam_channel::bounded(1);
     let thread = NamespacedThread::spawn("test-server", move || {
         let mut messages_received = 0;
@@ -76,7 +422,7 @@ fn start_echo_server(
         let (node, listener) = node::split();
         node.signals().send_with_timer((), *TIMEOUT);
 
-        let (listener_id, server_addr) = node.network().listen(transport, LOCAL_ADDR).unwrap();
+        let (listener_id, server_addr) = listen_local(node.network(), transport_listen);
         tx.send(server_addr).unwrap();
 
         listener.for_each(move |event| match event {
@@ -85,7 +431,7 @@ fn start_echo_server(
                 NetEvent::Message(endpoint, data) => {
                     assert_eq!(MIN_MESSAGE, data);
 
-                    let status = node.network().send(endpoint, &data);
+                    let status = node.network().send(endpoint, data);
                     assert_eq!(SendStatus::Sent, status);
 
                     messages_received += 1;
@@ -107,7 +453,12 @@ fn start_echo_server(
                         false => unreachable!(),
                     }
                 }
-                NetEvent::Disconnected(endpoint) => {
+                NetEvent::ConnectionFailed(..) => unreachable!(),
+                NetEvent::Drained(_) => unreachable!(),
+                NetEvent::Reconnecting(_) | NetEvent::Reconnected(_) => unreachable!(),
+                NetEvent::Closed(..) => unreachable!(),
+                NetEvent::Error(..) => unreachable!(),
+                NetEvent::Disconnected(endpoint, _) => {
                     match transport.is_connection_oriented() {
                         true => {
                             disconnections += 1;
@@ -130,8 +481,8 @@ fn start_echo_server(
 }
 
 fn start_echo_client_manager(
-    transport: Transport,
-    server_addr: SocketAddr,
+    transport_connect: TransportConnect,
+    server_addr: RemoteAddr,
     clients_number: usize,
 ) -> NamespacedThread<()> {
     NamespacedThread::spawn("test-client", move || {
@@ -141,7 +492,10 @@ fn start_echo_client_manager(
         let mut clients = HashSet::new();
 
         for _ in 0..clients_number {
-            let (server, _) = node.network().connect(transport, server_addr).unwrap();
+            let (server, _) = node
+                .network()
+                .connect_with(transport_connect.clone(), server_addr.clone())
+                .unwrap();
             let status = node.network().send(server, MIN_MESSAGE);
             assert_eq!(SendStatus::Sent, status);
             assert!(clients.insert(server));
@@ -154,27 +508,32 @@ fn start_echo_client_manager(
                     assert!(clients.remove(&endpoint));
                     assert_eq!(MIN_MESSAGE, data);
                     node.network().remove(endpoint.resource_id());
-                    if clients.len() == 0 {
+                    if clients.is_empty() {
                         node.stop(); //Exit from thread.
                     }
                 }
                 NetEvent::Connected(..) => unreachable!(),
-                NetEvent::Disconnected(_) => unreachable!(),
+                NetEvent::ConnectionFailed(..) => unreachable!(),
+                NetEvent::Drained(_) => unreachable!(),
+                NetEvent::Reconnecting(_) | NetEvent::Reconnected(_) => unreachable!(),
+                NetEvent::Closed(..) => unreachable!(),
+                NetEvent::Error(..) => unreachable!(),
+                NetEvent::Disconnected(..) => unreachable!(),
             },
         });
     })
 }
 
 fn start_burst_receiver(
-    transport: Transport,
+    transport_listen: TransportListen,
     expected_count: usize,
-) -> (NamespacedThread<()>, SocketAddr) {
+) -> (NamespacedThread<()>, RemoteAddr) {
     let (tx, rx) = crossbeam_channel::bounded(1);
     let thread = NamespacedThread::spawn("test-receiver", move || {
         let (node, listener) = node::split();
         node.signals().send_with_timer((), *TIMEOUT);
 
-        let (_, receiver_addr) = node.network().listen(transport, LOCAL_ADDR).unwrap();
+        let (_, receiver_addr) = listen_local(node.network(), transport_listen);
         tx.send(receiver_addr).unwrap();
 
         let mut count = 0;
@@ -183,14 +542,19 @@ fn start_burst_receiver(
             NodeEvent::Network(net_event) => match net_event {
                 NetEvent::Message(_, data) => {
                     let expected_message = format!("{}: {}", SMALL_MESSAGE, count);
-                    assert_eq!(expected_message, String::from_utf8_lossy(&data));
+                    assert_eq!(expected_message, String::from_utf8_lossy(data));
                     count += 1;
                     if count == expected_count {
                         node.stop();
                     }
                 }
                 NetEvent::Connected(..) => (),
-                NetEvent::Disconnected(_) => (),
+                NetEvent::ConnectionFailed(..) => unreachable!(),
+                NetEvent::Drained(_) => unreachable!(),
+                NetEvent::Reconnecting(_) | NetEvent::Reconnected(_) => unreachable!(),
+                NetEvent::Closed(..) => unreachable!(),
+                NetEvent::Error(..) => unreachable!(),
+                NetEvent::Disconnected(..) => (),
             },
         });
     });
@@ -198,24 +562,33 @@ fn start_burst_receiver(
 }
 
 fn start_burst_sender(
-    transport: Transport,
-    receiver_addr: SocketAddr,
+    transport_connect: TransportConnect,
+    receiver_addr: RemoteAddr,
     expected_count: usize,
 ) -> NamespacedThread<()> {
+    let transport = transport_connect.transport().unwrap();
     NamespacedThread::spawn("test-sender", move || {
-        let (node, _) = node::split::<()>();
+        let (node, listener) = node::split::<()>();
 
-        let (receiver, _) = node.network().connect(transport, receiver_addr).unwrap();
+        let (receiver, _) = node.network().connect_with(transport_connect, receiver_addr).unwrap();
 
         for count in 0..expected_count {
             let message = format!("{}: {}", SMALL_MESSAGE, count);
             let status = node.network().send(receiver, message.as_bytes());
-            assert_eq!(SendStatus::Sent, status);
+            assert!(matches!(status, SendStatus::Sent | SendStatus::Enqueued));
             if !transport.is_connection_oriented() {
                 // We need a rate to not lose packet.
                 std::thread::sleep(Duration::from_micros(20));
             }
         }
+
+        // The enqueued messages are only delivered by a graceful close.
+        assert!(node.network().close_gracefully(receiver, *TIMEOUT));
+        listener.for_each(move |event| {
+            if let NodeEvent::Network(NetEvent::Closed(..)) = event {
+                node.stop();
+            }
+        });
     })
 }
 
@@ -227,33 +600,128 @@ fn start_burst_sender(
 #[cfg_attr(feature = "udp", test_case(Transport::Udp, 100))]
 #[cfg_attr(feature = "websocket", test_case(Transport::Ws, 1))]
 #[cfg_attr(feature = "websocket", test_case(Transport::Ws, 100))]
+#[cfg_attr(feature = "tls", test_case(Transport::FramedTls, 1))]
+#[cfg_attr(feature = "tls", test_case(Transport::FramedTls, 100))]
+#[cfg_attr(all(unix, feature = "unix"), test_case(Transport::UnixStream, 1))]
+#[cfg_attr(all(unix, feature = "unix"), test_case(Transport::UnixStream, 100))]
+#[cfg_attr(all(unix, feature = "unix"), test_case(Transport::UnixDatagram, 1))]
+#[cfg_attr(all(unix, feature = "unix"), test_case(Transport::UnixDatagram, 100))]
+#[cfg_attr(feature = "quic", test_case(Transport::Quic, 1))]
+#[cfg_attr(feature = "quic", test_case(Transport::Quic, 100))]
+#[cfg_attr(feature = "udp", test_case(Transport::ReliableUdp, 1))]
+#[cfg_attr(feature = "udp", test_case(Transport::ReliableUdp, 100))]
+#[cfg_attr(feature = "memory", test_case(Transport::Memory, 1))]
+#[cfg_attr(feature = "memory", test_case(Transport::Memory, 100))]
 // NOTE: A medium-high `clients` value can exceeds the "open file" limits of an OS in CI
 // with an obfuscated error message.
 fn echo(transport: Transport, clients: usize) {
+    echo_with(listen_config(transport), connect_config(transport), clients);
+}
+
+#[cfg(all(feature = "websocket", feature = "tls"))]
+#[test_case(1)]
+#[test_case(100)]
+fn wss_echo(clients: usize) {
+    echo_with(tls::wss_listen(), tls::wss_connect(), clients);
+}
+
+fn echo_with(
+    transport_listen: TransportListen,
+    transport_connect: TransportConnect,
+    clients: usize,
+) {
     //util::init_logger(LogThread::Enabled); // Enable it for better debugging
 
-    let (_server_thread, server_addr) = start_echo_server(transport, clients);
-    let _client_thread = start_echo_client_manager(transport, server_addr, clients);
+    let (_server_thread, server_addr) = start_echo_server(transport_listen, clients);
+    let _client_thread = start_echo_client_manager(transport_connect, server_addr, clients);
 }
 
 // Tcp: Does not apply: it's stream based
 #[cfg_attr(feature = "udp", test_case(Transport::Udp, 2000))]
 #[cfg_attr(feature = "tcp", test_case(Transport::FramedTcp, 200000))]
 #[cfg_attr(feature = "websocket", test_case(Transport::Ws, 200000))]
+#[cfg_attr(feature = "tls", test_case(Transport::FramedTls, 200000))]
+#[cfg_attr(all(unix, feature = "unix"), test_case(Transport::UnixStream, 200000))]
+#[cfg_attr(all(unix, feature = "unix"), test_case(Transport::UnixDatagram, 2000))]
+#[cfg_attr(feature = "quic", test_case(Transport::Quic, 20000))]
+#[cfg_attr(feature = "udp", test_case(Transport::ReliableUdp, 200000))]
+#[cfg_attr(feature = "memory", test_case(Transport::Memory, 200000))]
 fn burst(transport: Transport, messages_count: usize) {
+    burst_with(listen_config(transport), connect_config(transport), messages_count);
+}
+
+#[cfg(all(feature = "websocket", feature = "tls"))]
+#[test_case(200000)]
+fn wss_burst(messages_count: usize) {
+    burst_with(tls::wss_listen(), tls::wss_connect(), messages_count);
+}
+
+fn burst_with(
+    transport_listen: TransportListen,
+    transport_connect: TransportConnect,
+    messages_count: usize,
+) {
     //util::init_logger(LogThread::Enabled); // Enable it for better debugging
 
-    let (_receiver_thread, server_addr) = start_burst_receiver(transport, messages_count);
-    let _sender_thread = start_burst_sender(transport, server_addr, messages_count);
+    let (_receiver_thread, server_addr) = start_burst_receiver(transport_listen, messages_count);
+    let _sender_thread = start_burst_sender(transport_connect, server_addr, messages_count);
 }
 
 #[cfg_attr(feature = "tcp", test_case(Transport::Tcp, BIG_MESSAGE_SIZE))]
 #[cfg_attr(feature = "tcp", test_case(Transport::FramedTcp, BIG_MESSAGE_SIZE))]
 #[cfg_attr(feature = "udp", test_case(Transport::Udp, udp::MAX_COMPATIBLE_PAYLOAD_LEN))]
 #[cfg_attr(feature = "websocket", test_case(Transport::Ws, BIG_MESSAGE_SIZE))]
+#[cfg_attr(feature = "tls", test_case(Transport::FramedTls, BIG_MESSAGE_SIZE))]
+#[cfg_attr(all(unix, feature = "unix"), test_case(Transport::UnixStream, BIG_MESSAGE_SIZE))]
+#[cfg_attr(
+    all(target_os = "linux", feature = "unix"),
+    test_case(Transport::UnixDatagram, unix_datagram::MAX_PAYLOAD_LEN)
+)]
+#[cfg_attr(feature = "quic", test_case(Transport::Quic, BIG_MESSAGE_SIZE))]
+#[cfg_attr(feature = "udp", test_case(Transport::ReliableUdp, reliable_udp::MAX_PAYLOAD_LEN))]
+#[cfg_attr(feature = "memory", test_case(Transport::Memory, BIG_MESSAGE_SIZE))]
 fn message_size(transport: Transport, message_size: usize) {
+    message_size_with(listen_config(transport), connect_config(transport), message_size);
+}
+
+#[cfg(all(feature = "websocket", feature = "tls"))]
+#[test_case(BIG_MESSAGE_SIZE)]
+fn wss_message_size(message_size: usize) {
+    message_size_with(tls::wss_listen(), tls::wss_connect(), message_size);
+}
+
+#[cfg(feature = "tcp")]
+#[test_case(BIG_MESSAGE_SIZE)]
+fn tcp_socket_options_message_size(message_size: usize) {
+    let socket_options = TcpSocketOptions {
+        nodelay: Some(true),
+        keepalive: Some(TcpKeepalive { idle: Some(Duration::from_secs(30)), ..Default::default() }),
+        recv_buffer_size: Some(1024 * 64),
+        send_buffer_size: Some(1024 * 64),
+        ttl: Some(32),
+        ..Default::default()
+    };
+    let listen_config = FramedTcpListenConfig {
+        socket_options: socket_options.clone(),
+        backlog: Some(16),
+        ..Default::default()
+    };
+    let connect_config = FramedTcpConnectConfig { socket_options, ..Default::default() };
+    message_size_with(
+        TransportListen::FramedTcp(listen_config),
+        TransportConnect::FramedTcp(connect_config),
+        message_size,
+    );
+}
+
+fn message_size_with(
+    transport_listen: TransportListen,
+    transport_connect: TransportConnect,
+    message_size: usize,
+) {
     //util::init_logger(LogThread::Enabled); // Enable it for better debugging
 
+    let transport = transport_listen.transport().unwrap();
     assert!(message_size <= transport.max_message_size());
 
     let mut rng = rand::rngs::StdRng::seed_from_u64(42);
@@ -262,9 +730,9 @@ fn message_size(transport: Transport, message_size: usize) {
     let (node, listener) = node::split();
     node.signals().send_with_timer((), *TIMEOUT);
 
-    let (_, receiver_addr) = node.network().listen(transport, LOCAL_ADDR).unwrap();
+    let (_, receiver_addr) = listen_local(node.network(), transport_listen);
 
-    let (receiver, _) = node.network().connect(transport, receiver_addr).unwrap();
+    let (receiver, _) = node.network().connect_with(transport_connect, receiver_addr).unwrap();
 
     if !transport.is_connection_oriented() {
         let status = node.network().send(receiver, &sent_message);
@@ -285,7 +753,7 @@ fn message_size(transport: Transport, message_size: usize) {
                     node.stop();
                 }
                 else {
-                    received_message.extend_from_slice(&data);
+                    received_message.extend_from_slice(data);
                 }
             }
             NetEvent::Connected(..) => {
@@ -294,15 +762,21 @@ fn message_size(transport: Transport, message_size: usize) {
                     let sent_message = sent_message.clone();
                     _async_sender = Some(NamespacedThread::spawn("test-sender", move || {
                         let status = node.network().send(receiver, &sent_message);
-                        assert_eq!(status, SendStatus::Sent);
-                        assert!(node.network().remove(receiver.resource_id()));
+                        assert!(matches!(status, SendStatus::Sent | SendStatus::Enqueued));
+                        // The enqueued data is only written by a graceful close.
+                        assert!(node.network().close_gracefully(receiver, *TIMEOUT));
                     }));
                 }
                 else {
                     unreachable!();
                 }
             }
-            NetEvent::Disconnected(_) => {
+            NetEvent::ConnectionFailed(..) => unreachable!(),
+            NetEvent::Drained(_) => unreachable!(),
+            NetEvent::Reconnecting(_) | NetEvent::Reconnected(_) => unreachable!(),
+            NetEvent::Closed(..) => (),
+            NetEvent::Error(..) => unreachable!(),
+            NetEvent::Disconnected(..) => {
                 assert_eq!(sent_message.len(), received_message.len());
                 assert_eq!(sent_message, received_message);
                 node.stop();
@@ -310,3 +784,1732 @@ fn message_size(transport: Transport, message_size: usize) {
         },
     });
 }
+
+#[cfg_attr(feature = "tcp", test_case(Transport::Tcp))]
+#[cfg_attr(feature = "tcp", test_case(Transport::FramedTcp))]
+#[cfg_attr(feature = "websocket", test_case(Transport::Ws))]
+#[cfg_attr(feature = "tls", test_case(Transport::FramedTls))]
+#[cfg_attr(all(unix, feature = "unix"), test_case(Transport::UnixStream))]
+#[cfg_attr(feature = "memory", test_case(Transport::Memory))]
+fn connect_async(transport: Transport) {
+    connect_async_with(listen_config(transport), connect_config(transport));
+}
+
+#[cfg(all(feature = "websocket", feature = "tls"))]
+#[test]
+fn wss_connect_async() {
+    connect_async_with(tls::wss_listen(), tls::wss_connect());
+}
+
+fn connect_async_with(transport_listen: TransportListen, transport_connect: TransportConnect) {
+    //util::init_logger(LogThread::Enabled); // Enable it for better debugging
+
+    let (node, listener) = node::split();
+    node.signals().send_with_timer((), *TIMEOUT);
+
+    let (listener_id, server_addr) = listen_local(node.network(), transport_listen);
+    let (server, _) = node.network().connect_async_with(transport_connect, server_addr).unwrap();
+
+    listener.for_each(move |event| match event {
+        NodeEvent::Signal(_) => panic!("{}", TIMEOUT_EVENT_RECV_ERR),
+        NodeEvent::Network(net_event) => match net_event {
+            NetEvent::Connected(endpoint, id) if endpoint == server => {
+                assert_eq!(server.resource_id(), id);
+                assert_eq!(SendStatus::Sent, node.network().send(server, MIN_MESSAGE));
+            }
+            NetEvent::Connected(_, id) => assert_eq!(listener_id, id),
+            NetEvent::Message(endpoint, data) if endpoint == server => {
+                assert_eq!(SMALL_MESSAGE.as_bytes(), data);
+                node.stop();
+            }
+            NetEvent::Message(endpoint, data) => {
+                assert_eq!(MIN_MESSAGE, data);
+                let status = node.network().send(endpoint, SMALL_MESSAGE.as_bytes());
+                assert_eq!(SendStatus::Sent, status);
+            }
+            NetEvent::ConnectionFailed(..) => unreachable!(),
+            NetEvent::Drained(_) => unreachable!(),
+            NetEvent::Reconnecting(_) | NetEvent::Reconnected(_) => unreachable!(),
+            NetEvent::Closed(..) => unreachable!(),
+            NetEvent::Error(..) => unreachable!(),
+            NetEvent::Disconnected(..) => unreachable!(),
+        },
+    });
+}
+
+#[cfg_attr(feature = "tcp", test_case(Transport::Tcp))]
+#[cfg_attr(feature = "tcp", test_case(Transport::FramedTcp))]
+#[cfg_attr(feature = "websocket", test_case(Transport::Ws))]
+#[cfg_attr(feature = "tls", test_case(Transport::FramedTls))]
+fn connect_async_refused(transport: Transport) {
+    //util::init_logger(LogThread::Enabled); // Enable it for better debugging
+
+    // An address with nobody listening.
+    let addr = std::net::TcpListener::bind(LOCAL_ADDR).unwrap().local_addr().unwrap();
+
+    let (node, listener) = node::split();
+    node.signals().send_with_timer((), *TIMEOUT);
+
+    let server = match node.network().connect_async_with(connect_config(transport), addr) {
+        Ok((server, _)) => server,
+        // The OS could know the result before starting the connection.
+        Err(err) => return assert_eq!(ErrorKind::ConnectionRefused, err.kind()),
+    };
+
+    listener.for_each(move |event| match event {
+        NodeEvent::Signal(_) => panic!("{}", TIMEOUT_EVENT_RECV_ERR),
+        NodeEvent::Network(net_event) => match net_event {
+            NetEvent::ConnectionFailed(endpoint, err) => {
+                assert_eq!(server, endpoint);
+                assert_eq!(ErrorKind::ConnectionRefused, err.kind());
+                assert_eq!(SendStatus::ResourceNotFound, node.network().send(server, MIN_MESSAGE));
+                assert!(!node.network().remove(server.resource_id()));
+                node.stop();
+            }
+            NetEvent::Connected(..) => unreachable!(),
+            NetEvent::Message(..) => unreachable!(),
+            NetEvent::Disconnected(..) => unreachable!(),
+            NetEvent::Drained(_) => unreachable!(),
+            NetEvent::Reconnecting(_) | NetEvent::Reconnected(_) => unreachable!(),
+            NetEvent::Closed(..) => unreachable!(),
+            NetEvent::Error(..) => unreachable!(),
+        },
+    });
+}
+
+#[cfg(any(feature = "websocket", feature = "tls"))]
+#[cfg_attr(feature = "websocket", test_case(Transport::Ws))]
+#[cfg_attr(feature = "tls", test_case(Transport::FramedTls))]
+fn connect_timeout(transport: Transport) {
+    // The connection is accepted by the OS, but nobody answers the handshake.
+    let listener = std::net::TcpListener::bind(LOCAL_ADDR).unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let (node, _listener) = node::split::<()>();
+    let options =
+        ConnectOptions { timeout: Some(Duration::from_millis(100)), ..Default::default() };
+    let result = node.network().connect_with_options(connect_config(transport), addr, options);
+    assert_eq!(ErrorKind::TimedOut, result.unwrap_err().kind());
+}
+
+#[cfg(feature = "tcp")]
+#[test]
+fn connect_max_timeout() {
+    let listener = std::net::TcpListener::bind(LOCAL_ADDR).unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let (node, _listener) = node::split::<()>();
+    let options = ConnectOptions { timeout: Some(Duration::MAX), ..Default::default() };
+    node.network().connect_with_options(Transport::Tcp.into(), addr, options).unwrap();
+}
+
+#[cfg(feature = "tcp")]
+#[test]
+fn connect_max_idle_timeout() {
+    let listener = std::net::TcpListener::bind(LOCAL_ADDR).unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let (node, _listener) = node::split::<()>();
+    let options = ConnectOptions { idle_timeout: Some(Duration::MAX), ..Default::default() };
+    node.network().connect_with_options(Transport::Tcp.into(), addr, options).unwrap();
+}
+
+#[cfg(feature = "udp")]
+#[test]
+fn reliable_udp_connect_timeout() {
+    // The socket receives the connection request, but nobody answers it.
+    let socket = std::net::UdpSocket::bind(LOCAL_ADDR).unwrap();
+    let addr = socket.local_addr().unwrap();
+
+    let (node, _listener) = node::split::<()>();
+    let options =
+        ConnectOptions { timeout: Some(Duration::from_millis(100)), ..Default::default() };
+    let start = Instant::now();
+    let result = node.network().connect_with_options(Transport::ReliableUdp.into(), addr, options);
+    assert_eq!(ErrorKind::TimedOut, result.unwrap_err().kind());
+    assert!(start.elapsed() < Duration::from_secs(1));
+}
+
+#[cfg_attr(feature = "tcp", test_case(Transport::Tcp))]
+#[cfg_attr(feature = "tcp", test_case(Transport::FramedTcp))]
+#[cfg_attr(feature = "websocket", test_case(Transport::Ws))]
+fn connect_retries(transport: Transport) {
+    // An address with nobody listening.
+    let addr = std::net::TcpListener::bind(LOCAL_ADDR).unwrap().local_addr().unwrap();
+
+    let (node, _listener) = node::split::<()>();
+    let options =
+        ConnectOptions { retries: 2, backoff: Duration::from_millis(50), ..Default::default() };
+    let start = Instant::now();
+    let result = node.network().connect_with_options(transport.into(), addr, options);
+    assert_eq!(ErrorKind::ConnectionRefused, result.unwrap_err().kind());
+    assert!(start.elapsed() >= Duration::from_millis(150)); // Waited 50ms and 100ms
+}
+
+#[cfg(feature = "tcp")]
+#[test]
+fn connect_retries_until_listening() {
+    let addr = std::net::TcpListener::bind(LOCAL_ADDR).unwrap().local_addr().unwrap();
+    let _listener_thread = NamespacedThread::spawn("test-listener", move || {
+        std::thread::sleep(Duration::from_millis(100));
+        let listener = std::net::TcpListener::bind(addr).unwrap();
+        listener.accept().unwrap();
+    });
+
+    let (node, _listener) = node::split::<()>();
+    let options =
+        ConnectOptions { retries: 10, backoff: Duration::from_millis(20), ..Default::default() };
+    node.network().connect_with_options(Transport::Tcp.into(), addr, options).unwrap();
+}
+
+#[cfg(feature = "tcp")]
+#[test]
+fn send_enqueued() {
+    const CHUNK_SIZE: usize = 1024 * 64;
+
+    // The peer does not read until the OS buffers are full.
+    let listener = std::net::TcpListener::bind(LOCAL_ADDR).unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let (node, node_listener) = node::split::<()>();
+    let _node_task = node_listener.for_each_async(|_| ());
+
+    let (receiver, _) = node.network().connect(Transport::Tcp, addr).unwrap();
+    let (mut stream, _) = listener.accept().unwrap();
+    stream.set_read_timeout(Some(*TIMEOUT)).unwrap();
+
+    let mut sent_data = Vec::new();
+    for number in 0..u8::MAX {
+        let chunk = [number; CHUNK_SIZE];
+        let status = node.network().send(receiver, &chunk);
+        sent_data.extend_from_slice(&chunk);
+        match status {
+            SendStatus::Sent => continue,
+            SendStatus::Enqueued => break,
+            status => panic!("Unexpected status: {:?}", status),
+        }
+    }
+
+    // Once there is enqueued data, the following data is enqueued after it.
+    let chunk = [u8::MAX; CHUNK_SIZE];
+    assert_eq!(SendStatus::Enqueued, node.network().send(receiver, &chunk));
+    sent_data.extend_from_slice(&chunk);
+
+    let mut received_data = vec![0; sent_data.len()];
+    stream.read_exact(&mut received_data).unwrap();
+    assert!(sent_data == received_data);
+
+    node.stop();
+}
+
+#[cfg(feature = "tcp")]
+#[test]
+fn send_limit_drained() {
+    const CHUNK_SIZE: usize = 1024 * 64;
+
+    let listener = std::net::TcpListener::bind(LOCAL_ADDR).unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let (node, node_listener) = node::split();
+    node.signals().send_with_timer((), *TIMEOUT);
+
+    let (receiver, _) = node.network().connect(Transport::Tcp, addr).unwrap();
+    let (stream, _) = listener.accept().unwrap();
+
+    let limit = SendLimit { high_water: CHUNK_SIZE * 4, low_water: CHUNK_SIZE };
+    assert!(node.network().set_send_limit(receiver.resource_id(), Some(limit)));
+
+    // The peer does not read until the limit is exceeded.
+    let chunk = [42; CHUNK_SIZE];
+    let mut sent_size = 0;
+    loop {
+        match node.network().send(receiver, &chunk) {
+            SendStatus::Sent | SendStatus::Enqueued => sent_size += CHUNK_SIZE,
+            SendStatus::WouldExceedLimit => break,
+            status => panic!("Unexpected status: {:?}", status),
+        }
+    }
+    assert_eq!(SendStatus::WouldExceedLimit, node.network().send(receiver, &chunk));
+
+    // The stream is kept open until the end of the test to accept the following sends.
+    let mut reader_stream = stream.try_clone().unwrap();
+    let _reader_thread = NamespacedThread::spawn("test-reader", move || {
+        let mut received_data = vec![0; sent_size];
+        reader_stream.read_exact(&mut received_data).unwrap();
+    });
+
+    node_listener.for_each(move |event| match event {
+        NodeEvent::Signal(_) => panic!("{}", TIMEOUT_EVENT_RECV_ERR),
+        NodeEvent::Network(net_event) => match net_event {
+            NetEvent::Drained(endpoint) => {
+                assert_eq!(receiver, endpoint);
+                let status = node.network().send(receiver, &chunk);
+                assert!(matches!(status, SendStatus::Sent | SendStatus::Enqueued));
+                node.stop();
+            }
+            NetEvent::Connected(..) => unreachable!(),
+            NetEvent::ConnectionFailed(..) => unreachable!(),
+            NetEvent::Message(..) => unreachable!(),
+            NetEvent::Disconnected(..) => unreachable!(),
+            NetEvent::Reconnecting(_) | NetEvent::Reconnected(_) => unreachable!(),
+            NetEvent::Closed(..) => unreachable!(),
+            NetEvent::Error(..) => unreachable!(),
+        },
+    });
+}
+
+#[cfg_attr(feature = "tcp", test_case(Transport::FramedTcp))]
+#[cfg_attr(feature = "websocket", test_case(Transport::Ws))]
+#[cfg_attr(feature = "tls", test_case(Transport::FramedTls))]
+fn send_limit_drained_by_node(transport: Transport) {
+    const CHUNK_SIZE: usize = 1024 * 64;
+
+    // The receiver does not read while it is blocked processing the first message.
+    let (unblock_tx, unblock_rx) = crossbeam_channel::bounded(1);
+    let (receiver_node, receiver_listener) = node::split::<()>();
+    let (_, receiver_addr) = listen_local(receiver_node.network(), listen_config(transport));
+    let mut blocked = false;
+    let _receiver_task = receiver_listener.for_each_async(move |event| {
+        if let NodeEvent::Network(NetEvent::Message(..)) = event {
+            if !blocked {
+                blocked = true;
+                unblock_rx.recv().unwrap();
+            }
+        }
+    });
+
+    let (node, node_listener) = node::split();
+    node.signals().send_with_timer((), *TIMEOUT);
+
+    let config = connect_config(transport);
+    let (receiver, _) = node.network().connect_with(config, receiver_addr).unwrap();
+
+    let limit = SendLimit { high_water: CHUNK_SIZE * 4, low_water: CHUNK_SIZE };
+    assert!(node.network().set_send_limit(receiver.resource_id(), Some(limit)));
+
+    let chunk = [42; CHUNK_SIZE];
+    loop {
+        match node.network().send(receiver, &chunk) {
+            SendStatus::Sent | SendStatus::Enqueued => continue,
+            SendStatus::WouldExceedLimit => break,
+            status => panic!("Unexpected status: {:?}", status),
+        }
+    }
+    unblock_tx.send(()).unwrap();
+
+    node_listener.for_each(move |event| match event {
+        NodeEvent::Signal(_) => panic!("{}", TIMEOUT_EVENT_RECV_ERR),
+        NodeEvent::Network(net_event) => match net_event {
+            NetEvent::Drained(endpoint) => {
+                assert_eq!(receiver, endpoint);
+                node.stop();
+            }
+            NetEvent::Connected(..) => unreachable!(),
+            NetEvent::ConnectionFailed(..) => unreachable!(),
+            NetEvent::Message(..) => unreachable!(),
+            NetEvent::Disconnected(..) => unreachable!(),
+            NetEvent::Reconnecting(_) | NetEvent::Reconnected(_) => unreachable!(),
+            NetEvent::Closed(..) => unreachable!(),
+            NetEvent::Error(..) => unreachable!(),
+        },
+    });
+    receiver_node.stop();
+}
+
+#[cfg(all(feature = "tcp", unix))]
+#[test]
+fn tcp_reuse_port() {
+    let (node, _listener) = node::split::<()>();
+
+    let socket_options = TcpSocketOptions { reuse_port: Some(true), ..Default::default() };
+    let config = TransportListen::Tcp(TcpListenConfig { socket_options, ..Default::default() });
+    let (_, addr) = node.network().listen_with(config.clone(), LOCAL_ADDR).unwrap();
+    node.network().listen_with(config, addr).unwrap();
+
+    // Without the option, the address can not be shared.
+    let err = node.network().listen(Transport::Tcp, addr).unwrap_err();
+    assert_eq!(ErrorKind::AddrInUse, err.kind());
+}
+
+#[cfg_attr(feature = "tcp", test_case(Transport::Tcp))]
+#[cfg_attr(feature = "tcp", test_case(Transport::FramedTcp))]
+#[cfg_attr(feature = "udp", test_case(Transport::Udp))]
+#[cfg_attr(feature = "websocket", test_case(Transport::Ws))]
+fn connect_from(transport: Transport) {
+    // A free port is found by binding it and releasing it.
+    let local_addr = match transport {
+        Transport::Udp => std::net::UdpSocket::bind(LOCAL_ADDR).unwrap().local_addr().unwrap(),
+        _ => std::net::TcpListener::bind(LOCAL_ADDR).unwrap().local_addr().unwrap(),
+    };
+
+    let (node, listener) = node::split();
+    node.signals().send_with_timer((), *TIMEOUT);
+
+    let (_, server_addr) = node.network().listen(transport, LOCAL_ADDR).unwrap();
+    let (server, addr) = node.network().connect_from(transport, local_addr, server_addr).unwrap();
+    assert_eq!(local_addr, addr);
+    assert_eq!(SendStatus::Sent, node.network().send(server, MIN_MESSAGE));
+
+    listener.for_each(move |event| match event {
+        NodeEvent::Signal(_) => panic!("{}", TIMEOUT_EVENT_RECV_ERR),
+        NodeEvent::Network(net_event) => match net_event {
+            NetEvent::Connected(endpoint, _) => assert_eq!(local_addr, endpoint.addr()),
+            NetEvent::Message(endpoint, data) => {
+                assert_eq!(local_addr, endpoint.addr());
+                assert_eq!(MIN_MESSAGE, data);
+                node.stop();
+            }
+            NetEvent::ConnectionFailed(..) => unreachable!(),
+            NetEvent::Drained(_) => unreachable!(),
+            NetEvent::Reconnecting(_) | NetEvent::Reconnected(_) => unreachable!(),
+            NetEvent::Closed(..) => unreachable!(),
+            NetEvent::Error(..) => unreachable!(),
+            NetEvent::Disconnected(..) => (),
+        },
+    });
+}
+
+#[cfg(feature = "memory")]
+#[test]
+fn connect_from_not_ip_transport() {
+    let (node, _listener) = node::split::<()>();
+    node.network().listen(Transport::Memory, "connect-from-test").unwrap();
+
+    let local_addr = "127.0.0.1:0".parse().unwrap();
+    let err = node.network().connect_from(Transport::Memory, local_addr, "connect-from-test");
+    assert_eq!(ErrorKind::InvalidInput, err.unwrap_err().kind());
+}
+
+/// Host name only known by the proxy stand-in, that resolves it as the local host.
+#[cfg(any(feature = "tcp", feature = "websocket"))]
+const PROXIED_HOST: &str = "server.invalid";
+
+/// Proxy stand-in that tunnels one connection authenticated as `user:pass`.
+#[cfg(any(feature = "tcp", feature = "websocket"))]
+fn start_proxy(protocol: ProxyProtocol) -> (NamespacedThread<()>, SocketAddr) {
+    let listener = StdTcpListener::bind(LOCAL_ADDR).unwrap();
+    let proxy_addr = listener.local_addr().unwrap();
+    let thread = NamespacedThread::spawn("test-proxy", move || {
+        let (mut client, _) = listener.accept().unwrap();
+        let target_addr = match protocol {
+            ProxyProtocol::Socks5 => {
+                let mut buffer = [0; 3];
+                client.read_exact(&mut buffer).unwrap();
+                assert_eq!([5, 1, 2], buffer); // Only the user/pass method is offered
+                client.write_all(&[5, 2]).unwrap();
+
+                let mut auth = [0; 11];
+                client.read_exact(&mut auth).unwrap();
+                assert_eq!(b"\x01\x04user\x04pass", &auth);
+                client.write_all(&[1, 0]).unwrap();
+
+                let mut request = [0; 4];
+                client.read_exact(&mut request).unwrap();
+                assert_eq!([5, 1, 0], request[0..3]);
+                let host = match request[3] {
+                    1 => {
+                        let mut ip = [0; 4];
+                        client.read_exact(&mut ip).unwrap();
+                        std::net::Ipv4Addr::from(ip).to_string()
+                    }
+                    3 => {
+                        let mut len = [0; 1];
+                        client.read_exact(&mut len).unwrap();
+                        let mut host = vec![0; len[0] as usize];
+                        client.read_exact(&mut host).unwrap();
+                        String::from_utf8(host).unwrap()
+                    }
+                    _ => unreachable!(),
+                };
+                let mut port = [0; 2];
+                client.read_exact(&mut port).unwrap();
+                client.write_all(&[5, 0, 0, 1, 0, 0, 0, 0, 0, 0]).unwrap();
+                format!("{}:{}", host, u16::from_be_bytes(port))
+            }
+            ProxyProtocol::Http => {
+                let mut request = Vec::new();
+                while !request.ends_with(b"\r\n\r\n") {
+                    let mut byte = [0; 1];
+                    client.read_exact(&mut byte).unwrap();
+                    request.push(byte[0]);
+                }
+                let request = String::from_utf8(request).unwrap();
+                assert!(request.contains("Proxy-Authorization: Basic dXNlcjpwYXNz\r\n"));
+                client.write_all(b"HTTP/1.1 200 Connection established\r\n\r\n").unwrap();
+                let target = request.strip_prefix("CONNECT ").unwrap().split(' ').next().unwrap();
+                target.to_string()
+            }
+        };
+
+        let target_addr = target_addr.replace(PROXIED_HOST, "127.0.0.1");
+        relay(client, StdTcpStream::connect(target_addr).unwrap());
+    });
+    (thread, proxy_addr)
+}
+
+/// Forwards the data between two streams until one of them is closed.
+#[cfg(any(feature = "tcp", feature = "websocket"))]
+fn relay(mut client: StdTcpStream, mut target: StdTcpStream) {
+    let (mut client_reader, mut target_reader) =
+        (client.try_clone().unwrap(), target.try_clone().unwrap());
+    let _relay_thread = NamespacedThread::spawn("relay", move || {
+        std::io::copy(&mut target_reader, &mut client).ok();
+        client.shutdown(std::net::Shutdown::Both).ok();
+    });
+    std::io::copy(&mut client_reader, &mut target).ok();
+    target.shutdown(std::net::Shutdown::Both).ok();
+}
+
+#[cfg_attr(feature = "tcp", test_case(Transport::Tcp, ProxyProtocol::Socks5))]
+#[cfg_attr(feature = "tcp", test_case(Transport::Tcp, ProxyProtocol::Http))]
+#[cfg_attr(feature = "tcp", test_case(Transport::FramedTcp, ProxyProtocol::Socks5))]
+#[cfg_attr(feature = "tcp", test_case(Transport::FramedTcp, ProxyProtocol::Http))]
+#[cfg_attr(feature = "websocket", test_case(Transport::Ws, ProxyProtocol::Socks5))]
+#[cfg_attr(feature = "websocket", test_case(Transport::Ws, ProxyProtocol::Http))]
+fn connect_through_proxy(transport: Transport, protocol: ProxyProtocol) {
+    let (_proxy_thread, proxy_addr) = start_proxy(protocol);
+
+    let (node, listener) = node::split();
+    node.signals().send_with_timer((), *TIMEOUT);
+
+    let (_, server_addr) = node.network().listen(transport, LOCAL_ADDR).unwrap();
+    let credentials = ProxyCredentials { username: "user".into(), password: "pass".into() };
+    let proxy = Proxy { protocol, addr: proxy_addr.to_string(), credentials: Some(credentials) };
+    let options = ConnectOptions { proxy: Some(proxy), ..Default::default() };
+    let (server, _) =
+        node.network().connect_with_options(transport.into(), server_addr, options).unwrap();
+    assert_eq!(server_addr, server.addr());
+    assert_eq!(SendStatus::Sent, node.network().send(server, MIN_MESSAGE));
+
+    listener.for_each(move |event| match event {
+        NodeEvent::Signal(_) => panic!("{}", TIMEOUT_EVENT_RECV_ERR),
+        NodeEvent::Network(net_event) => match net_event {
+            NetEvent::Connected(..) => (),
+            NetEvent::Message(_, data) => {
+                assert_eq!(MIN_MESSAGE, data);
+                node.network().remove(server.resource_id());
+                node.stop();
+            }
+            NetEvent::ConnectionFailed(..) => unreachable!(),
+            NetEvent::Drained(_) => unreachable!(),
+            NetEvent::Reconnecting(_) | NetEvent::Reconnected(_) => unreachable!(),
+            NetEvent::Closed(..) => unreachable!(),
+            NetEvent::Error(..) => unreachable!(),
+            NetEvent::Disconnected(..) => (),
+        },
+    });
+}
+
+#[cfg_attr(feature = "tcp", test_case(ProxyProtocol::Socks5))]
+#[cfg_attr(feature = "tcp", test_case(ProxyProtocol::Http))]
+fn connect_through_proxy_host_name(protocol: ProxyProtocol) {
+    let (_proxy_thread, proxy_addr) = start_proxy(protocol);
+
+    let (node, listener) = node::split();
+    node.signals().send_with_timer((), *TIMEOUT);
+
+    // The host name can only be resolved by the proxy.
+    let (_, server_addr) = node.network().listen(Transport::FramedTcp, LOCAL_ADDR).unwrap();
+    let credentials = ProxyCredentials { username: "user".into(), password: "pass".into() };
+    let proxy = Proxy { protocol, addr: proxy_addr.to_string(), credentials: Some(credentials) };
+    let options = ConnectOptions { proxy: Some(proxy), ..Default::default() };
+    let host = format!("{}:{}", PROXIED_HOST, server_addr.port());
+    let (server, _) =
+        node.network().connect_with_options(Transport::FramedTcp.into(), host, options).unwrap();
+    assert_eq!(proxy_addr, server.addr());
+    assert_eq!(SendStatus::Sent, node.network().send(server, MIN_MESSAGE));
+
+    listener.for_each(move |event| match event {
+        NodeEvent::Signal(_) => panic!("{}", TIMEOUT_EVENT_RECV_ERR),
+        NodeEvent::Network(net_event) => match net_event {
+            NetEvent::Connected(..) => (),
+            NetEvent::Message(_, data) => {
+                assert_eq!(MIN_MESSAGE, data);
+                node.network().remove(server.resource_id());
+                node.stop();
+            }
+            NetEvent::ConnectionFailed(..) => unreachable!(),
+            NetEvent::Drained(_) => unreachable!(),
+            NetEvent::Reconnecting(_) | NetEvent::Reconnected(_) => unreachable!(),
+            NetEvent::Closed(..) => unreachable!(),
+            NetEvent::Error(..) => unreachable!(),
+            NetEvent::Disconnected(..) => (),
+        },
+    });
+}
+
+#[cfg(feature = "udp")]
+#[test]
+fn connect_through_proxy_not_tcp_transport() {
+    let (node, _listener) = node::split::<()>();
+    let (_, addr) = node.network().listen(Transport::Udp, LOCAL_ADDR).unwrap();
+
+    let proxy = "socks5://127.0.0.1:1080".parse().unwrap();
+    let options = ConnectOptions { proxy: Some(proxy), ..Default::default() };
+    let err = node.network().connect_with_options(Transport::Udp.into(), addr, options);
+    assert_eq!(ErrorKind::InvalidInput, err.unwrap_err().kind());
+}
+
+/// Load balancer stand-in that forwards one connection to `server_addr`
+/// preceded by a PROXY protocol `header`.
+#[cfg(any(feature = "tcp", feature = "websocket"))]
+fn start_load_balancer(
+    server_addr: SocketAddr,
+    header: Vec<u8>,
+) -> (NamespacedThread<()>, SocketAddr) {
+    let listener = StdTcpListener::bind(LOCAL_ADDR).unwrap();
+    let balancer_addr = listener.local_addr().unwrap();
+    let thread = NamespacedThread::spawn("test-load-balancer", move || {
+        let (client, _) = listener.accept().unwrap();
+        let mut server = StdTcpStream::connect(server_addr).unwrap();
+        server.write_all(&header).unwrap();
+        relay(client, server);
+    });
+    (thread, balancer_addr)
+}
+
+#[cfg_attr(feature = "tcp", test_case(Transport::Tcp, 1))]
+#[cfg_attr(feature = "tcp", test_case(Transport::FramedTcp, 1))]
+#[cfg_attr(feature = "websocket", test_case(Transport::Ws, 1))]
+#[cfg_attr(feature = "tcp", test_case(Transport::Tcp, 2))]
+#[cfg_attr(feature = "tcp", test_case(Transport::FramedTcp, 2))]
+#[cfg_attr(feature = "websocket", test_case(Transport::Ws, 2))]
+fn listen_proxy_protocol(transport: Transport, version: u8) {
+    let client_addr: SocketAddr = "192.0.2.1:5000".parse().unwrap();
+    let header = match version {
+        1 => b"PROXY TCP4 192.0.2.1 127.0.0.1 5000 80\r\n".to_vec(),
+        _ => {
+            let mut header = b"\r\n\r\n\0\r\nQUIT\n\x21\x11\x00\x0C".to_vec();
+            header.extend_from_slice(&[192, 0, 2, 1, 127, 0, 0, 1, 0x13, 0x88, 0, 80]);
+            header
+        }
+    };
+
+    let (node, listener) = node::split();
+    node.signals().send_with_timer((), *TIMEOUT);
+
+    let options = ListenOptions { proxy_protocol: true, ..Default::default() };
+    let (_, server_addr) =
+        node.network().listen_with_options(transport.into(), LOCAL_ADDR, options).unwrap();
+    let (_balancer_thread, balancer_addr) = start_load_balancer(server_addr, header);
+
+    let (client, _) = node.network().connect(transport, balancer_addr).unwrap();
+    assert_eq!(SendStatus::Sent, node.network().send(client, MIN_MESSAGE));
+
+    let mut connected = false;
+    listener.for_each(move |event| match event {
+        NodeEvent::Signal(_) => panic!("{}", TIMEOUT_EVENT_RECV_ERR),
+        NodeEvent::Network(net_event) => match net_event {
+            NetEvent::Connected(endpoint, _) => {
+                assert_eq!(client_addr, endpoint.addr());
+                connected = true;
+            }
+            NetEvent::Message(endpoint, data) => {
+                assert!(connected);
+                assert_eq!(client_addr, endpoint.addr());
+                assert_eq!(MIN_MESSAGE, data);
+                node.network().remove(client.resource_id());
+                node.stop();
+            }
+            NetEvent::ConnectionFailed(..) => unreachable!(),
+            NetEvent::Drained(_) => unreachable!(),
+            NetEvent::Reconnecting(_) | NetEvent::Reconnected(_) => unreachable!(),
+            NetEvent::Closed(..) => unreachable!(),
+            NetEvent::Error(..) => unreachable!(),
+            NetEvent::Disconnected(..) => (),
+        },
+    });
+}
+
+#[cfg(feature = "tcp")]
+#[test]
+fn listen_proxy_protocol_malformed_header() {
+    let (node, listener) = node::split();
+    node.signals().send_with_timer((), *TIMEOUT);
+
+    let options = ListenOptions { proxy_protocol: true, ..Default::default() };
+    let (_, server_addr) =
+        node.network().listen_with_options(Transport::Tcp.into(), LOCAL_ADDR, options).unwrap();
+
+    // The malformed connection is rejected without any event.
+    let mut malformed = StdTcpStream::connect(server_addr).unwrap();
+    malformed.write_all(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+    let mut buffer = [0; 1];
+    assert!(matches!(malformed.read(&mut buffer), Ok(0) | Err(_))); // Closed by the listener
+
+    let mut valid = StdTcpStream::connect(server_addr).unwrap();
+    valid.write_all(b"PROXY TCP6 2001:db8::1 ::1 5000 80\r\n").unwrap();
+    valid.write_all(MIN_MESSAGE).unwrap();
+
+    let client_addr: SocketAddr = "[2001:db8::1]:5000".parse().unwrap();
+    listener.for_each(move |event| match event {
+        NodeEvent::Signal(_) => panic!("{}", TIMEOUT_EVENT_RECV_ERR),
+        NodeEvent::Network(net_event) => match net_event {
+            NetEvent::Connected(endpoint, _) => assert_eq!(client_addr, endpoint.addr()),
+            NetEvent::Message(endpoint, data) => {
+                assert_eq!(client_addr, endpoint.addr());
+                assert_eq!(MIN_MESSAGE, data);
+                node.stop();
+            }
+            NetEvent::ConnectionFailed(..) => unreachable!(),
+            NetEvent::Drained(_) => unreachable!(),
+            NetEvent::Reconnecting(_) | NetEvent::Reconnected(_) => unreachable!(),
+            NetEvent::Closed(..) => unreachable!(),
+            NetEvent::Error(..) => unreachable!(),
+            NetEvent::Disconnected(..) => unreachable!(),
+        },
+    });
+}
+
+#[cfg(feature = "tcp")]
+#[test]
+fn listen_proxy_protocol_header_timeout() {
+    let (node, listener) = node::split();
+    node.signals().send_with_timer((), *TIMEOUT);
+
+    let options =
+        ListenOptions { proxy_protocol: true, max_connections: Some(1), ..Default::default() };
+    let (_, server_addr) =
+        node.network().listen_with_options(Transport::Tcp.into(), LOCAL_ADDR, options).unwrap();
+
+    // The silent connection takes the only connection of the listener until its header times out.
+    let start = Instant::now();
+    let mut silent = StdTcpStream::connect(server_addr).unwrap();
+    std::thread::sleep(Duration::from_millis(100));
+    let mut valid = StdTcpStream::connect(server_addr).unwrap();
+    valid.write_all(b"PROXY TCP6 2001:db8::1 ::1 5000 80\r\n").unwrap();
+
+    listener.for_each(move |event| match event {
+        NodeEvent::Signal(_) => panic!("{}", TIMEOUT_EVENT_RECV_ERR),
+        NodeEvent::Network(net_event) => match net_event {
+            NetEvent::Connected(..) => {
+                assert!(start.elapsed() >= Duration::from_secs(5));
+                node.stop();
+            }
+            NetEvent::Message(..) => unreachable!(),
+            NetEvent::ConnectionFailed(..) => unreachable!(),
+            NetEvent::Drained(_) => unreachable!(),
+            NetEvent::Reconnecting(_) | NetEvent::Reconnected(_) => unreachable!(),
+            NetEvent::Closed(..) => unreachable!(),
+            NetEvent::Error(..) => unreachable!(),
+            NetEvent::Disconnected(..) => unreachable!(),
+        },
+    });
+
+    let mut buffer = [0; 1];
+    assert!(matches!(silent.read(&mut buffer), Ok(0) | Err(_))); // Closed by the listener
+}
+
+#[cfg(feature = "udp")]
+#[test]
+fn listen_proxy_protocol_not_supported() {
+    let (node, _listener) = node::split::<()>();
+    let options = ListenOptions { proxy_protocol: true, ..Default::default() };
+    let err = node.network().listen_with_options(Transport::Udp.into(), LOCAL_ADDR, options);
+    assert_eq!(ErrorKind::InvalidInput, err.unwrap_err().kind());
+}
+
+#[cfg(feature = "tcp")]
+#[test]
+fn listen_max_connections() {
+    enum Signal {
+        Timeout,
+        NoMoreAccepted,
+    }
+
+    let (node, listener) = node::split();
+    node.signals().send_with_timer(Signal::Timeout, *TIMEOUT);
+
+    let options = ListenOptions { max_connections: Some(1), ..Default::default() };
+    let (listener_id, server_addr) =
+        node.network().listen_with_options(Transport::Tcp.into(), LOCAL_ADDR, options).unwrap();
+    node.network().connect(Transport::Tcp, server_addr).unwrap();
+
+    let mut accepted = Vec::new();
+    listener.for_each(move |event| match event {
+        NodeEvent::Signal(Signal::Timeout) => panic!("{}", TIMEOUT_EVENT_RECV_ERR),
+        NodeEvent::Signal(Signal::NoMoreAccepted) => {
+            // Closing the first connection allows to accept the second one.
+            assert_eq!(1, accepted.len());
+            node.network().remove(accepted[0]);
+        }
+        NodeEvent::Network(net_event) => match net_event {
+            NetEvent::Connected(endpoint, id) => {
+                assert_eq!(listener_id, id);
+                accepted.push(endpoint.resource_id());
+                match accepted.len() {
+                    1 => {
+                        node.network().connect(Transport::Tcp, server_addr).unwrap();
+                        let delay = Duration::from_millis(100);
+                        node.signals().send_with_timer(Signal::NoMoreAccepted, delay);
+                    }
+                    _ => node.stop(),
+                }
+            }
+            NetEvent::Disconnected(..) => (),
+            NetEvent::Message(..) => unreachable!(),
+            NetEvent::ConnectionFailed(..) => unreachable!(),
+            NetEvent::Drained(_) => unreachable!(),
+            NetEvent::Reconnecting(_) | NetEvent::Reconnected(_) => unreachable!(),
+            NetEvent::Closed(..) => unreachable!(),
+            NetEvent::Error(..) => unreachable!(),
+        },
+    });
+}
+
+#[cfg_attr(feature = "tcp", test_case(Transport::Tcp))]
+#[cfg_attr(feature = "tcp", test_case(Transport::FramedTcp))]
+#[cfg_attr(feature = "memory", test_case(Transport::Memory))]
+fn listen_max_connections_burst(transport: Transport) {
+    const MAX_CONNECTIONS: usize = 2;
+    const CONNECTIONS: usize = 4;
+
+    enum Signal {
+        Timeout,
+        NoMoreAccepted,
+    }
+
+    let (node, listener) = node::split();
+    node.signals().send_with_timer(Signal::Timeout, *TIMEOUT);
+
+    let listen_addr = match transport {
+        #[cfg(feature = "memory")]
+        Transport::Memory => memory::listener_name(),
+        _ => LOCAL_ADDR.to_string(),
+    };
+    let options = ListenOptions { max_connections: Some(MAX_CONNECTIONS), ..Default::default() };
+    let (listener_id, server_addr) =
+        node.network().listen_with_options(transport.into(), listen_addr.clone(), options).unwrap();
+    let connect_addr = match transport {
+        #[cfg(feature = "memory")]
+        Transport::Memory => listen_addr,
+        _ => server_addr.to_string(),
+    };
+    for _ in 0..CONNECTIONS {
+        node.network().connect(transport, connect_addr.clone()).unwrap();
+    }
+
+    let delay = Duration::from_millis(100);
+    let mut accepted = Vec::new();
+    let mut removed = 0;
+    listener.for_each(move |event| match event {
+        NodeEvent::Signal(Signal::Timeout) => panic!("{}", TIMEOUT_EVENT_RECV_ERR),
+        NodeEvent::Signal(Signal::NoMoreAccepted) => {
+            // Closing a connection allows to accept only one more.
+            assert_eq!(MAX_CONNECTIONS + removed, accepted.len());
+            match removed < CONNECTIONS - MAX_CONNECTIONS {
+                true => {
+                    node.network().remove(accepted[removed]);
+                    removed += 1;
+                }
+                false => node.stop(),
+            }
+        }
+        NodeEvent::Network(net_event) => match net_event {
+            NetEvent::Connected(endpoint, id) => {
+                assert_eq!(listener_id, id);
+                accepted.push(endpoint.resource_id());
+                assert!(accepted.len() <= MAX_CONNECTIONS + removed);
+                if accepted.len() == MAX_CONNECTIONS + removed {
+                    node.signals().send_with_timer(Signal::NoMoreAccepted, delay);
+                }
+            }
+            NetEvent::Disconnected(..) => (),
+            NetEvent::Message(..) => unreachable!(),
+            NetEvent::ConnectionFailed(..) => unreachable!(),
+            NetEvent::Drained(_) => unreachable!(),
+            NetEvent::Reconnecting(_) | NetEvent::Reconnected(_) => unreachable!(),
+            NetEvent::Closed(..) => unreachable!(),
+            NetEvent::Error(..) => unreachable!(),
+        },
+    });
+}
+
+#[cfg_attr(feature = "tcp", test_case(Transport::Tcp))]
+#[cfg_attr(feature = "websocket", test_case(Transport::Ws))]
+#[cfg_attr(feature = "memory", test_case(Transport::Memory))]
+fn pause_accept(transport: Transport) {
+    enum Signal {
+        Timeout,
+        Resume,
+    }
+
+    let (node, listener) = node::split();
+    node.signals().send_with_timer(Signal::Timeout, *TIMEOUT);
+
+    let listen_addr = match transport {
+        #[cfg(feature = "memory")]
+        Transport::Memory => memory::listener_name(),
+        _ => LOCAL_ADDR.to_string(),
+    };
+    let (listener_id, server_addr) = node.network().listen(transport, listen_addr.clone()).unwrap();
+    let connect_addr = match transport {
+        #[cfg(feature = "memory")]
+        Transport::Memory => listen_addr,
+        _ => server_addr.to_string(),
+    };
+    assert!(node.network().pause_accept(listener_id));
+
+    node.network().connect_async(transport, connect_addr).unwrap();
+    node.signals().send_with_timer(Signal::Resume, Duration::from_millis(100));
+
+    let mut resumed = false;
+    listener.for_each(move |event| match event {
+        NodeEvent::Signal(Signal::Timeout) => panic!("{}", TIMEOUT_EVENT_RECV_ERR),
+        NodeEvent::Signal(Signal::Resume) => {
+            resumed = true;
+            assert!(node.network().resume_accept(listener_id));
+        }
+        NodeEvent::Network(net_event) => match net_event {
+            NetEvent::Connected(_, id) if id == listener_id => {
+                assert!(resumed);
+                node.stop();
+            }
+            NetEvent::Connected(..) => (), // The connecting side
+            NetEvent::Disconnected(..) => (),
+            NetEvent::Message(..) => unreachable!(),
+            NetEvent::ConnectionFailed(..) => unreachable!(),
+            NetEvent::Drained(_) => unreachable!(),
+            NetEvent::Reconnecting(_) | NetEvent::Reconnected(_) => unreachable!(),
+            NetEvent::Closed(..) => unreachable!(),
+            NetEvent::Error(..) => unreachable!(),
+        },
+    });
+}
+
+#[cfg_attr(feature = "tcp", test_case(Transport::Tcp))]
+#[cfg_attr(feature = "tcp", test_case(Transport::FramedTcp))]
+#[cfg_attr(feature = "udp", test_case(Transport::Udp))]
+#[cfg_attr(feature = "websocket", test_case(Transport::Ws))]
+fn listen_accept_filter(transport: Transport) {
+    let (node, listener) = node::split();
+    node.signals().send_with_timer((), *TIMEOUT);
+
+    // Only the first peer is rejected.
+    let rejected = Mutex::new(None);
+    let filter =
+        AcceptFilter::new(move |addr| *rejected.lock().unwrap().get_or_insert(addr) != addr);
+    let options = ListenOptions { accept_filter: Some(filter), ..Default::default() };
+    let (listener_id, server_addr) =
+        node.network().listen_with_options(transport.into(), LOCAL_ADDR, options).unwrap();
+
+    // The rejected websocket connection is closed during its handshake.
+    if let Ok((rejected, _)) = node.network().connect(transport, server_addr) {
+        node.network().send(rejected, b"rejected");
+    }
+    let (accepted, accepted_addr) = node.network().connect(transport, server_addr).unwrap();
+    assert_eq!(SendStatus::Sent, node.network().send(accepted, b"accepted"));
+
+    listener.for_each(move |event| match event {
+        NodeEvent::Signal(_) => panic!("{}", TIMEOUT_EVENT_RECV_ERR),
+        NodeEvent::Network(net_event) => match net_event {
+            NetEvent::Connected(endpoint, id) => {
+                assert_eq!(listener_id, id);
+                assert_eq!(accepted_addr, endpoint.addr());
+            }
+            NetEvent::Message(endpoint, data) => {
+                assert_eq!(accepted_addr, endpoint.addr());
+                assert_eq!(b"accepted", data);
+                node.stop();
+            }
+            NetEvent::Disconnected(endpoint, _) => assert_ne!(accepted, endpoint),
+            NetEvent::ConnectionFailed(..) => unreachable!(),
+            NetEvent::Drained(_) => unreachable!(),
+            NetEvent::Reconnecting(_) | NetEvent::Reconnected(_) => unreachable!(),
+            NetEvent::Closed(..) => unreachable!(),
+            NetEvent::Error(..) => unreachable!(),
+        },
+    });
+}
+
+#[cfg_attr(feature = "tcp", test_case(Transport::Tcp))]
+#[cfg_attr(feature = "tcp", test_case(Transport::FramedTcp))]
+#[cfg_attr(feature = "websocket", test_case(Transport::Ws))]
+fn listen_idle_timeout(transport: Transport) {
+    const IDLE_TIMEOUT: Duration = Duration::from_millis(200);
+
+    let (node, listener) = node::split();
+    node.signals().send_with_timer((), *TIMEOUT);
+
+    let options = ListenOptions { idle_timeout: Some(IDLE_TIMEOUT), ..Default::default() };
+    let (_, server_addr) =
+        node.network().listen_with_options(transport.into(), LOCAL_ADDR, options).unwrap();
+    let (server, _) = node.network().connect(transport, server_addr).unwrap();
+    let sent = Instant::now();
+    assert_eq!(SendStatus::Sent, node.network().send(server, MIN_MESSAGE));
+
+    listener.for_each(move |event| match event {
+        NodeEvent::Signal(_) => panic!("{}", TIMEOUT_EVENT_RECV_ERR),
+        NodeEvent::Network(net_event) => match net_event {
+            NetEvent::Connected(..) => (),
+            NetEvent::Message(..) => (),
+            NetEvent::Disconnected(endpoint, _) => {
+                // The client is not disconnected by itself.
+                if endpoint != server {
+                    assert!(sent.elapsed() >= IDLE_TIMEOUT);
+                    node.stop();
+                }
+            }
+            NetEvent::ConnectionFailed(..) => unreachable!(),
+            NetEvent::Drained(_) => unreachable!(),
+            NetEvent::Reconnecting(_) | NetEvent::Reconnected(_) => unreachable!(),
+            NetEvent::Closed(..) => unreachable!(),
+            NetEvent::Error(..) => unreachable!(),
+        },
+    });
+}
+
+#[cfg_attr(feature = "tcp", test_case(Transport::Tcp))]
+#[cfg_attr(feature = "tcp", test_case(Transport::FramedTcp))]
+#[cfg_attr(feature = "websocket", test_case(Transport::Ws))]
+fn connect_idle_timeout(transport: Transport) {
+    const IDLE_TIMEOUT: Duration = Duration::from_millis(200);
+    const SEND_DELAY: Duration = Duration::from_millis(150);
+
+    enum Signal {
+        Timeout,
+        Send,
+    }
+
+    let (node, listener) = node::split();
+    node.signals().send_with_timer(Signal::Timeout, *TIMEOUT);
+
+    let (_, server_addr) = node.network().listen(transport, LOCAL_ADDR).unwrap();
+    let options = ConnectOptions { idle_timeout: Some(IDLE_TIMEOUT), ..Default::default() };
+    let start = Instant::now();
+    let (server, _) =
+        node.network().connect_with_options(transport.into(), server_addr, options).unwrap();
+
+    // Receiving a message restarts the count of the idle timeout.
+    node.signals().send_with_timer(Signal::Send, SEND_DELAY);
+
+    let mut client = None;
+    listener.for_each(move |event| match event {
+        NodeEvent::Signal(Signal::Timeout) => panic!("{}", TIMEOUT_EVENT_RECV_ERR),
+        NodeEvent::Signal(Signal::Send) => {
+            assert_eq!(SendStatus::Sent, node.network().send(client.unwrap(), MIN_MESSAGE));
+        }
+        NodeEvent::Network(net_event) => match net_event {
+            NetEvent::Connected(endpoint, _) => client = Some(endpoint),
+            NetEvent::Message(endpoint, _) => assert_eq!(server, endpoint),
+            NetEvent::Disconnected(endpoint, reason) => {
+                assert_eq!(server, endpoint);
+                assert_eq!(DisconnectReason::Timeout, reason);
+                assert!(start.elapsed() >= SEND_DELAY + IDLE_TIMEOUT);
+                node.stop();
+            }
+            NetEvent::ConnectionFailed(..) => unreachable!(),
+            NetEvent::Drained(_) => unreachable!(),
+            NetEvent::Reconnecting(_) | NetEvent::Reconnected(_) => unreachable!(),
+            NetEvent::Closed(..) => unreachable!(),
+            NetEvent::Error(..) => unreachable!(),
+        },
+    });
+}
+
+#[cfg_attr(feature = "tcp", test_case(Transport::Tcp))]
+#[cfg_attr(feature = "tcp", test_case(Transport::FramedTcp))]
+#[cfg_attr(feature = "websocket", test_case(Transport::Ws))]
+fn connect_reconnect(transport: Transport) {
+    let (node, listener) = node::split();
+    node.signals().send_with_timer((), *TIMEOUT);
+
+    let (listener_id, server_addr) = node.network().listen(transport, LOCAL_ADDR).unwrap();
+    let reconnect = Reconnect { backoff: Duration::from_millis(10), ..Default::default() };
+    let options = ConnectOptions { reconnect: Some(reconnect), ..Default::default() };
+    let (server, _) =
+        node.network().connect_with_options(transport.into(), server_addr, options).unwrap();
+
+    let mut accepted = 0;
+    let mut reconnecting = false;
+    listener.for_each(move |event| match event {
+        NodeEvent::Signal(_) => panic!("{}", TIMEOUT_EVENT_RECV_ERR),
+        NodeEvent::Network(net_event) => match net_event {
+            NetEvent::Connected(client, id) => {
+                assert_eq!(listener_id, id);
+                accepted += 1;
+                if accepted == 1 {
+                    // The first connection is lost by the client.
+                    assert!(node.network().remove(client.resource_id()));
+                }
+            }
+            NetEvent::Reconnecting(endpoint) => {
+                assert_eq!(server, endpoint);
+                assert_eq!(SendStatus::ResourceNotAvailable, node.network().send(server, &[]));
+                reconnecting = true;
+            }
+            NetEvent::Reconnected(endpoint) => {
+                assert_eq!(server, endpoint);
+                assert!(reconnecting);
+                assert_eq!(SendStatus::Sent, node.network().send(server, SMALL_MESSAGE.as_bytes()));
+            }
+            NetEvent::Message(endpoint, data) => {
+                assert_ne!(server, endpoint);
+                assert_eq!(2, accepted);
+                assert_eq!(SMALL_MESSAGE.as_bytes(), data);
+                node.stop();
+            }
+            NetEvent::Disconnected(..) => unreachable!(),
+            NetEvent::ConnectionFailed(..) => unreachable!(),
+            NetEvent::Drained(_) => unreachable!(),
+            NetEvent::Closed(..) => unreachable!(),
+            NetEvent::Error(..) => unreachable!(),
+        },
+    });
+}
+
+#[cfg(feature = "tcp")]
+#[test]
+fn connect_reconnect_max_attempts() {
+    const MAX_ATTEMPTS: usize = 3;
+    const BACKOFF: Duration = Duration::from_millis(20);
+
+    let (node, listener) = node::split();
+    node.signals().send_with_timer((), *TIMEOUT);
+
+    let (listener_id, server_addr) = node.network().listen(Transport::Tcp, LOCAL_ADDR).unwrap();
+    let reconnect = Reconnect {
+        backoff: BACKOFF,
+        jitter: 0.0,
+        max_attempts: Some(MAX_ATTEMPTS),
+        ..Default::default()
+    };
+    let options = ConnectOptions { reconnect: Some(reconnect), ..Default::default() };
+    let (server, _) =
+        node.network().connect_with_options(Transport::Tcp.into(), server_addr, options).unwrap();
+
+    let mut lost = None;
+    listener.for_each(move |event| match event {
+        NodeEvent::Signal(_) => panic!("{}", TIMEOUT_EVENT_RECV_ERR),
+        NodeEvent::Network(net_event) => match net_event {
+            NetEvent::Connected(client, _) => {
+                // The server is gone, so all the attempts fail.
+                assert!(node.network().remove(listener_id));
+                assert!(node.network().remove(client.resource_id()));
+            }
+            NetEvent::Reconnecting(endpoint) => {
+                assert_eq!(server, endpoint);
+                assert!(lost.is_none());
+                lost = Some(Instant::now());
+            }
+            NetEvent::Disconnected(endpoint, _) => {
+                assert_eq!(server, endpoint);
+                // Waiting times of 20, 40 and 80 ms.
+                let backoff = BACKOFF * ((1 << MAX_ATTEMPTS) - 1);
+                assert!(lost.unwrap().elapsed() >= backoff);
+                assert_eq!(SendStatus::ResourceNotFound, node.network().send(server, &[]));
+                node.stop();
+            }
+            NetEvent::Reconnected(_) => unreachable!(),
+            NetEvent::Message(..) => unreachable!(),
+            NetEvent::ConnectionFailed(..) => unreachable!(),
+            NetEvent::Drained(_) => unreachable!(),
+            NetEvent::Closed(..) => unreachable!(),
+            NetEvent::Error(..) => unreachable!(),
+        },
+    });
+}
+
+#[cfg(feature = "tcp")]
+#[test]
+fn connect_reconnect_timeout() {
+    const CONNECT_TIMEOUT: Duration = Duration::from_millis(200);
+
+    let (node, listener) = node::split();
+    node.signals().send_with_timer((), *TIMEOUT);
+
+    let config = TransportListen::Tcp(TcpListenConfig { backlog: Some(1), ..Default::default() });
+    let (listener_id, server_addr) = node.network().listen_with(config, LOCAL_ADDR).unwrap();
+    let reconnect = Reconnect {
+        backoff: Duration::from_millis(10),
+        jitter: 0.0,
+        max_attempts: Some(1),
+        ..Default::default()
+    };
+    let options = ConnectOptions {
+        timeout: Some(CONNECT_TIMEOUT),
+        reconnect: Some(reconnect),
+        ..Default::default()
+    };
+    let (server, _) =
+        node.network().connect_with_options(Transport::Tcp.into(), server_addr, options).unwrap();
+
+    let mut queued = Vec::new();
+    let mut lost = None;
+    listener.for_each(move |event| match event {
+        NodeEvent::Signal(_) => panic!("{}", TIMEOUT_EVENT_RECV_ERR),
+        NodeEvent::Network(net_event) => match net_event {
+            NetEvent::Connected(client, _) => {
+                // Once the accept queue of the server is full, the new connections never finish.
+                assert!(node.network().pause_accept(listener_id));
+                while let Ok(stream) = StdTcpStream::connect_timeout(&server_addr, CONNECT_TIMEOUT)
+                {
+                    queued.push(stream);
+                }
+                assert!(node.network().remove(client.resource_id()));
+            }
+            NetEvent::Reconnecting(endpoint) => {
+                assert_eq!(server, endpoint);
+                lost = Some(Instant::now());
+            }
+            NetEvent::Disconnected(endpoint, reason) => {
+                assert_eq!(server, endpoint);
+                assert_eq!(DisconnectReason::Timeout, reason);
+                assert!(lost.unwrap().elapsed() >= CONNECT_TIMEOUT);
+                node.stop();
+            }
+            NetEvent::Reconnected(_) => unreachable!(),
+            NetEvent::Message(..) => unreachable!(),
+            NetEvent::ConnectionFailed(..) => unreachable!(),
+            NetEvent::Drained(_) => unreachable!(),
+            NetEvent::Closed(..) => unreachable!(),
+            NetEvent::Error(..) => unreachable!(),
+        },
+    });
+}
+
+#[cfg_attr(feature = "tcp", test_case(Transport::Tcp))]
+#[cfg_attr(feature = "tcp", test_case(Transport::FramedTcp))]
+#[cfg_attr(feature = "websocket", test_case(Transport::Ws))]
+#[cfg_attr(feature = "tls", test_case(Transport::FramedTls))]
+#[cfg_attr(feature = "quic", test_case(Transport::Quic))]
+#[cfg_attr(feature = "memory", test_case(Transport::Memory))]
+fn close_gracefully(transport: Transport) {
+    let (node, listener) = node::split();
+    node.signals().send_with_timer((), *TIMEOUT);
+
+    let (_, server_addr) = listen_local(node.network(), listen_config(transport));
+    let (server, _) = node.network().connect_with(connect_config(transport), server_addr).unwrap();
+
+    // The data enqueued before closing is delivered.
+    let message = vec![42; BIG_MESSAGE_SIZE];
+    assert_ne!(SendStatus::ResourceNotFound, node.network().send(server, &message));
+    assert!(node.network().close_gracefully(server, *TIMEOUT));
+    // The close could have already finished, removing the resource.
+    assert_ne!(SendStatus::Sent, node.network().send(server, &message));
+
+    let mut received = 0;
+    let mut disconnected = false;
+    listener.for_each(move |event| match event {
+        NodeEvent::Signal(_) => panic!("{}", TIMEOUT_EVENT_RECV_ERR),
+        NodeEvent::Network(net_event) => match net_event {
+            NetEvent::Connected(..) => (),
+            NetEvent::Message(endpoint, data) => {
+                assert_ne!(server, endpoint);
+                received += data.len();
+            }
+            NetEvent::Disconnected(endpoint, _) => {
+                assert_ne!(server, endpoint);
+                assert_eq!(BIG_MESSAGE_SIZE, received);
+                disconnected = true;
+            }
+            NetEvent::Closed(endpoint, completed) => {
+                assert_eq!(server, endpoint);
+                assert!(completed);
+                assert!(disconnected || !transport.is_connection_oriented());
+                assert_eq!(BIG_MESSAGE_SIZE, received);
+                assert!(!node.network().remove(server.resource_id()));
+                node.stop();
+            }
+            NetEvent::ConnectionFailed(..) => unreachable!(),
+            NetEvent::Drained(_) => unreachable!(),
+            NetEvent::Reconnecting(_) | NetEvent::Reconnected(_) => unreachable!(),
+            NetEvent::Error(..) => unreachable!(),
+        },
+    });
+}
+
+#[cfg_attr(feature = "tcp", test_case(Transport::Tcp, DisconnectReason::Closed))]
+#[cfg_attr(feature = "tcp", test_case(Transport::FramedTcp, DisconnectReason::Closed))]
+#[cfg_attr(
+    feature = "websocket",
+    test_case(Transport::Ws, DisconnectReason::WebSocketClose { code: 4000, reason: "Bye".into() })
+)]
+fn disconnect_reason(transport: Transport, expected_reason: DisconnectReason) {
+    let (node, listener) = node::split();
+    node.signals().send_with_timer((), *TIMEOUT);
+
+    let (_, server_addr) = node.network().listen(transport, LOCAL_ADDR).unwrap();
+    let (server, _) = node.network().connect(transport, server_addr).unwrap();
+
+    let reason = CloseReason { code: 4000, reason: "Bye".into() };
+    assert!(node.network().close_gracefully_with_reason(server, *TIMEOUT, reason));
+
+    let mut disconnected = false;
+    listener.for_each(move |event| match event {
+        NodeEvent::Signal(_) => panic!("{}", TIMEOUT_EVENT_RECV_ERR),
+        NodeEvent::Network(net_event) => match net_event {
+            NetEvent::Connected(..) => (),
+            NetEvent::Disconnected(endpoint, reason) => {
+                assert_ne!(server, endpoint);
+                assert_eq!(expected_reason, reason);
+                disconnected = true;
+            }
+            NetEvent::Closed(endpoint, _) => {
+                assert_eq!(server, endpoint);
+                assert!(disconnected);
+                node.stop();
+            }
+            _ => unreachable!(),
+        },
+    });
+}
+
+#[cfg(feature = "tcp")]
+#[test]
+fn close_gracefully_timeout() {
+    const CLOSE_TIMEOUT: Duration = Duration::from_millis(100);
+
+    // The peer keeps the connection open after receiving the FIN.
+    let peer = StdTcpListener::bind(LOCAL_ADDR).unwrap();
+    let peer_addr = peer.local_addr().unwrap();
+
+    let (node, listener) = node::split();
+    node.signals().send_with_timer((), *TIMEOUT);
+
+    let (server, _) = node.network().connect(Transport::Tcp, peer_addr).unwrap();
+    let _peer_stream = peer.accept().unwrap();
+
+    let start = Instant::now();
+    assert!(node.network().close_gracefully(server, CLOSE_TIMEOUT));
+
+    listener.for_each(move |event| match event {
+        NodeEvent::Signal(_) => panic!("{}", TIMEOUT_EVENT_RECV_ERR),
+        NodeEvent::Network(net_event) => match net_event {
+            NetEvent::Closed(endpoint, completed) => {
+                assert_eq!(server, endpoint);
+                assert!(!completed);
+                assert!(start.elapsed() >= CLOSE_TIMEOUT);
+                node.stop();
+            }
+            _ => unreachable!(),
+        },
+    });
+}
+
+#[cfg(feature = "tcp")]
+#[test]
+fn node_shutdown() {
+    let (node, listener) = node::split();
+    node.signals().send_with_timer((), *TIMEOUT);
+
+    let (listener_id, server_addr) =
+        node.network().listen(Transport::FramedTcp, LOCAL_ADDR).unwrap();
+    let (server, _) = node.network().connect(Transport::FramedTcp, server_addr).unwrap();
+
+    let handler = node.clone();
+    let mut closed = Vec::new();
+    listener.for_each(|event| match event {
+        NodeEvent::Signal(_) => panic!("{}", TIMEOUT_EVENT_RECV_ERR),
+        NodeEvent::Network(net_event) => match net_event {
+            NetEvent::Connected(client, _) => {
+                assert_eq!(SendStatus::Sent, node.network().send(server, MIN_MESSAGE));
+                assert_eq!(SendStatus::Sent, node.network().send(client, MIN_MESSAGE));
+                // A timeout too long to be represented never expires.
+                node.shutdown(Duration::MAX);
+            }
+            NetEvent::Message(_, data) => assert_eq!(MIN_MESSAGE, data),
+            NetEvent::Closed(endpoint, completed) => {
+                assert!(completed);
+                closed.push(endpoint);
+            }
+            NetEvent::Disconnected(..) => unreachable!(),
+            NetEvent::ConnectionFailed(..) => unreachable!(),
+            NetEvent::Drained(_) => unreachable!(),
+            NetEvent::Reconnecting(_) | NetEvent::Reconnected(_) => unreachable!(),
+            NetEvent::Error(..) => unreachable!(),
+        },
+    });
+
+    // Both sides of the connection are closed and the node is stopped.
+    assert_eq!(2, closed.len());
+    assert!(closed.contains(&server));
+    assert!(!handler.is_running());
+    assert!(!handler.network().remove(listener_id));
+}
+
+#[cfg(feature = "tcp")]
+const HEARTBEAT: Heartbeat = Heartbeat { interval: Duration::from_millis(50), max_missed: 3 };
+
+#[cfg(feature = "tcp")]
+#[test]
+fn framed_tcp_heartbeat_lost_peer() {
+    // The peer keeps the connection open without answering the heartbeats.
+    let peer = StdTcpListener::bind(LOCAL_ADDR).unwrap();
+    let peer_addr = peer.local_addr().unwrap();
+
+    let (node, listener) = node::split();
+    node.signals().send_with_timer((), *TIMEOUT);
+
+    let config = FramedTcpConnectConfig { heartbeat: Some(HEARTBEAT), ..Default::default() };
+    let start = Instant::now();
+    let connect = TransportConnect::FramedTcp(config);
+    let (server, _) = node.network().connect_with(connect, peer_addr).unwrap();
+    let _peer_stream = peer.accept().unwrap();
+
+    listener.for_each(move |event| match event {
+        NodeEvent::Signal(_) => panic!("{}", TIMEOUT_EVENT_RECV_ERR),
+        NodeEvent::Network(net_event) => match net_event {
+            NetEvent::Disconnected(endpoint, _) => {
+                assert_eq!(server, endpoint);
+                assert!(start.elapsed() >= HEARTBEAT.interval * HEARTBEAT.max_missed);
+                node.stop();
+            }
+            _ => unreachable!(),
+        },
+    });
+}
+
+#[cfg(feature = "tcp")]
+#[test_case(true ; "heartbeat in listener")]
+#[test_case(false ; "heartbeat in connection")]
+fn framed_tcp_heartbeat_answered(in_listener: bool) {
+    enum Signal {
+        Timeout,
+        Check,
+    }
+
+    let (node, listener) = node::split();
+    node.signals().send_with_timer(Signal::Timeout, *TIMEOUT);
+
+    let heartbeat = Some(HEARTBEAT);
+    let listen_config = match in_listener {
+        true => FramedTcpListenConfig { heartbeat, ..Default::default() },
+        false => FramedTcpListenConfig::default(),
+    };
+    let connect_config = match in_listener {
+        true => FramedTcpConnectConfig::default(),
+        false => FramedTcpConnectConfig { heartbeat, ..Default::default() },
+    };
+    let listen = TransportListen::FramedTcp(listen_config);
+    let (_, server_addr) = node.network().listen_with(listen, LOCAL_ADDR).unwrap();
+    let connect = TransportConnect::FramedTcp(connect_config);
+    node.network().connect_with(connect, server_addr).unwrap();
+
+    // The connection is kept alive by the heartbeats, that are not received as messages.
+    node.signals().send_with_timer(Signal::Check, HEARTBEAT.interval * HEARTBEAT.max_missed * 4);
+
+    listener.for_each(move |event| match event {
+        NodeEvent::Signal(Signal::Timeout) => panic!("{}", TIMEOUT_EVENT_RECV_ERR),
+        NodeEvent::Signal(Signal::Check) => node.stop(),
+        NodeEvent::Network(net_event) => match net_event {
+            NetEvent::Connected(..) => (),
+            _ => unreachable!(),
+        },
+    });
+}
+
+#[cfg(all(feature = "tcp", feature = "websocket"))]
+#[test]
+fn invalid_addresses() {
+    let (node, _listener) = node::split::<()>();
+
+    let err = node.network().connect(Transport::Ws, "ws://[invalid").unwrap_err();
+    assert!(matches!(err, message_io::Error::InvalidAddress(_)));
+
+    let err = node.network().connect(Transport::Tcp, "no-port").unwrap_err();
+    assert!(matches!(err, message_io::Error::UnresolvableHost(ref host) if host == "no-port"));
+
+    let path = std::path::Path::new("/tmp/no-socket");
+    let err = node.network().listen(Transport::Tcp, path).unwrap_err();
+    assert!(matches!(err, message_io::Error::InvalidAddress(_)));
+    assert_eq!(ErrorKind::InvalidInput, err.kind());
+}
+
+#[test]
+fn unsupported_transport() {
+    let (node, _listener) = node::split::<()>();
+
+    let adapter_id = ResourceId::MAX_ADAPTER_ID;
+    let config = TransportConnect::Custom(CustomConfig::new(adapter_id));
+    let err = node.network().connect_with(config, LOCAL_ADDR).unwrap_err();
+    assert!(matches!(err, message_io::Error::UnsupportedTransport(id) if id == adapter_id));
+
+    let config = TransportListen::Custom(CustomConfig::new(u8::MAX));
+    let err = node.network().listen_with(config.clone(), LOCAL_ADDR).unwrap_err();
+    assert!(matches!(err, message_io::Error::UnsupportedTransport(u8::MAX)));
+    assert_eq!(None, config.transport());
+
+    assert!(Transport::try_from(u8::MAX).is_err());
+
+    // The resources of an adapter not mounted are never found.
+    let resource_id = ResourceId::from(adapter_id as usize | 1 << 7);
+    assert_eq!(
+        (adapter_id, ResourceType::Local),
+        (resource_id.adapter_id(), resource_id.resource_type())
+    );
+    let endpoint = Endpoint::from_listener(resource_id, "127.0.0.1:80".parse().unwrap()).unwrap();
+    assert_eq!(SendStatus::ResourceNotFound, node.network().send(endpoint, MIN_MESSAGE));
+    assert!(!node.network().set_send_limit(resource_id, None));
+    assert!(!node.network().pause_accept(resource_id));
+    assert!(!node.network().close_gracefully(endpoint, *TIMEOUT));
+    assert!(!node.network().remove(resource_id));
+}
+
+#[cfg(all(feature = "websocket", feature = "tls"))]
+#[test]
+fn wss_url() {
+    //util::init_logger(LogThread::Enabled); // Enable it for better debugging
+
+    let (_server_thread, server_addr) = start_echo_server(tls::wss_listen(), 1);
+
+    let (node, listener) = node::split();
+    node.signals().send_with_timer((), *TIMEOUT);
+
+    let url = format!("wss://localhost:{}/message-io-test", server_addr.socket_addr().port());
+    let (server, _) = node.network().connect_with(tls::wss_connect(), url).unwrap();
+    assert_eq!(SendStatus::Sent, node.network().send(server, MIN_MESSAGE));
+
+    listener.for_each(move |event| match event {
+        NodeEvent::Signal(_) => panic!("{}", TIMEOUT_EVENT_RECV_ERR),
+        NodeEvent::Network(net_event) => match net_event {
+            NetEvent::Message(endpoint, data) => {
+                assert_eq!(server, endpoint);
+                assert_eq!(MIN_MESSAGE, data);
+                node.network().remove(server.resource_id());
+                node.stop();
+            }
+            NetEvent::Connected(..) => unreachable!(),
+            NetEvent::ConnectionFailed(..) => unreachable!(),
+            NetEvent::Drained(_) => unreachable!(),
+            NetEvent::Reconnecting(_) | NetEvent::Reconnected(_) => unreachable!(),
+            NetEvent::Closed(..) => unreachable!(),
+            NetEvent::Error(..) => unreachable!(),
+            NetEvent::Disconnected(..) => unreachable!(),
+        },
+    });
+}
+
+#[cfg(all(target_os = "linux", feature = "unix"))]
+#[test]
+fn unix_stream_abstract_namespace() {
+    //util::init_logger(LogThread::Enabled); // Enable it for better debugging
+
+    let name = format!("@message-io-test-{}", std::process::id());
+    let (node, listener) = node::split();
+    node.signals().send_with_timer((), *TIMEOUT);
+
+    node.network().listen(Transport::UnixStream, &name).unwrap();
+    let (receiver, _) = node.network().connect(Transport::UnixStream, &name).unwrap();
+    assert_eq!(SendStatus::Sent, node.network().send(receiver, MIN_MESSAGE));
+
+    listener.for_each(move |event| match event {
+        NodeEvent::Signal(_) => panic!("{}", TIMEOUT_EVENT_RECV_ERR),
+        NodeEvent::Network(net_event) => match net_event {
+            NetEvent::Message(_, data) => {
+                assert_eq!(MIN_MESSAGE, data);
+                node.stop();
+            }
+            NetEvent::Connected(..) => (),
+            NetEvent::ConnectionFailed(..) => unreachable!(),
+            NetEvent::Drained(_) => unreachable!(),
+            NetEvent::Reconnecting(_) | NetEvent::Reconnected(_) => unreachable!(),
+            NetEvent::Closed(..) => unreachable!(),
+            NetEvent::Error(..) => unreachable!(),
+            NetEvent::Disconnected(..) => unreachable!(),
+        },
+    });
+}
+
+#[cfg(all(unix, feature = "unix"))]
+#[test]
+fn unix_stream_socket_file_removed() {
+    let (node, _listener) = node::split::<()>();
+
+    let path = unix::socket_path();
+    let (id, _) = node.network().listen(Transport::UnixStream, &path).unwrap();
+    assert!(path.exists());
+    assert!(node.network().remove(id));
+    assert!(!path.exists());
+}
+
+#[cfg(all(unix, feature = "unix"))]
+#[test]
+fn unix_datagram_socket_pair() {
+    //util::init_logger(LogThread::Enabled); // Enable it for better debugging
+
+    let (socket, peer_socket) = std::os::unix::net::UnixDatagram::pair().unwrap();
+    peer_socket.set_read_timeout(Some(*TIMEOUT)).unwrap();
+
+    let (node, listener) = node::split();
+    node.signals().send_with_timer((), *TIMEOUT);
+
+    let config = TransportConnect::UnixDatagram(
+        unix_datagram::UnixDatagramConnectConfig::with_socket(socket),
+    );
+    let (endpoint, _) = node.network().connect_with(config, "").unwrap();
+    assert_eq!(SendStatus::Sent, node.network().send(endpoint, MIN_MESSAGE));
+
+    let mut buffer = [0; 16];
+    let size = peer_socket.recv(&mut buffer).unwrap();
+    assert_eq!(MIN_MESSAGE, &buffer[..size]);
+    peer_socket.send(SMALL_MESSAGE.as_bytes()).unwrap();
+
+    listener.for_each(move |event| match event {
+        NodeEvent::Signal(_) => panic!("{}", TIMEOUT_EVENT_RECV_ERR),
+        NodeEvent::Network(net_event) => match net_event {
+            NetEvent::Message(message_endpoint, data) => {
+                assert_eq!(endpoint, message_endpoint);
+                assert_eq!(SMALL_MESSAGE.as_bytes(), data);
+                node.stop();
+            }
+            NetEvent::Connected(..) => unreachable!(),
+            NetEvent::ConnectionFailed(..) => unreachable!(),
+            NetEvent::Drained(_) => unreachable!(),
+            NetEvent::Reconnecting(_) | NetEvent::Reconnected(_) => unreachable!(),
+            NetEvent::Closed(..) => unreachable!(),
+            NetEvent::Error(..) => unreachable!(),
+            NetEvent::Disconnected(..) => unreachable!(),
+        },
+    });
+}
+
+#[cfg(feature = "udp")]
+#[test]
+fn reliable_udp_lossy_link() {
+    //util::init_logger(LogThread::Enabled); // Enable it for better debugging
+
+    const MESSAGES_COUNT: u32 = 2000;
+
+    let (tx, rx) = crossbeam_channel::bounded(1);
+    let _server_thread = NamespacedThread::spawn("test-server", move || {
+        let (node, listener) = node::split();
+        node.signals().send_with_timer((), *TIMEOUT);
+
+        let (_, server_addr) = node.network().listen(Transport::ReliableUdp, LOCAL_ADDR).unwrap();
+        tx.send(server_addr).unwrap();
+
+        listener.for_each(move |event| match event {
+            NodeEvent::Signal(_) => panic!("{}", TIMEOUT_EVENT_RECV_ERR),
+            NodeEvent::Network(net_event) => match net_event {
+                NetEvent::Message(endpoint, data) => {
+                    let status = node.network().send(endpoint, data);
+                    assert!(matches!(status, SendStatus::Sent | SendStatus::Enqueued));
+                }
+                NetEvent::Connected(..) => (),
+                NetEvent::ConnectionFailed(..) => unreachable!(),
+                NetEvent::Drained(_) => unreachable!(),
+                NetEvent::Reconnecting(_) | NetEvent::Reconnected(_) => unreachable!(),
+                NetEvent::Closed(..) => unreachable!(),
+                NetEvent::Error(..) => unreachable!(),
+                NetEvent::Disconnected(..) => node.stop(),
+            },
+        });
+    });
+
+    let server_addr = rx.recv_timeout(*TIMEOUT).expect(TIMEOUT_EVENT_RECV_ERR);
+    let proxy = lossy::LossyProxy::new(server_addr);
+
+    let (node, listener) = node::split();
+    node.signals().send_with_timer((), *TIMEOUT);
+
+    let (server, _) = node.network().connect(Transport::ReliableUdp, proxy.addr()).unwrap();
+    for number in 0..MESSAGES_COUNT {
+        let status = node.network().send(server, &number.to_be_bytes());
+        assert!(matches!(status, SendStatus::Sent | SendStatus::Enqueued));
+    }
+
+    let mut expected: u32 = 0;
+    listener.for_each(move |event| match event {
+        NodeEvent::Signal(_) => panic!("{}", TIMEOUT_EVENT_RECV_ERR),
+        NodeEvent::Network(net_event) => match net_event {
+            NetEvent::Message(endpoint, data) => {
+                assert_eq!(server, endpoint);
+                assert_eq!(expected.to_be_bytes(), data);
+                expected += 1;
+                if expected == MESSAGES_COUNT {
+                    node.network().remove(server.resource_id());
+                    node.stop();
+                }
+            }
+            NetEvent::Connected(..) => unreachable!(),
+            NetEvent::ConnectionFailed(..) => unreachable!(),
+            NetEvent::Drained(_) => unreachable!(),
+            NetEvent::Reconnecting(_) | NetEvent::Reconnected(_) => unreachable!(),
+            NetEvent::Closed(..) => unreachable!(),
+            NetEvent::Error(..) => unreachable!(),
+            NetEvent::Disconnected(..) => unreachable!(),
+        },
+    });
+}
+
+#[cfg(feature = "tcp")]
+#[test]
+fn custom_adapter() {
+    //util::init_logger(LogThread::Enabled); // Enable it for better debugging
+
+    let network = network::Builder::new().mount(custom::ADAPTER_ID, custom::RawTcpAdapter);
+    let (node, listener) = node::split_with(network);
+    node.signals().send_with_timer((), *TIMEOUT);
+
+    let listen_config = TransportListen::Custom(CustomConfig::new(custom::ADAPTER_ID));
+    let (listener_id, addr) = node.network().listen_with(listen_config, LOCAL_ADDR).unwrap();
+
+    let greeting = custom::Greeting(SMALL_MESSAGE.as_bytes().to_vec());
+    let connect_config =
+        TransportConnect::Custom(CustomConfig::with_data(custom::ADAPTER_ID, greeting));
+    let (server, _) = node.network().connect_with(connect_config, addr).unwrap();
+    assert_eq!(custom::ADAPTER_ID, server.resource_id().adapter_id());
+
+    let mut client = None;
+    listener.for_each(move |event| match event {
+        NodeEvent::Signal(_) => panic!("{}", TIMEOUT_EVENT_RECV_ERR),
+        NodeEvent::Network(net_event) => match net_event {
+            NetEvent::Connected(endpoint, id) => {
+                assert_eq!(listener_id, id);
+                client = Some(endpoint);
+            }
+            NetEvent::Message(endpoint, data) if Some(endpoint) == client => {
+                assert_eq!(SMALL_MESSAGE.as_bytes(), data);
+                assert_eq!(SendStatus::Sent, node.network().send(endpoint, MIN_MESSAGE));
+            }
+            NetEvent::Message(endpoint, data) => {
+                assert_eq!(server, endpoint);
+                assert_eq!(MIN_MESSAGE, data);
+                node.stop();
+            }
+            NetEvent::ConnectionFailed(..) => unreachable!(),
+            NetEvent::Drained(_) => unreachable!(),
+            NetEvent::Reconnecting(_) | NetEvent::Reconnected(_) => unreachable!(),
+            NetEvent::Closed(..) => unreachable!(),
+            NetEvent::Error(..) => unreachable!(),
+            NetEvent::Disconnected(..) => unreachable!(),
+        },
+    });
+}
+
+#[cfg(feature = "tcp")]
+#[test]
+fn custom_adapter_accept_error() {
+    let network = network::Builder::new().mount(custom::ADAPTER_ID, custom::RawTcpAdapter);
+    let (node, listener) = node::split_with(network);
+    node.signals().send_with_timer((), *TIMEOUT);
+
+    let listen_config =
+        TransportListen::Custom(CustomConfig::with_data(custom::ADAPTER_ID, custom::RejectAll));
+    let (listener_id, addr) = node.network().listen_with(listen_config, LOCAL_ADDR).unwrap();
+
+    let connect_config = TransportConnect::Custom(CustomConfig::new(custom::ADAPTER_ID));
+    node.network().connect_with(connect_config, addr).unwrap();
+
+    listener.for_each(move |event| match event {
+        NodeEvent::Signal(_) => panic!("{}", TIMEOUT_EVENT_RECV_ERR),
+        NodeEvent::Network(net_event) => match net_event {
+            NetEvent::Error(id, err) => {
+                assert_eq!(listener_id, id);
+                assert_eq!(ErrorKind::PermissionDenied, err.kind());
+                // The listener keeps working after the error.
+                assert!(node.network().remove(listener_id));
+                node.stop();
+            }
+            _ => unreachable!(),
+        },
+    });
+}
+
+#[cfg(feature = "tcp")]
+#[test]
+#[should_panic(expected = "in use")]
+fn custom_adapter_id_in_use() {
+    network::Builder::new().mount(Transport::Tcp.id(), custom::RawTcpAdapter);
+}